@@ -13,6 +13,7 @@ use web_framework_shared::matcher::Matcher;
 use crate::aspect_knockoff_provider;
 use crate::aspect_knockoff_provider::aspect_parse_provider::MethodAdviceAspectCodegen;
 use crate::aspect_knockoff_provider::{AspectInfo, MethodAdviceChain};
+use crate::aspect_knockoff_provider::method_security::MethodSecurityWeaver;
 
 use knockoff_logging::*;
 use lazy_static::lazy_static;
@@ -87,6 +88,9 @@ impl AspectParser {
                 match i {
                     ImplItem::Method(ref mut method) => {
                         info!("Found method {}", SynHelper::get_str(method.clone()));
+                        // the check is part of the original logic, so it is kept when the method
+                        // is wrapped with advice.
+                        MethodSecurityWeaver::weave(method);
                         let return_type = Self::get_return_type(&method);
                         let args = Self::get_args_info(method);
                         info!("Adding method advice aspect to: {}", SynHelper::get_str(method.clone()));
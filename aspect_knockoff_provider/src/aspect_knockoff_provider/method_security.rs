@@ -0,0 +1,133 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Attribute, FnArg, ImplItemMethod, LitStr, parse2, Pat, ReturnType, Stmt, Type};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use codegen_utils::syn_helper::SynHelper;
+use web_framework_shared::method_security::SecurityExpression;
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("method_security.rs");
+
+/// Weaves the authorization check for #[secured("ROLE_ADMIN")] and
+/// #[pre_authorize("hasAuthority('x') and principal == #id")] into the start of the method, so
+/// that it runs before any other advice. The check returns the AccessDeniedError converted into
+/// the error of the Result the method returns.
+pub struct MethodSecurityWeaver;
+
+impl MethodSecurityWeaver {
+
+    pub fn is_method_security(attr: &Attribute) -> bool {
+        attr.path.segments.last()
+            .map(|segment| segment.ident == "secured" || segment.ident == "pre_authorize")
+            .or(Some(false))
+            .unwrap()
+    }
+
+    pub fn weave(method: &mut ImplItemMethod) {
+        let security_attrs = method.attrs.iter()
+            .filter(|attr| Self::is_method_security(attr))
+            .cloned()
+            .collect::<Vec<Attribute>>();
+        if security_attrs.len() == 0 {
+            return;
+        }
+        method.attrs.retain(|attr| !Self::is_method_security(attr));
+
+        let checks = security_attrs.iter()
+            .map(|attr| Self::check_tokens(method, attr))
+            .collect::<Vec<TokenStream>>();
+        let checks = if Self::returns_result(method) {
+            checks
+        } else {
+            vec![Self::compile_error(format!(
+                "{} has method security, so it must return a Result with an error that implements From<AccessDeniedError>.",
+                method.sig.ident
+            ))]
+        };
+
+        info!("Adding method security checks to {}.", method.sig.ident.to_string());
+        checks.into_iter().rev()
+            .flat_map(|check| parse2::<Stmt>(check).ok().into_iter())
+            .for_each(|stmt| method.block.stmts.insert(0, stmt));
+    }
+
+    fn check_tokens(method: &ImplItemMethod, attr: &Attribute) -> TokenStream {
+        let values = match attr.parse_args_with(Punctuated::<LitStr, Comma>::parse_terminated) {
+            Ok(values) => values.into_iter().collect::<Vec<LitStr>>(),
+            Err(e) => return Self::compile_error(format!("Could not parse {}: {}.", SynHelper::get_str(attr), e))
+        };
+        if values.len() == 0 {
+            return Self::compile_error(format!("{} requires at least one value.", SynHelper::get_str(attr)));
+        }
+        if attr.path.segments.last().unwrap().ident == "secured" {
+            quote! {
+                if let Err(access_denied) = web_framework_shared::method_security::MethodSecurity::check_secured(&[#(#values),*]) {
+                    return Err(access_denied.into());
+                }
+            }
+        } else {
+            Self::pre_authorize_tokens(method, &values[0])
+        }
+    }
+
+    fn pre_authorize_tokens(method: &ImplItemMethod, expression: &LitStr) -> TokenStream {
+        let parsed = match SecurityExpression::parse(expression.value().as_str()) {
+            Ok(parsed) => parsed,
+            Err(e) => return Self::compile_error(format!("Invalid pre_authorize expression: {}", e))
+        };
+        let method_args = Self::arg_idents(method);
+        let mut arguments = parsed.arguments();
+        arguments.sort();
+        arguments.dedup();
+        if let Some(missing) = arguments.iter().find(|a| !method_args.contains(a)) {
+            return Self::compile_error(format!("#{} in the pre_authorize expression is not an argument of {}.", missing, method.sig.ident));
+        }
+        let arg_idents = arguments.iter()
+            .map(|a| syn::Ident::new(a.as_str(), Span::call_site()))
+            .collect::<Vec<syn::Ident>>();
+        quote! {
+            if let Err(access_denied) = web_framework_shared::method_security::MethodSecurity::check_pre_authorize(
+                #expression, &[#((#arguments, #arg_idents.to_string())),*]
+            ) {
+                return Err(access_denied.into());
+            }
+        }
+    }
+
+    fn arg_idents(method: &ImplItemMethod) -> Vec<String> {
+        method.sig.inputs.iter()
+            .flat_map(|input| match input {
+                FnArg::Typed(typed) => match typed.pat.as_ref() {
+                    Pat::Ident(ident) => vec![ident.ident.to_string()],
+                    _ => vec![]
+                },
+                FnArg::Receiver(_) => vec![]
+            })
+            .collect()
+    }
+
+    fn returns_result(method: &ImplItemMethod) -> bool {
+        match &method.sig.output {
+            ReturnType::Type(_, ty) => match ty.as_ref() {
+                Type::Path(path) => path.path.segments.last()
+                    .map(|segment| segment.ident == "Result")
+                    .or(Some(false))
+                    .unwrap(),
+                _ => false
+            },
+            ReturnType::Default => false
+        }
+    }
+
+    fn compile_error(message: String) -> TokenStream {
+        error!("{}", message);
+        quote! {
+            compile_error!(#message);
+        }
+    }
+}
@@ -26,6 +26,7 @@ import_logger!("provider.rs");
 pub mod aspect_parse_provider;
 pub mod aspect_ts_generator;
 pub mod aspect_item_modifier;
+pub mod method_security;
 pub mod debug;
 
 #[derive(Clone, Default, Debug)]
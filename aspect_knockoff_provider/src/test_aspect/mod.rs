@@ -14,6 +14,7 @@ use module_macro_shared::item_struct_parser::ItemStructParser;
 use crate::aspect_knockoff_provider::aspect_item_modifier::AspectParser;
 use crate::aspect_knockoff_provider::aspect_parse_provider::ParsedAspects;
 use crate::aspect_knockoff_provider::aspect_ts_generator::AspectGenerator;
+use crate::aspect_knockoff_provider::method_security::MethodSecurityWeaver;
 
 #[test]
 fn test_parse_aspect() {
@@ -54,3 +55,38 @@ fn test_parse_aspect() {
 
 }
 
+#[test]
+fn test_weave_method_security() {
+    let mut method: syn::ImplItemMethod = syn::parse_quote! {
+        #[secured("ROLE_ADMIN")]
+        #[pre_authorize("hasAuthority('x') and principal == #id")]
+        pub fn find(&self, id: String) -> Result<String, AccessDeniedError> {
+            Ok(id)
+        }
+    };
+    MethodSecurityWeaver::weave(&mut method);
+    assert!(method.attrs.is_empty());
+    assert_eq!(method.block.stmts.len(), 3);
+    let woven = method.block.to_token_stream().to_string();
+    assert!(woven.contains("check_secured"));
+    assert!(woven.contains("check_pre_authorize"));
+    assert!(woven.contains("id . to_string ()"));
+
+    let mut not_result: syn::ImplItemMethod = syn::parse_quote! {
+        #[secured("ROLE_ADMIN")]
+        pub fn find(&self) -> String {
+            String::default()
+        }
+    };
+    MethodSecurityWeaver::weave(&mut not_result);
+    assert!(not_result.block.to_token_stream().to_string().contains("compile_error"));
+
+    let mut missing_arg: syn::ImplItemMethod = syn::parse_quote! {
+        #[pre_authorize("principal == #other")]
+        pub fn find(&self, id: String) -> Result<String, AccessDeniedError> {
+            Ok(id)
+        }
+    };
+    MethodSecurityWeaver::weave(&mut missing_arg);
+    assert!(missing_arg.block.to_token_stream().to_string().contains("compile_error"));
+}
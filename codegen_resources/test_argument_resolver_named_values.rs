@@ -0,0 +1,17 @@
+#[controller]
+pub struct TestController;
+
+#[request_mapping(/v1/api/test)]
+impl TestController {
+    #[get_mapping(/one)]
+    pub fn get_test_request_body(
+        #[request_param(page, default_value = "1")] page: Option<u32>,
+        #[request_param] ids: Vec<u64>,
+        #[request_header(X-Request-Id)] request_id: &str,
+        #[cookie_value(session)] session: Option<String>,
+        #[request_attribute(user)] user: String,
+        #[csrf_token] csrf: CsrfToken
+    ) -> String {
+        String::default()
+    }
+}
@@ -11,7 +11,12 @@ pub trait Repo<'a, T: Entity<ID>, ID> : Send + Sync{
     async fn find_by_id(&self, id: &ID) -> Option<T>
     where
         Self: Sized;
+    /// Inserts the entity, or replaces the entity with the same id.
     async fn save(&self, to_save: &'a T) -> ID
+    where
+        Self: Sized;
+    /// Returns if an entity with the id existed.
+    async fn delete_by_id(&self, id: &ID) -> bool
     where
         Self: Sized;
     fn get(data: Option<Self::Data>) -> Self
@@ -0,0 +1,35 @@
+use syn::Item;
+use codegen_utils::syn_helper::SynHelper;
+use collection_util::add_to_multi_value;
+use module_macro_shared::parse_container::{MetadataItemId, ParseContainer};
+use crate::delegator_knockoff_provider::{DELEGATING_TRAIT, DelegatingTrait};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("delegating_parse_provider.rs");
+
+#[derive(Clone, Default)]
+pub struct ParsedDelegatingTraits;
+
+impl ParsedDelegatingTraits {
+
+    /// Adds the traits annotated with #[delegating] to the container, so that the delegator can be
+    /// generated after the whole program has been parsed, when all of the beans implementing them
+    /// are known.
+    pub fn parse_update(items: &mut Item, parse_container: &mut ParseContainer) {
+        match items {
+            Item::Trait(item_trait) => {
+                if DelegatingTrait::is_delegating(&item_trait.attrs) {
+                    info!("Found delegating trait {:?}.", SynHelper::get_str(&item_trait.ident));
+                    add_to_multi_value(&mut parse_container.provided_items,
+                                       Box::new(DelegatingTrait { item_trait: item_trait.clone() }),
+                                       MetadataItemId::new("".to_string(), DELEGATING_TRAIT.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
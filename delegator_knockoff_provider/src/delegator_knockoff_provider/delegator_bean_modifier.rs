@@ -0,0 +1,87 @@
+use quote::{format_ident, quote};
+use syn::{ItemStruct, parse2, Type};
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::bean::{AbstractionLevel, BeanDefinition, BeanType};
+use module_macro_shared::parse_container::{MetadataItem, MetadataItemId, ParseContainer};
+use module_macro_shared::profile_tree::ProfileBuilder;
+use crate::delegator_knockoff_provider::{DELEGATING_TRAIT, DelegatingTrait};
+use crate::delegator_knockoff_provider::delegator_ts_generator::DelegatorGenerator;
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("delegator_bean_modifier.rs");
+
+#[derive(Clone, Default)]
+pub struct DelegatorBeans;
+
+impl DelegatorBeans {
+
+    /// Adds a bean for each {Trait}Delegator, with an #[autowired] field for each of the beans it
+    /// delegates to, so that its factory is generated and it can be autowired like any other bean.
+    /// The delegator itself is generated in the #[boot_knockoff] module by the DelegatorGenerator.
+    pub fn do_modify(parse_container: &mut ParseContainer) {
+        let delegating_traits = parse_container.provided_items
+            .get_mut(&MetadataItemId::new("".to_string(), DELEGATING_TRAIT.to_string()))
+            .into_iter()
+            .flat_map(|provided| provided.iter_mut())
+            .flat_map(|to_cast| to_cast.as_any().downcast_mut::<DelegatingTrait>()
+                .map(|delegating_trait| delegating_trait.clone())
+                .into_iter()
+            )
+            .collect::<Vec<DelegatingTrait>>();
+
+        delegating_traits.iter()
+            .map(|delegating_trait| Self::delegator_bean(parse_container, delegating_trait))
+            .collect::<Vec<BeanDefinition>>()
+            .into_iter()
+            .for_each(|delegator| {
+                info!("Adding delegator bean {:?}.", &delegator.id);
+                parse_container.injectable_types_builder.insert(delegator.id.clone(), delegator);
+            });
+    }
+
+    pub(crate) fn delegator_bean(parse_container: &ParseContainer, delegating_trait: &DelegatingTrait) -> BeanDefinition {
+        let trait_ident = &delegating_trait.item_trait.ident;
+        let vis = &delegating_trait.item_trait.vis;
+        let delegator_ident = format_ident!("{}Delegator", trait_ident);
+        let delegates = DelegatorGenerator::delegate_beans(parse_container.injectable_types_builder.values(), trait_ident);
+        info!("Found {} delegates for the {:?} bean.", delegates.len(), SynHelper::get_str(&delegator_ident));
+        let field_idents = delegates.iter()
+            .map(|delegate| DelegatorGenerator::field_ident(&delegate.id))
+            .collect::<Vec<syn::Ident>>();
+        let field_types = delegates.iter()
+            .map(|delegate| &delegate.concrete_type)
+            .collect::<Vec<&Type>>();
+
+        let item_struct: ItemStruct = syn::parse_quote! {
+            #[service(#delegator_ident)]
+            #vis struct #delegator_ident {
+                #(
+                    #[autowired]
+                    pub #field_idents: Arc<#field_types>,
+                )*
+            }
+        };
+
+        BeanDefinition {
+            struct_type: parse2::<Type>(quote! { #delegator_ident }).ok(),
+            struct_found: Some(item_struct.clone()),
+            traits_impl: vec![],
+            enum_found: None,
+            path_depth: vec![],
+            deps_map: vec![],
+            id: delegator_ident.to_string(),
+            profile: vec![ProfileBuilder::default()],
+            ident: Some(delegator_ident.clone()),
+            fields: vec![item_struct.fields.clone()],
+            bean_type: Some(BeanType::Singleton(AbstractionLevel::Concrete)),
+            mutable: false,
+            factory_fn: None,
+            declaration_generics: Some(item_struct.generics.clone()),
+            qualifiers: vec![],
+        }
+    }
+}
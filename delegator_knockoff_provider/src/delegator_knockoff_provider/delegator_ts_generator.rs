@@ -0,0 +1,354 @@
+use std::ops::Deref;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{FnArg, GenericArgument, ItemTrait, parse2, parse_quote, PathArguments, ReturnType, TraitItem, TraitItemMethod, Type};
+use syn::spanned::Spanned;
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::bean::{BeanDefinition, BeanDefinitionType, DependencyDescriptor};
+use module_macro_shared::parse_container::MetadataItemId;
+use module_macro_shared::profile_tree::{ProfileBuilder, ProfileTree};
+use crate::delegator_knockoff_provider::{DELEGATING_TRAIT, DelegateBean, DelegatingTrait};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("delegator_ts_generator.rs");
+
+/// Generates, for each trait annotated with #[delegating], a {Trait}Delegator struct holding an
+/// Arc of every bean of the default profile implementing the trait by its concrete type, which is
+/// autowired through the bean added by the DelegatorBeans. The delegator implements the trait by
+/// calling each bean in turn through the concrete type, so there is no dynamic dispatch.
+/// Methods returning () are called on every bean, and methods returning Option return the first
+/// Some. The beans are called in #[ordered] order, and then by id.
+pub struct DelegatorGenerator {
+    pub(crate) delegating_traits: Vec<(DelegatingTrait, Vec<DelegateBean>)>
+}
+
+impl DelegatorGenerator {
+
+    pub fn new(profile_tree: &mut ProfileTree) -> Self {
+        let found = profile_tree.provided_items.remove(&MetadataItemId::new("".to_string(), DELEGATING_TRAIT.to_string()))
+            .into_iter()
+            .flat_map(|removed| removed.into_iter())
+            .flat_map(|to_cast| DelegatingTrait::parse_values(&mut Some(to_cast))
+                .map(|delegating_trait| delegating_trait.clone())
+                .into_iter()
+            )
+            .collect::<Vec<DelegatingTrait>>();
+        let delegating_traits = found.into_iter()
+            .map(|delegating_trait| {
+                let beans = profile_tree.injectable_types.get(&ProfileBuilder::default())
+                    .into_iter()
+                    .flat_map(|bean_definitions| bean_definitions.iter())
+                    .flat_map(|bean_definition| match bean_definition {
+                        BeanDefinitionType::Concrete { bean } => Some(bean),
+                        BeanDefinitionType::Abstract { .. } => None
+                    });
+                let delegates = Self::delegate_beans(beans, &delegating_trait.item_trait.ident);
+                info!("Found {} delegates for {:?}.", delegates.len(), SynHelper::get_str(&delegating_trait.item_trait.ident));
+                (delegating_trait, delegates)
+            })
+            .collect();
+        Self {
+            delegating_traits
+        }
+    }
+
+    /// The MutableModuleModifier replaces each delegating trait with the trait followed by its
+    /// delegator.
+    pub fn generate_token_stream(&self) -> TokenStream {
+        let trait_idents = self.delegating_traits.iter()
+            .map(|(delegating_trait, _)| delegating_trait.item_trait.ident.to_string())
+            .collect::<Vec<String>>();
+        let delegators = self.delegating_traits.iter()
+            .map(|(delegating_trait, delegates)| Self::delegator_tokens(&delegating_trait.item_trait, delegates).to_string())
+            .collect::<Vec<String>>();
+
+        quote! {
+
+            pub struct DelegatorGeneratorMutableModifier;
+
+            impl MutableModuleModifier for DelegatorGeneratorMutableModifier {
+
+                fn matches(item: &mut Item) -> bool {
+                    match item {
+                        Item::Trait(item_trait) => {
+                            #(
+                                if item_trait.ident.to_string() == #trait_idents {
+                                    return true;
+                                }
+                            )*
+                        }
+                        _ => {}
+                    }
+                    false
+                }
+
+                fn do_provide(item: &mut Item) -> Option<TokenStream> {
+                    match item {
+                        Item::Trait(item_trait) => {
+                            #(
+                                if item_trait.ident.to_string() == #trait_idents {
+                                    let mut ts = quote::ToTokens::to_token_stream(item_trait);
+                                    ts.extend(#delegators.parse::<TokenStream>().ok());
+                                    return Some(ts);
+                                }
+                            )*
+                        }
+                        _ => {}
+                    }
+                    None
+                }
+
+            }
+        }
+    }
+
+    pub fn delegator_tokens(item_trait: &ItemTrait, delegates: &Vec<DelegateBean>) -> TokenStream {
+        let trait_ident = &item_trait.ident;
+        let vis = &item_trait.vis;
+        let delegator_ident = format_ident!("{}Delegator", trait_ident);
+        let (impl_generics, ty_generics, where_clause) = item_trait.generics.split_for_impl();
+        let trait_path = quote! { #trait_ident #ty_generics };
+
+        let delegate_fields = delegates.iter()
+            .map(|delegate| (Self::field_ident(&delegate.id), delegate.concrete_type.clone()))
+            .collect::<Vec<(Ident, Type)>>();
+        let field_idents = delegate_fields.iter().map(|(field, _)| field).collect::<Vec<&Ident>>();
+        let field_types = delegate_fields.iter().map(|(_, ty)| ty).collect::<Vec<&Type>>();
+
+        // async_trait has to be on the impl as well as on the trait.
+        let async_attrs = item_trait.attrs.iter()
+            .filter(|attr| attr.path.segments.last()
+                .filter(|segment| segment.ident.to_string().as_str() == "async_trait")
+                .is_some())
+            .collect::<Vec<&syn::Attribute>>();
+
+        let trait_items = item_trait.items.iter()
+            .map(|trait_item| match trait_item {
+                TraitItem::Method(method) => Self::delegate_method(method, &trait_path, &delegate_fields),
+                other => {
+                    let message = format!("#[delegating] trait {} can only contain methods, found {}.",
+                                          trait_ident, SynHelper::get_str(other));
+                    quote! { compile_error!(#message); }
+                }
+            })
+            .collect::<Vec<TokenStream>>();
+
+        quote! {
+            #vis struct #delegator_ident {
+                #(pub #field_idents: std::sync::Arc<#field_types>,)*
+            }
+
+            impl #delegator_ident {
+                pub fn new(#(#field_idents: std::sync::Arc<#field_types>),*) -> Self {
+                    Self {
+                        #(#field_idents,)*
+                    }
+                }
+            }
+
+            #(#async_attrs)*
+            impl #impl_generics #trait_path for #delegator_ident #where_clause {
+                #(#trait_items)*
+            }
+        }
+    }
+
+    fn delegate_method(method: &TraitItemMethod, trait_path: &TokenStream, delegates: &Vec<(Ident, Type)>) -> TokenStream {
+        let mut sig = method.sig.clone();
+        let method_ident = &sig.ident;
+
+        let is_ref_receiver = sig.inputs.first()
+            .map(|receiver| match receiver {
+                FnArg::Receiver(receiver) => receiver.reference.is_some() && receiver.mutability.is_none(),
+                FnArg::Typed(_) => false
+            })
+            .or(Some(false))
+            .unwrap();
+        if !is_ref_receiver {
+            let message = format!("#[delegating] method {} must take &self, as the beans are held in an Arc.", method_ident);
+            return quote! { #sig { compile_error!(#message) } };
+        }
+
+        // the patterns of the trait method can be anything, so the args are renamed to be passed on.
+        let mut args = vec![];
+        sig.inputs.iter_mut()
+            .enumerate()
+            .for_each(|(i, fn_arg)| if let FnArg::Typed(pat_type) = fn_arg {
+                let arg = format_ident!("arg_{}", i);
+                pat_type.pat = Box::new(parse_quote!(#arg));
+                pat_type.attrs.clear();
+                args.push((arg, pat_type.ty.deref().clone()));
+            });
+
+        // the by-value args are cloned for every bean but the last.
+        if delegates.len() > 1 {
+            if let Some((_, ty)) = args.iter().find(|(_, ty)| Self::is_never_clone(ty)) {
+                let message = format!("#[delegating] method {} takes {} by value, which is not Clone, so it can \
+                                       not be passed to each bean. Take it by reference instead.",
+                                      method_ident, SynHelper::get_str(ty));
+                return quote! { #sig { compile_error!(#message) } };
+            }
+        }
+
+        let method_ident = &sig.ident;
+        let calls = delegates.iter()
+            .enumerate()
+            .map(|(i, (field, concrete_type))| {
+                let is_last = i == delegates.len() - 1;
+                let call_args = args.iter()
+                    .map(|(arg, ty)| if matches!(ty, Type::Reference(_)) || is_last {
+                        quote! { #arg }
+                    } else {
+                        // spanned to the arg type, so an arg that is not Clone fails at the trait method.
+                        quote_spanned! { ty.span() => <#ty as std::clone::Clone>::clone(&#arg) }
+                    })
+                    .collect::<Vec<TokenStream>>();
+                let call = quote! { <#concrete_type as #trait_path>::#method_ident(&self.#field, #(#call_args),*) };
+                if sig.asyncness.is_some() {
+                    quote! { #call.await }
+                } else {
+                    call
+                }
+            })
+            .collect::<Vec<TokenStream>>();
+
+        let block = match &sig.output {
+            ReturnType::Default => quote! {
+                #(#calls;)*
+            },
+            ReturnType::Type(_, ty) if Self::is_unit(ty) => quote! {
+                #(#calls;)*
+            },
+            ReturnType::Type(_, ty) if Self::is_option(ty) => quote! {
+                #(
+                    if let Some(found) = #calls {
+                        return Some(found);
+                    }
+                )*
+                None
+            },
+            ReturnType::Type(_, ty) => {
+                let message = format!("#[delegating] method {} must return () or Option, but returns {}.",
+                                      method_ident, SynHelper::get_str(ty));
+                quote! { compile_error!(#message) }
+            }
+        };
+
+        quote! {
+            #[allow(unused_variables)]
+            #sig {
+                #block
+            }
+        }
+    }
+
+    /// The by-value arg types that can never be Clone, an impl Trait, a trait object, or a Box of
+    /// a trait object.
+    fn is_never_clone(ty: &Type) -> bool {
+        match ty {
+            Type::ImplTrait(_) | Type::TraitObject(_) => true,
+            Type::Paren(paren) => Self::is_never_clone(&paren.elem),
+            Type::Path(path) => path.path.segments.last()
+                .filter(|segment| segment.ident.to_string().as_str() == "Box")
+                .map(|segment| match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args.args.iter()
+                        .any(|arg| matches!(arg, GenericArgument::Type(Type::TraitObject(_)))),
+                    _ => false
+                })
+                .or(Some(false))
+                .unwrap(),
+            _ => false
+        }
+    }
+
+    fn is_unit(ty: &Type) -> bool {
+        matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+    }
+
+    fn is_option(ty: &Type) -> bool {
+        match ty {
+            Type::Path(path) => path.path.segments.last()
+                .filter(|segment| segment.ident.to_string().as_str() == "Option")
+                .is_some(),
+            _ => false
+        }
+    }
+
+    /// The beans implementing the trait in the default profile, each bean only once. The delegator
+    /// is a bean of the default profile, so a bean only in another profile is not delegated to, the
+    /// same way as a collection is only injected with the beans of its own profile. A bean with
+    /// #[runtime_arg] fields can only be created by its ObjectProvider, so it is not delegated to.
+    pub(crate) fn delegate_beans<'a>(beans: impl Iterator<Item=&'a BeanDefinition>, trait_ident: &Ident) -> Vec<DelegateBean> {
+        let mut delegates = beans
+            .filter(|bean| bean.get_runtime_args().len() == 0)
+            .flat_map(|bean| bean.traits_impl.iter()
+                .filter(|trait_impl| Self::implements(trait_impl, trait_ident))
+                .filter(|trait_impl| Self::is_default_profile(bean, trait_impl))
+                .next()
+                .map(|trait_impl| (bean, trait_impl))
+            )
+            .flat_map(|(bean, trait_impl)| bean.struct_type.clone()
+                .or(bean.ident.as_ref().map(|i| parse2::<Type>(i.to_token_stream()).ok()).flatten())
+                .map(|concrete_type| DelegateBean {
+                    id: bean.id.clone(),
+                    concrete_type,
+                    order: Self::get_order(bean, trait_impl),
+                })
+            )
+            .collect::<Vec<DelegateBean>>();
+        delegates.sort_by(|one, two| (one.order.is_none(), one.order, &one.id)
+            .cmp(&(two.order.is_none(), two.order, &two.id)));
+        delegates.dedup_by(|one, two| one.id == two.id);
+        delegates
+    }
+
+    fn is_default_profile(bean: &BeanDefinition, trait_impl: &DependencyDescriptor) -> bool {
+        let profiles = bean.profile.iter()
+            .chain(trait_impl.profile.iter())
+            .collect::<Vec<&ProfileBuilder>>();
+        profiles.len() == 0 || profiles.contains(&&ProfileBuilder::default())
+    }
+
+    fn implements(trait_impl: &DependencyDescriptor, trait_ident: &Ident) -> bool {
+        trait_impl.item_impl.as_ref()
+            .map(|item_impl| item_impl.trait_.as_ref())
+            .flatten()
+            .map(|(_, path, _)| path.segments.last())
+            .flatten()
+            .filter(|segment| &segment.ident == trait_ident)
+            .is_some()
+    }
+
+    fn get_order(bean: &BeanDefinition, trait_impl: &DependencyDescriptor) -> Option<i64> {
+        let attrs = bean.struct_found.iter().flat_map(|s| s.attrs.iter())
+            .chain(bean.enum_found.iter().flat_map(|e| e.attrs.iter()))
+            .chain(trait_impl.item_impl.iter().flat_map(|i| i.attrs.iter()))
+            .cloned()
+            .collect::<Vec<syn::Attribute>>();
+        SynHelper::get_attr_from_vec(&attrs, &vec!["ordered"])
+            .map(|order| order.trim().parse::<i64>().ok())
+            .flatten()
+    }
+
+    /// The bean id in snake case, so FirstHandler is held in first_handler.
+    pub(crate) fn field_ident(id: &str) -> Ident {
+        let mut field = String::new();
+        id.chars().enumerate().for_each(|(i, c)| {
+            if c.is_uppercase() {
+                if i != 0 && !field.ends_with('_') {
+                    field.push('_');
+                }
+                field.extend(c.to_lowercase());
+            } else if c.is_alphanumeric() {
+                field.push(c);
+            } else if !field.ends_with('_') {
+                field.push('_');
+            }
+        });
+        Ident::new(field.as_str(), Span::call_site())
+    }
+}
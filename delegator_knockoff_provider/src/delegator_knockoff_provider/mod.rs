@@ -0,0 +1,58 @@
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use syn::{Attribute, ItemTrait, Type};
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::impl_parse_values;
+use module_macro_shared::parse_container::MetadataItem;
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("delegator_knockoff_provider.rs");
+
+pub mod delegating_parse_provider;
+pub mod delegator_bean_modifier;
+pub mod delegator_ts_generator;
+
+pub const DELEGATING_TRAIT: &str = "DelegatingTrait";
+
+/// A trait annotated with #[delegating], found while parsing the program.
+#[derive(Clone)]
+pub struct DelegatingTrait {
+    pub item_trait: ItemTrait
+}
+
+impl DelegatingTrait {
+    pub fn is_delegating(attrs: &Vec<Attribute>) -> bool {
+        attrs.iter()
+            .any(|attr| attr.path.segments.last()
+                .filter(|segment| segment.ident.to_string().as_str() == "delegating")
+                .is_some())
+    }
+}
+
+impl MetadataItem for DelegatingTrait {
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Debug for DelegatingTrait {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DelegatingTrait")
+            .field("item_trait", &SynHelper::get_str(&self.item_trait.ident))
+            .finish()
+    }
+}
+
+impl_parse_values!(DelegatingTrait);
+
+/// A bean implementing the delegating trait, held by the delegator as Arc<concrete_type>.
+#[derive(Clone)]
+pub struct DelegateBean {
+    pub id: String,
+    pub concrete_type: Type,
+    pub order: Option<i64>
+}
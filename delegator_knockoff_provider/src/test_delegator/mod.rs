@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+use quote::ToTokens;
+use syn::{Item, ItemTrait};
+
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::{get_test_module_parser, ItemParser, ParseContainer, ProfileProfileTreeModifier, ProfileTreeBuilder, ProfileTreeModifier};
+use module_macro_shared::item_impl_parser::ItemImplParser;
+use module_macro_shared::item_struct_parser::ItemStructParser;
+use module_macro_shared::profile_tree::ProfileBuilder;
+
+use crate::delegator_knockoff_provider::DelegateBean;
+use crate::delegator_knockoff_provider::delegating_parse_provider::ParsedDelegatingTraits;
+use crate::delegator_knockoff_provider::delegator_bean_modifier::DelegatorBeans;
+use crate::delegator_knockoff_provider::delegator_ts_generator::DelegatorGenerator;
+
+fn parse_handlers() -> ParseContainer {
+    let mut parse_container = ParseContainer::default();
+    let mut read = syn::parse_str::<syn::File>(r#"
+        #[delegating]
+        pub trait Handler: Send + Sync {
+            fn handle(&self, name: &str, value: String);
+            fn find(&self, name: &str) -> Option<String>;
+        }
+
+        #[service(Third)]
+        #[derive(Default)]
+        pub struct Third {}
+
+        #[service(FirstHandler)]
+        #[ordered(0)]
+        #[derive(Default)]
+        pub struct FirstHandler {}
+
+        #[service(Second)]
+        #[derive(Default)]
+        pub struct Second {}
+
+        impl Handler for Third {
+            fn handle(&self, name: &str, value: String) {}
+            fn find(&self, name: &str) -> Option<String> { None }
+        }
+
+        impl Handler for FirstHandler {
+            fn handle(&self, name: &str, value: String) {}
+            fn find(&self, name: &str) -> Option<String> { None }
+        }
+
+        impl Handler for Second {
+            fn handle(&self, name: &str, value: String) {}
+            fn find(&self, name: &str) -> Option<String> { None }
+        }
+    "#).unwrap();
+
+    let mut module_parser = get_test_module_parser();
+
+    read.items.iter_mut().for_each(|f| {
+        match f {
+            Item::Struct(s) => {
+                ItemStructParser::parse_item(&PathBuf::new(), &mut parse_container, s, vec![], &mut module_parser);
+            }
+            Item::Impl(i) => {
+                ItemImplParser::parse_item(&PathBuf::new(), &mut parse_container, i, vec![], &mut module_parser);
+            }
+            _ => {}
+        }
+    });
+
+    read.items.iter_mut().for_each(|f| {
+        ParsedDelegatingTraits::parse_update(f, &mut parse_container)
+    });
+
+    parse_container
+}
+
+#[test]
+fn test_generate_delegator() {
+    let mut parse_container = parse_handlers();
+    let p = Box::new(ProfileProfileTreeModifier::new(&parse_container.injectable_types_builder));
+    let mut profile_tree = ProfileTreeBuilder::build_profile_tree(&mut parse_container.injectable_types_builder, vec![p], &mut parse_container.provided_items);
+
+    let generator = DelegatorGenerator::new(&mut profile_tree);
+    assert_eq!(generator.delegating_traits.len(), 1);
+    assert_eq!(generator.delegating_traits[0].1.iter().map(|d| d.id.as_str()).collect::<Vec<&str>>(),
+               vec!["FirstHandler", "Second", "Third"]);
+
+    let generated = SynHelper::get_str(generator.generate_token_stream());
+    assert!(generated.contains("impl MutableModuleModifier for DelegatorGeneratorMutableModifier"), "{}", generated);
+    assert!(generated.contains("pub struct HandlerDelegator"), "{}", generated);
+}
+
+#[test]
+fn test_delegator_bean() {
+    let mut parse_container = parse_handlers();
+    let other = vec![ProfileBuilder { profile: "Other".to_string() }];
+    parse_container.injectable_types_builder.get_mut("Second")
+        .map(|second| {
+            second.profile = other.clone();
+            second.traits_impl.iter_mut().for_each(|trait_impl| trait_impl.profile = other.clone());
+        });
+
+    DelegatorBeans::do_modify(&mut parse_container);
+
+    let delegator = parse_container.injectable_types_builder.get("HandlerDelegator").unwrap();
+    assert_eq!(delegator.profile, vec![ProfileBuilder::default()]);
+    let delegator = SynHelper::get_str(delegator.struct_found.as_ref().unwrap());
+    assert!(delegator.contains("# [autowired] pub first_handler : Arc < FirstHandler >"), "{}", delegator);
+    assert!(delegator.contains("# [autowired] pub third : Arc < Third >"), "{}", delegator);
+    assert!(!delegator.contains("second"), "{}", delegator);
+}
+
+#[test]
+fn test_delegator_tokens() {
+    let item_trait: ItemTrait = syn::parse_quote! {
+        pub trait Handler: Send + Sync {
+            fn handle(&self, name: &str, value: String);
+            fn find(&self, _: &str) -> Option<String>;
+            fn count(&self) -> usize;
+            fn reset(&mut self);
+            fn visit(&self, visitor: impl Fn(&str));
+        }
+    };
+    let delegates = vec![
+        DelegateBean { id: "FirstHandler".to_string(), concrete_type: syn::parse_quote!(FirstHandler), order: Some(0) },
+        DelegateBean { id: "Second".to_string(), concrete_type: syn::parse_quote!(Second), order: None },
+    ];
+    let delegator = DelegatorGenerator::delegator_tokens(&item_trait, &delegates).to_string();
+
+    assert!(delegator.contains("pub struct HandlerDelegator { pub first_handler : std :: sync :: Arc < FirstHandler > , pub second : std :: sync :: Arc < Second > , }"), "{}", delegator);
+    assert!(delegator.contains("< FirstHandler as Handler > :: handle (& self . first_handler , arg_1 , < String as std :: clone :: Clone > :: clone (& arg_2)) ; < Second as Handler > :: handle (& self . second , arg_1 , arg_2) ;"), "{}", delegator);
+    assert!(delegator.contains("if let Some (found) = < FirstHandler as Handler > :: find (& self . first_handler , arg_1) { return Some (found) ; }"), "{}", delegator);
+    assert!(delegator.contains("method count must return () or Option"), "{}", delegator);
+    assert!(delegator.contains("method reset must take &self"), "{}", delegator);
+    assert!(delegator.contains("method visit takes impl Fn (& str) by value, which is not Clone"), "{}", delegator);
+    assert!(!delegator.contains("dyn"), "{}", delegator);
+}
+
+#[test]
+fn test_field_ident() {
+    assert_eq!(DelegatorGenerator::field_ident("FirstHandler").to_token_stream().to_string(), "first_handler");
+    assert_eq!(DelegatorGenerator::field_ident("second").to_token_stream().to_string(), "second");
+}
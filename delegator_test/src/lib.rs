@@ -243,27 +243,76 @@ fn test_attribute_handler_mapping() {
     let mut context = UserRequestContext::default();
     context.request = Some(ReturnRequest::default());
 
-    let a = attr.one.do_action(
+    let a = tokio_test::block_on(attr.one.do_action(
         &request,
         res,
         &RequestContextData { request_context_data: ctx },
-        &mut Some(context.into()));
+        &mut Some(context.into())));
 
     assert!(a.is_some());
+    let string = serde_json::to_string(&ReturnRequest {value: String::from("Whatever")}).unwrap();
+    assert_eq!(a.unwrap().value, string);
 
     let ctx = Context::with_converter_registry(
         ConverterRegistry::new(None, Some(Box::new(DelegatingMessageConverter::new()))));
     let mut context = UserRequestContext::default();
     context.request = None;
+    let res = &mut WebResponse::default();
 
-    let a = attr.one.do_action(
+    let a = tokio_test::block_on(attr.one.do_action(
         &request, res, &RequestContextData { request_context_data: ctx } ,
-        &mut Some(context.into()));
+        &mut Some(context.into())));
+
+    assert!(a.is_none());
+    assert_eq!(res.status, web_framework_shared::request::StatusCode::BAD_REQUEST);
+
+}
+
+#[test]
+fn test_async_handler_request_body() {
+    let listable: ListableBeanFactory = AbstractListableFactory::<DefaultProfile>::new();
+    let attr = AttributeHandlerMapping::new(&listable);
+    let ctx = Context::with_converter_registry(
+        ConverterRegistry::new(None, Some(Box::new(DelegatingMessageConverter::new()))));
+    let res = &mut WebResponse::default();
+    let mut request = WebRequest::default();
+    request.headers.insert("Content-Type".into(), "application/json".into());
+    let mut context = UserRequestContext::default();
+    context.request = Some(AnotherRequest { value: String::from("another") });
+
+    let a = tokio_test::block_on(attr.another.do_action(
+        &request,
+        res,
+        &RequestContextData { request_context_data: ctx },
+        &mut Some(context.into())));
 
     assert!(a.is_some());
-    let string = serde_json::to_string(&ReturnRequest {value: String::from("Whatever")}).unwrap();
-    assert_eq!(a.unwrap().value, string);
+    assert_eq!(res.status, web_framework_shared::request::StatusCode::OK);
+}
+
+#[test]
+fn test_dispatch_streaming_handler() {
+    let listable: ListableBeanFactory = AbstractListableFactory::<DefaultProfile>::new();
+    let attr = AttributeHandlerMapping::new(&listable);
+    let mut request = WebRequest::default();
+    request.uri = "/v1/dummy/events".parse().unwrap();
+    request.method = "GET".parse().unwrap();
+    request.headers.insert("Accept".into(), "text/event-stream".into());
+    let res = &mut WebResponse::default();
+
+    tokio_test::block_on(attr.dispatch(&request, res));
+
+    assert_eq!(res.status, web_framework_shared::request::StatusCode::OK);
+    assert!(res.is_streaming());
+    assert_eq!(res.content_type(), Some("text/event-stream"));
 
+    request.headers.insert("Accept".into(), "application/json".into());
+    let res = &mut WebResponse::default();
+
+    tokio_test::block_on(attr.dispatch(&request, res));
+
+    assert_eq!(res.status, web_framework_shared::request::StatusCode::NOT_ACCEPTABLE);
+    assert!(!res.is_streaming());
 }
 
 #[test]
@@ -290,8 +339,8 @@ fn test_with_filter_chain() {
     let mut request = WebRequest::default();
     request.body = serde_json::to_string(&ReturnRequest{value: String::from("Hello!")}).unwrap();
     request.headers.insert("Content-Type".into(), "application/json".into());
-    fc.do_filter(&request, x, &RequestContextData{ request_context_data: ctx },
-                 &mut Some(UserRequestContext::default().into()));
+    tokio_test::block_on(fc.do_filter(&request, x, &RequestContextData{ request_context_data: ctx },
+                                      &mut Some(UserRequestContext::default().into())));
 
     let string = serde_json::to_string(&ReturnRequest {value: String::from("Whatever")}).unwrap();
     println!("{}", string);
@@ -312,6 +361,35 @@ fn test_app_ctx() {
     assert!(found.is_some());
 }
 
+#[test]
+fn test_singleton_lifecycle_methods_called_once() {
+    use std::sync::atomic::Ordering;
+    let mut app_ctx = AppCtx::new();
+    let lifecycle_bean = app_ctx.get_bean::<TestLifecycleBean>().unwrap();
+    let one = app_ctx.get_bean::<TestInjectLifecycleBeanOne>().unwrap();
+    let two = app_ctx.get_bean::<TestInjectLifecycleBeanTwo>().unwrap();
+    assert!(Arc::ptr_eq(&lifecycle_bean, &one.lifecycle_bean));
+    assert!(Arc::ptr_eq(&lifecycle_bean, &two.lifecycle_bean));
+    assert_eq!(lifecycle_bean.post_construct_calls.load(Ordering::SeqCst), 1);
+    assert_eq!(lifecycle_bean.pre_destroy_calls.load(Ordering::SeqCst), 0);
+
+    app_ctx.close();
+    app_ctx.close();
+    assert_eq!(lifecycle_bean.pre_destroy_calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_object_provider_shares_singletons() {
+    use std::sync::atomic::Ordering;
+    let app_ctx = AppCtx::new();
+    let lifecycle_bean = app_ctx.get_bean::<TestLifecycleBean>().unwrap();
+    let provider = app_ctx.get_bean::<TestInjectObjectProvider>().unwrap();
+    let prototype = provider.prototypes.get_object(String::from("one"));
+    assert_eq!(prototype.name, "one");
+    assert!(Arc::ptr_eq(&lifecycle_bean, &prototype.lifecycle_bean));
+    assert_eq!(lifecycle_bean.post_construct_calls.load(Ordering::SeqCst), 1);
+}
+
 fn create_with_extra_field() {
     let ten = Ten {
     };
@@ -1,5 +1,5 @@
 use std::marker::PhantomData;
-use spring_knockoff_boot_macro::{service, autowired, enum_service, knockoff_ignore, prototype};
+use spring_knockoff_boot_macro::{service, autowired, enum_service, knockoff_ignore, prototype, post_construct, pre_destroy};
 use serde::{Deserialize, Serialize};
 
 use configuration_properties_macro::ConfigurationProperties;
@@ -68,6 +68,8 @@ pub trait HasEnum<T: Send + Sync>: Send + Sync  {
 }
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use module_macro_lib::module_macro_lib::knockoff_context::ObjectProvider;
 
 #[service(TestWithGenerics)]
 pub struct TestWithGenerics {
@@ -182,3 +184,51 @@ pub struct TestInjectPrototypeBeanFromFactoryFn {
     #[prototype]
     pub test_prototype_bean: TestPrototypeBeanFromFactoryFn
 }
+
+#[service(TestLifecycleBean)]
+#[derive(Default)]
+pub struct TestLifecycleBean {
+    pub post_construct_calls: AtomicUsize,
+    pub pre_destroy_calls: AtomicUsize
+}
+
+impl TestLifecycleBean {
+    #[post_construct]
+    pub fn init(&self) {
+        self.post_construct_calls.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[pre_destroy]
+    pub fn destroy(&self) {
+        self.pre_destroy_calls.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[service(TestInjectLifecycleBeanOne)]
+pub struct TestInjectLifecycleBeanOne {
+    #[autowired]
+    #[service]
+    pub lifecycle_bean: Arc<TestLifecycleBean>
+}
+
+#[service(TestInjectLifecycleBeanTwo)]
+pub struct TestInjectLifecycleBeanTwo {
+    #[autowired]
+    #[service]
+    pub lifecycle_bean: Arc<TestLifecycleBean>
+}
+
+#[prototype(TestRuntimeArgPrototype)]
+pub struct TestRuntimeArgPrototype {
+    #[runtime_arg]
+    pub name: String,
+    #[autowired]
+    #[service]
+    pub lifecycle_bean: Arc<TestLifecycleBean>
+}
+
+#[service(TestInjectObjectProvider)]
+pub struct TestInjectObjectProvider {
+    #[autowired]
+    pub prototypes: ObjectProvider<TestRuntimeArgPrototype>
+}
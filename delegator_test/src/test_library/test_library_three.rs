@@ -8,6 +8,7 @@ pub mod test_library_four {
     use spring_knockoff_boot_macro::{autowired, bean, service, request_body, controller, get_mapping, request_mapping, knockoff_ignore};
     use std::sync::{Arc, Mutex};
     use serde::{Serialize, Deserialize};
+    use web_framework_shared::streaming::{SseEvent, StreamingResponseBody};
 
     #[derive(Default)]
     pub struct TestOneHundred;
@@ -57,6 +58,16 @@ pub mod test_library_four {
             println!("{}", self.test_one.two);
             ReturnRequest { value: String::from("Whatever") }
         }
+
+        #[get_mapping(/v1/dummy/another)]
+        pub async fn do_another_request(&self, #[request_body] another: AnotherRequest) -> AnotherRequest {
+            AnotherRequest { value: another.value }
+        }
+
+        #[get_mapping(/v1/dummy/events, produces = "text/event-stream")]
+        pub fn do_events(&self) -> StreamingResponseBody {
+            StreamingResponseBody::from_bytes(SseEvent::data("one").to_bytes(), Some("text/event-stream"))
+        }
     }
 
     #[service(Four)]
@@ -1,7 +1,7 @@
 use std::ops::Deref;
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
-use syn::{parse_str, Attribute, Block, FnArg, ImplItem, ImplItemMethod, ItemImpl, ItemStruct, Stmt, Type};
+use syn::{parse_str, Attribute, Block, FnArg, GenericArgument, ImplItem, ImplItemMethod, ItemImpl, ItemStruct, PathArguments, Stmt, Type, TypePath};
 
 // Type alias for proc_macro2::TokenStream to distinguish from proc_macro::TokenStream
 type TokenStream2 = proc_macro2::TokenStream;
@@ -9,10 +9,12 @@ use codegen_utils::syn_helper::SynHelper;
 use knockoff_logging::log_message;
 use module_macro_shared::bean::{BeanDefinition, BeanDefinitionType};
 use module_macro_shared::profile_tree::ProfileTree;
+use web_framework_shared::http_method::HttpMethod;
 use web_framework_shared::matcher::{AntPathRequestMatcher, AntStringRequestMatcher, Matcher};
 
 use module_macro_shared::dependency::DependencyDescriptor;
-use web_framework_shared::argument_resolver::{ArgumentResolver, ResolveArguments};
+use web_framework_shared::argument_resolver::{ArgumentResolver, NamedValueInfo, ResolveArguments};
+use web_framework_shared::argument_resolver::request_body_argument_resolver::RequestBodyArgumentResolver;
 
 use knockoff_logging::*;
 use lazy_static::lazy_static;
@@ -23,7 +25,8 @@ import_logger_root!("lib.rs", concat!(project_directory!(), "/log_out/handler_ma
 
 pub struct HandlerMappingBuilder {
     controllers: Vec<ControllerBean>,
-    message_converters: Vec<MessageConverterBean>
+    message_converters: Vec<MessageConverterBean>,
+    exception_handlers: Vec<ExceptionHandlerBean>
 }
 
 pub struct HandlerMappingBootFactory {}
@@ -45,8 +48,16 @@ impl HandlerMappingBuilder {
         info!("Parsing controllers.");
         let controller_beans = Self::find_bean_definition(items, vec!["controller", "rest_controller"])
             .iter()
+            .filter(|bean| !Self::filter_controller_advice_beans(bean))
             .flat_map(|bean| Self::create_controller_beans(bean))
             .collect::<Vec<ControllerBean>>();
+
+        info!("Parsing controller advice.");
+
+        let exception_handlers = Self::find_bean_definition(items, vec!["controller_advice"])
+            .iter()
+            .flat_map(|bean| Self::create_exception_handler_beans(bean))
+            .collect::<Vec<ExceptionHandlerBean>>();
         
         info!("Parsing message converters.");
         
@@ -57,7 +68,8 @@ impl HandlerMappingBuilder {
 
         Self {
             controllers: controller_beans,
-            message_converters: vec![]
+            message_converters,
+            exception_handlers
         }
 
     }
@@ -95,6 +107,16 @@ impl HandlerMappingBuilder {
             .is_some()
     }
 
+    fn filter_controller_advice_beans(b: &&BeanDefinition) -> bool {
+        b.struct_found.as_ref()
+            .map(|s| SynHelper::get_attr_from_vec(
+                &s.attrs,
+                &vec!["controller_advice"])
+            )
+            .flatten()
+            .is_some()
+    }
+
     fn filter_message_converter_beans(b: &&BeanDefinition) -> bool {
         b.struct_found.as_ref()
             .map(|s| SynHelper::get_attr_from_vec(
@@ -108,20 +130,66 @@ impl HandlerMappingBuilder {
     fn create_controller_beans(bean: &BeanDefinition) -> Vec<ControllerBean> {
         bean.traits_impl.iter()
             .flat_map(|b| Self::create_req_matcher_tuple(b))
-            .flat_map(|autowire| Self::expand_autowire_for_items(autowire))
             .flat_map(|i| Self::create_controller_bean(i))
             .collect::<Vec<ControllerBean>>()
     }
 
-    fn expand_autowire_for_items(autowire: (DependencyDescriptor, Vec<AntPathRequestMatcher>)) -> Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>, ImplItem)> {
-        autowire.0.item_impl
-            .as_ref()
-            .map(|i| i.items.as_ref())
-            .or(Some(&vec![]))
-            .unwrap()
-            .iter()
-            .map(|i| (autowire.0.clone(), autowire.1.clone(), i.clone()))
-            .collect::<Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>, ImplItem)>>()
+    fn create_exception_handler_beans(bean: &BeanDefinition) -> Vec<ExceptionHandlerBean> {
+        bean.traits_impl.iter()
+            .flat_map(|dd| dd.item_impl.clone().into_iter())
+            .flat_map(|item_impl| {
+                let advice_ty = item_impl.self_ty.deref().clone();
+                item_impl.items.iter()
+                    .flat_map(|i| match i {
+                        ImplItem::Method(impl_item_method) => {
+                            Self::create_exception_handler_bean(&advice_ty, impl_item_method)
+                                .into_iter()
+                                .collect::<Vec<ExceptionHandlerBean>>()
+                        }
+                        _ => {
+                            vec![]
+                        }
+                    })
+                    .collect::<Vec<ExceptionHandlerBean>>()
+            })
+            .map(|e| {
+                info!("Found exception handler {} for {}.", &e.method.to_string(), SynHelper::get_str(&e.error_type));
+                e
+            })
+            .collect()
+    }
+
+    /// Parses #[exception_handler(ErrorType)] or #[exception_handler(ErrorType, status = 404)].
+    fn create_exception_handler_bean(advice_ty: &Type, method: &ImplItemMethod) -> Option<ExceptionHandlerBean> {
+        let attr = SynHelper::get_attribute_from_vec(&method.attrs, &vec!["exception_handler"])?;
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => {
+                let error_type = list.nested.iter()
+                    .find_map(|meta| match meta {
+                        syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                            Some(Type::Path(TypePath { qself: None, path: path.clone() }))
+                        }
+                        _ => None
+                    })?;
+                let status = list.nested.iter()
+                    .find_map(|meta| Self::extract_int_from_meta(meta, "status"));
+                let returns_response_entity = ArgumentResolver::resolve_fn_arg_fn_output(method)
+                    .map(|output| Self::is_response_entity(&output))
+                    .or(Some(false))
+                    .unwrap();
+                Some(ExceptionHandlerBean {
+                    advice_type: advice_ty.clone(),
+                    method: method.sig.ident.clone(),
+                    error_type,
+                    status,
+                    returns_response_entity
+                })
+            }
+            _ => {
+                error!("Exception handler {} did not declare the error type it handles.", method.sig.ident.to_string());
+                None
+            }
+        }
     }
 
     fn create_message_converter_bean(bean: &BeanDefinition) -> Vec<MessageConverterBean> {
@@ -130,6 +198,7 @@ impl HandlerMappingBuilder {
         
         let depth = bean.path_depth.clone();
         let struct_ty = bean.struct_found.as_ref().unwrap().ident.clone().to_string();
+        let generic = bean.struct_found.as_ref().unwrap().generics.params.len() != 0;
 
         let mut out = "".to_string();
 
@@ -170,7 +239,8 @@ impl HandlerMappingBuilder {
                             request_type,
                             response_type,
                             media_type,
-                            alias,
+                            alias: alias.or(Some(struct_ty.to_lowercase())),
+                            generic
                         }
                     })
                     .or_else(|err| {
@@ -206,7 +276,25 @@ impl HandlerMappingBuilder {
         (media_type, alias)
     }
 
+    /// The request and response types are the type arguments of MessageConverter<Request, Response>
+    /// in the impl, or if those are not provided, the type parameters of the impl.
     fn parse_req_response(item_impl: &ItemImpl) -> (Option<TokenStream>, Option<TokenStream>) {
+        let trait_types = item_impl.trait_.as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|s| match &s.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter()
+                    .flat_map(|a| match a {
+                        GenericArgument::Type(t) => vec![t.to_token_stream()],
+                        _ => vec![]
+                    })
+                    .collect::<Vec<TokenStream>>(),
+                _ => vec![]
+            })
+            .or(Some(vec![]))
+            .unwrap();
+        if trait_types.len() == 2 && !Self::is_impl_type_param(item_impl, &trait_types[0]) {
+            return (Some(trait_types[0].clone()), Some(trait_types[1].clone()));
+        }
         let (request_type, response_type) = if let Some(generics) = &item_impl.generics.params.iter().next() {
             if let syn::GenericParam::Type(type_param) = generics {
                 // If there's at least one generic, assume it's the request type
@@ -246,6 +334,14 @@ impl HandlerMappingBuilder {
         (request_type, response_type)
     }
 
+    fn is_impl_type_param(item_impl: &ItemImpl, ty: &TokenStream) -> bool {
+        item_impl.generics.params.iter()
+            .any(|p| match p {
+                syn::GenericParam::Type(type_param) => type_param.ident.to_string() == ty.to_string(),
+                _ => false
+            })
+    }
+
     fn create_controller_bean(i: (DependencyDescriptor, Vec<AntPathRequestMatcher>, ImplItem)) -> Vec<ControllerBean> {
         match i.2 {
             ImplItem::Method(impl_item_method) => {
@@ -253,7 +349,11 @@ impl HandlerMappingBuilder {
                     method: impl_item_method.clone(),
                     ant_path_request_matcher: i.1.clone(),
                     arguments_resolved: ArgumentResolver::resolve_argument_methods(&impl_item_method),
-                    self_struct: i.0.item_impl.unwrap().self_ty.deref().clone(),
+                    self_struct: i.0.item_impl.as_ref().unwrap().self_ty.deref().clone(),
+                    produces: Self::mapping_media_types(&impl_item_method.attrs, "produces"),
+                    consumes: Self::mapping_media_types(&impl_item_method.attrs, "consumes"),
+                    cross_origin: Self::cross_origin(&impl_item_method.attrs)
+                        .or_else(|| i.0.item_impl.as_ref().and_then(|item_impl| Self::cross_origin(&item_impl.attrs))),
                 }]
             }
             _ => {
@@ -262,19 +362,20 @@ impl HandlerMappingBuilder {
         }
     }
 
-    fn create_req_matcher_tuple(b: &DependencyDescriptor) -> Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>)> {
+    /// Each method of the impl, with the request matchers of its own mapping.
+    fn create_req_matcher_tuple(b: &DependencyDescriptor) -> Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>, ImplItem)> {
         b.item_impl.as_ref().map(|item| item.items.iter()
             .flat_map(|i| {
                 match i {
                     ImplItem::Method(impl_item_method) => {
-                        vec![(b.clone(), Self::create_request_matcher(&impl_item_method.attrs))]
+                        vec![(b.clone(), Self::create_request_matcher(&impl_item_method.attrs), i.clone())]
                     }
                     _ => {
                         vec![]
                     }
                 }
             })
-            .collect::<Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>)>>())
+            .collect::<Vec<(DependencyDescriptor, Vec<AntPathRequestMatcher>, ImplItem)>>())
             .or(Some(vec![]))
             .unwrap()
     }
@@ -291,90 +392,160 @@ impl HandlerMappingBuilder {
         None
     }
 
-    fn generate_message_converter_tokens(&self) -> Option<TokenStream> {
-        if self.message_converters.is_empty() {
-            return None;
+    fn extract_int_from_meta(meta: &syn::NestedMeta, name: &str) -> Option<u16> {
+        if let syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) = meta {
+            if nv.path.is_ident(name) {
+                if let syn::Lit::Int(lit_int) = &nv.lit {
+                    return lit_int.base10_parse::<u16>().ok();
+                }
+            }
         }
+        None
+    }
 
-        use proc_macro2::Span;
-        use syn::LitStr;
-        use std::collections::HashMap;
-        use quote::{format_ident, quote};
+    /// Creates a HandlerMessageConverter for the request and response types of the controllers,
+    /// which delegates to the JsonMessageConverter, HtmlMessageConverter and the
+    /// #[message_converter] beans for those types, by content negotiation. Returns the module
+    /// containing it, and for each controller with a request body the Context to use.
+    fn generate_message_converter_tokens(&self, arg_types: &Vec<Type>, arg_outputs: &Vec<Type>) -> (TokenStream, Vec<TokenStream>) {
+        use quote::format_ident;
 
-        // Group converters by request/response types
-        let mut converter_groups: HashMap<(String, String), Vec<&MessageConverterBean>> = HashMap::new();
-        
-        for converter in &self.message_converters {
-            if let (Some(req_type), Some(resp_type)) = (&converter.request_type, &converter.response_type) {
-                let req_key = req_type.to_string();
-                let resp_key = resp_type.to_string();
-                converter_groups.entry((req_key, resp_key))
-                    .or_insert_with(Vec::new)
-                    .push(converter);
-            }
-        }
+        let mut group_keys: Vec<(String, String)> = vec![];
+        let mut group_tokens = vec![];
 
-        if converter_groups.is_empty() {
-            return None;
-        }
+        for (req_type, resp_type) in arg_types.iter().zip(arg_outputs.iter()) {
+            let key = (req_type.to_token_stream().to_string(), resp_type.to_token_stream().to_string());
+            if group_keys.contains(&key) {
+                continue;
+            }
+            // the fields of the delegating converter are named by the request type.
+            if group_keys.iter().any(|(req, _)| req == &key.0) {
+                error!("Request type {} is used with several response types, only the first is negotiated.", &key.0);
+                continue;
+            }
 
-        // Build the macro input structure
-        let mut group_tokens = Vec::new();
-        
-        for ((req_key, resp_key), converters) in converter_groups {
-            let mut converter_tokens = Vec::new();
-            
-            for converter in converters.iter() {
-                if let (Some(media_type), Some(alias)) = (&converter.media_type, &converter.alias) {
-                    let converter_path = &converter.converter_path;
-                    let req_type = converter.request_type.as_ref().unwrap();
-                    let resp_type = converter.response_type.as_ref().unwrap();
-                    let media_type_lit = LitStr::new(media_type, Span::call_site());
-                    let alias_str = format_ident!("{}", alias);
-                    
-                    converter_tokens.push(quote! {
-                        (#media_type_lit as #alias_str => #converter_path<#req_type, #resp_type>)
-                    });
+            let user_converters = self.message_converters.iter()
+                .filter(|c| c.request_type.as_ref().map(|r| r.to_string()) == Some(key.0.clone())
+                    && c.response_type.as_ref().map(|r| r.to_string()) == Some(key.1.clone()))
+                .filter(|c| c.media_type.is_some())
+                .collect::<Vec<&MessageConverterBean>>();
+
+            let mut converter_tokens = vec![];
+            // a #[message_converter] for the same media type replaces the default.
+            for (media_type, alias, default_converter) in vec![("application/json", "json", "JsonMessageConverter"), ("text/html", "html", "HtmlMessageConverter")] {
+                if user_converters.iter().any(|c| c.media_type.as_ref().unwrap() == media_type) {
+                    continue;
                 }
+                let alias = format_ident!("{}", alias);
+                let default_converter = format_ident!("{}", default_converter);
+                converter_tokens.push(quote! {
+                    (#media_type as #alias => #default_converter<#req_type, #resp_type>)
+                });
             }
-            
-            if !converter_tokens.is_empty() {
-                let converters_tuple = quote! { (#(#converter_tokens),*) };
-                let req_type = converters[0].request_type.as_ref().unwrap();
-                let resp_type = converters[0].response_type.as_ref().unwrap();
-                group_tokens.push(quote! {
-                    #converters_tuple ===> (#req_type => #resp_type)
+            for converter in user_converters.iter() {
+                let media_type = converter.media_type.as_ref().unwrap();
+                let alias = format_ident!("{}", converter.alias.as_ref().unwrap());
+                let converter_path = &converter.converter_path;
+                let converter_ty = if converter.generic {
+                    quote! { #converter_path<#req_type, #resp_type> }
+                } else {
+                    quote! { #converter_path }
+                };
+                converter_tokens.push(quote! {
+                    (#media_type as #alias => #converter_ty)
                 });
             }
+
+            group_tokens.push(quote! {
+                (#(#converter_tokens),*) ===> (#req_type => #resp_type)
+            });
+            group_keys.push(key);
         }
 
+        let contexts = arg_types.iter().zip(arg_outputs.iter())
+            .map(|(req_type, resp_type)| {
+                let key = (req_type.to_token_stream().to_string(), resp_type.to_token_stream().to_string());
+                if group_keys.contains(&key) {
+                    quote! {
+                        Context::<#req_type, #resp_type>::with_converter_registry(
+                            ConverterRegistry::new(None, Some(Box::new(handler_message_converters::HandlerMessageConverter::new())))
+                        )
+                    }
+                } else {
+                    quote! {
+                        Context::<#req_type, #resp_type>::new()
+                    }
+                }
+            })
+            .collect::<Vec<TokenStream>>();
+
         if group_tokens.is_empty() {
-            return None;
+            return (quote! {}, contexts);
         }
 
-        // Build the final macro call
-        Some(quote! {
-            create_delegating_message_converters!((
-                #(#group_tokens),*
-            ) => DelegatingMessageConverter);
-        })
+        let converter_module = quote! {
+            pub mod handler_message_converters {
+                use super::*;
+                use std::collections::HashMap;
+                use std::marker::PhantomData;
+                use paste::paste;
+                use serde::{Serialize, Deserialize};
+                use web_framework_shared::request::WebRequest;
+                use web_framework::web_framework::convert::MessageConverter;
+                use web_framework::web_framework::message::MessageType;
+                use web_framework::{create_delegating_message_converters, provide_default_message_converters};
+
+                provide_default_message_converters!();
+                create_delegating_message_converters!((
+                    #(#group_tokens),*
+                ) => HandlerMessageConverter);
+            }
+        };
+
+        (converter_module, contexts)
     }
 
     pub fn generate_token_stream(&self) -> TokenStream {
-        // Generate message converter tokens if needed
-        let message_converter_tokens = self.generate_message_converter_tokens();
-        
-        let (to_match, split) = self.get_request_matcher_info();
+        let handlers = self.handlers();
+
+        let (to_match, split) = Self::get_request_matcher_info(&handlers);
 
-        // Include the message converter macro if we have converters
-        let converter_tokens = message_converter_tokens.unwrap_or_else(|| quote! {});
+        let request_methods = Self::get_request_method_info(&handlers);
 
-        let (path_var_idents, path_var_names) = self.parse_path_variable_args();
+        let method_idents = Self::get_controller_method_idents(&handlers);
 
-        let (arg_idents, arg_types, arg_outputs, self_tys) = self.parse_request_body_args();
+        let (arg_bindings, call_args) = Self::resolved_argument_tokens(&handlers);
+
+        let validations = Self::request_body_validations(&handlers);
+
+        let awaits = Self::controller_method_awaits(&handlers);
+
+        let (arg_idents, arg_types, arg_outputs, self_tys, arg_errors) = Self::parse_request_body_args(&handlers);
+
+        let (body_bindings, read_bodies) = Self::request_body_tokens(&handlers);
+
+        let no_request_body_types = Self::no_request_body_types(&handlers);
+
+        let handler_indices = (0..arg_idents.len()).collect::<Vec<usize>>();
+
+        let (converter_module, handler_contexts) = self.generate_message_converter_tokens(&arg_types, &arg_outputs);
+
+        let (produces, consumes) = Self::mapping_media_type_info(&handlers);
+
+        let cross_origins = Self::cross_origin_info(&handlers);
 
         let method_logic_stmts = self.reparse_method_logic();
 
+        let response_writers = self.response_entity_writers(&arg_outputs);
+
+        let streamings = arg_outputs.iter()
+            .map(|output| Self::is_streaming_output(output))
+            .collect::<Vec<bool>>();
+
+        let result_handlers = self.result_handlers(&arg_errors);
+
+        let (advice_fields, advice_tys) = self.controller_advice_fields();
+
         // Add imports for message converters
         let ts = quote! {
             use web_framework::web_framework::convert::*;
@@ -385,19 +556,29 @@ impl HandlerMappingBuilder {
             use web_framework::web_framework::context::Context;
             use web_framework::web_framework::request_context::SessionContext;
             use web_framework_shared::request::WebRequest;
-            use web_framework_shared::controller::{ContextData, Data, HandlerExecutionChain};
-            use web_framework_shared::request::WebResponse;
+            use web_framework_shared::controller::{ContextData, Data, HandlerExecutionChain, write_unmatched_response};
+            use web_framework_shared::http_method::HttpMethod;
+            use web_framework_shared::media_type::ContentNegotiator;
+            use web_framework_shared::cors::{CorsConfiguration, CorsProcessor};
+            use web_framework_shared::argument_resolver::request_value_resolver::{ArgumentResolutionError, RequestValueResolver, RequestValueSource};
+            use web_framework_shared::request::{ResponseEntity, ResponseWriter, StatusCode, WebResponse};
             use web_framework_shared::EndpointMetadata;
             use web_framework_shared::Handler;
             use web_framework::web_framework::context::UserRequestContext;
             use web_framework::web_framework::context::RequestContextData;
             use web_framework_shared::controller::{HandlerExecutor, HandlerMethod, HandlerExecutorStruct};
-            use web_framework_shared::matcher::{AntPathRequestMatcher,AntStringRequestMatcher};
+            use web_framework_shared::matcher::{AntPathRequestMatcher,AntStringRequestMatcher,PathSpecificity};
 
             pub struct Dispatcher {
                 handler_mapping: AttributeHandlerMapping
             }
 
+            #(
+                /// The request type of a controller method without a #[request_body].
+                #[derive(Serialize, Deserialize, Clone, Default)]
+                pub struct #no_request_body_types;
+            )*
+
             pub struct AttributeHandlerMapping {
                 #(#arg_idents: Arc<HandlerExecutionChain<
                     UserRequestContext<#arg_types>,
@@ -409,29 +590,50 @@ impl HandlerMappingBuilder {
                         RequestContextData<#arg_types, #arg_outputs>,
                         #self_tys
                     >>>)*
+                /// The #[cross_origin] of each handler, by the index of the handler.
+                cross_origins: Vec<Option<CorsConfiguration>>
+            }
+
+            /// Contains the #[controller_advice] beans, which handle the errors returned from
+            /// controller methods.
+            pub struct ControllerAdvices {
+                #(#advice_fields: Arc<#advice_tys>,)*
+            }
+
+            impl ControllerAdvices {
+                fn new(listable: &ListableBeanFactory) -> Self {
+                    Self {
+                        #(#advice_fields: BeanContainer::<#advice_tys>::fetch_bean(listable).unwrap(),)*
+                    }
+                }
             }
 
             pub struct HandlerExecutorImpl <D: Data + Send + Sync, C: ContextData + Send + Sync, U> {
                 phantom_d: PhantomData<D>,
                 phantom_c: PhantomData<C>,
-                c: Arc<U>
+                c: Arc<U>,
+                advices: Arc<ControllerAdvices>,
+                request_matchers: Vec<AntPathRequestMatcher>
             }
 
             impl <D: Data + Send + Sync, C: ContextData + Send + Sync, U> HandlerExecutorImpl<D, C, U> {
-                fn new(c: Arc<U>) -> Self {
+                fn new(c: Arc<U>, advices: Arc<ControllerAdvices>, request_matchers: Vec<AntPathRequestMatcher>) -> Self {
                     Self {
                         phantom_d: PhantomData::default(),
                         phantom_c: PhantomData::default(),
-                        c
+                        c,
+                        advices,
+                        request_matchers
                     }
                 }
             }
 
             #(
 
+                #[web_framework_shared::async_trait]
                 impl Handler<#arg_types, #arg_outputs, UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>>
                 for HandlerExecutorImpl<UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>, #self_tys> {
-                    fn do_action(
+                    async fn do_action(
                         &self,
                         web_request: &WebRequest,
                         response: &mut WebResponse,
@@ -440,13 +642,13 @@ impl HandlerMappingBuilder {
                     ) -> Option<#arg_outputs> {
                         if request_context.as_ref().is_none() {
                             let hm = HandlerMethod::new(UserRequestContext::new_default().into());
-                            return self.execute_handler(hm, response, web_request);
+                            return self.execute_handler(hm, response, web_request).await;
                         }
 
                         let mut request_ctx_data: Option<Box<UserRequestContext<#arg_types>>> = None;
                         std::mem::swap(&mut request_ctx_data, request_context);
                         let hm = HandlerMethod::new(request_ctx_data.unwrap());
-                        self.execute_handler(hm, response, web_request)
+                        self.execute_handler(hm, response, web_request).await
                     }
 
                     /**
@@ -474,41 +676,37 @@ impl HandlerMappingBuilder {
                     }
                 }
 
+                #[web_framework_shared::async_trait]
                 impl HandlerExecutor<UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>, #arg_types, #arg_outputs>
                 for HandlerExecutorImpl<UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>, #self_tys>
                 {
-                    fn execute_handler(
+                    async fn execute_handler(
                         &self,
-                        mut handler: HandlerMethod<UserRequestContext<#arg_types>>,
+                        handler: HandlerMethod<UserRequestContext<#arg_types>>,
                         response: &mut WebResponse,
                         request: &WebRequest
                     ) -> Option<#arg_outputs> {
-                        if handler.request_ctx_data.as_ref().is_none() {
-                            let mut req = UserRequestContext::default();
-                            req.request = Some(#arg_types::default());
+                        #(#arg_bindings)*
 
-                            req.request
-                                .map(|#arg_idents| {
-                                    self.c.do_request(#arg_idents)
-                                })
-                        } else {
-                            println!("Not null!");
-                            handler.request_ctx_data.unwrap().request
-                                .map_or_else(|| {
-                                    println!("Was null!");
-                                    let #arg_idents = #arg_types::default();
-                                    self.c.do_request(#arg_idents)
-                                }, |#arg_idents| {
-                                    self.c.do_request(#arg_idents)
-                                })
-                                .into()
-                        }
+                        #validations
+
+                        #body_bindings
+
+                        let out = Some(self.c.#method_idents(#(#call_args),*)#awaits);
+
+                        #result_handlers
+
+                        #response_writers
+
+                        out
                     }
                 }
             )*
 
             impl AttributeHandlerMapping {
                 pub fn new(listable: &ListableBeanFactory) -> Self {
+                    let advices = Arc::new(ControllerAdvices::new(listable));
+
                     #(
                         let mut interceptors = vec![];
 
@@ -522,10 +720,13 @@ impl HandlerMappingBuilder {
                             }
                         )*
 
-                        let ant_path_request_matchers: AntPathRequestMatcher = AntPathRequestMatcher::new_from_request_matcher(ant_string_request_matchers);
+                        let ant_path_request_matchers: AntPathRequestMatcher = AntPathRequestMatcher::new_from_request_matcher_with_methods(
+                            ant_string_request_matchers,
+                            vec![#(#request_methods),*]
+                        );
                         request_matchers.push(ant_path_request_matchers);
 
-                        let context_item = Context::<#arg_types, #arg_outputs>::new();
+                        let context_item = #handler_contexts;
                         let context: Arc<RequestContextData<#arg_types, #arg_outputs>> = Arc::new(
                             RequestContextData {
                                 request_context_data: context_item
@@ -534,7 +735,7 @@ impl HandlerMappingBuilder {
 
                         let controller: Arc<#self_tys> = BeanContainer::<#self_tys>::fetch_bean(listable).unwrap();
                         let handler_executor: Arc<HandlerExecutorImpl<UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>, #self_tys>>
-                            = Arc::new(HandlerExecutorImpl::new(controller.clone()));
+                            = Arc::new(HandlerExecutorImpl::new(controller.clone(), advices.clone(), request_matchers.clone()));
 
                         let handler_executor: Arc<HandlerExecutorStruct<
                             HandlerExecutorImpl<UserRequestContext<#arg_types>, RequestContextData<#arg_types, #arg_outputs>, #self_tys>,
@@ -560,20 +761,180 @@ impl HandlerMappingBuilder {
 
                     Self {
                         #(#arg_idents,)*
+                        cross_origins: vec![#(#cross_origins),*]
                     }
                 }
 
+                /// Dispatches the request to the controller matching both the path and the method. If
+                /// the patterns of several controllers match, the most specific is used. If only the
+                /// path matched, 405 is written with the Allow header, or 204 for OPTIONS. The body is
+                /// read and written by the MessageConverter negotiated from the Content-Type and
+                /// Accept headers, with 415 or 406 if none match. A StreamingResponseBody is not
+                /// converted, so it is negotiated against the produces of the mapping, or the media
+                /// type of the stream. A CORS preflight is answered with the #[cross_origin] of the
+                /// controller for the requested method.
+                pub async fn dispatch(&self, request: &WebRequest, response: &mut WebResponse) {
+                    let mut allowed_methods: Vec<HttpMethod> = vec![];
+                    let mut most_specific: Option<(PathSpecificity, usize)> = None;
+                    let preflight_method = CorsProcessor::preflight_method(request);
+                    let mut preflight_cors: Option<&CorsConfiguration> = None;
+                    #(
+                        if self.#arg_idents.matches_path(request) {
+                            let path_methods = self.#arg_idents.allowed_methods(request);
+                            if preflight_cors.is_none() && preflight_method.map(|m| path_methods.contains(&m)).unwrap_or(false) {
+                                preflight_cors = self.cross_origins[#handler_indices].as_ref();
+                            }
+                            allowed_methods.extend(path_methods);
+                            if self.#arg_idents.matches(request) {
+                                let specificity = self.#arg_idents.specificity(request).unwrap_or_default();
+                                if most_specific.map(|(s, _)| specificity > s).unwrap_or(true) {
+                                    most_specific = Some((specificity, #handler_indices));
+                                }
+                            }
+                        }
+                    )*
+                    if let Some(cors) = preflight_cors {
+                        CorsProcessor::process_request(cors, request, response);
+                        return;
+                    }
+                    match most_specific.map(|(_, i)| i) {
+                        #(
+                            Some(#handler_indices) => {
+                                if let Some(cors) = self.cross_origins[#handler_indices].as_ref() {
+                                    if !CorsProcessor::process_request(cors, request, response) {
+                                        return;
+                                    }
+                                }
+                                let request_helpers = &self.#arg_idents.context.request_context_data.request_context;
+                                let mut request_context = UserRequestContext::<#arg_types>::new_default();
+                                #read_bodies
+                                let declared: Vec<String> = vec![#(#produces.to_string()),*];
+                                let producible = if #streamings {
+                                    declared
+                                } else {
+                                    ContentNegotiator::restrict(&request_helpers.media_types(), &declared)
+                                };
+                                let media_type = if #streamings && producible.len() == 0 {
+                                    None
+                                } else {
+                                    match ContentNegotiator::negotiate_produces(request, &producible) {
+                                        Ok(media_type) => Some(media_type),
+                                        Err(e) => {
+                                            ContentNegotiator::write_negotiation_error(response, &e);
+                                            return;
+                                        }
+                                    }
+                                };
+                                let out = self.#arg_idents.do_action(
+                                    request, response,
+                                    &self.#arg_idents.context,
+                                    &mut Some(request_context.into())
+                                ).await;
+                                if !response.is_error() && response.is_streaming() {
+                                    if let Err(e) = ContentNegotiator::negotiate_stream(request, response, media_type.as_ref()) {
+                                        ContentNegotiator::write_negotiation_error(response, &e);
+                                        return;
+                                    }
+                                } else if !response.is_error() {
+                                    out.as_ref()
+                                        .and_then(|out| request_helpers.convert_from(out, request, media_type.clone()))
+                                        .map(|out| {
+                                            media_type.as_ref().map(|media_type| response.set_content_type_if_absent(media_type));
+                                            response.write(out.as_bytes());
+                                        });
+                                }
+                                if HttpMethod::from_method(&request.method) == Some(HttpMethod::Head) {
+                                    response.clear_body();
+                                }
+                                return;
+                            }
+                        )*
+                        _ => {}
+                    }
+                    write_unmatched_response(request, response, &allowed_methods);
+                }
+
+            }
+
+            #[web_framework_shared::async_trait]
+            impl web_framework_shared::dispatch_server::RequestDispatcher for AttributeHandlerMapping {
+                async fn dispatch(&self, request: &WebRequest, response: &mut WebResponse) {
+                    AttributeHandlerMapping::dispatch(self, request, response).await
+                }
             }
 
-            // Include the message converter tokens if we have any
-            // provide_default_message_converters!();
-            // #converter_tokens
+            #converter_module
 
 
         };
         ts.into()
     }
 
+    /// If the controller returns a ResponseEntity, the status and headers it provides are written
+    /// to the WebResponse, and only the body is converted by the MessageConverter. A
+    /// StreamingResponseBody, or a ResponseEntity containing one, is set as the body stream of the
+    /// WebResponse, which is not converted.
+    fn response_entity_writers(&self, arg_outputs: &Vec<Type>) -> Vec<TokenStream> {
+        arg_outputs.iter()
+            .map(|output| {
+                match (Self::is_response_entity(output), Self::is_streaming_output(output)) {
+                    (true, true) => quote! {
+                        out.as_ref().map(|out| {
+                            response.apply_response_entity(out);
+                            response.set_body_stream(out.body.clone());
+                        });
+                    },
+                    (true, false) => quote! {
+                        out.as_ref().map(|out| response.apply_response_entity(out));
+                    },
+                    (false, true) => quote! {
+                        out.as_ref().map(|out| response.set_body_stream(out.clone()));
+                    },
+                    (false, false) => quote! {}
+                }
+            })
+            .collect()
+    }
+
+    /// A StreamingResponseBody, or a ResponseEntity containing one.
+    fn is_streaming_output(output: &Type) -> bool {
+        Self::response_entity_body(output)
+            .map(|body| Self::is_streaming_response_body(&body))
+            .or(Some(Self::is_streaming_response_body(output)))
+            .unwrap()
+    }
+
+    fn is_streaming_response_body(ty: &Type) -> bool {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .map(|s| s.ident.to_string() == "StreamingResponseBody")
+                    .or(Some(false))
+                    .unwrap()
+            }
+            _ => false
+        }
+    }
+
+    fn response_entity_body(ty: &Type) -> Option<Type> {
+        if !Self::is_response_entity(ty) {
+            return None;
+        }
+        Self::first_generic_arg(ty)
+    }
+
+    fn is_response_entity(ty: &Type) -> bool {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .map(|s| s.ident.to_string() == "ResponseEntity")
+                    .or(Some(false))
+                    .unwrap()
+            }
+            _ => false
+        }
+    }
+
     // Has to be statements or else the {} will not allow the let statements
     fn reparse_method_logic(&self) -> Vec<Vec<&Stmt>> {
         let method_logic = self.controllers.iter()
@@ -586,114 +947,572 @@ impl HandlerMappingBuilder {
         method_logic
     }
 
-    fn parse_path_variable_args(&self) -> (Vec<Vec<Ident>>, Vec<Vec<String>>) {
-        let path_var_idents = self.get_path_var_idents();
-        let path_var_names = self.get_path_var_names();
-        (path_var_idents, path_var_names)
+    /// Every controller method with a mapping, in the order of the fields of the
+    /// AttributeHandlerMapping, so that the lists generated for each handler line up.
+    fn handlers(&self) -> Vec<HandlerInfo> {
+        self.controllers.iter()
+            .filter(|c| c.ant_path_request_matcher.len() != 0)
+            .flat_map(|c| c.arguments_resolved.first().map(|resolver| (c, resolver)))
+            .enumerate()
+            .map(|(i, (controller, resolver))| {
+                let request_body = resolver.request_body_arguments.first();
+                let output_type = ArgumentResolver::resolve_fn_arg_fn_output(&controller.method)
+                    .or(Some(parse_str::<Type>("()").unwrap()))
+                    .unwrap();
+                let (output_type, error_type) = Self::split_result_type(&output_type)
+                    .map(|(ok, err)| (ok, Some(err)))
+                    .or(Some((output_type, None)))
+                    .unwrap();
+                let (field, request_type) = match request_body {
+                    Some(request_body) => (
+                        Ident::new(request_body.inner.name.as_str(), Span::mixed_site()),
+                        request_body.request_serialize_type.clone()
+                    ),
+                    None => (
+                        Ident::new(format!("handler_{}", i).as_str(), Span::mixed_site()),
+                        parse_str::<Type>(format!("NoRequestBody{}", i).as_str()).unwrap()
+                    )
+                };
+                HandlerInfo { controller, resolver, request_body, field, request_type, output_type, error_type }
+            })
+            .collect()
     }
 
-    // The path variables will be kept in a map, so you have to have the names to get them from
-    // the map.
-    fn get_path_var_names(&self) -> Vec<Vec<String>> {
-        let path_var_names = self.controllers.iter()
-            .flat_map(|c| c.arguments_resolved.iter()
-                .filter(|a| {
-                    a.path_variable_arguments.len() != 0
-                })
-                .map(|a| a.path_variable_arguments.clone())
-                .map(|args| {
-                    args.iter().map(|args| {
-                        args.inner.name.clone()
-                    }).collect::<Vec<String>>()
-                })
-            )
-            .collect::<Vec<Vec<String>>>();
-        path_var_names
-    }
-
-    fn get_path_var_idents(&self) -> Vec<Vec<Ident>> {
-        let mut path_var_idents = self.controllers.iter()
-            .flat_map(|c| c.arguments_resolved.iter()
-                .map(|a| a.path_variable_arguments.clone())
-                .map(|args| {
-                    args.iter().flat_map(|args| {
-                        if args.inner.name.len() != 0 {
-                            return vec![Ident::new(args.inner.name.as_str(), Span::mixed_site())];
+    /// If the controller returns Result<T, E>, the output of the handler is T, and E is passed to
+    /// the exception handler.
+    fn parse_request_body_args(handlers: &Vec<HandlerInfo>) -> (Vec<Ident>, Vec<Type>, Vec<Type>, Vec<Type>, Vec<Option<Type>>) {
+        (
+            handlers.iter().map(|h| h.field.clone()).collect(),
+            handlers.iter().map(|h| h.request_type.clone()).collect(),
+            handlers.iter().map(|h| h.output_type.clone()).collect(),
+            handlers.iter().map(|h| h.controller.self_struct.clone()).collect(),
+            handlers.iter().map(|h| h.error_type.clone()).collect()
+        )
+    }
+
+    /// For each handler, the binding of the request body passed to the controller, and the reading
+    /// of the body in the dispatch, negotiated from the Content-Type with 415 if it is not
+    /// supported. A body that is missing or could not be deserialized is written with 400, so the
+    /// controller is never called without it. A handler without a #[request_body] does not read
+    /// the body.
+    fn request_body_tokens(handlers: &Vec<HandlerInfo>) -> (Vec<TokenStream>, Vec<TokenStream>) {
+        handlers.iter()
+            .map(|h| match h.request_body {
+                Some(_) => {
+                    let field = &h.field;
+                    let field_name = field.to_string();
+                    let consumes = &h.controller.consumes;
+                    (
+                        quote! {
+                            let #field = match handler.request_ctx_data.and_then(|request_ctx_data| request_ctx_data.request) {
+                                Some(request_body) => request_body,
+                                None => {
+                                    response.set_status(StatusCode::BAD_REQUEST);
+                                    response.set_content_type_if_absent("application/json");
+                                    serde_json::to_string(&ArgumentResolutionError::new(#field_name, RequestValueSource::RequestBody, "request body was missing or could not be read."))
+                                        .map(|err| response.write(err.as_bytes()))
+                                        .ok();
+                                    return None;
+                                }
+                            };
+                        },
+                        quote! {
+                            let consumable = ContentNegotiator::restrict(&request_helpers.media_types(), &vec![#(#consumes.to_string()),*]);
+                            if let Err(e) = ContentNegotiator::negotiate_consumes(request, &consumable) {
+                                ContentNegotiator::write_negotiation_error(response, &e);
+                                return;
+                            }
+                            request_context.request = request_helpers.convert_to(request)
+                                .and_then(|message| message.message);
                         }
-                        vec![]
-                    }).collect::<Vec<Ident>>()
-                })
-            )
-            .collect::<Vec<Vec<Ident>>>();
-        path_var_idents
+                    )
+                }
+                None => (quote! {}, quote! {})
+            })
+            .unzip()
     }
 
-    fn parse_request_body_args(&self) -> (Vec<Ident>, Vec<Type>, Vec<Type>, Vec<Type>) {
-        let args = self.controllers.iter()
-            .flat_map(|c| c.arguments_resolved.iter().filter(|a| {
-                a.request_body_arguments.len() != 0
-            }).map(|r| {
-                let args = r.request_body_arguments.get(0)
-                    .unwrap();
-                (Ident::new(args.inner.name.as_str(), Span::mixed_site()), args.request_serialize_type.clone(), args.output_type.clone().unwrap(), c.self_struct.clone())
-            }))
-            .collect::<Vec<(Ident, Type, Type, Type)>>();
-
-        //TODO: These should be Vec<Vec<*>> because there will be tuples for multiple values...
-        //  Then the tuple will be unwrapped accordingly.
-        let arg_idents = args.iter()
-            .map(|i| i.0.clone())
+    fn no_request_body_types(handlers: &Vec<HandlerInfo>) -> Vec<Type> {
+        handlers.iter()
+            .filter(|h| h.request_body.is_none())
+            .map(|h| h.request_type.clone())
+            .collect()
+    }
+
+    fn split_result_type(ty: &Type) -> Option<(Type, Type)> {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .filter(|s| s.ident.to_string() == "Result")
+                    .and_then(|s| match &s.arguments {
+                        PathArguments::AngleBracketed(args) => {
+                            let types = args.args.iter()
+                                .flat_map(|a| match a {
+                                    GenericArgument::Type(t) => vec![t.clone()],
+                                    _ => vec![]
+                                })
+                                .collect::<Vec<Type>>();
+                            if types.len() == 2 {
+                                Some((types[0].clone(), types[1].clone()))
+                            } else {
+                                None
+                            }
+                        }
+                        _ => None
+                    })
+            }
+            _ => None
+        }
+    }
+
+    /// Converts Option<Result<T, E>> returned from the controller into Option<T>, writing the
+    /// error to the response using the exception handler for E, if one exists. Otherwise the
+    /// response is a 500.
+    fn result_handlers(&self, arg_errors: &Vec<Option<Type>>) -> Vec<TokenStream> {
+        arg_errors.iter()
+            .map(|error_type| match error_type {
+                None => quote! {},
+                Some(error_type) => {
+                    let handle_error = self.exception_handler_for(error_type)
+                        .map(|(field, handler)| Self::exception_handler_tokens(&field, handler))
+                        .or(Some(quote! {
+                            response.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                        }))
+                        .unwrap();
+                    quote! {
+                        let out = match out {
+                            Some(Ok(out)) => Some(out),
+                            Some(Err(err)) => {
+                                #handle_error
+                                None
+                            }
+                            None => None
+                        };
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn exception_handler_tokens(advice_field: &Ident, handler: &ExceptionHandlerBean) -> TokenStream {
+        let method = &handler.method;
+        let apply_status = if handler.returns_response_entity {
+            quote! {
+                response.apply_response_entity(&handled);
+            }
+        } else {
+            let status = handler.status.or(Some(500)).unwrap();
+            quote! {
+                response.set_status(StatusCode::from_u16(#status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        };
+        quote! {
+            let handled = self.advices.#advice_field.#method(err);
+            #apply_status
+            response.set_content_type_if_absent("application/json");
+            serde_json::to_string(&handled)
+                .map(|handled| response.write(handled.as_bytes()))
+                .ok();
+        }
+    }
+
+    fn exception_handler_for(&self, error_type: &Type) -> Option<(Ident, &ExceptionHandlerBean)> {
+        let error_type = Self::last_segment_str(error_type);
+        let (advice_fields, advice_tys) = self.controller_advice_fields();
+        self.exception_handlers.iter()
+            .find(|e| Self::last_segment_str(&e.error_type) == error_type)
+            .and_then(|e| {
+                let advice_ty = SynHelper::get_str(&e.advice_type);
+                advice_tys.iter()
+                    .position(|a| SynHelper::get_str(a) == advice_ty)
+                    .map(|i| (advice_fields[i].clone(), e))
+            })
+    }
+
+    fn last_segment_str(ty: &Type) -> String {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .map(|s| SynHelper::get_str(s))
+                    .or(Some(SynHelper::get_str(ty)))
+                    .unwrap()
+            }
+            _ => SynHelper::get_str(ty)
+        }
+    }
+
+    fn controller_advice_fields(&self) -> (Vec<Ident>, Vec<Type>) {
+        let mut advice_tys: Vec<Type> = vec![];
+        for e in self.exception_handlers.iter() {
+            if !advice_tys.iter().any(|a| SynHelper::get_str(a) == SynHelper::get_str(&e.advice_type)) {
+                advice_tys.push(e.advice_type.clone());
+            }
+        }
+        let advice_fields = (0..advice_tys.len())
+            .map(|i| Ident::new(format!("advice_{}", i).as_str(), Span::call_site()))
             .collect::<Vec<Ident>>();
-        let arg_types = args.iter()
-            .map(|i| i.1.clone())
-            .collect::<Vec<Type>>();
-        let self_types = args.iter()
-            .map(|i| i.3.clone())
-            .collect::<Vec<Type>>();
-        (arg_idents, arg_types, args.iter().map(|i| i.2.clone()).collect::<Vec<Type>>(), self_types)
-    }
-
-    fn get_request_matcher_info(&self) -> (Vec<Vec<String>>, Vec<Vec<String>>) {
-        let to_match = self.controllers.iter()
-            .map(|m| m.ant_path_request_matcher.iter()
+        (advice_fields, advice_tys)
+    }
+
+    fn get_request_matcher_info(handlers: &Vec<HandlerInfo>) -> (Vec<Vec<String>>, Vec<Vec<String>>) {
+        let to_match = handlers.iter()
+            .map(|h| h.controller.ant_path_request_matcher.iter()
                 .flat_map(|r| r.request_matchers.iter()
                     .map(|r| r.to_match.clone())
                 ).collect::<Vec<String>>()
             ).collect::<Vec<Vec<String>>>();
-        let split = self.controllers.iter()
-            .map(|m| m.ant_path_request_matcher.iter()
+        let split = handlers.iter()
+            .map(|h| h.controller.ant_path_request_matcher.iter()
                 .flat_map(|r| r.request_matchers.iter()
                     .map(|r| r.splitter.clone())
                 ).collect::<Vec<String>>()
             ).collect::<Vec<Vec<String>>>();
         (to_match, split)
     }
+
+    fn get_request_method_info(handlers: &Vec<HandlerInfo>) -> Vec<Vec<TokenStream>> {
+        handlers.iter()
+            .map(|h| {
+                let mut methods: Vec<HttpMethod> = vec![];
+                h.controller.ant_path_request_matcher.iter()
+                    .flat_map(|r| r.methods.iter())
+                    .for_each(|method| if !methods.contains(method) {
+                        methods.push(*method);
+                    });
+                methods.iter()
+                    .map(|method| {
+                        let method = Ident::new(format!("{:?}", method).as_str(), Span::call_site());
+                        quote! { HttpMethod::#method }
+                    })
+                    .collect::<Vec<TokenStream>>()
+            })
+            .collect::<Vec<Vec<TokenStream>>>()
+    }
+
+    /// For each handler, the let statements resolving the header, cookie, query param, request
+    /// attribute and csrf token arguments, and the arguments to call the controller with, in the
+    /// order of the method signature.
+    fn resolved_argument_tokens(handlers: &Vec<HandlerInfo>) -> (Vec<Vec<TokenStream>>, Vec<Vec<TokenStream>>) {
+        handlers.iter()
+            .map(|h| (h.controller, h.resolver))
+            .map(|(c, resolver)| {
+                let mut bindings = vec![];
+                let mut call_args = vec![];
+                let named_values = resolver.query_param_arguments.iter().map(|q| (&q.inner, quote! {RequestValueSource::QueryParam}))
+                    .chain(resolver.request_header_arguments.iter().map(|h| (&h.inner, quote! {RequestValueSource::Header})))
+                    .chain(resolver.cookie_value_arguments.iter().map(|h| (&h.inner, quote! {RequestValueSource::Cookie})))
+                    .chain(resolver.request_attribute_arguments.iter().map(|h| (&h.inner, quote! {RequestValueSource::Attribute})))
+                    .chain(resolver.csrf_token_arguments.iter().map(|h| (&h.inner, quote! {RequestValueSource::Attribute})))
+                    .collect::<Vec<(&NamedValueInfo, TokenStream)>>();
+                if resolver.path_variable_arguments.len() != 0 {
+                    bindings.push(quote! {
+                        let path_variables = self.request_matchers.iter()
+                            .flat_map(|r| r.extract_path_variables(request))
+                            .next()
+                            .or(Some(std::collections::HashMap::new()))
+                            .unwrap();
+                    });
+                }
+                c.method.sig.inputs.iter()
+                    .flat_map(|i| match i {
+                        FnArg::Typed(typed) => SynHelper::get_fn_arg_ident_type(typed).map(|t| t.0),
+                        FnArg::Receiver(_) => None
+                    })
+                    .for_each(|ident| {
+                        if resolver.request_body_arguments.iter().any(|r| r.inner.arg_ident.as_ref() == Some(&ident)) {
+                            call_args.push(quote! { #ident });
+                        } else if let Some(path_variable) = resolver.path_variable_arguments.iter()
+                            .find(|p| p.inner.arg_ident.as_ref() == Some(&ident)) {
+                            let (binding, call_arg) = Self::path_variable_binding(&ident, &path_variable.inner);
+                            bindings.push(binding);
+                            call_args.push(call_arg);
+                        } else if let Some((info, source)) = named_values.iter()
+                            .find(|(info, _)| info.arg_ident.as_ref() == Some(&ident)) {
+                            let (binding, call_arg) = Self::named_value_binding(&ident, info, source);
+                            bindings.push(binding);
+                            call_args.push(call_arg);
+                        }
+                    });
+                (bindings, call_args)
+            })
+            .unzip()
+    }
+
+    fn named_value_binding(ident: &Ident, info: &NamedValueInfo, source: &TokenStream) -> (TokenStream, TokenStream) {
+        let arg_type = info.arg_type.clone().unwrap();
+        let (owned_type, is_reference) = Self::owned_type(&arg_type);
+        let name = info.name.as_str();
+        let default_value = if info.has_default_value() {
+            let default_value = info.default_value.as_str();
+            quote! { Some(#default_value) }
+        } else {
+            quote! { None }
+        };
+        let required = info.required;
+        let resolve = if info.multi_valued {
+            let inner = Self::first_generic_arg(&owned_type).unwrap();
+            quote! { RequestValueResolver::resolve_multi::<#inner>(request, #source, #name, #default_value, #required) }
+        } else if NamedValueInfo::is_type(&owned_type, "Option") && required {
+            let inner = Self::first_generic_arg(&owned_type).unwrap();
+            quote! { RequestValueResolver::resolve_required::<#inner>(request, #source, #name, #default_value).map(Some) }
+        } else if NamedValueInfo::is_type(&owned_type, "Option") {
+            let inner = Self::first_generic_arg(&owned_type).unwrap();
+            quote! { RequestValueResolver::resolve_optional::<#inner>(request, #source, #name, #default_value) }
+        } else {
+            quote! { RequestValueResolver::resolve_required::<#owned_type>(request, #source, #name, #default_value) }
+        };
+        Self::resolved_binding(ident, &owned_type, is_reference, resolve)
+    }
+
+    /// Path variables are always required, as the pattern would not have matched without them.
+    fn path_variable_binding(ident: &Ident, info: &NamedValueInfo) -> (TokenStream, TokenStream) {
+        let arg_type = info.arg_type.clone().unwrap();
+        let (owned_type, is_reference) = Self::owned_type(&arg_type);
+        let name = info.name.as_str();
+        let resolve = quote! { RequestValueResolver::resolve_path_variable::<#owned_type>(&path_variables, #name) };
+        Self::resolved_binding(ident, &owned_type, is_reference, resolve)
+    }
+
+    /// If the argument could not be resolved or converted, the ArgumentResolutionError is written
+    /// with 400.
+    fn resolved_binding(ident: &Ident, owned_type: &Type, is_reference: bool, resolve: TokenStream) -> (TokenStream, TokenStream) {
+        let binding = quote! {
+            let #ident: #owned_type = match #resolve {
+                Ok(value) => value,
+                Err(err) => {
+                    response.set_status(StatusCode::BAD_REQUEST);
+                    response.set_content_type_if_absent("application/json");
+                    serde_json::to_string(&err)
+                        .map(|err| response.write(err.as_bytes()))
+                        .ok();
+                    return None;
+                }
+            };
+        };
+        let call_arg = if is_reference {
+            quote! { &#ident }
+        } else {
+            quote! { #ident }
+        };
+        (binding, call_arg)
+    }
+
+    /// Arguments such as &str are resolved to an owned String, and passed by reference.
+    fn owned_type(ty: &Type) -> (Type, bool) {
+        match ty {
+            Type::Reference(reference) => {
+                let elem = reference.elem.deref();
+                if SynHelper::get_str(elem) == "str" {
+                    (parse_str::<Type>("String").unwrap(), true)
+                } else {
+                    (elem.clone(), true)
+                }
+            }
+            _ => (ty.clone(), false)
+        }
+    }
+
+    fn first_generic_arg(ty: &Type) -> Option<Type> {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .and_then(|segment| match &segment.arguments {
+                        PathArguments::AngleBracketed(args) => args.args.iter()
+                            .flat_map(|arg| match arg {
+                                GenericArgument::Type(ty) => Some(ty.clone()),
+                                _ => None
+                            })
+                            .next(),
+                        _ => None
+                    })
+            }
+            _ => None
+        }
+    }
+
+    /// For #[request_body] #[validated] arguments, the request body is validated before the
+    /// controller is called, and the ValidationErrors are written with 400.
+    fn request_body_validations(handlers: &Vec<HandlerInfo>) -> Vec<TokenStream> {
+        handlers.iter()
+            .map(|h| {
+                if !h.request_body.map(|r| r.validated).unwrap_or(false) {
+                    return quote! {};
+                }
+                quote! {
+                    let validation = handler.request_ctx_data.as_ref()
+                        .and_then(|r| r.request.as_ref())
+                        .map(|r| web_framework_shared::validation::Validate::validate(r));
+                    if let Some(Err(errors)) = validation {
+                        response.set_status(StatusCode::BAD_REQUEST);
+                        response.set_content_type_if_absent("application/json");
+                        serde_json::to_string(&errors)
+                            .map(|errors| response.write(errors.as_bytes()))
+                            .ok();
+                        return None;
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// async controller methods are awaited, so that they do not block the runtime.
+    fn controller_method_awaits(handlers: &Vec<HandlerInfo>) -> Vec<TokenStream> {
+        handlers.iter()
+            .map(|h| if h.controller.method.sig.asyncness.is_some() {
+                quote! { .await }
+            } else {
+                quote! {}
+            })
+            .collect::<Vec<TokenStream>>()
+    }
+
+    /// The produces and consumes of the mapping, for each handler.
+    fn mapping_media_type_info(handlers: &Vec<HandlerInfo>) -> (Vec<Vec<String>>, Vec<Vec<String>>) {
+        (
+            handlers.iter().map(|h| h.controller.produces.clone()).collect(),
+            handlers.iter().map(|h| h.controller.consumes.clone()).collect()
+        )
+    }
+
+    /// The CorsConfiguration of the #[cross_origin] of each handler, or None.
+    fn cross_origin_info(handlers: &Vec<HandlerInfo>) -> Vec<TokenStream> {
+        handlers.iter()
+            .map(|h| match h.controller.cross_origin.as_ref() {
+                Some(cross_origin) => {
+                    let cross_origin = cross_origin.as_str();
+                    quote! { Some(CorsConfiguration::parse(#cross_origin)) }
+                }
+                None => quote! { Option::<CorsConfiguration>::None }
+            })
+            .collect()
+    }
+
+    /// The name of the controller method, for each handler.
+    fn get_controller_method_idents(handlers: &Vec<HandlerInfo>) -> Vec<Ident> {
+        handlers.iter()
+            .map(|h| h.controller.method.sig.ident.clone())
+            .collect::<Vec<Ident>>()
+    }
 }
 
 impl HandlerMappingBuilder {
 
     pub(crate) fn create_request_matcher(attr: &Vec<Attribute>) -> Vec<AntPathRequestMatcher> {
-        SynHelper::get_attr_from_vec(&attr, &vec!["get_mapping", "post_mapping"])
-            .map(|attr| {
-                log_message!("{} is the controller mapping for creating HandlerMapping.", &attr);
-                attr.split(",")
-                    .map(|s| s.replace(" ", ""))
-                    .collect::<Vec<String>>()
+        Self::mapping_methods().iter()
+            .flat_map(|(mapping, method)| {
+                SynHelper::get_attr_from_vec(&attr, &vec![*mapping])
+                    .map(|attr| {
+                        log_message!("{} is the controller mapping for creating HandlerMapping.", &attr);
+                        Self::split_mapping_paths(&attr)
+                    })
+                    .or(Some(vec![]))
+                    .unwrap()
+                    .iter()
+                    .filter(|s| !Self::is_mapping_media_type_entry(s))
+                    .map(|s| AntPathRequestMatcher::new_with_methods(s.as_str(), "/", vec![*method]))
+                    .collect::<Vec<AntPathRequestMatcher>>()
             })
-            .or(Some(vec![]))
-            .unwrap()
-            .iter()
-            .map(|s| AntPathRequestMatcher::new(s.as_str(), "/"))
             .collect::<Vec<AntPathRequestMatcher>>()
     }
 
+    /// The produces or consumes of the mapping, which may contain several comma separated media
+    /// types, as in #[post_mapping(/one, consumes = "application/json, text/html")].
+    fn mapping_media_types(attr: &Vec<Attribute>, name: &str) -> Vec<String> {
+        Self::mapping_methods().iter()
+            .flat_map(|(mapping, _)| SynHelper::get_attr_from_vec(attr, &vec![*mapping]).into_iter())
+            .flat_map(|attr| Self::split_mapping_paths(&attr).into_iter())
+            .flat_map(|entry| entry.strip_prefix(name)
+                .and_then(|e| e.strip_prefix("="))
+                .map(|value| value.trim_matches('"')
+                    .split(",")
+                    .map(|m| m.to_string())
+                    .collect::<Vec<String>>())
+                .into_iter())
+            .flatten()
+            .filter(|m| m.len() != 0)
+            .collect()
+    }
+
+    fn cross_origin(attr: &Vec<Attribute>) -> Option<String> {
+        SynHelper::get_attr_from_vec(attr, &vec!["cross_origin"])
+    }
+
+    fn is_mapping_media_type_entry(entry: &str) -> bool {
+        entry.starts_with("produces=") || entry.starts_with("consumes=")
+    }
+
+    /// Splits #[get_mapping(/one, /two/{id:[0-9]{1,3}})] on the commas that are not within a
+    /// path variable or a quoted media type.
+    fn split_mapping_paths(attr: &str) -> Vec<String> {
+        let mut paths = vec![];
+        let mut current = String::default();
+        let mut depth = 0;
+        let mut quoted = false;
+        for c in attr.chars().filter(|c| *c != ' ') {
+            match c {
+                '"' => quoted = !quoted,
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 && !quoted => {
+                    paths.push(current.clone());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        paths.push(current);
+        paths.into_iter()
+            .filter(|p| p.len() != 0)
+            .collect::<Vec<String>>()
+    }
+
+    fn mapping_methods() -> Vec<(&'static str, HttpMethod)> {
+        vec![
+            ("get_mapping", HttpMethod::Get),
+            ("post_mapping", HttpMethod::Post),
+            ("put_mapping", HttpMethod::Put),
+            ("delete_mapping", HttpMethod::Delete),
+            ("patch_mapping", HttpMethod::Patch)
+        ]
+    }
+
 }
 
 struct ControllerBean {
     method: ImplItemMethod,
     ant_path_request_matcher: Vec<AntPathRequestMatcher>,
     arguments_resolved: Vec<ArgumentResolver>,
-    self_struct: syn::Type
+    self_struct: syn::Type,
+    /// Restricts the media types written by the MessageConverters, as in
+    /// #[get_mapping(/one, produces = "application/json")]. Empty if not provided.
+    produces: Vec<String>,
+    consumes: Vec<String>,
+    /// The arguments of #[cross_origin] on the method, or else on the impl block, parsed with
+    /// CorsConfiguration::parse when the mapping is created.
+    cross_origin: Option<String>
+}
+
+/// A controller method routed by the AttributeHandlerMapping. If the method does not have a
+/// #[request_body], the request type is a generated NoRequestBody struct.
+struct HandlerInfo<'a> {
+    controller: &'a ControllerBean,
+    resolver: &'a ArgumentResolver,
+    request_body: Option<&'a RequestBodyArgumentResolver>,
+    /// The field of the handler in the AttributeHandlerMapping.
+    field: Ident,
+    request_type: Type,
+    output_type: Type,
+    error_type: Option<Type>
+}
+
+struct ExceptionHandlerBean {
+    advice_type: syn::Type,
+    method: Ident,
+    error_type: syn::Type,
+    status: Option<u16>,
+    returns_response_entity: bool
 }
 
 struct MessageConverterBean {
@@ -702,6 +1521,9 @@ struct MessageConverterBean {
     response_type: Option<TokenStream>,
     media_type: Option<String>,
     alias: Option<String>,
+    /// If the converter struct has type parameters, the request and response types are provided
+    /// to it, as with JsonMessageConverter<Request, Response>.
+    generic: bool
 }
 
 
@@ -714,6 +1536,7 @@ impl Default for MessageConverterBean {
             response_type: None,
             media_type: None,
             alias: None,
+            generic: false
         }
     }
 }
@@ -1,23 +1,30 @@
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::fmt::{Debug, Display, Formatter, Pointer};
+use std::future::Future;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use futures::{Sink};
-use hyper::{Body, Method, Request, Response, Server};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use async_trait::async_trait;
 use hyper::body::HttpBody;
+use hyper::header::CONTENT_LENGTH;
 use hyper::server::conn::{AddrStream};
 use hyper::service::{make_service_fn, Service, service_fn};
 use serde::{Deserialize, Serialize};
 use serde::de::StdError;
+use web_framework::web_framework::context::{RequestContextData, UserRequestContext};
+use web_framework::web_framework::filter::filter::FilterChain;
 use web_framework::web_framework::http::{
     RequestConversionError,
     RequestConverter,
 };
-use web_framework_shared::{ContextData, Data, EndpointMetadata, HandlerExecutor};
+use web_framework_shared::{EndpointMetadata, RequestDispatcher};
 use web_framework_shared::http_method::HttpMethod;
-use web_framework_shared::request::{WebRequest};
+use web_framework_shared::method_security::MethodSecurity;
+use web_framework_shared::request::{ResponseWriter, WebRequest, WebResponse};
 
 use knockoff_logging::*;
 use lazy_static::lazy_static;
@@ -26,45 +33,52 @@ use codegen_utils::project_directory;
 use web_framework::web_framework::convert::{RequestTypeExtractor};
 import_logger_root!("lib.rs", concat!(project_directory!(), "/log_out/hyper_request.log"));
 
-pub struct HyperRequestStream<RequestT, ResponseT, RequestExecutorT, RequestConverterT, D, Ctx>
-where 
-    D: Data + Send + Sync + ?Sized,
-    Ctx: ContextData + Send + Sync + ?Sized,
+pub struct HyperRequestStream<RequestT, ResponseT, DispatcherT, RequestConverterT>
+where
     ResponseT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
     RequestT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
-    RequestExecutorT: HandlerExecutor<D, Ctx, RequestT, ResponseT> + Send + Sync,
-    RequestConverterT: RequestConverter<Request<Body>, RequestT, HyperBodyConvertError> + 'static + Send + Sync
+    DispatcherT: RequestDispatcher,
+    RequestConverterT: RequestConverter<Request<Body>, WebRequest, HyperBodyConvertError> + 'static + Send + Sync
 {
-    pub request_executor: Arc<RequestExecutorT>,
+    pub dispatcher: Arc<DispatcherT>,
     pub converter: Arc<RequestConverterT>,
-    pub response: PhantomData<ResponseT>,
-    pub request: PhantomData<RequestT>,
-    pub d: PhantomData<D>,
-    pub ctx: PhantomData<Ctx>
+    /// Run before the request is dispatched, for the session, security, CSRF, etc.
+    pub filter_chain: Arc<FilterChain<RequestT, ResponseT>>,
+    pub context: Arc<RequestContextData<RequestT, ResponseT>>,
+    pub addr: SocketAddr
 }
 
-impl <RequestT, ResponseT, RequestExecutorT, RequestConverterT, D, Ctx>
-HyperRequestStream<RequestT, ResponseT, RequestExecutorT, RequestConverterT, D, Ctx>
+impl <RequestT, ResponseT, DispatcherT, RequestConverterT>
+HyperRequestStream<RequestT, ResponseT, DispatcherT, RequestConverterT>
 where
-    D: Data + Send + Sync + ?Sized,
-    Ctx: ContextData + Send + Sync + ?Sized,
     ResponseT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
     RequestT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
-    RequestExecutorT: HandlerExecutor<D, Ctx, RequestT, ResponseT> + Send + Sync + 'static,
-    RequestConverterT: RequestConverter<Request<Body>, RequestT, HyperBodyConvertError> + 'static + Send + Sync
+    DispatcherT: RequestDispatcher + 'static,
+    RequestConverterT: RequestConverter<Request<Body>, WebRequest, HyperBodyConvertError> + 'static + Send + Sync
 
 {
     pub fn new(
-        request_executor: RequestExecutorT,
-        converter: RequestConverterT
+        dispatcher: DispatcherT,
+        converter: RequestConverterT,
+        filter_chain: FilterChain<RequestT, ResponseT>,
+        context: RequestContextData<RequestT, ResponseT>
+    ) -> Self {
+        Self::new_with_addr(dispatcher, converter, filter_chain, context, ([127, 0, 0, 1], 3000).into())
+    }
+
+    pub fn new_with_addr(
+        dispatcher: DispatcherT,
+        converter: RequestConverterT,
+        filter_chain: FilterChain<RequestT, ResponseT>,
+        context: RequestContextData<RequestT, ResponseT>,
+        addr: SocketAddr
     ) -> Self {
         HyperRequestStream {
-            request_executor: request_executor.into(),
+            dispatcher: dispatcher.into(),
             converter: converter.into(),
-            response: Default::default(),
-            request: Default::default(),
-            d: Default::default(),
-            ctx: Default::default(),
+            filter_chain: filter_chain.into(),
+            context: context.into(),
+            addr
         }
     }
 }
@@ -93,60 +107,131 @@ impl HyperRequestStreamError {
 impl StdError for HyperRequestStreamError {
 }
 
-impl <RequestT, ResponseT, RequestExecutorT, RequestConverterT, D, Ctx>
-HyperRequestStream<RequestT, ResponseT, RequestExecutorT, RequestConverterT, D, Ctx>
+impl <RequestT, ResponseT, DispatcherT, RequestConverterT>
+HyperRequestStream<RequestT, ResponseT, DispatcherT, RequestConverterT>
     where
-        D: Data + Send + Sync + ?Sized,
-        Ctx: ContextData + Send + Sync + ?Sized,
         ResponseT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
         RequestT: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
-        RequestExecutorT: HandlerExecutor<D, Ctx, RequestT, ResponseT> + Send + Sync + 'static,
-        RequestConverterT: RequestConverter<Request<Body>, RequestT, HyperBodyConvertError> + 'static + Send + Sync
+        DispatcherT: RequestDispatcher + 'static,
+        RequestConverterT: RequestConverter<Request<Body>, WebRequest, HyperBodyConvertError> + 'static + Send + Sync
 {
 
+    /// Runs the server until ctrl-c is received, after which in-flight requests are allowed to
+    /// finish before returning.
     pub async fn do_run(&mut self) {
-        // let addr = ([127, 0, 0, 1], 3000).into();
-
-        // let service = make_service_fn(|cnn: &AddrStream| {
-        //     let converter = self.converter.clone();
-        //     let request_executor = self.request_executor.clone();
-            // async move  {
-            //     Ok::<_, Error>(service_fn(move |requested| {
-            //         let converter = converter.clone();
-            //         let request_executor = request_executor.clone();
-            //         async move {
-            //             converter.from(requested).await
-            //                 .map_err(|e| {
-            //                     error!("Error in service function converting from request: {:?}", e);
-            //                     e
-            //                 })
-            //                 .ok()
-            //                 .map(|mut converted| {
-            //                     let web_response
-            //                         = request_executor.execute_handler(converted);
-            //                     serde_json::to_string::<ResponseT>(&web_response)
-            //                         .map(|res| Response::new(Body::from(res)))
-            //                         .map_err(|e| {
-            //                             error!("Error in service function converting response to json: {:?}", e);
-            //                             HyperRequestStreamError::new("Failed.")
-            //                         })
-            //                 })
-            //                 .or(Some(Err(HyperRequestStreamError::new("Failed to convert request using request converter."))))
-            //                 .unwrap()
-            //         }
-            //     }))
-            // }
-        // });
-
-        // let server = Server::bind(&addr)
-        //     .serve(service);
-        // 
-        // if let Err(e) = server.await {
-        //     error!("server error: {:?}", e);
-        // }
+        self.do_run_with_shutdown(shutdown_signal()).await
+    }
+
+    /// Runs the server until the provided future completes. Each request is converted to a
+    /// WebRequest with the RequestConverter, passed through the FilterChain and then to the
+    /// RequestDispatcher, and the WebResponse is written back to the client.
+    pub async fn do_run_with_shutdown<F>(&mut self, shutdown: F)
+    where
+        F: Future<Output = ()> + Send + 'static
+    {
+        let converter = self.converter.clone();
+        let dispatcher = self.dispatcher.clone();
+        let filter_chain = self.filter_chain.clone();
+        let context = self.context.clone();
+
+        let service = make_service_fn(move |_: &AddrStream| {
+            let converter = converter.clone();
+            let dispatcher = dispatcher.clone();
+            let filter_chain = filter_chain.clone();
+            let context = context.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |requested| {
+                    let converter = converter.clone();
+                    let dispatcher = dispatcher.clone();
+                    let filter_chain = filter_chain.clone();
+                    let context = context.clone();
+                    // the authentication set by the security filters is only visible to this request.
+                    MethodSecurity::scope(async move {
+                        Ok::<_, Infallible>(Self::handle_request(converter, dispatcher, filter_chain, context, requested).await)
+                    })
+                }))
+            }
+        });
+
+        info!("Starting server on {}.", &self.addr);
+
+        let server = Server::bind(&self.addr)
+            .serve(service)
+            .with_graceful_shutdown(shutdown);
+
+        if let Err(e) = server.await {
+            error!("server error: {:?}", e);
+        }
+
+        info!("Server on {} shut down.", &self.addr);
+    }
+
+    /// The filters may write the whole response, as for a CORS preflight or a failed
+    /// authentication, in which case the request is not dispatched. The dispatcher writes the body
+    /// with the MessageConverter negotiated for the request.
+    async fn handle_request(
+        converter: Arc<RequestConverterT>,
+        dispatcher: Arc<DispatcherT>,
+        filter_chain: Arc<FilterChain<RequestT, ResponseT>>,
+        context: Arc<RequestContextData<RequestT, ResponseT>>,
+        requested: Request<Body>
+    ) -> Response<Body> {
+        match converter.from(requested).await {
+            Ok(web_request) => {
+                let mut web_response = WebResponse::default();
+                let mut request_context = Some(UserRequestContext::new_default().into());
+                filter_chain.dispatch(web_request, &mut web_response, &context, &mut request_context, dispatcher.as_ref()).await;
+                to_hyper_response(web_response)
+            }
+            Err(e) => {
+                error!("Error in service function converting from request: {:?}", e);
+                Self::error_response(StatusCode::BAD_REQUEST)
+            }
+        }
+    }
+
+    fn error_response(status: StatusCode) -> Response<Body> {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = status;
+        response
     }
 }
 
+/// Writes the bytes accumulated in the WebResponse as the body of the hyper Response, along with
+/// the status and headers. If the handler provided a StreamingResponseBody, it is written with
+/// chunked transfer encoding as the chunks are produced, without buffering the body.
+pub(crate) fn to_hyper_response(mut web_response: WebResponse) -> Response<Body> {
+    let body = match web_response.take_body_stream() {
+        Some(body_stream) => {
+            web_response.headers.remove(CONTENT_LENGTH);
+            Body::wrap_stream(body_stream)
+        }
+        None => {
+            let body = web_response.response_bytes()
+                .map_err(|e| {
+                    error!("Error reading bytes from web response: {}", e);
+                    e
+                })
+                .unwrap_or_default();
+            Body::from(body)
+        }
+    };
+    web_response.set_content_type_if_absent("application/json");
+    let mut response = Response::new(body);
+    *response.status_mut() = web_response.status;
+    *response.headers_mut() = web_response.headers;
+    response
+}
+
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        // returning would shut the server down as soon as it started.
+        error!("Failed to install ctrl-c handler: {:?}", e);
+        std::future::pending::<()>().await;
+    }
+    info!("Received shutdown signal, shutting down server.");
+}
+
 #[derive(Debug, Default)]
 pub struct Error;
 
@@ -218,7 +303,8 @@ impl <'a> RequestConverter<Request<Body>, WebRequest, HyperBodyConvertError> for
                         body: s,
                         uri,
                         method,
-                        endpoint_metadata
+                        endpoint_metadata,
+                        attributes: HashMap::new()
                     }
                 })
             })
@@ -238,10 +324,10 @@ impl RequestTypeExtractor<Request<Body>, EndpointMetadata> for HyperRequestExtra
 pub(crate) fn to_endpoint_metadata(request: &Request<Body>) -> EndpointMetadata {
     EndpointMetadata {
         path_variables: split_path_variables(request.uri().path()),
-        query_params: request.uri().path_and_query().iter()
-            .flat_map(|p| p.query().into_iter())
-            .flat_map(|q| split_query_params(q))
-            .collect::<HashMap<String, String>>(),
+        query_params: request.uri().query()
+            .map(|q| split_query_params(q))
+            .or(Some(HashMap::new()))
+            .unwrap(),
         host: request.uri().host().map(|h| h.to_string())
             .or(Some(String::default())).unwrap(),
     }
@@ -258,23 +344,7 @@ pub(crate) fn split_path_variables(in_string: &str) -> HashMap<usize, String> {
         .collect::<HashMap<usize, String>>()
 }
 
-pub(crate) fn split_query_params(in_string: &str) -> HashMap<String, String> {
-    in_string.split("&").into_iter()
-        .flat_map(|query_item| {
-            let mut query_item = query_item.split("=").into_iter()
-                .map(|query_items| query_items.to_string())
-                .collect::<Vec<String>>();
-            if query_item.len() == 0 {
-                vec![]
-            } else if query_item.len() != 2 {
-                error!("Query item found that should have had two parameters, one=two, but was {:?}", &query_item);
-                vec![]
-            } else {
-                let key = query_item.remove(0);
-                let value = query_item.remove(0);
-                vec![(key, value)]
-            }
-        }).collect::<HashMap<String, String>>()
+pub(crate) fn split_query_params(in_string: &str) -> HashMap<String, Vec<String>> {
+    EndpointMetadata::split_query_params(in_string)
 }
 
-
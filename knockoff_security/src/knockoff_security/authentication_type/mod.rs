@@ -39,8 +39,8 @@ pub struct AuthHelper;
 
 impl AuthHelper {
     fn get_authorization_header_split(request: &WebRequest, auth_header_name: Option<String>) -> Option<String> {
-        if request.headers.contains_key("Authorization") {
-            let auth_string = request.headers["Authorization"].clone();
+        if let Some(auth_string) = request.header("Authorization") {
+            let auth_string = auth_string.clone();
             return auth_header_name.map(|auth_header_name| {
                 if auth_string.contains(&auth_header_name.to_lowercase()) {
                     return Self::parse_auth_token(
@@ -93,27 +93,47 @@ pub trait AuthType: AuthenticationAware + Send + Sync + Default {
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct JwtToken {
     pub token: String,
+    /// Set by the AuthenticationProvider once the signature has been checked.
+    #[serde(default)]
+    pub principal: Option<String>,
 }
 
+/// The authorities and the principal are only trusted once the signature has been checked by the
+/// AuthenticationProvider, so before then the token has no authorities, and the principal is the
+/// unverified sub claim, or None if the token can't be parsed or has no sub claim.
 impl JwtToken {
+    pub fn new(token: String) -> Self {
+        Self { token, principal: None }
+    }
+
     pub fn get_authorities(&self) -> Vec<GrantedAuthority> {
-        todo!()
+        vec![]
     }
 
     pub fn get_credentials(&self) -> Option<String> {
-        todo!()
+        Some(self.token.clone())
     }
 
     pub fn get_principal(&self) -> Option<String> {
-        todo!()
+        self.principal.clone()
+            .or_else(|| self.unverified_claims()
+                .and_then(|claims| claims.get("sub").and_then(|sub| sub.as_str()).map(|sub| sub.to_string())))
     }
 
     pub fn set_credentials(&mut self, credential: String) {
-        todo!()
+        self.token = credential;
     }
 
     pub fn set_principal(&mut self, principal: String) {
-        todo!()
+        self.principal = Some(principal);
+    }
+
+    /// The claims of the payload, without checking the signature.
+    pub fn unverified_claims(&self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        self.token.split(".")
+            .nth(1)
+            .and_then(|payload| general_purpose::URL_SAFE_NO_PAD.decode(payload.trim_end_matches("=")).ok())
+            .and_then(|payload| serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(payload.as_slice()).ok())
     }
 }
 
@@ -129,7 +149,7 @@ impl JwtToken {
     pub fn parse_credentials_jwt(request: &WebRequest) -> Result<JwtToken, AuthenticationConversionError> {
         AuthHelper::get_authorization_header_split(request, Some(String::from("Bearer")))
             .map(|bearer_token| {
-                return Ok(JwtToken{ token: bearer_token})
+                return Ok(JwtToken::new(bearer_token))
             })
             .or(Some(Err(AuthenticationConversionError::new(String::from("Bearer token did not exist.")))))
             .unwrap()
@@ -239,31 +259,35 @@ pub struct UsernamePassword {
     pub password: String,
 }
 
+/// The authorities are loaded by the AuthenticationProvider once the password has been checked.
 impl UsernamePassword {
     pub fn get_authorities(&self) -> Vec<GrantedAuthority> {
-        todo!()
+        vec![]
     }
 
     pub  fn get_credentials(&self) -> Option<String> {
-        todo!()
+        Some(self.password.clone())
     }
 
     pub fn get_principal(&self) -> Option<String> {
-        todo!()
+        Some(self.username.clone())
     }
 
     pub  fn set_credentials(&mut self, credential: String) {
-        todo!()
+        self.password = credential;
     }
 
     pub  fn set_principal(&mut self, principal: String) {
-        todo!()
+        self.username = principal;
     }
 }
 
 impl Default for UsernamePassword {
     fn default() -> Self {
-        todo!()
+        Self {
+            username: String::default(),
+            password: String::default(),
+        }
     }
 }
 
@@ -292,10 +316,26 @@ impl UsernamePassword {
             .unwrap()
     }
 
+    /// The credentials are base64(username:password), where the username cannot contain a colon.
+    /// Credentials with the username and password encoded separately, base64(username):base64(password),
+    /// are also accepted.
     fn parse_username_password(auth_string: String) -> Result<UsernamePassword, AuthenticationConversionError> {
+        if !auth_string.contains(":") {
+            return general_purpose::STANDARD.decode(auth_string.as_bytes())
+                .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(auth_string.trim_end_matches("=").as_bytes()))
+                .map_err(|e| AuthenticationConversionError::new(e.to_string()))
+                .and_then(|decoded| String::from_utf8(decoded).map_err(|e| AuthenticationConversionError::new(e.to_string())))
+                .and_then(|decoded| decoded.split_once(":")
+                    .map(|(username, password)| UsernamePassword { username: username.to_string(), password: password.to_string() })
+                    .ok_or(AuthenticationConversionError::new(String::from("Basic credentials did not contain a colon."))));
+        }
+
         let mut auth_header = auth_string.as_str();
 
         let found = auth_header.split(":").collect::<Vec<&str>>();
+        if found.len() != 2 {
+            return Err(AuthenticationConversionError::new(String::from("Basic credentials were not username:password.")));
+        }
 
         let username64 = found[0];
         let password64 = found[1];
@@ -1,7 +1,7 @@
 use std::io::Bytes;
 use web_framework_shared::request::WebRequest;
 use base64::{Engine as _, engine::general_purpose};
-use crate::knockoff_security::authentication_type::{AuthHelper, UsernamePassword};
+use crate::knockoff_security::authentication_type::{AuthHelper, JwtToken, UsernamePassword};
 
 #[test]
 fn test_authorization_header_split() {
@@ -20,6 +20,35 @@ fn test_username_password_parse_creds() {
     assert_eq!(parsed.as_ref().unwrap().password, "456");
 }
 
+#[test]
+fn test_username_password_parse_basic_credentials() {
+    let mut web_request = WebRequest::default();
+    let credentials = general_purpose::STANDARD.encode("user:pass:word");
+    web_request.headers.insert("authorization".to_string(), format!("Basic {}", credentials));
+    let parsed = UsernamePassword::parse_credentials(&web_request).unwrap();
+    assert_eq!(parsed.get_principal(), Some("user".to_string()));
+    assert_eq!(parsed.get_credentials(), Some("pass:word".to_string()));
+
+    web_request.headers.insert("authorization".to_string(), format!("Basic {}", general_purpose::STANDARD.encode("user")));
+    assert!(UsernamePassword::parse_credentials(&web_request).is_err());
+    assert!(UsernamePassword::parse_credentials(&WebRequest::default()).is_err());
+    assert_eq!(UsernamePassword::default().username, "");
+}
+
+#[test]
+fn test_jwt_unverified_principal() {
+    let payload = general_purpose::URL_SAFE_NO_PAD.encode("{\"sub\":\"user\",\"scope\":\"read\"}");
+    let mut jwt = JwtToken::new(format!("header.{}.signature", payload));
+    assert_eq!(jwt.get_principal(), Some("user".to_string()));
+    assert_eq!(jwt.get_credentials(), Some(jwt.token.clone()));
+    assert!(jwt.get_authorities().is_empty());
+    assert_eq!(JwtToken::new("not a jwt".to_string()).get_principal(), None);
+    let no_sub = general_purpose::URL_SAFE_NO_PAD.encode("{\"scope\":\"read\"}");
+    assert_eq!(JwtToken::new(format!("header.{}.signature", no_sub)).get_principal(), None);
+    jwt.set_principal("verified".to_string());
+    assert_eq!(jwt.get_principal(), Some("verified".to_string()));
+}
+
 fn test_auth_header_split_template(username: &str, password: &str, header: &str, out: &str) {
     let split = AuthHelper::get_authorization_header_split(&test_web_request(username, password), Some(header.to_string()));
     assert!(split.is_some());
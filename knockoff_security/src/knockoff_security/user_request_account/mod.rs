@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::linked_list::LinkedList;
 use std::collections::HashMap;
 use data_framework::Entity;
+use web_framework_shared::authority::GrantedAuthority;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct UserSession {
@@ -14,10 +15,27 @@ pub struct SessionData {
     session_data: HashMap<String, String>,
 }
 
+impl SessionData {
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.session_data.get(key)
+    }
+
+    pub fn insert(&mut self, key: &str, value: &str) -> Option<String> {
+        self.session_data.insert(key.to_string(), value.to_string())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.session_data.remove(key)
+    }
+}
+
 pub trait UserAccount: Entity<String> + Clone {
     fn get_account_data(&self) -> AccountData;
     fn login(&self);
     fn get_password(&self) -> String;
+    /// The authorities of the account, copied into the AuthenticationToken when it is
+    /// authenticated.
+    fn get_authorities(&self) -> Vec<GrantedAuthority>;
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
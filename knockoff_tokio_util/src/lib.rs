@@ -1,10 +1,21 @@
 use std::future::Future;
 use std::hash::Hash;
-use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
 use std::collections::{HashMap, HashSet};
 
+/// Runs the future to completion from synchronous code. Inside a multi-thread tokio runtime the
+/// worker is handed off with block_in_place, so the future can use the runtime's IO and timers.
+/// Inside a current_thread runtime the only worker is blocked while the future runs, so a future
+/// waiting on the runtime, such as a tokio IO or timer future, never completes. The async
+/// #[post_construct] and #[pre_destroy] methods must therefore not wait on the runtime when the
+/// AppCtx is created or closed from a current_thread runtime.
 pub fn run_blocking<F: Future>(fn_to_run: F) -> F::Output {
-    futures::executor::block_on(fn_to_run)
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(fn_to_run))
+        }
+        _ => futures::executor::block_on(fn_to_run)
+    }
 }
 
 pub fn get_create_runtime<F: Future>(fn_to_run: F) -> F::Output {
@@ -24,6 +24,9 @@ impl ContextBuilder {
     pub fn build_token_stream(parse_container: &mut ParseContainer) -> TokenStream {
         parse_container.log_app_container_info();
         ParseContainerBuilder::build_parse_container(parse_container);
+        if let Err(errors) = ParseContainerBuilder::is_valid_ordering_create(parse_container) {
+            return errors.to_compile_error();
+        }
         let ctx
             = ApplicationContextGenerator::create_context_generator(&mut parse_container.profile_tree);
         ctx.generate_token_stream()
@@ -1,5 +1,6 @@
 use std::any::{Any, TypeId};
 use std::sync::{Arc, Mutex};
+pub use knockoff_tokio_util::run_blocking;
 
 /**
 This is the runtime application context.
@@ -8,6 +9,9 @@ pub trait ApplicationContext {
     fn new() -> Self;
     fn get_bean_for_profile<T: Any + Send + Sync, P: Profile>(&self) -> Option<Arc<T>>;
     fn get_bean<T: Any + Send + Sync>(&self) -> Option<Arc<T>>;
+    /// Calls the #[pre_destroy] methods of the beans, in reverse dependency order. Also called when
+    /// the context is dropped, and the methods are only called the first time.
+    fn close(&mut self);
 }
 
 pub trait Profile {
@@ -28,3 +32,34 @@ pub trait ContainsBeans {
     fn contains_type<T: 'static + Send + Sync>(&self) -> bool;
     fn contains_mutable_type<T: 'static + Send + Sync>(&self) -> bool;
 }
+
+/// Implemented for each #[prototype] bean with #[runtime_arg] fields. Args is the type of the
+/// single runtime arg, or a tuple of them in the order they are declared. The autowired fields are
+/// got from the BeanFactory, so the prototype shares the singletons of the context.
+pub trait RuntimeArgPrototype {
+    type Args;
+    type BeanFactory;
+    fn create_prototype(bean_factory: &Self::BeanFactory, args: Self::Args) -> Self;
+}
+
+/// Autowired to create a new T each time get_object is called, with the #[runtime_arg] fields
+/// set to the args and the other fields autowired from the bean factory it was created with.
+pub struct ObjectProvider<T: RuntimeArgPrototype> {
+    bean_factory: Arc<T::BeanFactory>
+}
+
+impl<T: RuntimeArgPrototype> ObjectProvider<T> {
+    pub fn new(bean_factory: Arc<T::BeanFactory>) -> Self {
+        Self { bean_factory }
+    }
+
+    pub fn get_object(&self, args: T::Args) -> T {
+        T::create_prototype(&self.bean_factory, args)
+    }
+}
+
+impl<T: RuntimeArgPrototype> Clone for ObjectProvider<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.bean_factory.clone())
+    }
+}
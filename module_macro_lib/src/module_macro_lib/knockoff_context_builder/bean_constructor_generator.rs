@@ -62,6 +62,9 @@ impl BeanConstructorGenerator {
                 prototype_abstract_mutable_identifiers, prototype_abstract_mutable_field_types, prototype_concrete_mutable_abstract)
                 = bean_factory_info.get_field_prototype_types();
             info!("Creating constructor for {:?}", SynHelper::get_str(&struct_type));
+            let (collection_field_idents, collection_field_types) = bean_factory_info.get_collection_field_types();
+            let (object_provider_field_idents, object_provider_field_types) = bean_factory_info.get_object_provider_field_types();
+            let (runtime_arg_field_idents, runtime_arg_field_types) = bean_factory_info.get_runtime_arg_field_types();
             let default_type = bean_factory_info.default_field_info
                 .iter()
                 .map(|f| &f.field_type)
@@ -81,6 +84,9 @@ impl BeanConstructorGenerator {
                     #(#prototype_mutable_identifiers: Mutex<#prototype_mutable_field_types>,)*
                     #(#prototype_abstract_field_idents: #prototype_abstract_field_types,)*
                     #(#prototype_abstract_mutable_identifiers: Mutex<Box<#prototype_abstract_mutable_field_types>>,)*
+                    #(#collection_field_idents: #collection_field_types,)*
+                    #(#object_provider_field_idents: #object_provider_field_types,)*
+                    #(#runtime_arg_field_idents: #runtime_arg_field_types,)*
                 ) -> Self {
                     Self {
                         #(#default_ident: #default_type::default(),)*
@@ -92,6 +98,9 @@ impl BeanConstructorGenerator {
                         #(#prototype_mutable_identifiers,)*
                         #(#prototype_abstract_field_idents,)*
                         #(#prototype_abstract_mutable_identifiers,)*
+                        #(#collection_field_idents,)*
+                        #(#object_provider_field_idents,)*
+                        #(#runtime_arg_field_idents,)*
                     }
                 }
             }
@@ -50,10 +50,20 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
                     type U = #concrete_type;
                     fn get_bean(listable_bean_factory: &ListableBeanFactory) -> BeanDefinition<#concrete_type> {
 
+                        if let Some(inner) = listable_bean_factory.get_singleton::<#concrete_type>() {
+                            return Self {
+                                inner
+                            };
+                        }
+
                         #create_bean_tokens
 
+                        let inner = Arc::new(inner);
+                        listable_bean_factory.register_pre_destroy(&inner, pre_destroy);
+                        listable_bean_factory.add_singleton(&inner);
+
                         Self {
-                            inner: Arc::new(inner)
+                            inner
                         }
                     }
 
@@ -114,10 +124,21 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
 
                 fn get_bean(listable_bean_factory: &ListableBeanFactory) -> BeanDefinition<#concrete_type> {
 
+                    // shares the bean with the concrete type, so it is only created once.
+                    if let Some(inner) = listable_bean_factory.get_singleton::<#concrete_type>() {
+                        return BeanDefinition {
+                            inner
+                        };
+                    }
+
                     #create_bean_tokens
 
+                    let inner = Arc::new(inner);
+                    listable_bean_factory.register_pre_destroy(&inner, pre_destroy);
+                    listable_bean_factory.add_singleton(&inner);
+
                     BeanDefinition {
-                        inner: Arc::new(inner)
+                        inner
                     }
                 }
 
@@ -160,6 +181,16 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
         }
     }
 
+    /// A prototype with #[runtime_arg] fields can only be created with the args, so it has no
+    /// BeanFactory, and only the PrototypeBeanFactoryGenerator generates its create fn.
+    fn runtime_arg_factory<ConcreteTypeT: ToTokens>(concrete_type: &ConcreteTypeT,
+                                                    bean_factory_info: &BeanFactoryInfo,
+                                                    create_bean_tokens: TokenStream) -> TokenStream {
+        log_message!("Skipping factories for {}, as it is created with #[runtime_arg] fields.",
+            SynHelper::get_str(concrete_type));
+        TokenStream::default()
+    }
+
     fn create_concrete_bean_factories_for_bean(
         bean_factory_info: &BeanFactoryInfo
     ) -> TokenStream {
@@ -169,6 +200,13 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
 
         info!("Creating concrete factories for bean.");
 
+        if bean_factory_info.has_runtime_args() {
+            return Self::create_bean_tokens(bean_factory_info, profile_ident, &concrete_type)
+                .map(|create_bean_tokens| Self::runtime_arg_factory(&concrete_type, bean_factory_info, create_bean_tokens))
+                .or(Some(TokenStream::default()))
+                .unwrap();
+        }
+
         Self::create_bean_tokens(bean_factory_info, profile_ident, &concrete_type)
             .map(|create_bean_tokens| {
                 info!("Creating concrete bean factory tokens.");
@@ -205,6 +243,12 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
 
         info!("Creating abstract bean factories.");
 
+        if bean_factory_info.has_runtime_args() {
+            log_message!("Skipping abstract factories for {}, as it is created with #[runtime_arg] fields.",
+                SynHelper::get_str(abstract_type));
+            return TokenStream::default();
+        }
+
         Self::create_bean_tokens(bean_factory_info, profile_ident, &concrete_type)
             .map(|create_bean_tokens| {
 
@@ -318,6 +362,13 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
         log_message!("{} is number of abstract idents, {} is number of abstract field types.", abstract_field_idents.len(), abstract_field_types.len());
         log_message!("{} is number of abstract mutable idents, {} is number of abstract mutable field types.", abstract_mutable_idents.len(), abstract_mutable_field_types.len());
 
+        let collection_tokens = bean_factory_info.collection_tokens(profile_ident);
+        let (collection_field_idents, _) = bean_factory_info.get_collection_field_types();
+        let object_provider_tokens = bean_factory_info.object_provider_tokens();
+        let (object_provider_field_idents, _) = bean_factory_info.get_object_provider_field_types();
+        let (runtime_arg_field_idents, _) = bean_factory_info.get_runtime_arg_field_types();
+        let lifecycle_tokens = bean_factory_info.lifecycle_tokens(concrete_type);
+
         let create_beans_tokens = quote! {
                 #(
                     let bean_def: BeanDefinition<#field_types> = <ListableBeanFactory as BeanFactory<#field_types, #profile_ident >>::get_bean(listable_bean_factory);
@@ -360,12 +411,19 @@ pub trait BeanFactoryGenerator: TokenStreamGenerator {
                     let #prototype_abstract_mutable_idents = Mutex::new(bean_def);
                 )*
 
+                #collection_tokens
+                #object_provider_tokens
+
                 let inner = #concrete_type::new(
                     #(#prototype_field_idents,)* #(#prototype_mutable_identifiers,)*
                     #(#prototype_abstract_field_idents,)* #(#prototype_abstract_mutable_idents,)*
                     #(#field_idents,)* #(#mutable_identifiers,)*
                     #(#abstract_field_idents,)* #(#abstract_mutable_idents,)*
+                    #(#collection_field_idents,)*
+                    #(#object_provider_field_idents,)* #(#runtime_arg_field_idents,)*
                 );
+
+                #lifecycle_tokens
         };
 
         create_beans_tokens.into()
@@ -73,6 +73,10 @@ impl BeanFactoryGenerator for FactoryFnBeanFactoryGenerator {
         log_message!("{} is number of abstract idents, {} is number of abstract field types.", abstract_field_idents.len(), abstract_field_types.len());
         log_message!("{} is number of abstract mutable idents, {} is number of abstract mutable field types.", abstract_mutable_idents.len(), abstract_mutable_field_types.len());
 
+        let collection_tokens = bean_factory_info.collection_tokens(profile_ident);
+        let object_provider_tokens = bean_factory_info.object_provider_tokens();
+        let lifecycle_tokens = bean_factory_info.lifecycle_tokens(concrete_type);
+
         let create_beans_tokens = quote! {
                 #(
                     let bean_def: BeanDefinition<#field_types> = <ListableBeanFactory as BeanFactory<#field_types, #profile_ident >>::get_bean(listable_bean_factory);
@@ -114,9 +118,14 @@ impl BeanFactoryGenerator for FactoryFnBeanFactoryGenerator {
                         );
                     let #prototype_abstract_mutable_idents = Mutex::new(bean_def);
                 )*
+                #collection_tokens
+                #object_provider_tokens
+
                 let inner = #factory_fn(
                     #(#fn_args,)*
                 );
+
+                #lifecycle_tokens
         };
 
         create_beans_tokens.into()
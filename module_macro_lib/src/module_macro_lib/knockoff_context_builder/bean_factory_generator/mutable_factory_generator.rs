@@ -44,10 +44,20 @@ impl BeanFactoryGenerator for MutableBeanFactoryGenerator {
                 type U = Mutex<#concrete_type>;
                 fn get_bean(listable_bean_factory: &ListableBeanFactory) -> MutableBeanDefinition<Mutex<#concrete_type >> {
 
+                    if let Some(inner) = listable_bean_factory.get_singleton::<Mutex<#concrete_type>>() {
+                        return Self {
+                            inner
+                        };
+                    }
+
                     #create_bean_tokens
 
+                    let inner = Arc::new(Mutex::new(inner));
+                    listable_bean_factory.register_mutable_pre_destroy(&inner, pre_destroy);
+                    listable_bean_factory.add_singleton(&inner);
+
                     Self {
-                        inner: Arc::new(Mutex::new(inner))
+                        inner
                     }
                 }
 
@@ -105,11 +115,20 @@ impl BeanFactoryGenerator for MutableBeanFactoryGenerator {
                 type U = Mutex<Box<dyn #abstract_type>>;
                 fn get_bean(listable_bean_factory: &ListableBeanFactory) -> MutableBeanDefinition<Self::U> {
 
+                    if let Some(inner) = listable_bean_factory.get_singleton::<Mutex<Box<dyn #abstract_type>>>() {
+                        return MutableBeanDefinition {
+                            inner
+                        };
+                    }
+
                     #create_bean_tokens
 
+                    // the concrete type is erased in the Box, so the #[pre_destroy] methods are only
+                    // called for the beans injected as the concrete type, Mutex<T> or Arc<dyn Trait>.
                     let m = MutableBeanDefinition {
                         inner: Arc::new(Mutex::new(Box::new(inner) as Box<dyn #abstract_type>))
                     };
+                    listable_bean_factory.add_singleton(&m.inner);
                     m
                 }
 
@@ -26,8 +26,44 @@ impl TokenStreamGenerator for PrototypeBeanFactoryGenerator {
 }
 
 /// MutablePrototypeBeanFactoryGenerator not necessary because they'll prototype bean.
+/// Prototype beans are owned by the caller, so the #[pre_destroy] methods are not registered.
 impl BeanFactoryGenerator for PrototypeBeanFactoryGenerator {
 
+    /// Generates create(listable_bean_factory, args...), taking the #[runtime_arg] fields in the
+    /// order they are declared, and the RuntimeArgPrototype impl the ObjectProvider calls with its
+    /// ListableBeanFactory and the args as a tuple, or the single arg.
+    fn runtime_arg_factory<ConcreteTypeT: ToTokens>(concrete_type: &ConcreteTypeT,
+                                                    bean_factory_info: &BeanFactoryInfo,
+                                                    create_bean_tokens: TokenStream) -> TokenStream {
+        let (runtime_arg_field_idents, runtime_arg_field_types) = bean_factory_info.get_runtime_arg_field_types();
+        let (args_type, args_pattern) = if runtime_arg_field_idents.len() == 1 {
+            let (runtime_arg_field_ident, runtime_arg_field_type) = (&runtime_arg_field_idents[0], &runtime_arg_field_types[0]);
+            (quote! { #runtime_arg_field_type }, quote! { #runtime_arg_field_ident })
+        } else {
+            (quote! { (#(#runtime_arg_field_types),*) }, quote! { (#(#runtime_arg_field_idents),*) })
+        };
+        quote! {
+            impl #concrete_type {
+                pub fn create(listable_bean_factory: &ListableBeanFactory, #(#runtime_arg_field_idents: #runtime_arg_field_types),*) -> #concrete_type {
+
+                    #create_bean_tokens
+
+                    inner
+                }
+            }
+
+            impl module_macro_lib::module_macro_lib::knockoff_context::RuntimeArgPrototype for #concrete_type {
+                type Args = #args_type;
+                type BeanFactory = ListableBeanFactory;
+
+                fn create_prototype(listable_bean_factory: &ListableBeanFactory, args: Self::Args) -> Self {
+                    let #args_pattern = args;
+                    #concrete_type::create(listable_bean_factory, #(#runtime_arg_field_idents),*)
+                }
+            }
+        }
+    }
+
     fn concrete_bean_factory_tokens<ConcreteTypeT: ToTokens>(concrete_type: &ConcreteTypeT, profile_ident: &Ident) -> TokenStream {
         quote! {
         }
@@ -1,4 +1,5 @@
 use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
 use std::rc::Rc;
 use std::sync::Arc;
 use proc_macro2::{Ident, Span, TokenStream};
@@ -8,7 +9,7 @@ use syn::token::Struct;
 use codegen_utils::syn_helper::SynHelper;
 use module_macro_shared::bean::{BeanDefinition, BeanType};
 
-use module_macro_shared::dependency::{DependencyDescriptor, DependencyMetadata, DepType};
+use module_macro_shared::dependency::{AutowiredType, CollectionDependency, CollectionType, DependencyDescriptor, DependencyMetadata, DepType};
 use module_macro_shared::functions::ModulesFunctions;
 use module_macro_shared::profile_tree::ProfileBuilder;
 use knockoff_logging::*;
@@ -34,6 +35,11 @@ pub struct BeanFactoryInfo {
     pub(crate) singleton_field_type_info: BeansFieldTypeInfo,
     pub(crate) prototype_field_type_info: BeansFieldTypeInfo,
     pub(crate) default_field_info: Vec<DefaultFieldInfo>,
+    pub(crate) collection_field_info: Vec<CollectionFieldInfo>,
+    /// The #[autowired] ObjectProvider<T> fields.
+    pub(crate) object_provider_field_info: Vec<DefaultFieldInfo>,
+    /// The #[runtime_arg] fields of a prototype, passed to its create fn.
+    pub(crate) runtime_arg_field_info: Vec<DefaultFieldInfo>,
     pub(crate) concrete_type: Option<Type>,
     pub(crate) is_enum: bool,
     pub(crate) is_default: bool,
@@ -41,7 +47,25 @@ pub struct BeanFactoryInfo {
     pub(crate) ident_type: Option<Ident>,
     pub(crate) profile: Option<ProfileBuilder>,
     pub(crate) factory_fn: Option<ModulesFunctions>,
-    pub(crate) constructable: bool
+    pub(crate) constructable: bool,
+    pub(crate) post_construct: Vec<LifecycleMethod>,
+    pub(crate) pre_destroy: Vec<LifecycleMethod>
+}
+
+/// A Vec<Arc<dyn Trait>> or HashMap<String, Arc<dyn Trait>> dependency, created from each of the
+/// beans implementing the trait.
+#[derive(Clone)]
+pub struct CollectionFieldInfo {
+    pub(crate) field_ident: Ident,
+    pub(crate) field_type: Type,
+    pub(crate) collection: CollectionDependency
+}
+
+/// A method of the bean annotated with #[post_construct] or #[pre_destroy].
+#[derive(Clone)]
+pub struct LifecycleMethod {
+    pub(crate) method: Ident,
+    pub(crate) is_async: bool
 }
 
 #[derive(Clone)]
@@ -132,6 +156,142 @@ impl BeanFactoryInfo {
             .unwrap()
     }
 
+    /// Called after `inner` is created, once the autowired fields are set. The #[post_construct]
+    /// methods are called on `inner`, and `pre_destroy` is set to the fn calling the #[pre_destroy]
+    /// methods, which the factory registers with the ListableBeanFactory once the bean is wrapped.
+    /// Async methods are run to completion with run_blocking. The factory only runs these when the
+    /// singleton is created, not when it is fetched from the ListableBeanFactory.
+    pub(crate) fn lifecycle_tokens<ConcreteTypeT: ToTokens>(&self, concrete_type: &ConcreteTypeT) -> TokenStream {
+        let post_construct = self.post_construct.iter()
+            .map(|lifecycle_method| Self::lifecycle_method_call(lifecycle_method, quote! { inner }))
+            .collect::<Vec<TokenStream>>();
+        let post_construct = if post_construct.len() != 0 {
+            quote! {
+                #[allow(unused_mut)]
+                let mut inner = inner;
+                #(#post_construct)*
+            }
+        } else {
+            quote! {}
+        };
+
+        let pre_destroy = self.pre_destroy.iter()
+            .map(|lifecycle_method| Self::lifecycle_method_call(lifecycle_method, quote! { bean }))
+            .collect::<Vec<TokenStream>>();
+        let pre_destroy = if pre_destroy.len() != 0 {
+            quote! {
+                Some(|bean: &#concrete_type| {
+                    #(#pre_destroy)*
+                })
+            }
+        } else {
+            quote! { None }
+        };
+
+        quote! {
+            #post_construct
+            #[allow(unused_variables)]
+            let pre_destroy: Option<fn(&#concrete_type)> = #pre_destroy;
+        }
+    }
+
+    /// Binds each collection dependency, creating each of the beans and casting it to the
+    /// Arc<dyn Trait>.
+    pub(crate) fn collection_tokens(&self, profile_ident: &Ident) -> TokenStream {
+        let collections = self.collection_field_info.iter()
+            .map(|collection_field| {
+                let field_ident = &collection_field.field_ident;
+                let field_type = &collection_field.field_type;
+                let element_type = &collection_field.collection.element_type;
+                let beans = collection_field.collection.beans.iter()
+                    .map(|bean| {
+                        let concrete_type = &bean.concrete_type;
+                        if bean.prototype {
+                            quote! {
+                                Arc::new(<ListableBeanFactory as PrototypeBeanFactory<#concrete_type, #profile_ident>>::get_prototype_bean(listable_bean_factory)) as #element_type
+                            }
+                        } else {
+                            quote! {
+                                <ListableBeanFactory as BeanFactory<#concrete_type, #profile_ident>>::get_bean(listable_bean_factory).inner as #element_type
+                            }
+                        }
+                    })
+                    .collect::<Vec<TokenStream>>();
+                match collection_field.collection.collection_type {
+                    CollectionType::Vec => quote! {
+                        let #field_ident: #field_type = vec![
+                            #(#beans,)*
+                        ];
+                    },
+                    CollectionType::HashMap => {
+                        let keys = collection_field.collection.beans.iter()
+                            .map(|bean| bean.key.as_str())
+                            .collect::<Vec<&str>>();
+                        quote! {
+                            let #field_ident: #field_type = vec![
+                                #((String::from(#keys), #beans),)*
+                            ].into_iter().collect();
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<TokenStream>>();
+        quote! {
+            #(#collections)*
+        }
+    }
+
+    pub(crate) fn get_collection_field_types(&self) -> (Vec<Ident>, Vec<Type>) {
+        self.collection_field_info.iter()
+            .map(|collection_field| (collection_field.field_ident.clone(), collection_field.field_type.clone()))
+            .unzip()
+    }
+
+    /// Binds each ObjectProvider dependency. The provider creates the prototype when it is called,
+    /// so nothing is created here, and it holds a handle to the ListableBeanFactory so the
+    /// prototype's autowired fields are the singletons of the context.
+    pub(crate) fn object_provider_tokens(&self) -> TokenStream {
+        let (object_provider_field_idents, object_provider_field_types) = self.get_object_provider_field_types();
+        quote! {
+            #(
+                let #object_provider_field_idents: #object_provider_field_types = module_macro_lib::module_macro_lib::knockoff_context::ObjectProvider::new(
+                    Arc::new(listable_bean_factory.handle())
+                );
+            )*
+        }
+    }
+
+    pub(crate) fn get_object_provider_field_types(&self) -> (Vec<Ident>, Vec<Type>) {
+        Self::get_field_info_types(&self.object_provider_field_info)
+    }
+
+    pub(crate) fn get_runtime_arg_field_types(&self) -> (Vec<Ident>, Vec<Type>) {
+        Self::get_field_info_types(&self.runtime_arg_field_info)
+    }
+
+    pub(crate) fn has_runtime_args(&self) -> bool {
+        self.runtime_arg_field_info.len() != 0
+    }
+
+    fn get_field_info_types(field_info: &Vec<DefaultFieldInfo>) -> (Vec<Ident>, Vec<Type>) {
+        field_info.iter()
+            .map(|field| (field.field_ident.clone(), field.field_type.clone()))
+            .unzip()
+    }
+
+    fn lifecycle_method_call(lifecycle_method: &LifecycleMethod, receiver: TokenStream) -> TokenStream {
+        let method = &lifecycle_method.method;
+        if lifecycle_method.is_async {
+            quote! {
+                module_macro_lib::module_macro_lib::knockoff_context::run_blocking(#receiver.#method());
+            }
+        } else {
+            quote! {
+                #receiver.#method();
+            }
+        }
+    }
+
     pub(crate) fn get_concrete_type(&self) -> Option<Type> {
         assert!(self.concrete_type.is_some() || self.ident_type.is_some(),
                 "Could not retrieve concrete type when creating concrete bean factory.");
@@ -249,6 +409,75 @@ pub trait BeanFactoryInfoFactory<T> {
 
     fn create_bean_factory_info(bean: &T) -> Vec<BeanFactoryInfo>;
 
+    /// The methods in the impl blocks of the bean, not the trait impls, with the lifecycle
+    /// attribute, in the order they were declared.
+    fn get_lifecycle_methods(bean: &BeanDefinition, lifecycle_attr: &str) -> Vec<LifecycleMethod> {
+        bean.traits_impl.iter()
+            .flat_map(|dep| dep.item_impl.as_ref())
+            .filter(|item_impl| item_impl.trait_.is_none())
+            .flat_map(|item_impl| item_impl.items.iter())
+            .flat_map(|impl_item| match impl_item {
+                ImplItem::Method(method) => Some(method),
+                _ => None
+            })
+            .filter(|method| method.attrs.iter()
+                .any(|attr| attr.path.segments.last()
+                    .map(|segment| segment.ident == lifecycle_attr)
+                    .or(Some(false))
+                    .unwrap()
+                )
+            )
+            .map(|method| {
+                log_message!("Found {} method {} for bean {}.", lifecycle_attr, &method.sig.ident, &bean.id);
+                LifecycleMethod {
+                    method: method.sig.ident.clone(),
+                    is_async: method.sig.asyncness.is_some(),
+                }
+            })
+            .collect()
+    }
+
+    fn get_collection_fields(bean: &BeanDefinition) -> Vec<CollectionFieldInfo> {
+        bean.deps_map.iter()
+            .flat_map(|dep_type| dep_type.collection().as_ref()
+                .map(|collection| match dep_type.bean_info() {
+                    AutowiredType::AutowireField { field, .. } => field.ident.clone()
+                        .map(|field_ident| (field_ident, field.ty.clone())),
+                    AutowiredType::AutowiredFnArg { fn_arg_ident, fn_arg, .. } => {
+                        Some((fn_arg_ident.clone(), fn_arg.ty.deref().clone()))
+                    }
+                }
+                .map(|(field_ident, field_type)| CollectionFieldInfo {
+                    field_ident,
+                    field_type,
+                    collection: collection.clone(),
+                }))
+                .flatten()
+            )
+            .collect()
+    }
+
+    fn get_object_provider_fields(bean: &BeanDefinition) -> Vec<DefaultFieldInfo> {
+        bean.deps_map.iter()
+            .filter(|dep_type| dep_type.object_provider().is_some())
+            .flat_map(|dep_type| match dep_type.bean_info() {
+                AutowiredType::AutowireField { field, .. } => field.ident.clone()
+                    .map(|field_ident| DefaultFieldInfo { field_ident, field_type: field.ty.clone() }),
+                AutowiredType::AutowiredFnArg { fn_arg_ident, fn_arg, .. } => Some(DefaultFieldInfo {
+                    field_ident: fn_arg_ident.clone(),
+                    field_type: fn_arg.ty.deref().clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn get_runtime_arg_fields(bean: &BeanDefinition) -> Vec<DefaultFieldInfo> {
+        bean.get_runtime_args().into_iter()
+            .flat_map(|field| field.ident.clone()
+                .map(|field_ident| DefaultFieldInfo { field_ident, field_type: field.ty.clone() }))
+            .collect()
+    }
+
     fn get_mutable_singleton_field_ids(token_type: &BeanDefinition) -> Vec<MutableFieldInfo> {
         Self::get_field_ids::<MutableFieldInfo>(token_type, &Self::create_mutable_singleton_dep_type)
     }
@@ -299,6 +528,7 @@ pub trait BeanFactoryInfoFactory<T> {
         }
 
         info!("Setting default fields {} and {} for {:?}", bean.deps_map.len(), bean.fields[0].len(), bean);
+        let runtime_args = bean.get_runtime_args();
         match &bean.fields[0] {
             Fields::Named(n) => {
                 info!("Has {} fields.", n.named.len());
@@ -309,6 +539,7 @@ pub trait BeanFactoryInfoFactory<T> {
                             &vec!["autowired"]
                         ).is_none()
                     )
+                    .filter(|f| !runtime_args.iter().any(|runtime_arg| runtime_arg.ident == f.ident))
                     .flat_map(|f| {
                         if f.ident.as_ref().is_some() {
                             vec![DefaultFieldInfo{
@@ -485,6 +716,7 @@ pub trait BeanFactoryInfoFactory<T> {
     ) -> Vec<U> {
         let field_types = token_type.deps_map
             .iter()
+            .filter(|d| d.collection().is_none() && d.object_provider().is_none())
             .flat_map(|d| creator(d)
                 .map(|item| vec![item])
                 .or(Some(vec![]))
@@ -537,6 +769,9 @@ impl BeanFactoryInfoFactory<BeanDefinition> for ConcreteBeanFactoryInfo {
                     abstract_mutable_field_type_info: mutable_abstract_prototype_fields.clone(),
                 },
                 default_field_info: default_field_info.clone(),
+                collection_field_info: Self::get_collection_fields(bean),
+                object_provider_field_info: Self::get_object_provider_fields(bean),
+                runtime_arg_field_info: Self::get_runtime_arg_fields(bean),
                 concrete_type: bean.struct_type.clone(),
                 is_enum: bean.enum_found.is_some(),
                 abstract_type: None,
@@ -544,7 +779,9 @@ impl BeanFactoryInfoFactory<BeanDefinition> for ConcreteBeanFactoryInfo {
                 profile: Some(ProfileBuilder::default()),
                 factory_fn: bean.factory_fn.clone(),
                 constructable: bean.is_constructable(),
-                is_default: bean.has_default()
+                is_default: bean.has_default(),
+                post_construct: Self::get_lifecycle_methods(bean, "post_construct"),
+                pre_destroy: Self::get_lifecycle_methods(bean, "pre_destroy")
             })
             .collect::<Vec<BeanFactoryInfo>>()
     }
@@ -588,6 +825,9 @@ impl BeanFactoryInfoFactory<(BeanDefinition, DependencyDescriptor, ProfileBuilde
                     abstract_mutable_field_type_info: mutable_abstract_prototype_fields,
                 },
                 default_field_info,
+                collection_field_info: Self::get_collection_fields(bean),
+                object_provider_field_info: Self::get_object_provider_fields(bean),
+                runtime_arg_field_info: Self::get_runtime_arg_fields(bean),
                 concrete_type: bean.struct_type.clone(),
                 abstract_type,
                 is_enum: bean.enum_found.is_some(),
@@ -595,7 +835,9 @@ impl BeanFactoryInfoFactory<(BeanDefinition, DependencyDescriptor, ProfileBuilde
                 profile: Some(bean_type.2.to_owned()),
                 factory_fn: bean.factory_fn.clone(),
                 constructable: bean_type.0.is_constructable(),
-                is_default: bean_type.0.has_default()
+                is_default: bean_type.0.has_default(),
+                post_construct: Self::get_lifecycle_methods(bean, "post_construct"),
+                pre_destroy: Self::get_lifecycle_methods(bean, "pre_destroy")
             }
         ]
     }
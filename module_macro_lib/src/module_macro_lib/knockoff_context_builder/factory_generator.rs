@@ -58,6 +58,63 @@ pub trait FactoryGenerator: {
                     );
                 }
 
+                /// A ListableBeanFactory sharing the singletons and #[pre_destroy] methods of this one,
+                /// held by the ObjectProviders so the prototypes they create are autowired with the
+                /// singletons of the context.
+                fn handle(&self) -> ListableBeanFactory {
+                    ListableBeanFactory {
+                        singleton_bean_definitions: HashMap::new(),
+                        mutable_bean_definitions: HashMap::new(),
+                        singletons: self.singletons.clone(),
+                        pre_destroy_callbacks: self.pre_destroy_callbacks.clone()
+                    }
+                }
+
+                /// The singleton created by an earlier call to get_bean, if there was one. The lock is
+                /// not held while the bean is created, as creating it gets the beans it depends on.
+                fn get_singleton<T: 'static + Send + Sync>(&self) -> Option<Arc<T>> {
+                    self.singletons.lock().unwrap()
+                        .get(&TypeId::of::<Arc<T>>())
+                        .map(|bean| bean.clone().downcast::<T>().ok())
+                        .flatten()
+                }
+
+                fn add_singleton<T: 'static + Send + Sync>(&self, bean: &Arc<T>) {
+                    self.singletons.lock().unwrap()
+                        .insert(TypeId::of::<Arc<T>>(), bean.clone() as Arc<dyn Any + Send + Sync>);
+                }
+
+                fn register_pre_destroy<T: 'static + Send + Sync>(&self, bean: &Arc<T>, pre_destroy: Option<fn(&T)>) {
+                    pre_destroy.map(|pre_destroy| {
+                        let bean = bean.clone();
+                        self.pre_destroy_callbacks.lock().unwrap()
+                            .push(Box::new(move || pre_destroy(&bean)));
+                    });
+                }
+
+                fn register_mutable_pre_destroy<T: 'static + Send>(&self, bean: &Arc<Mutex<T>>, pre_destroy: Option<fn(&T)>) {
+                    pre_destroy.map(|pre_destroy| {
+                        let bean = bean.clone();
+                        self.pre_destroy_callbacks.lock().unwrap()
+                            .push(Box::new(move || {
+                                bean.lock().map(|bean| pre_destroy(&bean)).ok();
+                            }));
+                    });
+                }
+
+                /// Calls the #[pre_destroy] methods in the reverse of the order the beans were created.
+                /// The dependencies of a bean are created before it, so a bean is destroyed before the
+                /// beans it depends on. Each callback is only called once. The singletons are then
+                /// released, as a bean holding an ObjectProvider holds a handle to them.
+                fn destroy(&self) {
+                    let pre_destroy_callbacks = self.pre_destroy_callbacks.lock().unwrap()
+                        .drain(..)
+                        .collect::<Vec<Box<dyn Fn() + Send + Sync>>>();
+                    pre_destroy_callbacks.iter().rev()
+                        .for_each(|pre_destroy| pre_destroy());
+                    self.singletons.lock().unwrap().clear();
+                }
+
                 /// Important to note that if this was dyn Any + Send + Sync the type id would be different.
                 /// Therefore, it is important to have it only be called with the impl type, or the dyn
                 /// type for the abstract.
@@ -197,7 +254,9 @@ impl FactoryGen {
                     let mut mutable_bean_definitions: HashMap<TypeId, MutableBeanDefinition<dyn Any + Send + Sync>> = HashMap::new();
                     let mut listable_bean_factory = ListableBeanFactory {
                         singleton_bean_definitions,
-                        mutable_bean_definitions
+                        mutable_bean_definitions,
+                        singletons: Arc::new(Mutex::new(HashMap::new())),
+                        pre_destroy_callbacks: Arc::new(Mutex::new(vec![]))
                     };
                     #(
                         let next_bean_definition = <dyn BeanFactory<#singleton_idents, #profile_name, U = #singleton_idents>>::get_bean(&listable_bean_factory);
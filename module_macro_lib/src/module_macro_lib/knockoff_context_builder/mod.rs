@@ -288,7 +288,11 @@ impl ApplicationContextGenerator {
             #[derive(Default)]
             pub struct ListableBeanFactory {
                 singleton_bean_definitions: HashMap<TypeId, BeanDefinition<dyn Any + Send + Sync>>,
-                mutable_bean_definitions: HashMap<TypeId, MutableBeanDefinition<dyn Any + Send + Sync>>
+                mutable_bean_definitions: HashMap<TypeId, MutableBeanDefinition<dyn Any + Send + Sync>>,
+                /// Every singleton created by a FactoryBean, so that it is created once and shared by
+                /// the beans depending on it.
+                singletons: Arc<Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+                pre_destroy_callbacks: Arc<Mutex<Vec<Box<dyn Fn() + Send + Sync>>>>
             }
 
             impl ContainsBeans for ListableBeanFactory {
@@ -384,6 +388,11 @@ impl ApplicationContextGenerator {
                         .flatten()
                 }
 
+                fn close(&mut self) {
+                    self.factories.values()
+                        .for_each(|factory| factory.destroy());
+                }
+
                 fn new() -> Self {
                     let mut factories = HashMap::new();
                     #(
@@ -402,6 +411,12 @@ impl ApplicationContextGenerator {
                 }
 
             }
+
+            impl Drop for AppCtx {
+                fn drop(&mut self) {
+                    self.close();
+                }
+            }
         };
 
         injectable_code.into()
@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, HashSet};
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+use module_macro_shared::bean::{BeanDefinition, BeanType};
+use module_macro_shared::dependency::{AutowiredType, DependencyMetadata, DepType};
+use module_macro_shared::get_abstract_type;
+use module_macro_shared::parse_container::ParseContainer;
+use module_macro_shared::profile_tree::ProfileBuilder;
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("bean_dependency_validator.rs");
+
+/// The types an autowired field is unwrapped from to find the type of the bean, as in
+/// Arc<Mutex<Box<dyn Found>>> is a dependency on a bean implementing Found.
+const WRAPPER_TYPES: [&str; 6] = ["Arc", "Mutex", "RwLock", "Box", "Rc", "RefCell"];
+
+type DependencyGraph<'a> = BTreeMap<&'a String, Vec<(&'a String, &'a DependencyMetadata)>>;
+
+/// Checks that each #[autowired] field resolves to exactly one bean for each profile, and that
+/// the beans do not depend on each other in a cycle, so that the problem is reported at the
+/// field rather than in the generated code.
+pub struct BeanDependencyValidator<'a> {
+    parse_container: &'a ParseContainer,
+    /// Sorted, so that the diagnostics and the ordering are the same for each build.
+    bean_ids: Vec<&'a String>
+}
+
+impl<'a> BeanDependencyValidator<'a> {
+
+    pub fn new(parse_container: &'a ParseContainer) -> Self {
+        let mut bean_ids = parse_container.injectable_types_builder.keys().collect::<Vec<&String>>();
+        bean_ids.sort();
+        Self { parse_container, bean_ids }
+    }
+
+    /// The bean ids ordered so that each bean comes after the beans it depends on, or an error
+    /// for each dependency that is missing, ambiguous or part of a cycle.
+    pub fn validate(&self) -> Result<Vec<String>, syn::Error> {
+        let mut errors = vec![];
+        let mut graph: DependencyGraph<'a> = BTreeMap::new();
+        for &bean_id in self.bean_ids.iter() {
+            let bean = self.bean(bean_id);
+            if let Some(error) = Self::runtime_arg_error(bean) {
+                errors.push(error);
+            }
+            let mut edges = vec![];
+            for dependency in bean.deps_map.iter() {
+                match self.resolve(bean, dependency) {
+                    Ok(dependency_ids) => {
+                        if !*dependency.bean_info().lazy() {
+                            dependency_ids.into_iter().for_each(|id| edges.push((id, dependency)));
+                        }
+                    }
+                    Err(error) => errors.push(error)
+                }
+            }
+            graph.insert(bean_id, edges);
+        }
+
+        let ordering = Self::order(&graph, &mut errors);
+
+        errors.into_iter()
+            .reduce(|mut combined, error| {
+                combined.combine(error);
+                combined
+            })
+            .map(|errors| {
+                log_message!("Found invalid bean dependencies: {}", errors.to_string());
+                Err(errors)
+            })
+            .or(Some(Ok(ordering)))
+            .unwrap()
+    }
+
+    fn bean(&self, bean_id: &String) -> &'a BeanDefinition {
+        self.parse_container.injectable_types_builder.get(bean_id).unwrap()
+    }
+
+    /// The fields a bean declares as #[runtime_arg] are passed to its create fn, which is only
+    /// generated for prototypes.
+    fn runtime_arg_error(bean: &BeanDefinition) -> Option<syn::Error> {
+        if matches!(bean.bean_type, Some(BeanType::Prototype(_))) {
+            return None;
+        }
+        bean.get_runtime_args().into_iter()
+            .map(|field| syn::Error::new(field.span(), format!(
+                "#[runtime_arg] field `{}.{}` can only be declared on a #[prototype] bean, as a singleton is created without arguments.",
+                bean.id, field.ident.as_ref().map(|ident| ident.to_string()).or(Some("_".to_string())).unwrap()
+            )))
+            .reduce(|mut combined, error| {
+                combined.combine(error);
+                combined
+            })
+    }
+
+    /// The ids of the beans the dependency can be injected with. A collection is injected with
+    /// each of the beans implementing the trait, so it can be empty or contain many. An
+    /// ObjectProvider creates the bean only when it is called, so it is never part of a cycle.
+    fn resolve(&self, bean: &'a BeanDefinition, dependency: &'a DependencyMetadata) -> Result<Vec<&'a String>, syn::Error> {
+        if let Some(object_provider) = dependency.object_provider().as_ref() {
+            let type_key = Self::type_key(&object_provider.prototype_type.to_token_stream().to_string());
+            let provided = self.bean_ids.iter()
+                .filter(|id| Self::provides(self.bean(id), &type_key))
+                .any(|id| Self::has_runtime_args(self.bean(id)));
+            return if provided {
+                Ok(vec![])
+            } else {
+                Err(syn::Error::new(Self::span(dependency), format!(
+                    "no #[prototype] bean of type `{}` with #[runtime_arg] fields was found for the ObjectProvider `{}`.",
+                    type_key, Self::dependency_name(bean, dependency)
+                )))
+            };
+        }
+        if let Some(collection) = dependency.collection().as_ref() {
+            let type_key = Self::type_key(&collection.abstract_type.to_token_stream().to_string());
+            return Ok(self.bean_ids.iter()
+                .filter(|id| Self::provides(self.bean(id), &type_key))
+                .map(|id| *id)
+                .collect());
+        }
+        let type_name = dependency.dep_type_field_type().to_token_stream().to_string();
+        let type_key = Self::type_key(&type_name);
+        if type_key.len() == 0 || Self::is_generic_param(bean, &type_key) {
+            return Ok(vec![]);
+        }
+
+        let candidates = self.bean_ids.iter()
+            .filter(|id| Self::provides(self.bean(id), &type_key))
+            .map(|id| *id)
+            .collect::<Vec<&String>>();
+        if candidates.len() == 0 {
+            return Err(syn::Error::new(Self::span(dependency), format!(
+                "no bean of type `{}` was found for `{}`. Annotate a struct implementing `{}`, or a fn returning it, with #[service], #[component] or #[bean].",
+                type_key, Self::dependency_name(bean, dependency), type_key
+            )));
+        }
+
+        let qualifier = Self::qualifier(dependency)
+            .filter(|qualifier| *qualifier != type_key);
+        let qualified = match qualifier.as_ref() {
+            Some(qualifier) => candidates.iter()
+                .filter(|id| Self::is_qualified(self.bean(id), qualifier))
+                .map(|id| *id)
+                .collect::<Vec<&String>>(),
+            None => candidates.clone()
+        };
+        if qualified.len() == 0 {
+            return Err(syn::Error::new(Self::span(dependency), format!(
+                "no bean of type `{}` with qualifier `{}` was found for `{}`. The beans of type `{}` are {}.",
+                type_key, qualifier.unwrap(), Self::dependency_name(bean, dependency), type_key, Self::list(&candidates)
+            )));
+        }
+
+        if let Some(profile) = self.ambiguous_profile(&qualified, &type_key) {
+            let ambiguous = qualified.iter()
+                .filter(|id| self.profiles(self.bean(id), &type_key).contains(&profile))
+                .map(|id| *id)
+                .collect::<Vec<&String>>();
+            return Err(syn::Error::new(Self::span(dependency), format!(
+                "`{}` of type `{}` matches {} beans in profile `{}`: {}. Add #[qualifier({})] to the {} to choose one.",
+                Self::dependency_name(bean, dependency), type_key, ambiguous.len(), profile.profile,
+                Self::list(&ambiguous), ambiguous[0], Self::dependency_kind(dependency)
+            )));
+        }
+
+        if let Some(runtime_arg_bean) = qualified.iter().filter(|id| Self::has_runtime_args(self.bean(id))).next() {
+            return Err(syn::Error::new(Self::span(dependency), format!(
+                "`{}` is created with #[runtime_arg] fields, so it cannot be autowired into `{}`. Autowire an ObjectProvider<{}> instead.",
+                runtime_arg_bean, Self::dependency_name(bean, dependency), type_key
+            )));
+        }
+
+        Ok(qualified)
+    }
+
+    fn has_runtime_args(bean: &BeanDefinition) -> bool {
+        matches!(bean.bean_type, Some(BeanType::Prototype(_))) && bean.get_runtime_args().len() != 0
+    }
+
+    /// The first profile with more than one of the beans in it.
+    fn ambiguous_profile(&self, bean_ids: &Vec<&'a String>, type_key: &str) -> Option<ProfileBuilder> {
+        let mut seen = HashSet::new();
+        bean_ids.iter()
+            .flat_map(|id| self.profiles(self.bean(id), type_key))
+            .filter(|profile| !seen.insert(profile.clone()))
+            .next()
+    }
+
+    /// The profiles the bean provides the type in, as a trait impl can be in a profile other than
+    /// the struct's.
+    fn profiles(&self, bean: &BeanDefinition, type_key: &str) -> Vec<ProfileBuilder> {
+        let mut profiles = bean.profile.clone();
+        bean.traits_impl.iter()
+            .filter(|trait_impl| get_abstract_type(trait_impl)
+                .map(|abstract_type| Self::key_matches(&Self::type_key(&abstract_type.to_token_stream().to_string()), type_key))
+                .or(Some(false))
+                .unwrap()
+            )
+            .flat_map(|trait_impl| trait_impl.profile.iter())
+            .for_each(|profile| profiles.push(profile.clone()));
+        if profiles.len() == 0 {
+            profiles.push(ProfileBuilder::default());
+        }
+        profiles.sort();
+        profiles.dedup();
+        profiles
+    }
+
+    fn provides(bean: &BeanDefinition, type_key: &str) -> bool {
+        Self::concrete_type_keys(bean).iter().any(|key| Self::key_matches(key, type_key))
+            || bean.traits_impl.iter()
+                .flat_map(|trait_impl| get_abstract_type(trait_impl).into_iter())
+                .any(|abstract_type| Self::key_matches(&Self::type_key(&abstract_type.to_token_stream().to_string()), type_key))
+    }
+
+    fn concrete_type_keys(bean: &BeanDefinition) -> Vec<String> {
+        let mut keys = vec![Self::type_key(&bean.id)];
+        bean.struct_type.as_ref()
+            .map(|struct_type| keys.push(Self::type_key(&struct_type.to_token_stream().to_string())));
+        bean.ident.as_ref()
+            .filter(|_| bean.factory_fn.is_none())
+            .map(|ident| keys.push(ident.to_string()));
+        keys
+    }
+
+    fn is_qualified(bean: &BeanDefinition, qualifier: &str) -> bool {
+        Self::concrete_type_keys(bean).iter().any(|key| key == qualifier)
+            || bean.qualifiers.iter()
+                .chain(bean.traits_impl.iter().flat_map(|trait_impl| trait_impl.qualifiers.iter()))
+                .any(|bean_qualifier| Self::unquote(bean_qualifier) == qualifier)
+    }
+
+    fn is_generic_param(bean: &BeanDefinition, type_key: &str) -> bool {
+        bean.declaration_generics.iter()
+            .chain(bean.struct_found.iter().map(|item_struct| &item_struct.generics))
+            .flat_map(|generics| generics.type_params())
+            .any(|type_param| type_param.ident.to_string() == type_key)
+    }
+
+    /// The name of the type the dependency is resolved with: the wrapper types, dyn, references,
+    /// lifetimes, trait bounds and the module paths are removed. The generic args are kept, so
+    /// Repo<User> and Repo<Order> are different types.
+    pub fn type_key(type_name: &str) -> String {
+        let mut key = type_name.split_whitespace()
+            .filter(|token| !matches!(*token, "dyn" | "mut" | "&") && !token.starts_with("'"))
+            .collect::<String>()
+            .trim_start_matches("&")
+            .to_string();
+        loop {
+            let inner = match key.split_once("<") {
+                Some((outer, inner)) if key.ends_with(">") => {
+                    let outer = outer.rsplit("::").next().unwrap();
+                    if !WRAPPER_TYPES.contains(&outer) {
+                        break;
+                    }
+                    inner[..inner.len() - 1].to_string()
+                }
+                _ => break
+            };
+            key = inner;
+        }
+        let mut depth = 0;
+        let bounds = key.char_indices()
+            .find(|(_, c)| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                *c == '+' && depth == 0
+            })
+            .map(|(i, _)| i)
+            .or(Some(key.len()))
+            .unwrap();
+        key[..bounds].split_inclusive(|c| matches!(c, '<' | '>' | ','))
+            .map(|segment| segment.rsplit("::").next().unwrap())
+            .collect::<String>()
+    }
+
+    /// Whether a type key the bean provides matches the type key of a dependency. The generic args
+    /// only have to be the same if both have them, as the bean id and the ident of a generic struct
+    /// have none.
+    pub fn key_matches(bean_key: &str, type_key: &str) -> bool {
+        bean_key == type_key
+            || (!(bean_key.contains("<") && type_key.contains("<"))
+                && bean_key.split("<").next() == type_key.split("<").next())
+    }
+
+    /// The qualifier of #[autowired(...)] or #[qualifier(...)], if not empty.
+    fn qualifier(dependency: &DependencyMetadata) -> Option<String> {
+        dependency.dep_type_maybe_qualifier().as_ref()
+            .map(|qualifier| Self::unquote(qualifier))
+            .filter(|qualifier| qualifier.len() != 0)
+    }
+
+    fn unquote(value: &str) -> String {
+        value.trim().trim_matches('"').to_string()
+    }
+
+    fn span(dependency: &DependencyMetadata) -> Span {
+        match dependency.bean_info() {
+            AutowiredType::AutowireField { field, .. } => field.span(),
+            AutowiredType::AutowiredFnArg { fn_arg, .. } => fn_arg.span()
+        }
+    }
+
+    fn dependency_name(bean: &BeanDefinition, dependency: &DependencyMetadata) -> String {
+        match dependency.bean_info() {
+            AutowiredType::AutowireField { field, .. } => format!(
+                "{}.{}", bean.id, field.ident.as_ref().map(|ident| ident.to_string()).or(Some("_".to_string())).unwrap()
+            ),
+            AutowiredType::AutowiredFnArg { fn_arg_ident, .. } => format!("{}({})", bean.id, fn_arg_ident)
+        }
+    }
+
+    fn dependency_kind(dependency: &DependencyMetadata) -> &'static str {
+        match dependency.bean_info() {
+            AutowiredType::AutowireField { .. } => "field",
+            AutowiredType::AutowiredFnArg { .. } => "argument"
+        }
+    }
+
+    fn list(bean_ids: &Vec<&String>) -> String {
+        bean_ids.iter()
+            .map(|id| format!("`{}`", id))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Orders the beans depth first, adding an error for each cycle at the dependency that
+    /// closes it.
+    fn order(graph: &DependencyGraph<'a>, errors: &mut Vec<syn::Error>) -> Vec<String> {
+        let mut ordering = vec![];
+        let mut visited = HashSet::new();
+        let mut reported = HashSet::new();
+        for bean_id in graph.keys() {
+            Self::visit(bean_id, graph, &mut vec![], &mut visited, &mut reported, &mut ordering, errors);
+        }
+        ordering
+    }
+
+    fn visit(
+        bean_id: &'a String,
+        graph: &DependencyGraph<'a>,
+        path: &mut Vec<&'a String>,
+        visited: &mut HashSet<&'a String>,
+        reported: &mut HashSet<Vec<String>>,
+        ordering: &mut Vec<String>,
+        errors: &mut Vec<syn::Error>
+    ) {
+        if visited.contains(bean_id) {
+            return;
+        }
+        path.push(bean_id);
+        for (dependency_id, dependency) in graph.get(bean_id).into_iter().flatten() {
+            match path.iter().position(|id| id == dependency_id) {
+                Some(start) => {
+                    let cycle = path[start..].iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<String>>();
+                    let mut cycle_beans = cycle.clone();
+                    cycle_beans.sort();
+                    if reported.insert(cycle_beans) {
+                        errors.push(Self::cycle_error(cycle, dependency));
+                    }
+                }
+                None => Self::visit(dependency_id, graph, path, visited, reported, ordering, errors)
+            }
+        }
+        path.pop();
+        visited.insert(bean_id);
+        ordering.push(bean_id.to_string());
+    }
+
+    fn cycle_error(mut cycle: Vec<String>, dependency: &DependencyMetadata) -> syn::Error {
+        cycle.push(cycle[0].clone());
+        syn::Error::new(Self::span(dependency), format!(
+            "dependency cycle: {}. Remove one of the #[autowired] {}s in the cycle.",
+            cycle.join(" -> "), Self::dependency_kind(dependency)
+        ))
+    }
+}
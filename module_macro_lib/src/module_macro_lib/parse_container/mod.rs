@@ -26,6 +26,7 @@ use crate::logger_lazy;
 use crate::module_macro_lib::context_builder::ContextBuilder;
 use crate::module_macro_lib::generics_provider::DelegatingGenericsProvider;
 use crate::module_macro_lib::knockoff_context_builder::token_stream_generator::TokenStreamGenerator;
+use crate::module_macro_lib::parse_container::bean_dependency_validator::BeanDependencyValidator;
 use crate::module_macro_lib::parse_container::parse_container_dependencies::{BuildDependencyParseContainer, DelegateParseContainerModifier};
 use crate::module_macro_lib::profile_tree::concrete_profile_tree_modifier::ConcreteTypeProfileTreeModifier;
 use crate::module_macro_lib::profile_tree::mutable_profile_tree_modifier::MutableProfileTreeModifier;
@@ -33,6 +34,7 @@ use crate::module_macro_lib::profile_tree::mutable_profile_tree_modifier::Mutabl
 import_logger!("parse_container.rs");
 
 pub mod parse_container_dependencies;
+pub mod bean_dependency_validator;
 
 pub struct ParseContainerBuilder {
     parse_container_builders: Vec<Box<dyn BuildParseContainer>>
@@ -54,9 +56,10 @@ impl ParseContainerBuilder {
 
     pub fn new() -> Self {
         Self {
+            // the modifiers run first, so the dependencies of the beans they add are parsed.
             parse_container_builders: vec![
-                Box::new(BuildDependencyParseContainer {}),
-                Box::new(DelegateParseContainerModifier {})
+                Box::new(DelegateParseContainerModifier {}),
+                Box::new(BuildDependencyParseContainer {})
             ]
         }
     }
@@ -92,33 +95,9 @@ impl ParseContainerBuilder {
     }
 
 
-    pub fn is_valid_ordering_create(parse_container: &ParseContainer) -> Vec<String> {
-        let mut already_processed = vec![];
-        for i_type in parse_container.injectable_types_builder.iter() {
-            if !Self::is_valid_ordering(parse_container, &mut already_processed, i_type.1) {
-                log_message!("Was not valid ordering!");
-                return vec![];
-            }
-        }
-        already_processed
-    }
-
-    pub fn is_valid_ordering(parse_container: &ParseContainer, already_processed: &mut Vec<String>, dep: &BeanDefinition) -> bool {
-        already_processed.push(dep.id.clone());
-        for dep_impl in &dep.deps_map {
-            let next_id = dep_impl.dep_type_identifier();
-            if already_processed.contains(&next_id) {
-                continue;
-            }
-            if !parse_container.injectable_types_builder.get(&next_id)
-                .map(|next| {
-                    return Self::is_valid_ordering(parse_container, already_processed, next);
-                })
-                .or(Some(false))
-                .unwrap() {
-                return false;
-            }
-        }
-        true
+    /// The bean ids ordered so that each bean comes after its dependencies, or the errors for
+    /// the #[autowired] fields that have no bean, more than one bean, or are part of a cycle.
+    pub fn is_valid_ordering_create(parse_container: &ParseContainer) -> Result<Vec<String>, syn::Error> {
+        BeanDependencyValidator::new(parse_container).validate()
     }
 }
@@ -3,6 +3,7 @@ use std::hash::{Hash, Hasher};
 use syn::{parse2, Type};
 use quote::{quote, ToTokens};
 use module_macro_shared::bean::{AbstractionLevel, BeanDefinition, BeanType};
+use module_macro_shared::profile_tree::ProfileBuilder;
 use module_macro_shared::profile_tree::profile_tree_modifier::ProfileTreeModifier;
 use module_macro_shared::profile_tree::ProfileTree;
 
@@ -12,9 +13,11 @@ use knockoff_logging::*;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
 use codegen_utils::project_directory;
-use module_macro_shared::dependency::DepType;
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::dependency::{CollectionBean, CollectionDependency, DepType};
 use module_macro_shared::get_abstract_type;
 use crate::logger_lazy;
+use crate::module_macro_lib::parse_container::bean_dependency_validator::BeanDependencyValidator;
 use crate::module_macro_lib::profile_tree::search_profile_tree::SearchProfileTree;
 import_logger!("concrete_profile_tree_modifier.rs");
 
@@ -34,6 +37,12 @@ impl Hash for BeanFieldTypes {
 
 pub struct AddConcreteTypesToBeansArgs {
     beans_to_types: HashMap<String, Type>,
+    /// The struct type for the bean ids and the qualifiers of the beans and their trait impls,
+    /// so that #[qualifier] can choose between the beans implementing the same trait.
+    qualified_types: HashMap<String, Type>,
+    /// The beans implementing each trait, with the profiles they implement it in, for the
+    /// Vec<Arc<dyn Trait>> and HashMap<String, Arc<dyn Trait>> dependencies.
+    trait_impls: HashMap<String, Vec<(CollectionBean, Vec<ProfileBuilder>)>>,
     bean_struct_ids: Vec<Type>
 }
 
@@ -51,8 +60,17 @@ impl ProfileTreeModifier for ConcreteTypeProfileTreeModifier {
 
     fn modify_bean(&self, dep_type: &mut BeanDefinition, profile_tree: &mut ProfileTree) {
         info!("Doing modify and bean.");
+        let bean_profiles = Self::profiles_or_default(dep_type.profile.clone());
         dep_type.deps_map.iter_mut()
             .for_each(|dep_type_to_test| {
+                if let Some(collection) = dep_type_to_test.collection().as_ref() {
+                    let mut collection = self.add_collection_beans(collection, &bean_profiles);
+                    dep_type_to_test.set_collection(&mut collection);
+                    return;
+                }
+                if dep_type_to_test.object_provider().is_some() {
+                    return;
+                }
                 if !self.beans_to_types.bean_struct_ids.iter()
                     .any(|bean_struct_id|
                         dep_type_to_test.bean_type_path()
@@ -65,7 +83,8 @@ impl ProfileTreeModifier for ConcreteTypeProfileTreeModifier {
                         // Here is where it's getting the struct, setting the concrete field type of the
                         // dependency metadata, which will then be used in the BeanFactoryInfo, when the
                         // factories are being created. See above TODO:
-                        .map(|q| self.beans_to_types.beans_to_types.get(q)
+                        .map(|q| self.beans_to_types.qualified_types.get(q.trim_matches('"'))
+                            .or(self.beans_to_types.beans_to_types.get(q))
                             .map(|type_to_set| dep_type_to_test.bean_info_mut()
                                 .set_concrete_type_of_field_bean_type(&mut Some(type_to_set.clone())))
                         )
@@ -96,6 +115,96 @@ impl ProfileTreeModifier for ConcreteTypeProfileTreeModifier {
 
 impl ConcreteTypeProfileTreeModifier {
 
+     /// The bean ids are added after the qualifiers, so that a bean id is not taken by the
+     /// qualifier of another bean.
+     fn create_qualified_types(profile_tree_items: &HashMap<String, BeanDefinition>) -> HashMap<String, Type> {
+         let mut qualified_types = profile_tree_items.values()
+             .flat_map(|b| b.struct_type.as_ref()
+                 .map(|struct_type| b.qualifiers.iter()
+                     .chain(b.traits_impl.iter().flat_map(|t| t.qualifiers.iter()))
+                     .map(|q| (q.trim_matches('"').to_string(), struct_type.clone()))
+                     .collect::<Vec<_>>()
+                 )
+                 .or(Some(vec![]))
+                 .unwrap()
+             )
+             .collect::<HashMap<String, Type>>();
+         profile_tree_items.values()
+             .flat_map(|b| b.struct_type.as_ref().map(|struct_type| (b.id.clone(), struct_type.clone())))
+             .for_each(|(id, struct_type)| { qualified_types.insert(id, struct_type); });
+         qualified_types
+     }
+
+     fn add_collection_beans(&self, collection: &CollectionDependency, bean_profiles: &Vec<ProfileBuilder>) -> Option<CollectionDependency> {
+         let type_key = BeanDependencyValidator::type_key(&SynHelper::get_str(&collection.abstract_type));
+         // matched the same way as the BeanDependencyValidator, so a Vec<Arc<dyn Repo<User>>> is
+         // not injected with the beans implementing Repo<Order>.
+         let mut beans = self.beans_to_types.trait_impls.iter()
+             .filter(|(trait_key, _)| BeanDependencyValidator::key_matches(trait_key, &type_key))
+             .flat_map(|(_, trait_impls)| trait_impls.iter())
+             .filter(|(_, profiles)| profiles.iter().any(|profile| bean_profiles.contains(profile)))
+             .map(|(collection_bean, _)| collection_bean.clone())
+             .collect::<Vec<CollectionBean>>();
+         beans.sort_by(|first, second| (first.order.is_none(), first.order, &first.key)
+             .cmp(&(second.order.is_none(), second.order, &second.key)));
+         log_message!("Injecting {} beans implementing {} as a collection.", beans.len(), &type_key);
+         Some(CollectionDependency { beans, ..collection.clone() })
+     }
+
+     fn create_trait_impls(profile_tree_items: &HashMap<String, BeanDefinition>) -> HashMap<String, Vec<(CollectionBean, Vec<ProfileBuilder>)>> {
+         let mut trait_impls: HashMap<String, Vec<(CollectionBean, Vec<ProfileBuilder>)>> = HashMap::new();
+         profile_tree_items.values()
+             .for_each(|bean| {
+                 let concrete_type = bean.struct_type.clone()
+                     .or(bean.ident.as_ref().map(|i| parse2::<Type>(i.to_token_stream()).ok()).flatten());
+                 // a bean with #[runtime_arg] fields can only be created by its ObjectProvider.
+                 if concrete_type.is_none() || bean.get_runtime_args().len() != 0 {
+                     return;
+                 }
+                 bean.traits_impl.iter()
+                     .flat_map(|trait_impl| get_abstract_type(trait_impl).map(|abstract_type| (trait_impl, abstract_type)))
+                     .for_each(|(trait_impl, abstract_type)| {
+                         let key = trait_impl.qualifiers.iter()
+                             .chain(bean.qualifiers.iter())
+                             .map(|qualifier| qualifier.trim_matches('"').to_string())
+                             .filter(|qualifier| qualifier.len() != 0)
+                             .next()
+                             .or(Some(bean.id.clone()))
+                             .unwrap();
+                         let order = bean.struct_found.iter().flat_map(|s| s.attrs.iter())
+                             .chain(bean.enum_found.iter().flat_map(|e| e.attrs.iter()))
+                             .chain(bean.factory_fn.iter().flat_map(|f| f.fn_found.item_fn.attrs.iter()))
+                             .chain(trait_impl.item_impl.iter().flat_map(|i| i.attrs.iter()))
+                             .cloned()
+                             .collect::<Vec<syn::Attribute>>();
+                         let order = SynHelper::get_attr_from_vec(&order, &vec!["ordered"])
+                             .map(|order| order.trim().parse::<i64>().ok())
+                             .flatten();
+                         let mut profiles = bean.profile.clone();
+                         profiles.extend(trait_impl.profile.iter().cloned());
+                         trait_impls.entry(BeanDependencyValidator::type_key(&SynHelper::get_str(&abstract_type)))
+                             .or_insert(vec![])
+                             .push((
+                                 CollectionBean {
+                                     key,
+                                     concrete_type: concrete_type.clone().unwrap(),
+                                     order,
+                                     prototype: matches!(bean.bean_type, Some(BeanType::Prototype(_))),
+                                 },
+                                 Self::profiles_or_default(profiles)
+                             ));
+                     });
+             });
+         trait_impls
+     }
+
+     fn profiles_or_default(mut profiles: Vec<ProfileBuilder>) -> Vec<ProfileBuilder> {
+         if profiles.len() == 0 {
+             profiles.push(ProfileBuilder::default());
+         }
+         profiles
+     }
+
      fn create_arg(profile_tree_items: &HashMap<String, BeanDefinition>) -> AddConcreteTypesToBeansArgs {
          AddConcreteTypesToBeansArgs {
              beans_to_types: profile_tree_items.iter().flat_map(|b| {
@@ -115,6 +224,8 @@ impl ConcreteTypeProfileTreeModifier {
                          .collect::<Vec<_>>()
                  }
              }).collect::<HashMap<String, Type>>(),
+             qualified_types: Self::create_qualified_types(profile_tree_items),
+             trait_impls: Self::create_trait_impls(profile_tree_items),
              bean_struct_ids:  profile_tree_items.values()
                  .flat_map(|s| s.struct_type.as_ref()
                      .map(|s| vec![s.clone()])
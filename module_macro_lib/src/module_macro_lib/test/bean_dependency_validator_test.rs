@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+use syn::ItemMod;
+use module_macro_shared::item_mod_parser::ItemModParser;
+use module_macro_shared::ItemParser;
+use module_macro_shared::parse_container::{BuildParseContainer, get_test_module_parser, ParseContainer};
+use crate::module_macro_lib::parse_container::bean_dependency_validator::BeanDependencyValidator;
+use crate::module_macro_lib::parse_container::parse_container_dependencies::BuildDependencyParseContainer;
+use crate::module_macro_lib::parse_container::ParseContainerBuilder;
+
+fn parse_container(module: &str) -> ParseContainer {
+    let mut item_mod = syn::parse_str::<ItemMod>(module).unwrap();
+    let mut container = ParseContainer::default();
+    let module_identifier = item_mod.ident.to_string();
+    ItemModParser::parse_item(
+        &PathBuf::new(),
+        &mut container,
+        &mut item_mod,
+        vec![module_identifier],
+        &mut get_test_module_parser()
+    );
+    BuildDependencyParseContainer {}.build_parse_container(&mut container);
+    container
+}
+
+fn validation_error(module: &str) -> String {
+    let container = parse_container(module);
+    let result = ParseContainerBuilder::is_valid_ordering_create(&container);
+    assert!(result.is_err());
+    result.err().unwrap().into_iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[test]
+fn test_valid_ordering() {
+    let container = parse_container(r#"
+        pub mod beans {
+            #[service(Four)]
+            #[derive(Default)]
+            pub struct Four {
+                #[autowired] pub one: Arc<Mutex<One>>,
+            }
+
+            #[service(One)]
+            #[derive(Default)]
+            pub struct One {}
+        }
+    "#);
+    let ordering = ParseContainerBuilder::is_valid_ordering_create(&container).unwrap();
+    let one = ordering.iter().position(|id| id == "One").unwrap();
+    let four = ordering.iter().position(|id| id == "Four").unwrap();
+    assert!(one < four);
+}
+
+#[test]
+fn test_dependency_cycle() {
+    let error = validation_error(r#"
+        pub mod beans {
+            #[service(A)]
+            #[derive(Default)]
+            pub struct A { #[autowired] pub b: Arc<B> }
+
+            #[service(B)]
+            #[derive(Default)]
+            pub struct B { #[autowired] pub c: Arc<C> }
+
+            #[service(C)]
+            #[derive(Default)]
+            pub struct C { #[autowired] pub a: Arc<A> }
+        }
+    "#);
+    assert!(error.contains("dependency cycle: A -> B -> C -> A"), "{}", error);
+    assert_eq!(error.matches("dependency cycle").count(), 1);
+}
+
+#[test]
+fn test_missing_bean() {
+    let error = validation_error(r#"
+        pub mod beans {
+            #[service(A)]
+            #[derive(Default)]
+            pub struct A { #[autowired] pub missing: Arc<Missing> }
+        }
+    "#);
+    assert!(error.contains("no bean of type `Missing` was found for `A.missing`"), "{}", error);
+}
+
+#[test]
+fn test_ambiguous_bean() {
+    let beans = r#"
+        pub trait Found: Send + Sync {}
+
+        impl Found for Four {}
+
+        impl Found for Five {}
+
+        #[service(Four)]
+        #[derive(Default)]
+        pub struct Four {}
+
+        #[service(Five)]
+        #[derive(Default)]
+        pub struct Five {}
+    "#;
+    let error = validation_error(format!(r#"
+        pub mod beans {{
+            {}
+            #[service(Once)]
+            #[derive(Default)]
+            pub struct Once {{ #[autowired] pub found: Arc<dyn Found> }}
+        }}
+    "#, beans).as_str());
+    assert!(error.contains("`Once.found` of type `Found` matches 2 beans in profile `DefaultProfile`: `Five`, `Four`"), "{}", error);
+    assert!(error.contains("Add #[qualifier(Five)] to the field"), "{}", error);
+
+    let container = parse_container(format!(r#"
+        pub mod beans {{
+            {}
+            #[service(Once)]
+            #[derive(Default)]
+            pub struct Once {{
+                #[autowired]
+                #[qualifier(Four)]
+                pub found: Arc<dyn Found>
+            }}
+        }}
+    "#, beans).as_str());
+    assert!(ParseContainerBuilder::is_valid_ordering_create(&container).is_ok());
+}
+
+#[test]
+fn test_type_key() {
+    assert_eq!(BeanDependencyValidator::type_key("Arc < Mutex < Box < dyn Found + Send > > >"), "Found");
+    assert_eq!(BeanDependencyValidator::type_key("& 'a crate :: beans :: One"), "One");
+    assert_eq!(BeanDependencyValidator::type_key("Arc < Repo < String > >"), "Repo<String>");
+    assert_eq!(BeanDependencyValidator::type_key("Arc < dyn crate :: Repo < crate :: User > + Send + Sync >"), "Repo<User>");
+    assert!(!BeanDependencyValidator::key_matches("Repo<User>", "Repo<Order>"));
+    assert!(BeanDependencyValidator::key_matches("Repo", "Repo<Order>"));
+}
+
+#[test]
+fn test_generic_trait_args_not_ambiguous() {
+    let beans = r#"
+        pub trait Repo<T>: Send + Sync {}
+
+        pub struct User {}
+
+        pub struct Order {}
+
+        impl Repo<User> for UserRepo {}
+
+        impl Repo<Order> for OrderRepo {}
+
+        #[service(UserRepo)]
+        #[derive(Default)]
+        pub struct UserRepo {}
+
+        #[service(OrderRepo)]
+        #[derive(Default)]
+        pub struct OrderRepo {}
+    "#;
+    let container = parse_container(format!(r#"
+        pub mod beans {{
+            {}
+            #[service(Once)]
+            #[derive(Default)]
+            pub struct Once {{ #[autowired] pub users: Arc<dyn Repo<User>> }}
+        }}
+    "#, beans).as_str());
+    assert!(ParseContainerBuilder::is_valid_ordering_create(&container).is_ok());
+
+    let error = validation_error(format!(r#"
+        pub mod beans {{
+            {}
+            #[service(Once)]
+            #[derive(Default)]
+            pub struct Once {{ #[autowired] pub items: Arc<dyn Repo<Item>> }}
+        }}
+    "#, beans).as_str());
+    assert!(error.contains("no bean of type `Repo<Item>` was found for `Once.items`"), "{}", error);
+}
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use quote::quote;
+use syn::ItemMod;
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::item_mod_parser::ItemModParser;
+use module_macro_shared::ItemParser;
+use module_macro_shared::parse_container::{get_test_module_parser, ParseContainer};
+use crate::module_macro_lib::knockoff_context_builder::bean_factory_info::{BeanFactoryInfo, BeanFactoryInfoFactory, ConcreteBeanFactoryInfo};
+
+fn bean_factory_info(module: &str, id: &str) -> BeanFactoryInfo {
+    let mut item_mod = syn::parse_str::<ItemMod>(module).unwrap();
+    let mut container = ParseContainer::default();
+    let module_identifier = item_mod.ident.to_string();
+    ItemModParser::parse_item(
+        &PathBuf::new(),
+        &mut container,
+        &mut item_mod,
+        vec![module_identifier],
+        &mut get_test_module_parser()
+    );
+    let bean = container.injectable_types_builder.get(id).unwrap();
+    ConcreteBeanFactoryInfo::create_bean_factory_info(bean).remove(0)
+}
+
+#[test]
+fn test_lifecycle_methods() {
+    let bean_factory_info = bean_factory_info(r#"
+        pub mod beans {
+            #[service(Pool)]
+            #[derive(Default)]
+            pub struct Pool {}
+
+            impl Pool {
+                #[post_construct]
+                pub fn init(&mut self) {}
+
+                #[post_construct]
+                pub async fn connect(&self) {}
+
+                #[pre_destroy]
+                pub async fn flush(&self) {}
+
+                pub fn other(&self) {}
+            }
+        }
+    "#, "Pool");
+
+    assert_eq!(bean_factory_info.post_construct.iter()
+                   .map(|m| (m.method.to_string(), m.is_async))
+                   .collect::<Vec<(String, bool)>>(),
+               vec![("init".to_string(), false), ("connect".to_string(), true)]);
+    assert_eq!(bean_factory_info.pre_destroy.len(), 1);
+    assert_eq!(bean_factory_info.pre_destroy[0].method.to_string(), "flush");
+
+    let tokens = SynHelper::get_str(bean_factory_info.lifecycle_tokens(&quote! { Pool }));
+    assert!(tokens.contains("inner . init () ;"), "{}", tokens);
+    assert!(tokens.contains("run_blocking (inner . connect ()) ;"), "{}", tokens);
+    assert!(tokens.contains("Some (| bean : & Pool |"), "{}", tokens);
+    assert!(tokens.contains("run_blocking (bean . flush ()) ;"), "{}", tokens);
+}
+
+#[test]
+fn test_no_lifecycle_methods() {
+    let bean_factory_info = bean_factory_info(r#"
+        pub mod beans {
+            #[service(One)]
+            #[derive(Default)]
+            pub struct One {}
+
+            impl One {
+                pub fn init(&self) {}
+            }
+        }
+    "#, "One");
+
+    assert!(bean_factory_info.post_construct.is_empty());
+    let tokens = SynHelper::get_str(bean_factory_info.lifecycle_tokens(&quote! { One }));
+    assert!(!tokens.contains("let mut inner"), "{}", tokens);
+    assert!(tokens.contains("Option < fn (& One) > = None"), "{}", tokens);
+}
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+use syn::{Ident, ItemMod};
+use proc_macro2::Span;
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::dependency::CollectionType;
+use module_macro_shared::item_mod_parser::ItemModParser;
+use module_macro_shared::ItemParser;
+use module_macro_shared::parse_container::{BuildParseContainer, get_test_module_parser, ParseContainer};
+use module_macro_shared::profile_tree::profile_tree_modifier::ProfileTreeModifier;
+use module_macro_shared::profile_tree::ProfileTree;
+use crate::module_macro_lib::knockoff_context_builder::bean_factory_info::{BeanFactoryInfoFactory, ConcreteBeanFactoryInfo};
+use crate::module_macro_lib::parse_container::parse_container_dependencies::BuildDependencyParseContainer;
+use crate::module_macro_lib::parse_container::ParseContainerBuilder;
+use crate::module_macro_lib::profile_tree::concrete_profile_tree_modifier::ConcreteTypeProfileTreeModifier;
+
+const HANDLERS: &str = r#"
+    pub mod beans {
+        pub trait Handler: Send + Sync {}
+
+        impl Handler for First {}
+
+        #[qualifier(second_handler)]
+        impl Handler for Second {}
+
+        impl Handler for Third {}
+
+        #[service(First)]
+        #[ordered(1)]
+        #[derive(Default)]
+        pub struct First {}
+
+        #[service(Second)]
+        #[ordered(0)]
+        #[derive(Default)]
+        pub struct Second {}
+
+        #[service(Third)]
+        #[derive(Default)]
+        pub struct Third {}
+
+        #[service(Registry)]
+        #[derive(Default)]
+        pub struct Registry {
+            #[autowired] pub handlers: Vec<Arc<dyn Handler>>,
+            #[autowired] pub handlers_by_name: HashMap<String, Arc<dyn Handler>>,
+        }
+    }
+"#;
+
+fn parse_container(module: &str) -> ParseContainer {
+    let mut item_mod = syn::parse_str::<ItemMod>(module).unwrap();
+    let mut container = ParseContainer::default();
+    let module_identifier = item_mod.ident.to_string();
+    ItemModParser::parse_item(
+        &PathBuf::new(),
+        &mut container,
+        &mut item_mod,
+        vec![module_identifier],
+        &mut get_test_module_parser()
+    );
+    BuildDependencyParseContainer {}.build_parse_container(&mut container);
+    container
+}
+
+#[test]
+fn test_collection_beans_ordered() {
+    let container = parse_container(HANDLERS);
+    let modifier = ConcreteTypeProfileTreeModifier::new(&container.injectable_types_builder);
+    let mut registry = container.injectable_types_builder.get("Registry").unwrap().clone();
+    modifier.modify_bean(&mut registry, &mut ProfileTree::default());
+
+    let collections = registry.deps_map.iter()
+        .flat_map(|dep| dep.collection().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(collections.len(), 2);
+
+    let handlers = collections.iter()
+        .find(|c| c.collection_type == CollectionType::Vec)
+        .unwrap();
+    assert_eq!(SynHelper::get_str(&handlers.abstract_type), "Handler");
+    assert_eq!(handlers.beans.iter().map(|b| SynHelper::get_str(&b.concrete_type)).collect::<Vec<String>>(),
+               vec!["Second", "First", "Third"]);
+
+    let handlers_by_name = collections.iter()
+        .find(|c| c.collection_type == CollectionType::HashMap)
+        .unwrap();
+    assert_eq!(handlers_by_name.beans.iter().map(|b| b.key.as_str()).collect::<Vec<&str>>(),
+               vec!["second_handler", "First", "Third"]);
+
+    let bean_factory_info = ConcreteBeanFactoryInfo::create_bean_factory_info(&registry).remove(0);
+    assert_eq!(bean_factory_info.collection_field_info.len(), 2);
+    assert!(bean_factory_info.singleton_field_type_info.abstract_field_type_info.is_empty());
+    let tokens = SynHelper::get_str(bean_factory_info.collection_tokens(&Ident::new("DefaultProfile", Span::call_site())));
+    assert!(tokens.contains("let handlers : Vec < Arc < dyn Handler > > = vec ! [< ListableBeanFactory as BeanFactory < Second , DefaultProfile >> :: get_bean (listable_bean_factory) . inner as Arc < dyn Handler > ,"), "{}", tokens);
+    assert!(tokens.contains("(String :: from (\"second_handler\") ,"), "{}", tokens);
+}
+
+#[test]
+fn test_collection_is_valid_without_beans() {
+    let container = parse_container(r#"
+        pub mod beans {
+            pub trait Handler: Send + Sync {}
+
+            #[service(Registry)]
+            #[derive(Default)]
+            pub struct Registry {
+                #[autowired] pub handlers: Vec<Arc<dyn Handler>>,
+            }
+        }
+    "#);
+    assert!(ParseContainerBuilder::is_valid_ordering_create(&container).is_ok());
+}
+
+#[test]
+fn test_collection_ordering() {
+    let container = parse_container(HANDLERS);
+    let ordering = ParseContainerBuilder::is_valid_ordering_create(&container).unwrap();
+    let registry = ordering.iter().position(|id| id == "Registry").unwrap();
+    ["First", "Second", "Third"].iter()
+        .for_each(|handler| assert!(ordering.iter().position(|id| id == handler).unwrap() < registry));
+}
+
+#[test]
+fn test_collection_beans_matched_by_generic_args() {
+    let container = parse_container(r#"
+        pub mod beans {
+            pub trait Repo<T>: Send + Sync {}
+
+            pub struct User {}
+
+            pub struct Order {}
+
+            impl Repo<User> for UserRepo {}
+
+            impl Repo<User> for CachedUserRepo {}
+
+            impl Repo<Order> for OrderRepo {}
+
+            #[service(UserRepo)]
+            #[derive(Default)]
+            pub struct UserRepo {}
+
+            #[service(CachedUserRepo)]
+            #[derive(Default)]
+            pub struct CachedUserRepo {}
+
+            #[service(OrderRepo)]
+            #[derive(Default)]
+            pub struct OrderRepo {}
+
+            #[service(Registry)]
+            #[derive(Default)]
+            pub struct Registry {
+                #[autowired] pub user_repos: Vec<Arc<dyn Repo<User>>>,
+            }
+        }
+    "#);
+    let modifier = ConcreteTypeProfileTreeModifier::new(&container.injectable_types_builder);
+    let mut registry = container.injectable_types_builder.get("Registry").unwrap().clone();
+    modifier.modify_bean(&mut registry, &mut ProfileTree::default());
+
+    let user_repos = registry.deps_map.iter()
+        .flat_map(|dep| dep.collection().clone())
+        .next()
+        .unwrap();
+    let mut user_repos = user_repos.beans.iter()
+        .map(|b| SynHelper::get_str(&b.concrete_type))
+        .collect::<Vec<String>>();
+    user_repos.sort();
+    assert_eq!(user_repos, vec!["CachedUserRepo", "UserRepo"]);
+}
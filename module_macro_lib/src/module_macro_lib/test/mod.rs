@@ -13,6 +13,10 @@ use module_macro_shared::parse_container::ParseContainer;
 pub mod module_tree_test;
 pub mod profile_tree_test;
 pub mod item_parser_test;
+pub mod bean_dependency_validator_test;
+pub mod bean_lifecycle_test;
+pub mod collection_injection_test;
+pub mod runtime_arg_prototype_test;
 
 // fn get_parse_container(module_app: &str, factories: &str) -> Option<ParseContainer> {
 //
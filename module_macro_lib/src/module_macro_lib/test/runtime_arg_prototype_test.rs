@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+use syn::{ItemMod, Type};
+use quote::quote;
+use codegen_utils::syn_helper::SynHelper;
+use module_macro_shared::item_mod_parser::ItemModParser;
+use module_macro_shared::ItemParser;
+use module_macro_shared::parse_container::{BuildParseContainer, get_test_module_parser, ParseContainer};
+use crate::module_macro_lib::knockoff_context_builder::bean_factory_generator::BeanFactoryGenerator;
+use crate::module_macro_lib::knockoff_context_builder::bean_factory_generator::prototype_factory_generator::PrototypeBeanFactoryGenerator;
+use crate::module_macro_lib::knockoff_context_builder::bean_factory_info::{BeanFactoryInfo, BeanFactoryInfoFactory, ConcreteBeanFactoryInfo};
+use crate::module_macro_lib::parse_container::parse_container_dependencies::BuildDependencyParseContainer;
+use crate::module_macro_lib::parse_container::ParseContainerBuilder;
+
+const WIDGETS: &str = r#"
+    pub mod beans {
+        #[service(Repo)]
+        #[derive(Default)]
+        pub struct Repo {}
+
+        #[prototype(Widget)]
+        pub struct Widget {
+            #[runtime_arg] pub name: String,
+            #[runtime_arg] pub count: u32,
+            #[autowired] pub repo: Arc<Repo>,
+            pub created: u64,
+        }
+
+        #[prototype(Label)]
+        pub struct Label {
+            #[runtime_arg] pub text: String,
+        }
+
+        #[service(WidgetFactory)]
+        pub struct WidgetFactory {
+            #[autowired] pub widgets: ObjectProvider<Widget>,
+        }
+    }
+"#;
+
+fn parse_container(module: &str) -> ParseContainer {
+    let mut item_mod = syn::parse_str::<ItemMod>(module).unwrap();
+    let mut container = ParseContainer::default();
+    let module_identifier = item_mod.ident.to_string();
+    ItemModParser::parse_item(
+        &PathBuf::new(),
+        &mut container,
+        &mut item_mod,
+        vec![module_identifier],
+        &mut get_test_module_parser()
+    );
+    BuildDependencyParseContainer {}.build_parse_container(&mut container);
+    container
+}
+
+fn bean_factory_info(container: &ParseContainer, bean_id: &str) -> BeanFactoryInfo {
+    ConcreteBeanFactoryInfo::create_bean_factory_info(container.injectable_types_builder.get(bean_id).unwrap()).remove(0)
+}
+
+fn validation_error(module: &str) -> String {
+    let container = parse_container(module);
+    let result = ParseContainerBuilder::is_valid_ordering_create(&container);
+    assert!(result.is_err());
+    result.err().unwrap().into_iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[test]
+fn test_runtime_arg_fields() {
+    let container = parse_container(WIDGETS);
+    let widget = bean_factory_info(&container, "Widget");
+    assert!(widget.has_runtime_args());
+    let (runtime_arg_idents, _) = widget.get_runtime_arg_field_types();
+    assert_eq!(runtime_arg_idents.iter().map(|i| i.to_string()).collect::<Vec<String>>(), vec!["name", "count"]);
+    assert_eq!(widget.default_field_info.iter().map(|f| f.field_ident.to_string()).collect::<Vec<String>>(), vec!["created"]);
+
+    let widget_type: Type = syn::parse_str("Widget").unwrap();
+    let tokens = SynHelper::get_str(PrototypeBeanFactoryGenerator::runtime_arg_factory(&widget_type, &widget, quote! { let inner = Widget::new(); }));
+    assert!(tokens.contains("pub fn create (listable_bean_factory : & ListableBeanFactory , name : String , count : u32) -> Widget"), "{}", tokens);
+    assert!(tokens.contains("type Args = (String , u32) ;"), "{}", tokens);
+    assert!(tokens.contains("type BeanFactory = ListableBeanFactory ;"), "{}", tokens);
+    assert!(tokens.contains("fn create_prototype (listable_bean_factory : & ListableBeanFactory , args : Self :: Args) -> Self"), "{}", tokens);
+    assert!(tokens.contains("let (name , count) = args ; Widget :: create (listable_bean_factory , name , count)"), "{}", tokens);
+    assert!(!tokens.contains("ListableBeanFactory :: default ()"), "{}", tokens);
+
+    let label = bean_factory_info(&container, "Label");
+    let label_type: Type = syn::parse_str("Label").unwrap();
+    let tokens = SynHelper::get_str(PrototypeBeanFactoryGenerator::runtime_arg_factory(&label_type, &label, quote! { let inner = Label::new(); }));
+    assert!(tokens.contains("type Args = String ;"), "{}", tokens);
+    assert!(tokens.contains("let text = args ;"), "{}", tokens);
+}
+
+#[test]
+fn test_object_provider_field() {
+    let container = parse_container(WIDGETS);
+    let widget_factory = bean_factory_info(&container, "WidgetFactory");
+    let (object_provider_idents, object_provider_types) = widget_factory.get_object_provider_field_types();
+    assert_eq!(object_provider_idents.iter().map(|i| i.to_string()).collect::<Vec<String>>(), vec!["widgets"]);
+    assert_eq!(SynHelper::get_str(&object_provider_types[0]), "ObjectProvider < Widget >");
+    assert!(widget_factory.singleton_field_type_info.concrete_field_type_info.is_empty());
+    assert!(widget_factory.prototype_field_type_info.concrete_field_type_info.is_empty());
+    assert!(widget_factory.default_field_info.is_empty());
+
+    let tokens = SynHelper::get_str(widget_factory.object_provider_tokens());
+    assert!(tokens.contains("let widgets : ObjectProvider < Widget > = module_macro_lib :: module_macro_lib :: knockoff_context :: ObjectProvider :: new (Arc :: new (listable_bean_factory . handle ())) ;"), "{}", tokens);
+}
+
+#[test]
+fn test_object_provider_is_valid() {
+    let container = parse_container(WIDGETS);
+    assert!(ParseContainerBuilder::is_valid_ordering_create(&container).is_ok());
+}
+
+#[test]
+fn test_runtime_arg_bean_not_autowired() {
+    let error = validation_error(r#"
+        pub mod beans {
+            #[prototype(Label)]
+            pub struct Label {
+                #[runtime_arg] pub text: String,
+            }
+
+            #[service(Page)]
+            pub struct Page {
+                #[autowired] pub label: Label,
+            }
+        }
+    "#);
+    assert!(error.contains("`Label` is created with #[runtime_arg] fields, so it cannot be autowired into `Page.label`. Autowire an ObjectProvider<Label> instead."), "{}", error);
+}
+
+#[test]
+fn test_runtime_arg_requires_prototype() {
+    let error = validation_error(r#"
+        pub mod beans {
+            #[service(Label)]
+            pub struct Label {
+                #[runtime_arg] pub text: String,
+            }
+        }
+    "#);
+    assert!(error.contains("#[runtime_arg] field `Label.text` can only be declared on a #[prototype] bean"), "{}", error);
+}
+
+#[test]
+fn test_object_provider_requires_runtime_args() {
+    let error = validation_error(r#"
+        pub mod beans {
+            #[service(Repo)]
+            #[derive(Default)]
+            pub struct Repo {}
+
+            #[service(WidgetFactory)]
+            pub struct WidgetFactory {
+                #[autowired] pub repos: ObjectProvider<Repo>,
+            }
+        }
+    "#);
+    assert!(error.contains("no #[prototype] bean of type `Repo` with #[runtime_arg] fields was found for the ObjectProvider `WidgetFactory.repos`."), "{}", error);
+}
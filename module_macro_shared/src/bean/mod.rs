@@ -1,4 +1,4 @@
-use syn::{Attribute, Fields, Generics, ItemEnum, ItemStruct, ItemUse, parse2, parse_str, Path, Type};
+use syn::{Attribute, Field, Fields, Generics, ItemEnum, ItemStruct, ItemUse, parse2, parse_str, Path, Type};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -478,6 +478,22 @@ impl BeanDefinition {
             .is_some() || self.has_fn("default")
     }
 
+    /// The named fields annotated with #[runtime_arg], in the order they are declared, which are
+    /// passed in when the prototype is created rather than autowired.
+    pub fn get_runtime_args(&self) -> Vec<&Field> {
+        self.fields.iter()
+            .flat_map(|fields| match fields {
+                Fields::Named(named) => named.named.iter().collect::<Vec<&Field>>(),
+                _ => vec![]
+            })
+            .filter(|field| field.attrs.iter()
+                .any(|attr| attr.path.segments.last()
+                    .filter(|segment| segment.ident.to_string().as_str() == "runtime_arg")
+                    .is_some())
+            )
+            .collect()
+    }
+
     pub fn has_attribute(&self, matcher: &dyn Fn(&Attribute) -> bool) -> bool {
         self.iter_attrs().iter().flat_map(|a| a.iter())
             .any(|a| matcher(a))
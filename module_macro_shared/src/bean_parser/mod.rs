@@ -15,7 +15,7 @@ use knockoff_logging::*;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
 use codegen_utils::project_directory;
-use crate::{AbstractionLevel, AutowiredType, BeanDefinition, BeanPath, BeanType, DependencyMetadata, logger_lazy, ModulesFunctions, ParseUtil};
+use crate::{AbstractionLevel, AutowiredType, BeanDefinition, BeanPath, BeanType, CollectionDependency, DependencyMetadata, logger_lazy, ModulesFunctions, ObjectProviderDependency, ParseUtil};
 use crate::bean_dependency_path_parser::BeanDependencyPathParser;
 use crate::item_fn_parser::ItemFnParser;
 import_logger!("bean_parser.rs");
@@ -123,7 +123,9 @@ impl BeanDependencyParser {
                 info!("Found autowired value {:?}", &autowired_value);
                 AutowiredType::AutowireField{
                     //TODO: this should be a vec
-                    qualifier: Some(autowired_value.clone()).or(qualifier.clone()),
+                    qualifier: Some(autowired_value.clone())
+                        .filter(|autowired_value| autowired_value.len() != 0)
+                        .or(qualifier.clone()),
                     //TODO: this should be a vec
                     lazy: false,
                     field: field.clone(),
@@ -233,6 +235,14 @@ impl BeanDependencyParser {
         let bean_type = Self::get_bean_type(&bean_info, injectable_types_builder, fns, field_attrs);
 
         let generics = bean_info.generics().clone();
+        let collection = match &bean_info {
+            AutowiredType::AutowireField { field, .. } => CollectionDependency::from_type(&field.ty),
+            AutowiredType::AutowiredFnArg { fn_arg, .. } => CollectionDependency::from_type(&fn_arg.ty)
+        };
+        let object_provider = match &bean_info {
+            AutowiredType::AutowireField { field, .. } => ObjectProviderDependency::from_type(&field.ty),
+            AutowiredType::AutowiredFnArg { fn_arg, .. } => ObjectProviderDependency::from_type(&fn_arg.ty)
+        };
 
         if is_autowire_field {
             dep_impl
@@ -246,6 +256,8 @@ impl BeanDependencyParser {
                     is_abstract: None,
                     generics: generics.clone(),
                     qualifier: autowired_qualifier.clone(),
+                    collection: collection.clone(),
+                    object_provider: object_provider.clone(),
                 });
         } else if matches_fn_arg {
             dep_impl
@@ -259,6 +271,8 @@ impl BeanDependencyParser {
                     is_abstract: None,
                     generics: generics.clone(),
                     qualifier: autowired_qualifier.clone(),
+                    collection: collection.clone(),
+                    object_provider: object_provider.clone(),
                 });
         }
     }
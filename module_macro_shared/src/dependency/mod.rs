@@ -1,4 +1,4 @@
-use syn::{Attribute, Field, Generics, ImplItem, ItemImpl, Lifetime, PatType, Type, TypeArray};
+use syn::{Attribute, Field, GenericArgument, Generics, ImplItem, ItemImpl, Lifetime, PatType, Path, PathArguments, Type, TypeArray, TypeParamBound};
 use std::fmt::{Debug, Formatter};
 use std::fmt;
 use codegen_utils::syn_helper;
@@ -141,7 +141,9 @@ pub enum DependencyMetadata {
         bean_type_path: Option<BeanPath>,
         is_abstract: Option<bool>,
         generics: Generics,
-        qualifier: Option<String>
+        qualifier: Option<String>,
+        collection: Option<CollectionDependency>,
+        object_provider: Option<ObjectProviderDependency>
     },
     ArgDepType {
         bean_info: AutowiredType,
@@ -151,11 +153,126 @@ pub enum DependencyMetadata {
         bean_type_path: Option<BeanPath>,
         is_abstract: Option<bool>,
         generics: Generics,
-        qualifier: Option<String>
+        qualifier: Option<String>,
+        collection: Option<CollectionDependency>,
+        object_provider: Option<ObjectProviderDependency>
     }
 }
 
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum CollectionType {
+    Vec,
+    HashMap
+}
+
+/// An #[autowired] Vec<Arc<dyn Trait>>, or HashMap<String, Arc<dyn Trait>> keyed by the qualifier
+/// or the bean id, injected with each of the beans implementing the trait.
+#[derive(Clone)]
+pub struct CollectionDependency {
+    pub collection_type: CollectionType,
+    /// The Arc<dyn Trait> each bean is cast to.
+    pub element_type: Type,
+    pub abstract_type: Path,
+    /// Added by the ConcreteTypeProfileTreeModifier, sorted by #[ordered] and then by key.
+    pub beans: Vec<CollectionBean>
+}
+
+#[derive(Clone)]
+pub struct CollectionBean {
+    pub key: String,
+    pub concrete_type: Type,
+    pub order: Option<i64>,
+    pub prototype: bool
+}
+
+impl CollectionDependency {
+
+    pub fn from_type(collection_type: &Type) -> Option<Self> {
+        let segment = match collection_type {
+            Type::Path(type_path) => type_path.path.segments.last()?,
+            _ => return None
+        };
+        let args = match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter()
+                .flat_map(|arg| match arg {
+                    GenericArgument::Type(arg_type) => Some(arg_type),
+                    _ => None
+                })
+                .collect::<Vec<&Type>>(),
+            _ => return None
+        };
+        let (collection_type, element_type) = match (segment.ident.to_string().as_str(), args.as_slice()) {
+            ("Vec", [element_type]) => (CollectionType::Vec, *element_type),
+            ("HashMap", [key_type, element_type]) if SynHelper::get_str(key_type) == "String" => {
+                (CollectionType::HashMap, *element_type)
+            }
+            _ => return None
+        };
+        Self::get_abstract_type(element_type)
+            .map(|abstract_type| Self {
+                collection_type,
+                element_type: element_type.clone(),
+                abstract_type,
+                beans: vec![],
+            })
+    }
+
+    /// The trait of Arc<dyn Trait>.
+    fn get_abstract_type(element_type: &Type) -> Option<Path> {
+        let segment = match element_type {
+            Type::Path(type_path) => type_path.path.segments.last()?,
+            _ => return None
+        };
+        if segment.ident.to_string() != "Arc" {
+            return None;
+        }
+        match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter()
+                .flat_map(|arg| match arg {
+                    GenericArgument::Type(Type::TraitObject(trait_object)) => trait_object.bounds.iter()
+                        .flat_map(|bound| match bound {
+                            TypeParamBound::Trait(trait_bound) => Some(trait_bound.path.clone()),
+                            _ => None
+                        })
+                        .next(),
+                    _ => None
+                })
+                .next(),
+            _ => None
+        }
+    }
+}
+
+/// An #[autowired] ObjectProvider<T>, creating a new T each time it is called with the
+/// #[runtime_arg] fields of the prototype bean T.
+#[derive(Clone)]
+pub struct ObjectProviderDependency {
+    pub prototype_type: Type
+}
+
+impl ObjectProviderDependency {
+
+    pub fn from_type(provider_type: &Type) -> Option<Self> {
+        let segment = match provider_type {
+            Type::Path(type_path) => type_path.path.segments.last()?,
+            _ => return None
+        };
+        if segment.ident.to_string() != "ObjectProvider" {
+            return None;
+        }
+        match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter()
+                .flat_map(|arg| match arg {
+                    GenericArgument::Type(prototype_type) => Some(Self { prototype_type: prototype_type.clone() }),
+                    _ => None
+                })
+                .next(),
+            _ => None
+        }
+    }
+}
+
 impl DepType for DependencyMetadata {
     fn is_dep_type_abstract(&self) -> bool {
         DependencyMetadata::is_abstract(self)
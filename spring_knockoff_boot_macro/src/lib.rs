@@ -128,6 +128,30 @@ pub fn autowired(attr: TokenStream, input: TokenStream) -> TokenStream {
     input.into()
 }
 
+/// A method of the bean, called once the autowired fields are set. A singleton is only created
+/// once, so the method is called once for each singleton. Async methods are run to completion
+/// with run_blocking before the bean is injected, so within a current_thread runtime they must
+/// not wait on the runtime.
+#[proc_macro_attribute]
+pub fn post_construct(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
+/// A method of the bean taking &self, called when the AppCtx is closed or dropped, before the
+/// methods of the beans it depends on. Async methods are run with run_blocking, so within a
+/// current_thread runtime they must not wait on the runtime.
+#[proc_macro_attribute]
+pub fn pre_destroy(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
+/// A trait for which a {Trait}Delegator is generated, holding every bean implementing it by its
+/// concrete type and implementing the trait by calling each of them without dynamic dispatch.
+#[proc_macro_attribute]
+pub fn delegating(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
 #[proc_macro_attribute]
 pub fn enable_http_security(attr: TokenStream, input: TokenStream) -> TokenStream {
     strip_method_arg_attr(input)
@@ -148,6 +172,16 @@ pub fn rest_controller(attr: TokenStream, input: TokenStream) -> TokenStream {
     input.into()
 }
 
+#[proc_macro_attribute]
+pub fn controller_advice(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
+#[proc_macro_attribute]
+pub fn exception_handler(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
 #[proc_macro_attribute]
 pub fn request_mapping(attr: TokenStream, input: TokenStream) -> TokenStream {
     input.into()
@@ -168,6 +202,28 @@ pub fn post_mapping(attr: TokenStream, input: TokenStream) -> TokenStream {
     strip_method_arg_attr(input)
 }
 
+#[proc_macro_attribute]
+pub fn put_mapping(attr: TokenStream, input: TokenStream) -> TokenStream {
+    strip_method_arg_attr(input)
+}
+
+#[proc_macro_attribute]
+pub fn delete_mapping(attr: TokenStream, input: TokenStream) -> TokenStream {
+    strip_method_arg_attr(input)
+}
+
+#[proc_macro_attribute]
+pub fn patch_mapping(attr: TokenStream, input: TokenStream) -> TokenStream {
+    strip_method_arg_attr(input)
+}
+
+/// CORS for the controller method, or all of the methods of the impl block, read by the
+/// HandlerMappingBuilder, as in #[cross_origin(origins = "https://example.com", max_age = 3600)].
+#[proc_macro_attribute]
+pub fn cross_origin(attr: TokenStream, input: TokenStream) -> TokenStream {
+    input.into()
+}
+
 #[proc_macro_attribute]
 pub fn knockoff_ignore(attr: TokenStream, input: TokenStream) -> TokenStream {
     input.into()
@@ -193,6 +249,157 @@ pub fn request_body(attr: TokenStream, input: TokenStream) -> TokenStream {
     strip_method_arg_attr(input)
 }
 
+#[proc_macro_attribute]
+pub fn validated(attr: TokenStream, input: TokenStream) -> TokenStream {
+    strip_method_arg_attr(input)
+}
+
+/// Implements web_framework_shared::validation::Validate, checking each of the fields with the
+/// #[length(min = 1, max = 20)], #[range(min = 0, max = 150)], #[pattern("^[a-z]+$")] and #[email]
+/// attributes, each accepting an optional message. Fields with #[valid] are validated with their
+/// own Validate impl.
+#[proc_macro_derive(Validate, attributes(length, range, pattern, email, valid))]
+pub fn validate(input: TokenStream) -> TokenStream {
+    let derive_input: DeriveInput = parse_macro_input!(input as DeriveInput);
+    let ident = &derive_input.ident;
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let validations = match &derive_input.data {
+        Data::Struct(data_struct) => {
+            data_struct.fields.iter()
+                .enumerate()
+                .flat_map(|(i, field)| field_validations(i, field))
+                .collect::<Vec<proc_macro2::TokenStream>>()
+        }
+        _ => {
+            return syn::Error::new(derive_input.span(), "Validate can only be derived for structs.")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let ts = quote! {
+        impl #impl_generics web_framework_shared::validation::Validate for #ident #ty_generics #where_clause {
+            fn validate(&self) -> Result<(), web_framework_shared::validation::ValidationErrors> {
+                let mut errors = web_framework_shared::validation::ValidationErrors::default();
+                #(#validations)*
+                errors.into_result()
+            }
+        }
+    };
+    ts.into()
+}
+
+fn field_validations(i: usize, field: &Field) -> Vec<proc_macro2::TokenStream> {
+    let (accessor, name) = field.ident.as_ref()
+        .map(|ident| (quote! { #ident }, ident.to_string()))
+        .or(Some({
+            let index = syn::Index::from(i);
+            (quote! { #index }, i.to_string())
+        }))
+        .unwrap();
+    field.attrs.iter()
+        .flat_map(|attr| {
+            let attr_name = attr.path.get_ident().map(|i| i.to_string());
+            let meta = attr.parse_meta().ok();
+            let message = meta.as_ref()
+                .and_then(|m| validation_meta_str(m, "message"))
+                .map(|m| quote! { Some(#m) })
+                .or(Some(quote! { None }))
+                .unwrap();
+            match attr_name.as_ref().map(|a| a.as_str()) {
+                Some("length") => {
+                    let min = validation_bound(&meta, "min", false);
+                    let max = validation_bound(&meta, "max", false);
+                    Some(quote! {
+                        web_framework_shared::validation::Validator::validate_length(&self.#accessor, #name, #min, #max, #message, &mut errors);
+                    })
+                }
+                Some("range") => {
+                    let min = validation_bound(&meta, "min", true);
+                    let max = validation_bound(&meta, "max", true);
+                    Some(quote! {
+                        web_framework_shared::validation::Validator::validate_range(&self.#accessor, #name, #min, #max, #message, &mut errors);
+                    })
+                }
+                Some("pattern") => {
+                    meta.as_ref()
+                        .and_then(|m| validation_meta_str(m, "regex").or(validation_meta_lit_str(m)))
+                        .map(|pattern| quote! {
+                            web_framework_shared::validation::Validator::validate_pattern(&self.#accessor, #name, #pattern, #message, &mut errors);
+                        })
+                        .or(Some(syn::Error::new(attr.span(), "pattern requires a regex, #[pattern(\"^[a-z]+$\")].").to_compile_error()))
+                }
+                Some("email") => {
+                    Some(quote! {
+                        web_framework_shared::validation::Validator::validate_email(&self.#accessor, #name, #message, &mut errors);
+                    })
+                }
+                Some("valid") => {
+                    Some(quote! {
+                        web_framework_shared::validation::Validator::validate_nested(&self.#accessor, #name, &mut errors);
+                    })
+                }
+                _ => None
+            }
+        })
+        .collect()
+}
+
+fn validation_meta_str(meta: &syn::Meta, name: &str) -> Option<String> {
+    validation_meta_lit(meta, name)
+        .and_then(|lit| match lit {
+            syn::Lit::Str(lit_str) => Some(lit_str.value()),
+            _ => None
+        })
+}
+
+fn validation_meta_lit_str(meta: &syn::Meta) -> Option<String> {
+    match meta {
+        syn::Meta::List(list) => {
+            list.nested.iter()
+                .flat_map(|n| match n {
+                    syn::NestedMeta::Lit(syn::Lit::Str(lit_str)) => Some(lit_str.value()),
+                    _ => None
+                })
+                .next()
+        }
+        _ => None
+    }
+}
+
+fn validation_meta_lit(meta: &syn::Meta, name: &str) -> Option<syn::Lit> {
+    match meta {
+        syn::Meta::List(list) => {
+            list.nested.iter()
+                .flat_map(|n| match n {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident(name) => Some(name_value.lit.clone()),
+                    _ => None
+                })
+                .next()
+        }
+        _ => None
+    }
+}
+
+/// The bound as Option<usize> for length, or Option<f64> for range.
+fn validation_bound(meta: &Option<syn::Meta>, name: &str, float: bool) -> proc_macro2::TokenStream {
+    let value = meta.as_ref()
+        .and_then(|m| validation_meta_lit(m, name))
+        .and_then(|lit| match lit {
+            syn::Lit::Int(lit_int) => lit_int.base10_digits().parse::<f64>().ok(),
+            syn::Lit::Float(lit_float) => lit_float.base10_digits().parse::<f64>().ok(),
+            _ => None
+        });
+    match value {
+        Some(value) if float => quote! { Some(#value) },
+        Some(value) => {
+            let value = value as usize;
+            quote! { Some(#value) }
+        }
+        None => quote! { None }
+    }
+}
+
 fn strip_autowired(input: TokenStream) -> TokenStream {
     if input.to_string().as_str().contains("struct") {
         let mut found: ItemStruct = parse_macro_input!(input as ItemStruct);
@@ -204,20 +411,16 @@ fn strip_autowired(input: TokenStream) -> TokenStream {
     input.into()
 }
 
+/// Functions and methods, including async, const and pub(crate) ones, all parse as an
+/// ImplItemMethod, so the attributes are stripped from their args whatever the signature.
 fn strip_method_arg_attr(input: TokenStream) -> TokenStream {
-    let tokens_string = input.to_string();
-    if tokens_string.starts_with("impl") {
-        let mut found: ImplItemMethod = parse_macro_input!(input as ImplItemMethod);
-        strip_attrs_from_fn_args(&mut found.sig.inputs);
-        return found.to_token_stream().into()
-    } else if tokens_string.starts_with("pub fn")
-        || tokens_string.starts_with("fn ")
-        || tokens_string.starts_with("pub(crate) fn ") {
-        let mut found: ItemFn = parse_macro_input!(input as ItemFn);
-        strip_attrs_from_fn_args(&mut found.sig.inputs);
-        return found.to_token_stream().into()
+    match parse::<ImplItemMethod>(input.clone()) {
+        Ok(mut found) => {
+            strip_attrs_from_fn_args(&mut found.sig.inputs);
+            found.to_token_stream().into()
+        }
+        Err(_) => input
     }
-    input.into()
 }
 
 fn strip_attrs_from_fn_args(args: &mut Punctuated<FnArg, Comma>) {
@@ -10,6 +10,11 @@ use std::sync::{Arc, Mutex, MutexGuard};
 
 pub use authentication_gen::*;
 
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use codegen_utils::project_directory;
+import_logger_root!("lib.rs", concat!(project_directory!(), "/log_out/web_framework.log"));
+
 pub mod web_framework {
     pub mod context;
     pub mod request_context;
@@ -26,5 +31,5 @@ pub mod web_framework {
 #[test]
 fn o() {
     use knockoff_security::JwtToken;;
-    let j = AuthenticationType::Jwt( JwtToken{ token: "".to_string() } );
+    let j = AuthenticationType::Jwt( JwtToken::new("".to_string()) );
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ mod test;
 use core::borrow::BorrowMut;
 use crate::web_framework::convert::{AuthenticationConverterRegistry, ConverterRegistry, MessageConverter, Registration};
 use std::any::Any;
+use std::collections::HashMap;
 use std::{vec};
 use std::default::Default;
 use std::ops::{Deref, DerefMut};
@@ -10,7 +11,7 @@ use std::sync::{Arc};
 use serde::{Deserialize, Serialize};
 use web_framework_shared::controller::{ContextData, Data};
 use web_framework_shared::convert::Converter;
-use web_framework_shared::request::EndpointMetadata;
+use web_framework_shared::request::{EndpointMetadata, WebRequest};
 use crate::web_framework::context_builder::{FilterRegistrarBuilder};
 use crate::web_framework::http::{RequestConverter, RequestStream};
 use crate::web_framework::request_context::SessionContext;
@@ -44,7 +45,10 @@ pub struct UserRequestContext<Request>
 {
     pub request_context: SessionContext,
     pub request: Option<Request>,
-    pub endpoint_metadata: Option<EndpointMetadata>
+    pub endpoint_metadata: Option<EndpointMetadata>,
+    /// Set by the filters, which receive the WebRequest immutably, and added to the WebRequest
+    /// before it is dispatched, so the controller can resolve them.
+    pub attributes: HashMap<String, String>
 }
 
 impl<Request>  UserRequestContext<Request>
@@ -55,9 +59,20 @@ where
         Self {
             request_context: SessionContext::default(),
             request: None,
-            endpoint_metadata: Some(EndpointMetadata::default())
+            endpoint_metadata: Some(EndpointMetadata::default()),
+            attributes: HashMap::new()
         }
     }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        self.attributes.insert(name.to_string(), value.to_string());
+    }
+
+    /// Adds the attributes set by the filters to the request.
+    pub fn apply_attributes(&self, web_request: &mut WebRequest) {
+        self.attributes.iter()
+            .for_each(|(name, value)| web_request.set_attribute(name, value));
+    }
 }
 
 impl<Request> ContextData for UserRequestContext<Request>
@@ -87,14 +102,14 @@ impl <Request, Response> RequestHelpers<Request, Response>
     pub fn new() -> RequestHelpers<Request, Response> {
         Self {
             message_converters: ConverterRegistry::new(None, None),
-            authentication_manager: DelegatingAuthenticationManager { providers: Arc::new(vec![]) }
+            authentication_manager: DelegatingAuthenticationManager::new()
         }
     }
 
     pub fn with_converter_registry(message_converters: ConverterRegistry<Request, Response>) -> RequestHelpers<Request, Response> {
         Self {
             message_converters,
-            authentication_manager: DelegatingAuthenticationManager { providers: Arc::new(vec![]) }
+            authentication_manager: DelegatingAuthenticationManager::new()
         }
     }
 }
@@ -4,6 +4,7 @@ use std::sync::{Arc, Mutex};
 use core::borrow::BorrowMut;
 use knockoff_security::knockoff_security::authentication_type::{AuthenticationAware, AuthenticationConversionError};
 use authentication_gen::{AuthenticationTypeConverter, AuthenticationTypeConverterImpl};
+use web_framework_shared::authority::{DelegatingAuthoritiesMapper, GrantedAuthoritiesMapper, RoleHierarchy};
 use web_framework_shared::convert::Converter;
 use crate::web_framework::context::{Context, RequestHelpers};
 use web_framework_shared::request::{EndpointMetadata, WebRequest};
@@ -153,7 +154,9 @@ impl<Request, Response> Default for RequestContextBuilder<Request, Response>
         Self {
             message_converter_builder: registry,
             authentication_manager_builder: DelegatingAuthenticationManagerBuilder {
-                providers: Arc::new(Mutex::new(vec![].into()))
+                providers: Arc::new(Mutex::new(vec![].into())),
+                authorities_mappers: Arc::new(Mutex::new(vec![])),
+                role_hierarchy: Arc::new(Mutex::new(None))
             },
         }
     }
@@ -170,6 +173,9 @@ pub struct DispatcherBuilder<Request, Response>
 #[derive(Clone, Default)]
 pub struct DelegatingAuthenticationManagerBuilder {
     pub providers: Arc<Mutex<Vec<Box<dyn AuthenticationProvider>>>>,
+    /// Applied in the order they were added, and then the role hierarchy.
+    pub authorities_mappers: Arc<Mutex<Vec<Arc<dyn GrantedAuthoritiesMapper>>>>,
+    pub role_hierarchy: Arc<Mutex<Option<RoleHierarchy>>>,
 }
 
 impl DelegatingAuthenticationManagerBuilder {
@@ -177,6 +183,29 @@ impl DelegatingAuthenticationManagerBuilder {
     pub fn new() -> Self {
         DelegatingAuthenticationManagerBuilder {
             providers: Arc::new(Mutex::new(vec![])),
+            authorities_mappers: Arc::new(Mutex::new(vec![])),
+            role_hierarchy: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn authorities_mapper(&self, authorities_mapper: Arc<dyn GrantedAuthoritiesMapper>) {
+        self.authorities_mappers.lock().unwrap().push(authorities_mapper);
+    }
+
+    pub fn role_hierarchy(&self, role_hierarchy: RoleHierarchy) {
+        *self.role_hierarchy.lock().unwrap() = Some(role_hierarchy);
+    }
+
+    /// The mappers followed by the role hierarchy, so that the roles the mappers rename to are
+    /// expanded by the hierarchy.
+    fn get_authorities_mapper(&self) -> Option<Arc<dyn GrantedAuthoritiesMapper>> {
+        let mut mappers = self.authorities_mappers.lock().unwrap().clone();
+        self.role_hierarchy.lock().unwrap().clone()
+            .map(|role_hierarchy| mappers.push(Arc::new(role_hierarchy)));
+        match mappers.len() {
+            0 => None,
+            1 => mappers.pop(),
+            _ => Some(Arc::new(DelegatingAuthoritiesMapper { mappers }))
         }
     }
 
@@ -199,6 +228,7 @@ impl DelegatingAuthenticationManagerBuilder {
     pub(crate) fn build(&self) -> DelegatingAuthenticationManager {
         DelegatingAuthenticationManager {
             providers: Arc::new(self.get_provider()),
+            authorities_mapper: self.get_authorities_mapper(),
         }
     }
 }
@@ -1,7 +1,6 @@
 
 use core::borrow::BorrowMut;
 use crate::web_framework::context::RequestHelpers;
-use crate::web_framework::filter::filter::MediaType;
 use crate::web_framework::message::MessageType;
 use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
@@ -12,6 +11,7 @@ use std::vec;
 use knockoff_security::knockoff_security::authentication_type::{AuthenticationConversionError, Anonymous};
 use authentication_gen::{AuthenticationType, AuthenticationTypeConverter};
 use web_framework_shared::convert::Converter;
+use web_framework_shared::media_type::MediaType;
 use web_framework_shared::request::{EndpointMetadata, WebRequest};
 use knockoff_security::knockoff_security::authentication_type::AuthenticationAware;
 use crate::web_framework::security::authentication::{Authentication, AuthenticationConverter, AuthenticationDetails, AuthenticationToken};
@@ -47,6 +47,11 @@ macro_rules! provide_default_message_converters {
             None
         }
 
+        fn retrieve_media_type(request: &WebRequest) -> Option<web_framework_shared::media_type::MediaType> {
+            retrieve_media_header(&request.headers)
+                .and_then(|found| web_framework_shared::media_type::MediaType::parse(found))
+        }
+
 
         impl<Request, Response> JsonMessageConverter<Request, Response>
         where
@@ -57,11 +62,11 @@ macro_rules! provide_default_message_converters {
             fn convert_json_to(
                 &self,
                 request: &WebRequest,
-            ) -> Option<MessageType<Response>> {
+            ) -> Option<MessageType<Request>> {
                 let result = serde_json::from_str(&request.body);
                 match result {
                     Ok(mr) => {
-                        let message_type: MessageType<Response> = MessageType { message: mr };
+                        let message_type: MessageType<Request> = MessageType { message: mr };
                         Some(message_type)
                     }
                     Err(err) => {
@@ -72,14 +77,10 @@ macro_rules! provide_default_message_converters {
             }
 
             fn do_convert_json(&self, request: &WebRequest) -> bool {
-                for header in request.headers.iter() {
-                    for potential_media in media_header_keys() {
-                        if header.0 == potential_media && header.1.contains("json") {
-                            return true;
-                        }
-                    }
-                }
-                false
+                retrieve_media_type(request)
+                    .map(|m| m.sub_type == "json" || m.sub_type.ends_with("+json"))
+                    .or(Some(false))
+                    .unwrap()
             }
 
             fn convert_json_from(&self, request: &Response, web_request: &WebRequest) -> Option<String>
@@ -97,21 +98,24 @@ macro_rules! provide_default_message_converters {
             Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
             Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static
         {
+            /// An html form or page cannot be read into the request type.
             fn convert_html_to(&self, request: &WebRequest) -> Option<MessageType<Request>> {
-                todo!()
+                None
             }
 
+            /// Writes the response as escaped json in a minimal html page, for clients such as a
+            /// browser that only accept text/html.
             fn convert_html_from(&self,  request: &Response, request_body: &WebRequest) -> Option<String> {
-                todo!()
+                serde_json::to_string_pretty(request).ok()
+                    .map(|json| json.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;"))
+                    .map(|json| format!("<!DOCTYPE html><html><body><pre>{}</pre></body></html>", json))
             }
 
             fn do_convert_html(&self, request: &WebRequest) -> bool {
-                for header in request.headers.iter() {
-                    if (header.0 == "MediaType" || header.0 == "mediatype") && header.1.contains("json") {
-                        return true;
-                    }
-                }
-                false
+                retrieve_media_type(request)
+                    .map(|m| m.main_type == "text" && m.sub_type == "html")
+                    .or(Some(false))
+                    .unwrap()
             }
 
 
@@ -144,23 +148,12 @@ macro_rules! create_delegating_message_converters {
                         [<$field_name:lower _ $request:lower>]: $converter,
                     )*
                 )*
-                media_types: Vec<String>
             }
         }
 
         impl $delegator {
 
             pub fn new() -> Self where Self: Sized {
-                let mut media_types = vec![];
-                $(
-                    $(
-                        let next_message_converter = <$converter as MessageConverter<$request,$response>>::new_message_converter();
-                        let to_add = <$converter as MessageConverter<$request,$response>>::message_type(&next_message_converter);
-                        for media_type in &to_add {
-                            media_types.push(media_type.clone());
-                        }
-                    )*
-                )*
                 paste::paste!{
                     Self {
                             $(
@@ -168,7 +161,6 @@ macro_rules! create_delegating_message_converters {
                                      [<$field_name:lower _ $request:lower>]: <$converter as MessageConverter<$request,$response>>::new_message_converter(),
                                 )*
                             )*
-                            media_types
                         }
                     }
                 }
@@ -182,18 +174,21 @@ macro_rules! create_delegating_message_converters {
                     $delegator::new()
                 }
 
+                /// Reads the body with the converter for the Content-Type of the request.
                 fn convert_to(
                     &self,
                     request: &WebRequest,
-                ) -> Option<MessageType<$response>>
+                ) -> Option<MessageType<$request>>
                 where
                     Self: Sized,
                 {
-                    retrieve_media_header(&request.headers)
+                    web_framework_shared::media_type::ContentNegotiator::negotiate_consumes(request, &<Self as MessageConverter<$request, $response>>::message_type(self))
+                        .ok()
+                        .flatten()
                         .and_then(|found| {
                             paste! {
                                 $(
-                                    if found == $matcher || found == $matcher {
+                                    if found == $matcher {
                                         return <$converter as MessageConverter<$request, $response>>::convert_to(&self.[<$field_name:lower _ $request:lower>], request);
                                     }
                                 )*
@@ -203,36 +198,37 @@ macro_rules! create_delegating_message_converters {
                         })
                 }
 
+                /// Writes the body with the converter most preferred by the Accept header.
                 fn convert_from(&self, request_body: &$response, request: &WebRequest) -> Option<String>
                 where
                     Self: Sized,
                 {
-                    retrieve_media_header(&request.headers)
-                        .and_then(|found| {
-                            paste! {
-                                $(
-                                    if found == $matcher || found == $matcher {
-                                        return <$converter as MessageConverter<$request,$response>>::convert_from(&self.[<$field_name:lower _ $request:lower>], request_body, request);
-                                    }
-                                )*
-                            }
-                            None
-                        })
+                    web_framework_shared::media_type::ContentNegotiator::negotiate_produces(request, &<Self as MessageConverter<$request, $response>>::message_type(self))
+                        .ok()
+                        .and_then(|found| <Self as MessageConverter<$request, $response>>::convert_from_media_type(self, request_body, request, found.as_str()))
                 }
 
-                fn do_convert(&self, request: &WebRequest) -> bool {
+                fn convert_from_media_type(&self, request_body: &$response, request: &WebRequest, media_type: &str) -> Option<String> {
                     paste! {
                         $(
-                            if <$converter as MessageConverter<$request,$response>>::do_convert(&self.[<$field_name:lower _ $request:lower>], request) {
-                                return true;
+                            if media_type == $matcher {
+                                return <$converter as MessageConverter<$request,$response>>::convert_from(&self.[<$field_name:lower _ $request:lower>], request_body, request);
                             }
                         )*
-                        false
                     }
+                    None
                 }
 
+                fn do_convert(&self, request: &WebRequest) -> bool {
+                    web_framework_shared::media_type::ContentNegotiator::negotiate_consumes(request, &<Self as MessageConverter<$request, $response>>::message_type(self))
+                        .map(|found| found.is_some())
+                        .unwrap_or(false)
+                }
+
+                /// The media types of the converters for this request and response, in the order
+                /// they were registered, so that the first is used if there is no Accept header.
                 fn message_type(&self) -> Vec<String> {
-                    self.media_types.clone()
+                    vec![$($matcher.to_string()),*]
                 }
             }
 
@@ -309,6 +305,12 @@ pub trait MessageConverter<Request, Response>: Send + Sync
 
     fn convert_from(&self, request_body: &Response, request: &WebRequest) -> Option<String>;
 
+    /// Writes the body as the media type chosen by content negotiation, which is one of the
+    /// message types of this converter.
+    fn convert_from_media_type(&self, request_body: &Response, request: &WebRequest, media_type: &str) -> Option<String> {
+        self.convert_from(request_body, request)
+    }
+
     fn do_convert(&self, request: &WebRequest) -> bool;
 
     fn message_type(&self) -> Vec<String>;
@@ -447,10 +449,12 @@ impl <Request, Response> ConverterRegistryContainer<Request, Response> for Conve
         response: &Response,
         request: &WebRequest
     ) -> Option<String> {
+        let media_type_parsed = MediaType::parse(&media_type);
         if self.converters.message_type()
             .iter()
-            .any(|message_type| message_type.clone() == media_type) {
-            return self.converters.convert_from(response, request);
+            .flat_map(|message_type| MediaType::parse(message_type).into_iter())
+            .any(|message_type| media_type_parsed.as_ref().map(|m| message_type.includes(m)).unwrap_or(false)) {
+            return self.converters.convert_from_media_type(response, request, media_type.as_str());
         } else {
             None
         }
@@ -500,6 +504,10 @@ impl <Request, Response> Converters<Request, Response> for RequestHelpers<Reques
             web_request
         )
     }
+
+    fn media_types(&self) -> Vec<String> {
+        self.message_converters.converters.message_type()
+    }
 }
 
 
@@ -511,6 +519,10 @@ pub struct AuthenticationConverterRegistry {
 
 impl Converter<WebRequest, Result<Authentication, AuthenticationConversionError>> for AuthenticationConverterRegistry {
     fn convert(&self, from: &WebRequest) -> Result<Authentication, AuthenticationConversionError> {
+        if let Some(authentication) = self.converters.iter()
+            .find_map(|converter| converter.convert(from).ok()) {
+            return Ok(authentication);
+        }
         self.authentication_type_converter.deref().convert(from)
             .map(|auth_type| {
                 let authorities = auth_type.get_authorities().clone();
@@ -557,6 +569,10 @@ pub trait Converters<Request, Response>
         media_type: Option<String>,
     ) -> Option<String>;
 
+    /// The media types the registered MessageConverters can read and write, used for content
+    /// negotiation.
+    fn media_types(&self) -> Vec<String>;
+
 }
 
 pub trait  ConverterRegistryContainer<Request, Response>
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 use crate::web_framework::context::{Context, RequestContextData, RequestHelpers, UserRequestContext};
 use crate::web_framework::convert::{Converters, RequestTypeExtractor};
-use crate::web_framework::filter::filter::MediaType;
 use crate::web_framework::message::MessageType;
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use web_framework_shared::authority::GrantedAuthority;
 use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::media_type::ContentNegotiator;
 use crate::web_framework::context_builder::RequestContextBuilder;
 use web_framework_shared::request::{ResponseWriter, WebRequest, WebResponse};
 use crate::web_framework::request_context::SessionContext;
@@ -19,7 +19,7 @@ pub struct FilterExecutor;
 General dispatch_server for web request.
 */
 impl FilterExecutor {
-    pub(crate) fn do_request<'a, Response, Request>(
+    pub(crate) async fn do_request<'a, Response, Request>(
         &self,
         request: &WebRequest,
         response: &mut WebResponse,
@@ -32,18 +32,38 @@ impl FilterExecutor {
     {
         if action.authentication_granted(request_context) {
             // TODO: all of this logic will be added to the execution filter chain
-            action.do_action(&request, response, application_context, request_context)
+            let action_response = action.do_action(&request, response, application_context, request_context).await;
+            // a streaming body is written by the http layer rather than the converters, so only
+            // the media type of the stream is checked against the Accept header.
+            if !response.is_error() && response.is_streaming() {
+                if let Err(e) = ContentNegotiator::negotiate_stream(request, response, None) {
+                    ContentNegotiator::write_negotiation_error(response, &e);
+                }
+                return;
+            }
+            if action_response.is_none() || response.is_error() {
+                return;
+            }
+            // the response is written as the media type most preferred by the Accept header that
+            // the converters can write, or 406 if there is none.
+            let producible = application_context.request_context_data.request_context.media_types();
+            let media_type = match ContentNegotiator::negotiate_produces(request, &producible) {
+                Ok(media_type) => media_type,
+                Err(e) => {
+                    ContentNegotiator::write_negotiation_error(response, &e);
+                    return;
+                }
+            };
+            action_response
                 .and_then(|action_response| {
-                    let media_type = request.headers.get("mediatype").cloned()
-                        .or(request.headers.get("MediaType").cloned())
-                        .or(request.headers.get("Mediatype").cloned())
-                        .or(Some("application/json".to_string()));
-
                     application_context.request_context_data.request_context
-                        .convert_from(&action_response, &request, media_type)
+                        .convert_from(&action_response, &request, Some(media_type.clone()))
+                        .map(|converted| (converted, media_type))
                 })
-                .map(|response_to_write| {
+                .map(|(response_to_write, media_type)| {
                     println!("Found response!");
+                    // the converter only converts if it supports the media type.
+                    response.set_content_type_if_absent(&media_type);
                     response.write(response_to_write.clone().as_bytes());
                     response_to_write
                 });
@@ -6,13 +6,14 @@ pub mod filter {
 
     use crate::web_framework::context::{RequestContextData, UserRequestContext};
     use crate::web_framework::dispatch::FilterExecutor;
+    use async_trait::async_trait;
     use core::borrow::Borrow;
     use serde::{Deserialize, Serialize};
     use std::cmp::Ordering;
     use std::ops::Deref;
     use std::sync::Arc;
     use web_framework_shared::controller::{HandlerInterceptor, HandlerMethod};
-    use web_framework_shared::dispatch_server::Handler;
+    use web_framework_shared::dispatch_server::{Handler, RequestDispatcher};
     use web_framework_shared::request::WebResponse;
     use web_framework_shared::request::WebRequest;
 
@@ -55,10 +56,36 @@ pub mod filter {
             Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
             Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync
     {
-        pub fn do_filter(&self, request: &WebRequest, response: &mut WebResponse, ctx: &RequestContextData<Request, Response>,
-                         request_context: &mut Option<Box<UserRequestContext<Request>>>) {
-            self.filters.iter()
-                .for_each(|f| f.filter(request, response, ctx, request_context));
+        pub async fn do_filter(&self, request: &WebRequest, response: &mut WebResponse, ctx: &RequestContextData<Request, Response>,
+                               request_context: &mut Option<Box<UserRequestContext<Request>>>) {
+            for f in self.filters.iter() {
+                f.filter(request, response, ctx, request_context).await;
+                if response.is_error() || response.is_committed() {
+                    break;
+                }
+            }
+        }
+
+        /// Runs the filters, then dispatches the request with the attributes the filters set, unless
+        /// a filter set an error or committed the response. The filters are then called with
+        /// after_dispatch, in the reverse order.
+        pub async fn dispatch<DispatcherT: RequestDispatcher + ?Sized>(
+            &self,
+            mut request: WebRequest,
+            response: &mut WebResponse,
+            ctx: &RequestContextData<Request, Response>,
+            request_context: &mut Option<Box<UserRequestContext<Request>>>,
+            dispatcher: &DispatcherT
+        ) {
+            self.do_filter(&request, response, ctx, request_context).await;
+            if !response.is_error() && !response.is_committed() {
+                request_context.as_ref()
+                    .map(|request_context| request_context.apply_attributes(&mut request));
+                dispatcher.dispatch(&request, response).await;
+            }
+            for f in self.filters.iter().rev() {
+                f.actions.after_dispatch(&request, response, ctx, request_context).await;
+            }
         }
 
         pub fn new(mut filters: Vec<Filter<Request, Response>>) -> Self {
@@ -157,7 +184,7 @@ pub mod filter {
         Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
         Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
     {
-        fn filter(
+        async fn filter(
             &self,
             request: &WebRequest,
             response: &mut WebResponse,
@@ -165,17 +192,19 @@ pub mod filter {
             request_context: &mut Option<Box<UserRequestContext<Request>>>
         ) {
             self.dispatcher
-                .do_request(request, response, self.actions.clone(), ctx, request_context);
+                .do_request(request, response, self.actions.clone(), ctx, request_context)
+                .await;
         }
     }
 
 
+    #[async_trait]
     impl <Request, Response> HandlerInterceptor<UserRequestContext<Request>, RequestContextData<Request, Response>> for Filter<Request, Response>
         where
             Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
             Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
     {
-        fn pre_handle(&self,
+        async fn pre_handle(&self,
                       request: &WebRequest,
                       response: &mut WebResponse,
                       data: &mut HandlerMethod<UserRequestContext<Request>>,
@@ -185,10 +214,10 @@ pub mod filter {
                 request,
                 response,
                 ctx,
-                &mut data.request_ctx_data);
+                &mut data.request_ctx_data).await;
         }
 
-        fn post_handle(&self,
+        async fn post_handle(&self,
                        request: &WebRequest,
                        response: &mut WebResponse,
                        data: &mut HandlerMethod<UserRequestContext<Request>>,
@@ -197,7 +226,7 @@ pub mod filter {
             todo!()
         }
 
-        fn after_completion(&self,
+        async fn after_completion(&self,
                             request: &WebRequest,
                             response: &mut WebResponse,
                             data: &mut HandlerMethod<UserRequestContext<Request>>,
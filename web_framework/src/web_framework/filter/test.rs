@@ -5,6 +5,7 @@ mod test_filter {
     use crate::web_framework::filter::filter::{Filter, FilterChain};
     use crate::web_framework::message::{MessageConverterFilter, MessageType};
     use crate::{create_delegating_message_converters, provide_default_message_converters};
+    use async_trait::async_trait;
     use circular::Buffer;
     use serde::{Deserialize, Serialize};
     use std::io::{Read, Write};
@@ -35,8 +36,9 @@ mod test_filter {
     }
 
     struct TestAction;
+    #[async_trait]
     impl Handler<Example, Example, UserRequestContext<Example>, RequestContextData<Example, Example>> for TestAction {
-        fn do_action(&self, web_request: &WebRequest, response: &mut WebResponse, context: &RequestContextData<Example, Example>,
+        async fn do_action(&self, web_request: &WebRequest, response: &mut WebResponse, context: &RequestContextData<Example, Example>,
                      request_context: &mut Option<Box<UserRequestContext<Example>>>) -> Option<Example> {
             Some(Example{ value: String::from("hello!") })
         }
@@ -94,8 +96,8 @@ mod test_filter {
                                    Some(Box::new(DelegatingMessageConverter::new()))));
         let mut request = WebRequest::default();
         request.headers.insert("Content-Type".into(), "application/json".into());
-        fc.do_filter(&request, x, &RequestContextData{ request_context_data: ctx },
-                     &mut Some(UserRequestContext::default().into()));
+        tokio_test::block_on(fc.do_filter(&request, x, &RequestContextData{ request_context_data: ctx },
+                                          &mut Some(UserRequestContext::default().into())));
 
         assert_eq!(x.response, serde_json::to_string(&Example{value: "hello!".to_string() }).unwrap());
     }
@@ -16,6 +16,7 @@ use crate::web_framework::convert::Registration;
 use crate::web_framework::dispatch::FilterExecutor;
 use web_framework_shared::request::WebResponse;
 use web_framework_shared::request::WebRequest;
+use web_framework_shared::streaming::StreamingResponseBody;
 use crate::web_framework::request_context::SessionContext;
 use crate::web_framework::session::session::HttpSession;
 
@@ -42,21 +43,31 @@ pub trait RequestConversionError: Error {
 
 impl <'a> Into<ChunkedBytes> for &[u8] {
     fn into(self) -> ChunkedBytes {
-        let mut chunks = vec![];
-        let exact = self.chunks_exact(4096);
-        let remainder: [u8; 4096] = exact.remainder().try_into().unwrap();
-        for chunk in exact.into_iter() {
-            let found: [u8; 4096] = chunk.try_into().unwrap();
-            chunks.push(found);
-        }
-        chunks.push(remainder);
         ChunkedBytes {
-            bytes: chunks
+            bytes: self.chunks(ChunkedBytes::CHUNK_SIZE)
+                .map(|chunk| chunk.to_vec())
+                .collect()
         }
     }
 }
 
+/// Bytes split into chunks, the last of which may be shorter, that can be written as a
+/// StreamingResponseBody.
 pub struct ChunkedBytes {
-    bytes: Vec<[u8; 4096]>
+    bytes: Vec<Vec<u8>>
 }
 
+impl ChunkedBytes {
+    pub const CHUNK_SIZE: usize = 4096;
+
+    pub fn chunks(&self) -> &Vec<Vec<u8>> {
+        &self.bytes
+    }
+
+    pub fn into_streaming_body(self, content_type: Option<&str>) -> StreamingResponseBody {
+        StreamingResponseBody::from_stream(
+            futures::stream::iter(self.bytes.into_iter().map(|chunk| Ok(chunk))),
+            content_type
+        )
+    }
+}
@@ -1,6 +1,8 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use web_framework_shared::authority::GrantedAuthority;
 use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::media_type::ContentNegotiator;
 use web_framework_shared::request::{EndpointMetadata, WebRequest, WebResponse};
 use crate::web_framework::context::{Context, RequestContextData, UserRequestContext};
 use crate::web_framework::convert::{Converters, RequestTypeExtractor};
@@ -16,13 +18,14 @@ pub struct MessageType<T: Serialize>
 
 pub struct MessageConverterFilter;
 
+#[async_trait]
 impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>>
 for MessageConverterFilter
     where
         Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
         Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
 {
-    fn do_action(
+    async fn do_action(
         &self,
         web_request: &WebRequest,
         response: &mut WebResponse,
@@ -30,6 +33,13 @@ for MessageConverterFilter
         request_context: &mut Option<Box<UserRequestContext<Request>>>
     ) -> Option<Response> {
 
+        // the body is only read if a converter supports the Content-Type, otherwise 415.
+        let consumable = application_context.request_context_data.request_context.media_types();
+        if let Err(e) = ContentNegotiator::negotiate_consumes(web_request, &consumable) {
+            ContentNegotiator::write_negotiation_error(response, &e);
+            return None;
+        }
+
         application_context.request_context_data
             .request_context
             .convert_extract(&web_request)
@@ -2,5 +2,8 @@ use crate::web_framework::session::session::HttpSession;
 
 #[derive(Default, Clone)]
 pub struct SessionContext {
-    pub http_session: HttpSession
+    pub http_session: HttpSession,
+    /// The session as it was loaded by the SessionFilter, so that it is only saved again if it
+    /// was changed.
+    pub loaded_http_session: Option<HttpSession>
 }
\ No newline at end of file
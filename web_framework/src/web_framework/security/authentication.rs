@@ -5,9 +5,9 @@ use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
-use futures::executor;
-use knockoff_security::knockoff_security::authentication_type::{AuthenticationAware, AuthenticationConversionError};
-use web_framework_shared::authority::GrantedAuthority;
+use async_trait::async_trait;
+use knockoff_security::knockoff_security::authentication_type::{AuthenticationAware, AuthenticationConversionError, UsernamePassword};
+use web_framework_shared::authority::{GrantedAuthoritiesMapper, GrantedAuthority};
 use web_framework_shared::convert::Converter;
 use web_framework_shared::request::WebRequest;
 use crate::web_framework::convert::{AuthenticationConverterRegistry, Registration};
@@ -17,6 +17,8 @@ use crate::web_framework::security::user_details::UserDetailsService;
 #[derive(Clone, Default)]
 pub struct DelegatingAuthenticationManager {
     pub(crate) providers: Arc<Vec<Box<dyn AuthenticationProvider>>>,
+    /// Maps the authorities of authenticated tokens, for example with a RoleHierarchy.
+    pub(crate) authorities_mapper: Option<Arc<dyn GrantedAuthoritiesMapper>>,
 }
 
 
@@ -24,27 +26,86 @@ pub struct DelegatingAuthenticationManager {
 impl DelegatingAuthenticationManager {
     pub(crate) fn new() -> Self {
         Self {
-            providers: Arc::new(vec![])
+            providers: Arc::new(vec![]),
+            authorities_mapper: None
         }
     }
+
+    fn map_authorities(&self, mut auth_token: AuthenticationToken) -> AuthenticationToken {
+        if auth_token.authenticated {
+            self.authorities_mapper.as_ref().map(|authorities_mapper| {
+                let authorities = std::mem::take(&mut auth_token.authorities);
+                auth_token.authorities = authorities_mapper.map_authorities(authorities);
+            });
+        }
+        auth_token
+    }
 }
 
 
+#[async_trait]
 pub trait AuthenticationProvider: Send + Sync {
     fn supports(&self, authentication_token: &AuthenticationType) -> bool;
-    fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken;
+    async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken;
 }
 
+/// Authenticates the username and password with the user loaded from the UserDetailsService. The
+/// password is matched on the blocking pool, as the encoders are slow by design.
 pub struct DaoAuthenticationProvider<U, UDS>
     where
         U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync,
         UDS: UserDetailsService<U, String>
 {
     pub user_details_service: UDS,
-    pub password_encoder: Box<dyn PasswordEncoder>,
-    pub phantom_user: PhantomData<U>
+    pub password_encoder: Arc<dyn PasswordEncoder>,
+    pub phantom_user: PhantomData<U>,
+    /// Matched against when there is no user with the username, so that the response takes as
+    /// long as for a user that exists.
+    user_not_found_password: Option<String>
+}
+
+impl <U, UDS> DaoAuthenticationProvider<U, UDS>
+    where
+        U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync,
+        UDS: UserDetailsService<U, String>
+{
+    pub fn new(user_details_service: UDS, password_encoder: Arc<dyn PasswordEncoder>) -> Self {
+        let user_not_found_password = password_encoder.encode_password("userNotFoundPassword");
+        Self {
+            user_details_service,
+            password_encoder,
+            phantom_user: PhantomData::default(),
+            user_not_found_password,
+        }
+    }
+
+    async fn matches(&self, raw: &str, encoded: &str) -> bool {
+        let password_encoder = self.password_encoder.clone();
+        let (raw, encoded) = (raw.to_string(), encoded.to_string());
+        tokio::task::spawn_blocking(move || password_encoder.matches(&raw, &encoded))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Encodes the password again if it was encoded with weaker parameters or another algorithm,
+    /// now that the raw password is available, and passes it to the UserDetailsService.
+    async fn upgrade_encoding(&self, user_found: &U, raw: &str) {
+        if !self.password_encoder.upgrade_encoding(&user_found.get_password()) {
+            return;
+        }
+        let password_encoder = self.password_encoder.clone();
+        let raw = raw.to_string();
+        let encoded = tokio::task::spawn_blocking(move || password_encoder.encode_password(&raw))
+            .await
+            .ok()
+            .flatten();
+        if let Some(encoded) = encoded {
+            self.user_details_service.update_password(user_found, &encoded).await;
+        }
+    }
 }
 
+#[async_trait]
 impl <U, UDS> AuthenticationProvider for DaoAuthenticationProvider<U, UDS>
     where
         U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync,
@@ -62,18 +123,26 @@ impl <U, UDS> AuthenticationProvider for DaoAuthenticationProvider<U, UDS>
         }
     }
 
-    fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+    async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
         match auth_token.to_owned().auth {
             AuthenticationType::Password(username_password) => {
-                executor::block_on(self.user_details_service.load_by_username(&username_password.username))
-                    .map(|user_found| {
-                        if self.password_encoder.encode_password(&username_password.username) == user_found.get_password() {
+                let user_found = self.user_details_service.load_by_username(&username_password.username).await;
+                let encoded = user_found.as_ref()
+                    .map(|user_found| user_found.get_password())
+                    .or(self.user_not_found_password.clone())
+                    .unwrap_or_default();
+                let matched = self.matches(&username_password.password, &encoded).await;
+                match user_found {
+                    Some(user_found) => {
+                        if matched {
                             auth_token.authenticated = true;
+                            auth_token.authorities = user_found.get_authorities();
+                            self.upgrade_encoding(&user_found, &username_password.password).await;
                         }
-                        auth_token
-                    })
-                    .or(Some(&mut AuthenticationToken::default()))
-                    .unwrap().to_owned()
+                        auth_token.to_owned()
+                    }
+                    None => AuthenticationToken::default()
+                }
             }
             _ => {
                 auth_token.to_owned()
@@ -83,16 +152,18 @@ impl <U, UDS> AuthenticationProvider for DaoAuthenticationProvider<U, UDS>
 
 }
 
+#[async_trait]
 impl AuthenticationProvider for DelegatingAuthenticationManager {
 
     fn supports(&self, authentication_token: &AuthenticationType) -> bool {
         self.providers.iter().any(|auth| auth.supports(authentication_token))
     }
 
-    fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+    async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
         for provider in self.providers.iter() {
             if provider.supports(&auth_token.auth) {
-                return provider.authenticate(auth_token).to_owned();
+                let authenticated = provider.authenticate(auth_token).await.to_owned();
+                return self.map_authorities(authenticated);
             }
         }
         auth_token.to_owned()
@@ -108,6 +179,27 @@ pub struct AuthenticationToken {
     pub authorities: Vec<GrantedAuthority>
 }
 
+impl AuthenticationToken {
+    /// The token for the username and password, before it is authenticated.
+    pub fn username_password(username: &str, password: &str) -> Self {
+        Self {
+            name: username.to_string(),
+            auth: AuthenticationType::Password(UsernamePassword {
+                username: username.to_string(),
+                password: password.to_string(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Removes the password once authenticated, so it is not kept in the SecurityContextHolder.
+    pub fn erase_credentials(&mut self) {
+        if let AuthenticationType::Password(username_password) = &mut self.auth {
+            username_password.password = String::default();
+        }
+    }
+}
+
 /// Represents some details, like IP address, certificate serial number, etc.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum AuthenticationDetails {
@@ -1,30 +1,63 @@
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
+use authentication_gen::AuthenticationType;
+use web_framework_shared::http_method::HttpMethod;
 use web_framework_shared::matcher::{AntPathRequestMatcher, Matcher};
-use web_framework_shared::request::{AuthorizationObject, WebRequest};
+use web_framework_shared::request::{AuthorizationObject, StatusCode, WebRequest};
 use crate::web_framework::context_builder::DelegatingAuthenticationManagerBuilder;
 use crate::web_framework::convert::{Register, Registration};
 use crate::web_framework::security::authentication::{AuthenticationProvider, AuthenticationToken};
 
-//TODO: singleton of these can be added to each bean that has secured annotation and then create
-//  decorator for method and add check if authenticated first, and if so go on to execute block.
+/// Returned from #[secured] and #[pre_authorize] bean methods, which are checked against the
+/// SecurityContextHolder set for the request.
+pub use web_framework_shared::method_security::AccessDeniedError;
+
 pub trait AuthorizationManager<T: AuthorizationObject> {
     fn check(&self, authentication: &AuthenticationToken, to_check: &T) -> AuthorizationDecision;
 }
 
+/// What a request matching a RequestMatcherEntry requires of the authentication.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthorizationRule {
+    PermitAll,
+    DenyAll,
+    /// Any authentication that is not anonymous.
+    Authenticated,
+    /// Only the Anonymous authentication type, for example a login page.
+    Anonymous,
+    /// Any of the authorities of the AuthorityAuthorizationManager.
+    HasAnyAuthority
+}
+
+impl Default for AuthorizationRule {
+    fn default() -> Self {
+        AuthorizationRule::HasAnyAuthority
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct AuthorityAuthorizationManager<T: AuthorizationObject> {
     pub authorities: Vec<String>,
+    pub rule: AuthorizationRule,
     pub authorization_object: PhantomData<T>
 }
 
 impl <T: AuthorizationObject> AuthorizationManager<T> for AuthorityAuthorizationManager<T> {
     fn check(&self, authentication: &AuthenticationToken, to_check: &T) -> AuthorizationDecision {
-        if authentication.authorities.iter()
-            .any(|authority| self.authorities.contains(&authority.authority)) {
-            return AuthorizationDecision { granted: true };
+        let granted = match self.rule {
+            AuthorizationRule::PermitAll => true,
+            AuthorizationRule::DenyAll => false,
+            AuthorizationRule::Authenticated => !AuthorizationDecision::is_anonymous(authentication),
+            AuthorizationRule::Anonymous => AuthorizationDecision::is_anonymous(authentication),
+            AuthorizationRule::HasAnyAuthority => !AuthorizationDecision::is_anonymous(authentication)
+                && authentication.authorities.iter()
+                    .any(|authority| self.authorities.contains(&authority.authority))
+        };
+        if granted {
+            AuthorizationDecision::grant()
+        } else {
+            AuthorizationDecision::deny(authentication)
         }
-        AuthorizationDecision { granted: false }
     }
 }
 
@@ -39,12 +72,10 @@ pub struct DelegatingAuthorizationManagerBuilder {
 }
 
 impl DelegatingAuthorizationManagerBuilder {
+    /// The rules are cloned, so building again, e.g. for another filter chain, has the same rules.
     pub fn build(&self) -> RequestMatcherDelegatingAuthorizationManager {
-        let mut guard = self.authority_authorization_managers.as_ref().lock().unwrap();
-        let mut next = vec![];
-        std::mem::swap(&mut next, guard.as_mut());
         RequestMatcherDelegatingAuthorizationManager {
-            authority_authorization_managers: next,
+            authority_authorization_managers: self.authority_authorization_managers.lock().unwrap().clone(),
         }
     }
 
@@ -69,14 +100,20 @@ pub struct RequestMatcherEntry<T: AuthorizationManager<WebRequest>> {
 
 impl RequestMatcherEntry<AuthorityAuthorizationManager<WebRequest>> {
     pub fn new(endpoints: Vec<&str>, authorities: Vec<&str>) -> Self {
+        Self::new_with_rule(endpoints, vec![], AuthorizationRule::HasAnyAuthority, authorities)
+    }
+
+    /// If methods is empty, matches any method.
+    pub fn new_with_rule(endpoints: Vec<&str>, methods: Vec<HttpMethod>, rule: AuthorizationRule, authorities: Vec<&str>) -> Self {
         let ant_path_request_matcher = endpoints.iter()
-            .map(|e| AntPathRequestMatcher::new(e, "/"))
+            .map(|e| AntPathRequestMatcher::new_with_methods(e, "/", methods.clone()))
             .collect::<Vec<AntPathRequestMatcher>>();
          let authorities = authorities.iter()
              .map(|authority| authority.to_string())
              .collect::<Vec<String>>();
         let entry = AuthorityAuthorizationManager {
             authorities,
+            rule,
             authorization_object: Default::default(),
         };
         RequestMatcherEntry {
@@ -92,17 +129,49 @@ impl <T: AuthorizationManager<WebRequest>> RequestMatcherEntry<T> {
     }
 }
 
+/// The entries are checked in the order they were added, so more specific paths are added
+/// first, and a request matching none of them is denied.
 impl AuthorizationManager<WebRequest> for RequestMatcherDelegatingAuthorizationManager {
     fn check(&self, authentication: &AuthenticationToken, to_check: &WebRequest) -> AuthorizationDecision {
         self.authority_authorization_managers.iter()
             .filter(|a| a.matches(to_check))
             .map(|a| a.entry.check(authentication, to_check))
             .next()
-            .or(Some(AuthorizationDecision { granted: false }))
+            .or(Some(AuthorizationDecision::deny(authentication)))
             .unwrap()
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct AuthorizationDecision {
-    pub granted: bool
+    pub granted: bool,
+    /// Denied because the authentication was anonymous, so authenticating could grant access.
+    pub authentication_required: bool
+}
+
+impl AuthorizationDecision {
+    pub fn grant() -> Self {
+        Self { granted: true, authentication_required: false }
+    }
+
+    pub fn deny(authentication: &AuthenticationToken) -> Self {
+        Self { granted: false, authentication_required: Self::is_anonymous(authentication) }
+    }
+
+    pub fn is_anonymous(authentication: &AuthenticationToken) -> bool {
+        !authentication.authenticated
+            || matches!(authentication.auth, AuthenticationType::Unauthenticated(_))
+    }
+
+    /// 401 if authentication is required, 403 if the authentication does not have access, or
+    /// None if granted.
+    pub fn status(&self) -> Option<StatusCode> {
+        if self.granted {
+            None
+        } else if self.authentication_required {
+            Some(StatusCode::UNAUTHORIZED)
+        } else {
+            Some(StatusCode::FORBIDDEN)
+        }
+    }
 }
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use knockoff_security::knockoff_security::authentication_type::UsernamePassword;
+use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::request::{EndpointMetadata, StatusCode, WebRequest, WebResponse};
+use crate::web_framework::context::{RequestContextData, UserRequestContext};
+use crate::web_framework::dispatch::FilterExecutor;
+use crate::web_framework::filter::filter::Filter;
+use crate::web_framework::security::authentication::{AuthenticationProvider, AuthenticationToken, DelegatingAuthenticationManager};
+use crate::web_framework::security::security_filter::AuthenticationEntryPoint;
+
+/// Sends 401 with the WWW-Authenticate challenge, so the client retries with HTTP Basic
+/// credentials.
+#[derive(Clone, Debug)]
+pub struct BasicAuthenticationEntryPoint {
+    pub realm: String
+}
+
+impl BasicAuthenticationEntryPoint {
+    pub fn new(realm: &str) -> Self {
+        Self { realm: realm.to_string() }
+    }
+
+    pub fn challenge(&self) -> String {
+        format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm)
+    }
+}
+
+impl AuthenticationEntryPoint for BasicAuthenticationEntryPoint {
+    fn commence(&self, web_request: &WebRequest, response: &mut WebResponse) {
+        response.set_status(StatusCode::UNAUTHORIZED);
+        response.set_header("WWW-Authenticate", self.challenge().as_str());
+    }
+}
+
+/// Authenticates the Authorization: Basic header on each request. The authentication is only
+/// added to the SecurityContextHolder for the request, and never saved to the session, so each
+/// request has to send the credentials. A request without the header stays anonymous, and invalid
+/// credentials are challenged.
+pub struct BasicAuthenticationFilter
+{
+    authentication_manager: Arc<DelegatingAuthenticationManager>,
+    entry_point: BasicAuthenticationEntryPoint
+}
+
+impl BasicAuthenticationFilter
+{
+    pub fn new(authentication_manager: Arc<DelegatingAuthenticationManager>, realm: &str) -> Self {
+        Self {
+            authentication_manager,
+            entry_point: BasicAuthenticationEntryPoint::new(realm),
+        }
+    }
+
+    pub fn basic_authentication_filter<Request, Response>(
+        authentication_manager: Arc<DelegatingAuthenticationManager>,
+        realm: &str,
+        dispatcher: Arc<FilterExecutor>,
+        order: Option<u8>
+    ) -> Filter<Request, Response>
+        where
+            Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+            Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    {
+        Filter::new(
+            Arc::new(Self::new(authentication_manager, realm)),
+            order,
+            dispatcher
+        )
+    }
+
+    pub fn is_basic(web_request: &WebRequest) -> bool {
+        web_request.header("Authorization")
+            .and_then(|authorization| authorization.trim().split_once(" "))
+            .map(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+            .or(Some(false))
+            .unwrap()
+    }
+
+    /// The authenticated token, or None if there were no Basic credentials, or if they were
+    /// invalid, in which case the challenge is sent.
+    pub async fn authenticate(&self, web_request: &WebRequest, response: &mut WebResponse) -> Option<AuthenticationToken> {
+        if !Self::is_basic(web_request) {
+            return None;
+        }
+        let username_password = match UsernamePassword::parse_credentials(web_request) {
+            Ok(username_password) => username_password,
+            Err(_) => {
+                self.entry_point.commence(web_request, response);
+                return None;
+            }
+        };
+        let mut auth_token = AuthenticationToken::username_password(&username_password.username, &username_password.password);
+        let mut authenticated = self.authentication_manager.authenticate(&mut auth_token).await;
+        if !authenticated.authenticated {
+            self.entry_point.commence(web_request, response);
+            return None;
+        }
+        authenticated.erase_credentials();
+        Some(authenticated)
+    }
+}
+
+#[async_trait]
+impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for BasicAuthenticationFilter
+where
+    Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        if let Some(auth) = self.authenticate(web_request, response).await {
+            request_context.as_mut().map(|mut request_context| {
+                let security_context_holder = &mut request_context.request_context.http_session.security_context_holder;
+                security_context_holder.auth_token = Some(auth);
+                security_context_holder.set_current();
+            });
+        }
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use web_framework_shared::cors::{CorsConfiguration, CorsProcessor, UrlBasedCorsConfigurationSource};
+use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::request::{EndpointMetadata, WebRequest, WebResponse};
+use crate::web_framework::context::{RequestContextData, UserRequestContext};
+use crate::web_framework::dispatch::FilterExecutor;
+use crate::web_framework::filter::filter::Filter;
+
+/// The CorsConfiguration for each path pattern, as in
+/// mappings: { "/api/**": { allowed_origins: ["https://example.com"], ... } }.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CorsProperties {
+    pub mappings: HashMap<String, CorsConfiguration>
+}
+
+impl Default for CorsProperties {
+    fn default() -> Self {
+        Self {
+            mappings: HashMap::new()
+        }
+    }
+}
+
+/// Writes the CORS headers for the most specific path configured, rejecting requests from origins
+/// that are not allowed with 403. A preflight is answered and committed, so it has to be ordered
+/// before the authorization filter, which would otherwise reject it as it has no credentials.
+pub struct CorsFilter {
+    configuration_source: UrlBasedCorsConfigurationSource
+}
+
+impl CorsFilter {
+    pub fn new(configuration_source: UrlBasedCorsConfigurationSource) -> Self {
+        Self { configuration_source }
+    }
+
+    pub fn from_properties(properties: &CorsProperties) -> Self {
+        let mut configuration_source = UrlBasedCorsConfigurationSource::default();
+        properties.mappings.iter()
+            .for_each(|(path, configuration)| configuration_source.register(path, configuration.clone()));
+        Self::new(configuration_source)
+    }
+
+    pub fn cors_filter<Request, Response>(
+        cors_filter: CorsFilter,
+        dispatcher: Arc<FilterExecutor>,
+        order: Option<u8>
+    ) -> Filter<Request, Response>
+        where
+            Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+            Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    {
+        Filter::new(Arc::new(cors_filter), order, dispatcher)
+    }
+
+    /// Returns false if the request was rejected.
+    pub fn apply(&self, web_request: &WebRequest, response: &mut WebResponse) -> bool {
+        let configuration = match self.configuration_source.configuration(web_request) {
+            Some(configuration) => configuration,
+            None => return true
+        };
+        let accepted = CorsProcessor::process_request(configuration, web_request, response);
+        if accepted && CorsProcessor::is_preflight(web_request) {
+            response.commit();
+        }
+        accepted
+    }
+}
+
+#[async_trait]
+impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for CorsFilter
+where
+    Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        self.apply(web_request, response);
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use data_framework::Repo;
+use web_framework_shared::csrf::{CsrfToken, CSRF_TOKEN_ATTRIBUTE};
+use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::matcher::AntPathRequestMatcher;
+use web_framework_shared::request::{EndpointMetadata, StatusCode, WebRequest, WebResponse};
+use crate::web_framework::context::{RequestContextData, UserRequestContext};
+use crate::web_framework::dispatch::FilterExecutor;
+use crate::web_framework::filter::filter::Filter;
+use crate::web_framework::session::session::{HttpSession, SessionManager};
+
+/// Where the expected token is kept between requests.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CsrfMode {
+    /// The token is saved in the HttpSession, and the client sends it back in the header or the
+    /// form parameter.
+    SynchronizerToken,
+    /// The token is sent to the client in a cookie the page can read, and the client sends it back
+    /// in the header or the form parameter. Another site can send the cookie but cannot read it.
+    DoubleSubmitCookie
+}
+
+impl Default for CsrfMode {
+    fn default() -> Self {
+        CsrfMode::SynchronizerToken
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CsrfProperties {
+    pub mode: CsrfMode,
+    pub header_name: String,
+    pub parameter_name: String,
+    /// The cookie the token is sent in for DoubleSubmitCookie.
+    pub cookie_name: String,
+    /// The key of the token in the SessionData for SynchronizerToken.
+    pub session_attribute_name: String
+}
+
+impl Default for CsrfProperties {
+    fn default() -> Self {
+        Self {
+            mode: CsrfMode::default(),
+            header_name: "X-CSRF-TOKEN".to_string(),
+            parameter_name: "_csrf".to_string(),
+            cookie_name: "XSRF-TOKEN".to_string(),
+            session_attribute_name: "CSRF_TOKEN".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait CsrfTokenRepository: Send + Sync {
+    async fn load_token(&self, web_request: &WebRequest, http_session: &HttpSession) -> Option<String>;
+    async fn save_token(&self, token: &str, response: &mut WebResponse, http_session: &mut HttpSession);
+}
+
+/// Saves the token in the SessionData, creating the session if the request did not have one.
+pub struct HttpSessionCsrfTokenRepository<R> {
+    session_attribute_name: String,
+    session_manager: Arc<SessionManager<R>>
+}
+
+impl <R> HttpSessionCsrfTokenRepository<R>
+    where
+        R: for<'b> Repo<'b, HttpSession, String>
+{
+    pub fn new(properties: &CsrfProperties, session_manager: Arc<SessionManager<R>>) -> Self {
+        Self {
+            session_attribute_name: properties.session_attribute_name.clone(),
+            session_manager,
+        }
+    }
+}
+
+#[async_trait]
+impl <R> CsrfTokenRepository for HttpSessionCsrfTokenRepository<R>
+    where
+        R: for<'b> Repo<'b, HttpSession, String>
+{
+    async fn load_token(&self, web_request: &WebRequest, http_session: &HttpSession) -> Option<String> {
+        http_session.session_data.get(&self.session_attribute_name).cloned()
+    }
+
+    async fn save_token(&self, token: &str, response: &mut WebResponse, http_session: &mut HttpSession) {
+        http_session.session_data.insert(&self.session_attribute_name, token);
+        self.session_manager.save_new(http_session, response).await;
+    }
+}
+
+/// Sends the token in a cookie without HttpOnly, so that the page can copy it into the header.
+pub struct CookieCsrfTokenRepository {
+    cookie_name: String
+}
+
+impl CookieCsrfTokenRepository {
+    pub fn new(properties: &CsrfProperties) -> Self {
+        Self { cookie_name: properties.cookie_name.clone() }
+    }
+}
+
+#[async_trait]
+impl CsrfTokenRepository for CookieCsrfTokenRepository {
+    async fn load_token(&self, web_request: &WebRequest, http_session: &HttpSession) -> Option<String> {
+        web_request.cookie(&self.cookie_name)
+    }
+
+    async fn save_token(&self, token: &str, response: &mut WebResponse, http_session: &mut HttpSession) {
+        response.add_cookie(format!("{}={}; Path=/; SameSite=Lax", self.cookie_name, token).as_str());
+    }
+}
+
+/// Rejects POST, PUT, PATCH and DELETE requests with 403 unless they send the expected token in
+/// the header or the form parameter. Safe methods and exempt paths are not checked, but the token
+/// is still loaded, or generated and saved, so that the controller can render it with
+/// #[csrf_token].
+pub struct CsrfFilter {
+    properties: CsrfProperties,
+    token_repository: Arc<dyn CsrfTokenRepository>,
+    exempt_paths: Vec<AntPathRequestMatcher>
+}
+
+impl CsrfFilter {
+    pub fn new(properties: CsrfProperties, token_repository: Arc<dyn CsrfTokenRepository>, exempt_paths: Vec<&str>) -> Self {
+        Self {
+            properties,
+            token_repository,
+            exempt_paths: exempt_paths.into_iter()
+                .map(|path| AntPathRequestMatcher::new(path, "/"))
+                .collect(),
+        }
+    }
+
+    pub fn synchronizer_token<R>(properties: CsrfProperties, session_manager: Arc<SessionManager<R>>, exempt_paths: Vec<&str>) -> Self
+        where
+            R: for<'b> Repo<'b, HttpSession, String> + 'static
+    {
+        let token_repository = HttpSessionCsrfTokenRepository::new(&properties, session_manager);
+        Self::new(CsrfProperties { mode: CsrfMode::SynchronizerToken, ..properties }, Arc::new(token_repository), exempt_paths)
+    }
+
+    pub fn double_submit_cookie(properties: CsrfProperties, exempt_paths: Vec<&str>) -> Self {
+        let token_repository = CookieCsrfTokenRepository::new(&properties);
+        Self::new(CsrfProperties { mode: CsrfMode::DoubleSubmitCookie, ..properties }, Arc::new(token_repository), exempt_paths)
+    }
+
+    pub fn csrf_filter<Request, Response>(
+        csrf_filter: CsrfFilter,
+        dispatcher: Arc<FilterExecutor>,
+        order: Option<u8>
+    ) -> Filter<Request, Response>
+        where
+            Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+            Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    {
+        Filter::new(Arc::new(csrf_filter), order, dispatcher)
+    }
+
+    pub fn properties(&self) -> &CsrfProperties {
+        &self.properties
+    }
+
+    pub fn generate_token() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn requires_protection(&self, web_request: &WebRequest) -> bool {
+        !matches!(web_request.method.as_str(), "GET" | "HEAD" | "OPTIONS" | "TRACE")
+            && !self.exempt_paths.iter().any(|path| path.matches_path(web_request))
+    }
+
+    /// The token sent by the client, from the header or else from the url encoded form.
+    pub fn actual_token(&self, web_request: &WebRequest) -> Option<String> {
+        web_request.header(&self.properties.header_name)
+            .cloned()
+            .or_else(|| serde_urlencoded::from_str::<HashMap<String, String>>(&web_request.body)
+                .ok()
+                .and_then(|mut form| form.remove(&self.properties.parameter_name))
+            )
+    }
+
+    /// Checks the token, setting 403 and returning false if it is missing or does not match. The
+    /// expected token is added to the request attributes, so it can be resolved with #[csrf_token].
+    pub async fn protect(&self, web_request: &mut WebRequest, response: &mut WebResponse, http_session: &mut HttpSession) -> bool {
+        let expected = self.token_repository.load_token(web_request, http_session).await;
+        let token = match expected.as_ref() {
+            Some(expected) => expected.clone(),
+            None => {
+                let token = Self::generate_token();
+                self.token_repository.save_token(&token, response, http_session).await;
+                token
+            }
+        };
+        CsrfToken::new(&self.properties.header_name, &self.properties.parameter_name, &token)
+            .expose(web_request);
+        if !self.requires_protection(web_request) {
+            return true;
+        }
+        let matched = expected.zip(self.actual_token(web_request))
+            .map(|(expected, actual)| Self::constant_time_eq(expected.as_bytes(), actual.as_bytes()))
+            .or(Some(false))
+            .unwrap();
+        if !matched {
+            response.set_status(StatusCode::FORBIDDEN);
+        }
+        matched
+    }
+
+    fn constant_time_eq(expected: &[u8], actual: &[u8]) -> bool {
+        expected.len() == actual.len()
+            && expected.iter().zip(actual.iter()).fold(0, |diff, (e, a)| diff | (e ^ a)) == 0
+    }
+}
+
+#[async_trait]
+impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for CsrfFilter
+where
+    Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        // the filters receive the request immutably, so the token is added to the request context,
+        // which adds it to the request before it is dispatched.
+        let mut web_request = web_request.clone();
+        match request_context.as_mut() {
+            Some(request_context) => {
+                self.protect(&mut web_request, response, &mut request_context.request_context.http_session).await;
+                web_request.attribute(CSRF_TOKEN_ATTRIBUTE)
+                    .map(|token| request_context.set_attribute(CSRF_TOKEN_ATTRIBUTE, token));
+            }
+            None => {
+                self.protect(&mut web_request, response, &mut HttpSession::default()).await;
+            }
+        }
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use data_framework::Repo;
+use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::request::{EndpointMetadata, StatusCode, WebRequest, WebResponse};
+use crate::web_framework::context::{RequestContextData, UserRequestContext};
+use crate::web_framework::dispatch::FilterExecutor;
+use crate::web_framework::filter::filter::Filter;
+use crate::web_framework::security::authentication::{AuthenticationProvider, AuthenticationToken, DelegatingAuthenticationManager};
+use crate::web_framework::security::security_filter::AuthenticationEntryPoint;
+use crate::web_framework::security::security_context_holder::SecurityContextHolder;
+use crate::web_framework::session::session::{HttpSession, SessionManager};
+
+/// Configuration of form login. The login form is posted as application/x-www-form-urlencoded to
+/// the login processing path, and the response redirects to the default success url, or to the
+/// failure url if the credentials were invalid.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FormLoginProperties {
+    pub login_page: String,
+    pub login_processing_path: String,
+    pub username_parameter: String,
+    pub password_parameter: String,
+    pub default_success_url: String,
+    pub failure_url: String
+}
+
+impl Default for FormLoginProperties {
+    fn default() -> Self {
+        Self {
+            login_page: "/login".to_string(),
+            login_processing_path: "/login".to_string(),
+            username_parameter: "username".to_string(),
+            password_parameter: "password".to_string(),
+            default_success_url: "/".to_string(),
+            failure_url: "/login?error".to_string(),
+        }
+    }
+}
+
+/// Redirects anonymous requests that are denied to the login page.
+#[derive(Clone, Debug)]
+pub struct LoginUrlAuthenticationEntryPoint {
+    pub login_page: String
+}
+
+impl LoginUrlAuthenticationEntryPoint {
+    pub fn new(login_page: &str) -> Self {
+        Self { login_page: login_page.to_string() }
+    }
+
+    pub fn redirect(response: &mut WebResponse, location: &str) {
+        response.set_status(StatusCode::FOUND);
+        response.set_header("Location", location);
+    }
+}
+
+impl AuthenticationEntryPoint for LoginUrlAuthenticationEntryPoint {
+    fn commence(&self, web_request: &WebRequest, response: &mut WebResponse) {
+        Self::redirect(response, &self.login_page);
+    }
+}
+
+/// Authenticates the login form, and on success saves the authentication in the session, so
+/// that the SessionFilter adds it to the SecurityContextHolder of later requests with the session
+/// cookie.
+pub struct FormLoginFilter<R>
+{
+    properties: FormLoginProperties,
+    authentication_manager: Arc<DelegatingAuthenticationManager>,
+    session_manager: Arc<SessionManager<R>>
+}
+
+impl <R> FormLoginFilter<R>
+    where
+        R: for<'b> Repo<'b, HttpSession, String>
+{
+    pub fn new(properties: FormLoginProperties, authentication_manager: Arc<DelegatingAuthenticationManager>, session_manager: Arc<SessionManager<R>>) -> Self {
+        Self { properties, authentication_manager, session_manager }
+    }
+
+    pub fn form_login_filter<Request, Response>(
+        properties: FormLoginProperties,
+        authentication_manager: Arc<DelegatingAuthenticationManager>,
+        session_manager: Arc<SessionManager<R>>,
+        dispatcher: Arc<FilterExecutor>,
+        order: Option<u8>
+    ) -> Filter<Request, Response>
+        where
+            R: 'static,
+            Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+            Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    {
+        Filter::new(
+            Arc::new(Self::new(properties, authentication_manager, session_manager)),
+            order,
+            dispatcher
+        )
+    }
+
+    pub fn properties(&self) -> &FormLoginProperties {
+        &self.properties
+    }
+
+    /// Handles a POST to the login processing path, returning the session if the login
+    /// succeeded. The session id is rotated, so that a session id set before the login cannot be
+    /// used to take over the authenticated session. The redirect is committed, so the login post
+    /// is not dispatched.
+    pub async fn login(&self, web_request: &WebRequest, response: &mut WebResponse, previous_session: Option<&HttpSession>) -> Option<HttpSession> {
+        if web_request.method.as_str() != "POST" || web_request.uri.path() != self.properties.login_processing_path {
+            return None;
+        }
+        let form = serde_urlencoded::from_str::<HashMap<String, String>>(&web_request.body)
+            .unwrap_or_default();
+        let mut authenticated = match (form.get(&self.properties.username_parameter), form.get(&self.properties.password_parameter)) {
+            (Some(username), Some(password)) => {
+                let mut auth_token = AuthenticationToken::username_password(username, password);
+                self.authentication_manager.authenticate(&mut auth_token).await
+            }
+            _ => AuthenticationToken::default()
+        };
+        if !authenticated.authenticated {
+            LoginUrlAuthenticationEntryPoint::redirect(response, &self.properties.failure_url);
+            response.commit();
+            return None;
+        }
+
+        authenticated.erase_credentials();
+        let mut session = previous_session.cloned()
+            .or(Some(HttpSession::default()))
+            .unwrap();
+        session.security_context_holder = SecurityContextHolder { auth_token: Some(authenticated) };
+        self.session_manager.rotate_id(&mut session, response).await;
+        LoginUrlAuthenticationEntryPoint::redirect(response, &self.properties.default_success_url);
+        response.commit();
+        Some(session)
+    }
+}
+
+#[async_trait]
+impl <R, Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for FormLoginFilter<R>
+where
+    R: for<'b> Repo<'b, HttpSession, String>,
+    Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        let previous_session = request_context.as_ref()
+            .map(|request_context| request_context.request_context.http_session.clone())
+            .filter(|http_session| http_session.id.is_some());
+        if let Some(session) = self.login(web_request, response, previous_session.as_ref()).await {
+            request_context.as_mut().map(|mut request_context| {
+                request_context.request_context.http_session = session;
+                request_context.request_context.http_session.security_context_holder.set_current();
+            });
+        }
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
+}
@@ -1,13 +1,22 @@
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
+use web_framework_shared::authority::{GrantedAuthoritiesMapper, RoleHierarchy};
+use web_framework_shared::cors::{CorsConfiguration, UrlBasedCorsConfigurationSource};
+use web_framework_shared::http_method::HttpMethod;
 use web_framework_shared::request::WebRequest;
 use crate::web_framework::context_builder::DelegatingAuthenticationManagerBuilder;
 use crate::web_framework::convert::{Register, Registration};
 use crate::web_framework::filter::filter::FilterChain;
-use crate::web_framework::security::authorization::{AuthorityAuthorizationManager, AuthorizationManager, DelegatingAuthorizationManagerBuilder, RequestMatcherDelegatingAuthorizationManager, RequestMatcherEntry};
+use crate::web_framework::dispatch::FilterExecutor;
+use crate::web_framework::filter::filter::Filter;
+use crate::web_framework::security::authorization::{AuthorityAuthorizationManager, AuthorizationManager, AuthorizationRule, DelegatingAuthorizationManagerBuilder, RequestMatcherDelegatingAuthorizationManager, RequestMatcherEntry};
 use crate::web_framework::security::authentication::{AuthenticationProvider, DelegatingAuthenticationManager};
-use crate::web_framework::security::security_filter::SecurityFilterChain;
+use crate::web_framework::security::basic::BasicAuthenticationEntryPoint;
+use crate::web_framework::security::cors::CorsFilter;
+use crate::web_framework::security::csrf::{CsrfFilter, CsrfProperties, CsrfTokenRepository};
+use crate::web_framework::security::form_login::LoginUrlAuthenticationEntryPoint;
+use crate::web_framework::security::security_filter::{AuthenticationEntryPoint, AuthorizationFilter, SecurityFilterChain};
 
 pub struct HttpSecurity<Request, Response>
     where
@@ -16,6 +25,9 @@ pub struct HttpSecurity<Request, Response>
 {
     pub authentication_manager: Arc<Mutex<Option<DelegatingAuthenticationManagerBuilder>>>,
     pub authorization_manager: Arc<Mutex<Option<DelegatingAuthorizationManagerBuilder>>>,
+    pub authentication_entry_point: Arc<Mutex<Option<Arc<dyn AuthenticationEntryPoint>>>>,
+    pub csrf_exempt_paths: Arc<Mutex<Vec<String>>>,
+    pub cors_configuration_source: Arc<Mutex<UrlBasedCorsConfigurationSource>>,
     pub phantom_req: PhantomData<Request>,
     pub phantom_res: PhantomData<Response>
 }
@@ -28,6 +40,9 @@ impl <Request, Response> Default for HttpSecurity<Request, Response>
         Self {
             authentication_manager: Arc::new(Mutex::new(Some(DelegatingAuthenticationManagerBuilder::default()))),
             authorization_manager: Arc::new(Mutex::new(Some(DelegatingAuthorizationManagerBuilder::default()))),
+            authentication_entry_point: Arc::new(Mutex::new(None)),
+            csrf_exempt_paths: Arc::new(Mutex::new(vec![])),
+            cors_configuration_source: Arc::new(Mutex::new(UrlBasedCorsConfigurationSource::default())),
             phantom_req: Default::default(),
             phantom_res: Default::default(),
         }
@@ -62,6 +77,37 @@ impl <Request, Response> HttpSecurity<Request, Response>
         }).expect("Could not add request matcher to authorization manager.");
     }
 
+    /// Starts a rule for the paths, for example
+    /// http.path("/api/**").method(HttpMethod::Post).has_any_authority(["SCOPE_write"]).
+    /// Rules are checked in the order they are added, and a request matching no rule is denied.
+    pub fn path(&self, path: &str) -> AuthorizeRequest<'_, Request, Response> {
+        self.paths(vec![path])
+    }
+
+    pub fn paths(&self, paths: Vec<&str>) -> AuthorizeRequest<'_, Request, Response> {
+        AuthorizeRequest {
+            http: self,
+            paths: paths.into_iter().map(|path| path.to_string()).collect(),
+            methods: vec![],
+        }
+    }
+
+    /// Matches every path, so it is added last, for example http.any_request().authenticated().
+    pub fn any_request(&self) -> AuthorizeRequest<'_, Request, Response> {
+        self.path("/**")
+    }
+
+    /// Builds the rules added so far into a filter that sets 401 or 403 for denied requests.
+    pub fn authorization_filter(&self, dispatcher: Arc<FilterExecutor>, order: Option<u8>) -> Filter<Request, Response> {
+        let authorization_manager = self.authorization_manager.lock()
+            .map(|auth| auth.as_ref().map(|auth| auth.build()))
+            .expect("Could not build authorization manager.")
+            .or(Some(RequestMatcherDelegatingAuthorizationManager::default()))
+            .unwrap();
+        let entry_point = self.authentication_entry_point.lock().unwrap().clone();
+        AuthorizationFilter::authorization_filter(Arc::new(authorization_manager), entry_point, dispatcher, order)
+    }
+
     pub fn authentication_provider(&self, authentication_manager: Box<dyn AuthenticationProvider>) {
         self.authentication_manager.lock().map(|mut auth_manager| {
             auth_manager.as_mut().map(|auth_manager| {
@@ -70,6 +116,77 @@ impl <Request, Response> HttpSecurity<Request, Response>
         }).expect("Could not add delegating authentication manager.");
     }
 
+    /// Called by the authorization filter when an anonymous request is denied.
+    pub fn authentication_entry_point(&self, entry_point: Arc<dyn AuthenticationEntryPoint>) {
+        *self.authentication_entry_point.lock().unwrap() = Some(entry_point);
+    }
+
+    /// Denied anonymous requests are sent the WWW-Authenticate challenge for the realm. The
+    /// BasicAuthenticationFilter authenticates the credentials.
+    pub fn http_basic(&self, realm: &str) -> &Self {
+        self.authentication_entry_point(Arc::new(BasicAuthenticationEntryPoint::new(realm)));
+        self
+    }
+
+    /// Denied anonymous requests are redirected to the login page. The FormLoginFilter
+    /// authenticates the login form.
+    pub fn form_login(&self, login_page: &str) -> &Self {
+        self.authentication_entry_point(Arc::new(LoginUrlAuthenticationEntryPoint::new(login_page)));
+        self
+    }
+
+    /// The CORS allowed for the path, for example
+    /// http.cors("/api/**", CorsConfiguration::permit_default_values()).
+    pub fn cors(&self, path: &str, configuration: CorsConfiguration) -> &Self {
+        self.cors_configuration_source.lock().unwrap().register(path, configuration);
+        self
+    }
+
+    /// The CorsFilter for the paths added with cors. It should have a lower order than the
+    /// authorization filter, so that preflight requests are answered before they are denied.
+    pub fn cors_filter(&self, dispatcher: Arc<FilterExecutor>, order: Option<u8>) -> Filter<Request, Response> {
+        let configuration_source = self.cors_configuration_source.lock().unwrap().clone();
+        CorsFilter::cors_filter(CorsFilter::new(configuration_source), dispatcher, order)
+    }
+
+    /// Paths the CsrfFilter does not check, for example an api authenticated with bearer tokens.
+    pub fn csrf_ignoring(&self, paths: Vec<&str>) -> &Self {
+        self.csrf_exempt_paths.lock().unwrap()
+            .extend(paths.into_iter().map(|path| path.to_string()));
+        self
+    }
+
+    /// The CsrfFilter for the token repository, exempting the paths added with csrf_ignoring.
+    pub fn csrf_filter(&self, properties: CsrfProperties, token_repository: Arc<dyn CsrfTokenRepository>,
+                       dispatcher: Arc<FilterExecutor>, order: Option<u8>) -> Filter<Request, Response> {
+        let exempt_paths = self.csrf_exempt_paths.lock().unwrap().clone();
+        let csrf_filter = CsrfFilter::new(
+            properties, token_repository,
+            exempt_paths.iter().map(|path| path.as_str()).collect()
+        );
+        CsrfFilter::csrf_filter(csrf_filter, dispatcher, order)
+    }
+
+    /// Maps the authorities after authentication, so the rules only need the mapped authorities.
+    /// The mappers are applied in the order they were added, before the role hierarchy.
+    pub fn authorities_mapper(&self, authorities_mapper: Arc<dyn GrantedAuthoritiesMapper>) {
+        self.authentication_manager.lock().map(|mut auth_manager| {
+            auth_manager.as_mut().map(|auth_manager| {
+                auth_manager.authorities_mapper(authorities_mapper);
+            })
+        }).expect("Could not add authorities mapper.");
+    }
+
+    /// For example ROLE_ADMIN > ROLE_STAFF > ROLE_USER, so that has_role("USER") also permits
+    /// ROLE_ADMIN.
+    pub fn role_hierarchy(&self, role_hierarchy: RoleHierarchy) {
+        self.authentication_manager.lock().map(|mut auth_manager| {
+            auth_manager.as_mut().map(|auth_manager| {
+                auth_manager.role_hierarchy(role_hierarchy);
+            })
+        }).expect("Could not add role hierarchy.");
+    }
+
     pub fn authorization_manager(&self, authentication_manager: RequestMatcherEntry<AuthorityAuthorizationManager<WebRequest>>) {
         self.authorization_manager.lock().map(|mut auth_manager| {
             auth_manager.as_mut().map(|auth_manager| {
@@ -88,3 +205,78 @@ impl <Request, Response> HttpSecurity<Request, Response>
 
 }
 
+/// A rule being added with HttpSecurity::path, registered when it is completed with
+/// permit_all, deny_all, authenticated, anonymous or one of the authority checks.
+pub struct AuthorizeRequest<'a, Request, Response>
+    where
+        Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
+        Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static
+{
+    http: &'a HttpSecurity<Request, Response>,
+    paths: Vec<String>,
+    methods: Vec<HttpMethod>
+}
+
+impl <'a, Request, Response> AuthorizeRequest<'a, Request, Response>
+    where
+        Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
+        Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static
+{
+    /// Restricts the rule to the method. If no method is added, the rule matches any method.
+    pub fn method(mut self, method: HttpMethod) -> Self {
+        if !self.methods.contains(&method) {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    pub fn permit_all(self) -> &'a HttpSecurity<Request, Response> {
+        self.rule(AuthorizationRule::PermitAll, vec![])
+    }
+
+    pub fn deny_all(self) -> &'a HttpSecurity<Request, Response> {
+        self.rule(AuthorizationRule::DenyAll, vec![])
+    }
+
+    pub fn authenticated(self) -> &'a HttpSecurity<Request, Response> {
+        self.rule(AuthorizationRule::Authenticated, vec![])
+    }
+
+    pub fn anonymous(self) -> &'a HttpSecurity<Request, Response> {
+        self.rule(AuthorizationRule::Anonymous, vec![])
+    }
+
+    pub fn has_authority(self, authority: &str) -> &'a HttpSecurity<Request, Response> {
+        self.has_any_authority([authority])
+    }
+
+    pub fn has_any_authority<'c>(self, authorities: impl IntoIterator<Item = &'c str>) -> &'a HttpSecurity<Request, Response> {
+        let authorities = authorities.into_iter()
+            .map(|authority| authority.to_string())
+            .collect::<Vec<String>>();
+        self.rule(AuthorizationRule::HasAnyAuthority, authorities)
+    }
+
+    pub fn has_role(self, role: &str) -> &'a HttpSecurity<Request, Response> {
+        self.has_any_role([role])
+    }
+
+    /// Adds the ROLE_ prefix to each role.
+    pub fn has_any_role<'c>(self, roles: impl IntoIterator<Item = &'c str>) -> &'a HttpSecurity<Request, Response> {
+        let authorities = roles.into_iter()
+            .map(|role| format!("ROLE_{}", role))
+            .collect::<Vec<String>>();
+        self.rule(AuthorizationRule::HasAnyAuthority, authorities)
+    }
+
+    fn rule(self, rule: AuthorizationRule, authorities: Vec<String>) -> &'a HttpSecurity<Request, Response> {
+        let entry = RequestMatcherEntry::new_with_rule(
+            self.paths.iter().map(|path| path.as_str()).collect(),
+            self.methods,
+            rule,
+            authorities.iter().map(|authority| authority.as_str()).collect()
+        );
+        self.http.authorization_manager(entry);
+        self.http
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use authentication_gen::AuthenticationType;
+use knockoff_security::knockoff_security::authentication_type::{AuthenticationConversionError, JwtToken};
+use web_framework_shared::authority::GrantedAuthority;
+use web_framework_shared::convert::Converter;
+use web_framework_shared::request::WebRequest;
+use crate::web_framework::security::authentication::{Authentication, AuthenticationConverter, AuthenticationDetails, AuthenticationProvider, AuthenticationToken};
+
+/// The key used to check the signature, from configuration. The public keys are PEM encoded.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum JwtKey {
+    Hs256 { secret: String },
+    Rs256 { public_key_pem: String },
+    Es256 { public_key_pem: String }
+}
+
+impl JwtKey {
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            JwtKey::Hs256 { .. } => Algorithm::HS256,
+            JwtKey::Rs256 { .. } => Algorithm::RS256,
+            JwtKey::Es256 { .. } => Algorithm::ES256
+        }
+    }
+
+    fn decoding_key(&self) -> Result<DecodingKey, AuthenticationConversionError> {
+        match self {
+            JwtKey::Hs256 { secret } => Ok(DecodingKey::from_secret(secret.as_bytes())),
+            JwtKey::Rs256 { public_key_pem } => DecodingKey::from_rsa_pem(public_key_pem.as_bytes())
+                .map_err(|e| AuthenticationConversionError::new(format!("Invalid RS256 public key: {}.", e))),
+            JwtKey::Es256 { public_key_pem } => DecodingKey::from_ec_pem(public_key_pem.as_bytes())
+                .map_err(|e| AuthenticationConversionError::new(format!("Invalid ES256 public key: {}.", e)))
+        }
+    }
+}
+
+/// The key used to sign issued tokens. The private keys are PEM encoded.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum JwtSigningKey {
+    Hs256 { secret: String },
+    Rs256 { private_key_pem: String },
+    Es256 { private_key_pem: String }
+}
+
+impl JwtSigningKey {
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            JwtSigningKey::Hs256 { .. } => Algorithm::HS256,
+            JwtSigningKey::Rs256 { .. } => Algorithm::RS256,
+            JwtSigningKey::Es256 { .. } => Algorithm::ES256
+        }
+    }
+
+    fn encoding_key(&self) -> Result<EncodingKey, AuthenticationConversionError> {
+        match self {
+            JwtSigningKey::Hs256 { secret } => Ok(EncodingKey::from_secret(secret.as_bytes())),
+            JwtSigningKey::Rs256 { private_key_pem } => EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+                .map_err(|e| AuthenticationConversionError::new(format!("Invalid RS256 private key: {}.", e))),
+            JwtSigningKey::Es256 { private_key_pem } => EncodingKey::from_ec_pem(private_key_pem.as_bytes())
+                .map_err(|e| AuthenticationConversionError::new(format!("Invalid ES256 private key: {}.", e)))
+        }
+    }
+}
+
+/// Signs the claims of issued tokens.
+pub struct JwtEncoder {
+    encoding_key: EncodingKey,
+    algorithm: Algorithm
+}
+
+impl JwtEncoder {
+    pub fn new(key: &JwtSigningKey) -> Result<Self, AuthenticationConversionError> {
+        Ok(Self {
+            encoding_key: key.encoding_key()?,
+            algorithm: key.algorithm(),
+        })
+    }
+
+    pub fn encode(&self, claims: &HashMap<String, Value>) -> Result<String, AuthenticationConversionError> {
+        jsonwebtoken::encode(&Header::new(self.algorithm), claims, &self.encoding_key)
+            .map_err(|e| AuthenticationConversionError::new(format!("Could not sign token: {}.", e)))
+    }
+}
+
+/// Configuration of bearer token authentication. If issuer or audience are set then the iss and
+/// aud claims must match. exp is required, and nbf is checked if it exists.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JwtProperties {
+    pub key: JwtKey,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
+    pub audience: Vec<String>,
+    /// The claim containing the authorities, either an array or a space separated string.
+    #[serde(default = "JwtProperties::default_authorities_claim")]
+    pub authorities_claim: String,
+    /// Added to each authority from the claim, e.g. SCOPE_read.
+    #[serde(default = "JwtProperties::default_authority_prefix")]
+    pub authority_prefix: String,
+    /// Seconds of clock skew allowed when checking exp and nbf.
+    #[serde(default)]
+    pub leeway: u64
+}
+
+impl JwtProperties {
+    pub fn new(key: JwtKey) -> Self {
+        Self {
+            key,
+            issuer: None,
+            audience: vec![],
+            authorities_claim: Self::default_authorities_claim(),
+            authority_prefix: Self::default_authority_prefix(),
+            leeway: 0,
+        }
+    }
+
+    fn default_authorities_claim() -> String {
+        "scope".to_string()
+    }
+
+    fn default_authority_prefix() -> String {
+        "SCOPE_".to_string()
+    }
+}
+
+/// Checks the signature and claims of a token, and maps the authorities claim.
+pub struct JwtDecoder {
+    properties: JwtProperties,
+    decoding_key: DecodingKey,
+    validation: Validation
+}
+
+impl JwtDecoder {
+    pub fn new(properties: JwtProperties) -> Result<Self, AuthenticationConversionError> {
+        let decoding_key = properties.key.decoding_key()?;
+        let mut validation = Validation::new(properties.key.algorithm());
+        validation.validate_exp = true;
+        validation.validate_nbf = true;
+        validation.leeway = properties.leeway;
+        properties.issuer.as_ref()
+            .map(|issuer| validation.set_issuer(&[issuer]));
+        if properties.audience.len() == 0 {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(properties.audience.as_slice());
+        }
+        Ok(Self { properties, decoding_key, validation })
+    }
+
+    pub fn decode(&self, token: &str) -> Result<HashMap<String, Value>, AuthenticationConversionError> {
+        jsonwebtoken::decode::<HashMap<String, Value>>(token, &self.decoding_key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| AuthenticationConversionError::new(format!("Invalid bearer token: {}.", e)))
+    }
+
+    pub fn authorities(&self, claims: &HashMap<String, Value>) -> Vec<GrantedAuthority> {
+        let authorities = match claims.get(&self.properties.authorities_claim) {
+            Some(Value::String(authorities)) => authorities.split_whitespace()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>(),
+            Some(Value::Array(authorities)) => authorities.iter()
+                .flat_map(|a| a.as_str().into_iter())
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>(),
+            _ => vec![]
+        };
+        authorities.into_iter()
+            .map(|authority| GrantedAuthority { authority: format!("{}{}", &self.properties.authority_prefix, authority) })
+            .collect()
+    }
+}
+
+/// Authenticates the bearer token, setting the name from the sub claim and the authorities from
+/// the authorities claim if the token is valid.
+pub struct JwtAuthenticationProvider {
+    decoder: JwtDecoder
+}
+
+impl JwtAuthenticationProvider {
+    pub fn new(decoder: JwtDecoder) -> Self {
+        Self { decoder }
+    }
+}
+
+#[async_trait]
+impl AuthenticationProvider for JwtAuthenticationProvider {
+    fn supports(&self, authentication_type: &AuthenticationType) -> bool {
+        match authentication_type {
+            AuthenticationType::Jwt(_) => true,
+            _ => false
+        }
+    }
+
+    async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+        if let AuthenticationType::Jwt(jwt) = &mut auth_token.auth {
+            match self.decoder.decode(&jwt.token) {
+                Ok(claims) => {
+                    let name = claims.get("sub")
+                        .and_then(|sub| sub.as_str())
+                        .map(|sub| sub.to_string())
+                        .or(Some(String::default()))
+                        .unwrap();
+                    jwt.set_principal(name.clone());
+                    auth_token.name = name;
+                    auth_token.authorities = self.decoder.authorities(&claims);
+                    auth_token.authenticated = true;
+                }
+                Err(_) => {
+                    auth_token.authorities = vec![];
+                    auth_token.authenticated = false;
+                }
+            }
+        }
+        auth_token.to_owned()
+    }
+}
+
+/// Converts requests with an Authorization: Bearer header into an unauthenticated Jwt token, to be
+/// authenticated by the JwtAuthenticationProvider.
+#[derive(Default)]
+pub struct BearerTokenAuthenticationConverter;
+
+impl Converter<WebRequest, Result<Authentication, AuthenticationConversionError>> for BearerTokenAuthenticationConverter {
+    fn convert(&self, from: &WebRequest) -> Result<Authentication, AuthenticationConversionError> {
+        from.headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+            .and_then(|(_, value)| value.trim().split_once(" "))
+            .filter(|(scheme, token)| scheme.eq_ignore_ascii_case("bearer") && token.trim().len() != 0)
+            .map(|(_, token)| {
+                let jwt = JwtToken::new(token.trim().to_string());
+                let auth_token = AuthenticationToken {
+                    name: jwt.get_principal().or(Some(String::default())).unwrap(),
+                    auth: AuthenticationType::Jwt(jwt),
+                    authenticated: false,
+                    authorities: vec![],
+                };
+                Ok(Authentication {
+                    principal: auth_token.name.clone(),
+                    credentials: auth_token,
+                    details: AuthenticationDetails::default(),
+                    authenticated: false,
+                    authorities: vec![],
+                })
+            })
+            .or(Some(Err(AuthenticationConversionError::new("Bearer token did not exist.".to_string()))))
+            .unwrap()
+    }
+}
+
+impl AuthenticationConverter for BearerTokenAuthenticationConverter {
+}
@@ -4,6 +4,12 @@ pub mod http_security;
 pub mod authorization;
 pub mod authentication;
 pub mod password;
+pub mod jwt;
+pub mod token_endpoint;
+pub mod basic;
+pub mod form_login;
+pub mod csrf;
+pub mod cors;
 pub mod user_details;
 #[cfg(test)]
 pub mod test;
\ No newline at end of file
@@ -1,13 +1,244 @@
+use std::collections::HashMap;
+use argon2::Argon2;
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use password_hash::rand_core::OsRng;
+use pbkdf2::Pbkdf2;
+
 pub trait PasswordEncoder : Send + Sync {
-    fn encode_password(&self, unencoded: &str) -> String;
+    /// The encoded password, or None if the parameters of the encoder are invalid.
+    fn encode_password(&self, unencoded: &str) -> Option<String>;
+
+    /// If the raw password, once encoded, matches the encoded password that was stored.
+    fn matches(&self, raw: &str, encoded: &str) -> bool;
+
+    /// If the encoded password was created with weaker parameters, or another algorithm, and so
+    /// should be encoded again the next time the raw password is available.
+    fn upgrade_encoding(&self, _encoded: &str) -> bool {
+        false
+    }
 }
 
 #[derive(Clone)]
 pub struct NoOpPasswordEncoder;
 
 impl PasswordEncoder for NoOpPasswordEncoder {
-    fn encode_password(&self, unencoded: &str) -> String {
-        unencoded.to_string()
+    fn encode_password(&self, unencoded: &str) -> Option<String> {
+        Some(unencoded.to_string())
     }
+
+    fn matches(&self, raw: &str, encoded: &str) -> bool {
+        raw == encoded
+    }
+}
+
+/// Encodes with argon2id, as a PHC string containing the parameters and salt, e.g.
+/// $argon2id$v=19$m=19456,t=2,p=1$...
+#[derive(Clone)]
+pub struct Argon2PasswordEncoder {
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32
 }
 
+impl Default for Argon2PasswordEncoder {
+    fn default() -> Self {
+        Self {
+            memory_cost: argon2::Params::DEFAULT_M_COST,
+            iterations: argon2::Params::DEFAULT_T_COST,
+            parallelism: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl Argon2PasswordEncoder {
+    fn argon2(&self) -> Option<Argon2<'static>> {
+        argon2::Params::new(self.memory_cost, self.iterations, self.parallelism, None)
+            .map(|params| Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params))
+            .ok()
+    }
+}
+
+impl PasswordEncoder for Argon2PasswordEncoder {
+    fn encode_password(&self, unencoded: &str) -> Option<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .and_then(|argon2| argon2.hash_password(unencoded.as_bytes(), &salt).ok())
+            .map(|hash| hash.to_string())
+    }
+
+    fn matches(&self, raw: &str, encoded: &str) -> bool {
+        PasswordHash::new(encoded)
+            .map(|hash| Argon2::default().verify_password(raw.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    }
+
+    fn upgrade_encoding(&self, encoded: &str) -> bool {
+        PasswordHash::new(encoded)
+            .ok()
+            .and_then(|hash| argon2::Params::try_from(&hash).ok())
+            .map(|params| params.m_cost() < self.memory_cost
+                || params.t_cost() < self.iterations
+                || params.p_cost() < self.parallelism)
+            .or(Some(true))
+            .unwrap()
+    }
+}
+
+/// Encodes with bcrypt, e.g. $2b$10$..., where the cost is the log2 of the number of rounds.
+#[derive(Clone)]
+pub struct BCryptPasswordEncoder {
+    pub cost: u32
+}
+
+impl Default for BCryptPasswordEncoder {
+    fn default() -> Self {
+        Self {
+            cost: 10
+        }
+    }
+}
+
+impl PasswordEncoder for BCryptPasswordEncoder {
+    fn encode_password(&self, unencoded: &str) -> Option<String> {
+        bcrypt::hash(unencoded, self.cost).ok()
+    }
+
+    fn matches(&self, raw: &str, encoded: &str) -> bool {
+        bcrypt::verify(raw, encoded)
+            .unwrap_or(false)
+    }
+
+    fn upgrade_encoding(&self, encoded: &str) -> bool {
+        encoded.split("$")
+            .nth(2)
+            .and_then(|cost| cost.parse::<u32>().ok())
+            .map(|cost| cost < self.cost)
+            .or(Some(true))
+            .unwrap()
+    }
+}
+
+/// Encodes with PBKDF2 using HMAC-SHA256, as a PHC string, e.g. $pbkdf2-sha256$i=310000,l=32$...
+#[derive(Clone)]
+pub struct Pbkdf2PasswordEncoder {
+    pub rounds: u32,
+    pub output_length: usize
+}
+
+impl Default for Pbkdf2PasswordEncoder {
+    fn default() -> Self {
+        Self {
+            rounds: 310_000,
+            output_length: 32,
+        }
+    }
+}
+
+impl PasswordEncoder for Pbkdf2PasswordEncoder {
+    fn encode_password(&self, unencoded: &str) -> Option<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let params = pbkdf2::Params {
+            rounds: self.rounds,
+            output_length: self.output_length,
+        };
+        Pbkdf2.hash_password_customized(unencoded.as_bytes(), Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()), None, params, &salt)
+            .map(|hash| hash.to_string())
+            .ok()
+    }
+
+    fn matches(&self, raw: &str, encoded: &str) -> bool {
+        PasswordHash::new(encoded)
+            .map(|hash| Pbkdf2.verify_password(raw.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    }
+
+    fn upgrade_encoding(&self, encoded: &str) -> bool {
+        PasswordHash::new(encoded)
+            .ok()
+            .and_then(|hash| pbkdf2::Params::try_from(&hash).ok())
+            .map(|params| params.rounds < self.rounds || params.output_length < self.output_length)
+            .or(Some(true))
+            .unwrap()
+    }
+}
+
+/// Encodes with the encoder for id_for_encode, prefixing the hash with {id}, and matches with the
+/// encoder for the prefix of the stored hash, so that hashes from several algorithms can be in the
+/// same user store. upgrade_encoding is true for hashes that were not encoded with id_for_encode,
+/// so that they can be encoded again after a successful login.
+pub struct DelegatingPasswordEncoder {
+    id_for_encode: String,
+    encoders: HashMap<String, Box<dyn PasswordEncoder>>,
+    /// Used for hashes without an {id} prefix, for user stores created before the prefix was used.
+    default_for_matches: Option<Box<dyn PasswordEncoder>>
+}
+
+impl DelegatingPasswordEncoder {
+    /// None if there is no encoder for id_for_encode.
+    pub fn new(id_for_encode: &str, encoders: HashMap<String, Box<dyn PasswordEncoder>>) -> Option<Self> {
+        if !encoders.contains_key(id_for_encode) {
+            return None;
+        }
+        Some(Self {
+            id_for_encode: id_for_encode.to_string(),
+            encoders,
+            default_for_matches: None,
+        })
+    }
+
+    /// bcrypt is used to encode, and argon2, pbkdf2 and noop hashes can be matched.
+    pub fn create_delegating_password_encoder() -> Self {
+        let mut encoders: HashMap<String, Box<dyn PasswordEncoder>> = HashMap::new();
+        encoders.insert("bcrypt".to_string(), Box::new(BCryptPasswordEncoder::default()));
+        encoders.insert("argon2".to_string(), Box::new(Argon2PasswordEncoder::default()));
+        encoders.insert("pbkdf2".to_string(), Box::new(Pbkdf2PasswordEncoder::default()));
+        encoders.insert("noop".to_string(), Box::new(NoOpPasswordEncoder {}));
+        Self {
+            id_for_encode: "bcrypt".to_string(),
+            encoders,
+            default_for_matches: None,
+        }
+    }
+
+    pub fn default_for_matches(mut self, encoder: Box<dyn PasswordEncoder>) -> Self {
+        self.default_for_matches = Some(encoder);
+        self
+    }
+
+    /// Splits {id}hash into the id and the hash.
+    fn extract_id(encoded: &str) -> Option<(&str, &str)> {
+        encoded.strip_prefix("{")
+            .and_then(|e| e.split_once("}"))
+    }
+}
+
+impl PasswordEncoder for DelegatingPasswordEncoder {
+    fn encode_password(&self, unencoded: &str) -> Option<String> {
+        self.encoders.get(&self.id_for_encode)
+            .and_then(|encoder| encoder.encode_password(unencoded))
+            .map(|hash| format!("{{{}}}{}", &self.id_for_encode, hash))
+    }
+
+    fn matches(&self, raw: &str, encoded: &str) -> bool {
+        match Self::extract_id(encoded) {
+            Some((id, hash)) => self.encoders.get(id)
+                .map(|encoder| encoder.matches(raw, hash))
+                .or(Some(false))
+                .unwrap(),
+            None => self.default_for_matches.as_ref()
+                .map(|encoder| encoder.matches(raw, encoded))
+                .or(Some(false))
+                .unwrap()
+        }
+    }
+
+    fn upgrade_encoding(&self, encoded: &str) -> bool {
+        match Self::extract_id(encoded) {
+            Some((id, hash)) if id == self.id_for_encode => self.encoders.get(id)
+                .map(|encoder| encoder.upgrade_encoding(hash))
+                .or(Some(true))
+                .unwrap(),
+            _ => true
+        }
+    }
+}
@@ -1,8 +1,26 @@
 use serde::{Deserialize, Serialize};
+use web_framework_shared::method_security::{MethodSecurity, SecuredPrincipal};
 use crate::web_framework::security::authentication::AuthenticationToken;
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SecurityContextHolder {
     pub auth_token: Option<AuthenticationToken>
-}
\ No newline at end of file
+}
+
+impl SecurityContextHolder {
+
+    /// Makes the authentication the one that #[secured] and #[pre_authorize] methods are checked
+    /// against for the rest of the request, run within MethodSecurity::scope.
+    pub fn set_current(&self) {
+        MethodSecurity::set_current(self.auth_token.as_ref().map(|auth_token| SecuredPrincipal {
+            principal: auth_token.name.clone(),
+            authorities: auth_token.authorities.clone(),
+            authenticated: auth_token.authenticated,
+        }));
+    }
+
+    pub fn clear_current() {
+        MethodSecurity::set_current(None);
+    }
+}
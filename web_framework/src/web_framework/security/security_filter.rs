@@ -1,5 +1,6 @@
 use std::ptr::write_bytes;
 use std::sync::Arc;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use knockoff_security::knockoff_security::authentication_type::AuthenticationConversionError;
 use authentication_gen::{AuthenticationTypeConverter, AuthenticationTypeConverterImpl};
@@ -14,6 +15,7 @@ use web_framework_shared::request::{EndpointMetadata, WebRequest};
 use crate::web_framework::convert::AuthenticationConverterRegistry;
 use crate::web_framework::dispatch::FilterExecutor;
 use crate::web_framework::request_context::SessionContext;
+use crate::web_framework::security::authorization::{AuthorizationManager, RequestMatcherDelegatingAuthorizationManager};
 use crate::web_framework::security::authentication::{AuthenticationConverter, AuthenticationProvider, AuthenticationToken, DelegatingAuthenticationManager};
 use crate::web_framework::session::session::HttpSession;
 
@@ -28,20 +30,21 @@ impl <Request, Response> SecurityFilterChain<Request, Response>
     where
         Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static,
         Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync + 'static {
-    pub fn do_filter(&self,
-                     request: &WebRequest,
-                     response: &mut WebResponse,
-                     ctx: &RequestContextData<Request, Response>,
-                     request_context: &mut Option<Box<UserRequestContext<Request>>>)
+    pub async fn do_filter(&self,
+                           request: &WebRequest,
+                           response: &mut WebResponse,
+                           ctx: &RequestContextData<Request, Response>,
+                           request_context: &mut Option<Box<UserRequestContext<Request>>>)
     {
-        self.filters.do_filter(request, response, ctx, request_context);
+        self.filters.do_filter(request, response, ctx, request_context).await;
     }
 }
 
 //TODO: replace filter with action
+#[async_trait]
 pub trait AuthenticationFilter
 {
-    fn try_convert_to_authentication(
+    async fn try_convert_to_authentication(
         &self,
         request: &WebRequest,
     ) -> Result<AuthenticationToken, AuthenticationConversionError>;
@@ -63,11 +66,13 @@ impl Default for UsernamePasswordAuthenticationFilter
     }
 }
 
+#[async_trait]
 impl AuthenticationFilter for UsernamePasswordAuthenticationFilter {
-    fn try_convert_to_authentication(&self, request: &WebRequest) -> Result<AuthenticationToken, AuthenticationConversionError> {
-        self.converter
-            .convert(request)
-            .map(|mut auth_token|  self.authentication_manager.authenticate(&mut auth_token.credentials) )
+    async fn try_convert_to_authentication(&self, request: &WebRequest) -> Result<AuthenticationToken, AuthenticationConversionError> {
+        match self.converter.convert(request) {
+            Ok(mut auth_token) => Ok(self.authentication_manager.authenticate(&mut auth_token.credentials).await),
+            Err(e) => Err(e)
+        }
     }
 }
 
@@ -93,12 +98,13 @@ impl UsernamePasswordAuthenticationFilter
     }
 }
 
+#[async_trait]
 impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for UsernamePasswordAuthenticationFilter
 where
     Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
     Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
 {
-    fn do_action(
+    async fn do_action(
         &self,
         web_request: &WebRequest,
         response: &mut WebResponse,
@@ -106,14 +112,15 @@ where
         request_context: &mut Option<Box<UserRequestContext<Request>>>
         ) -> Option<Response> {
 
-        self.try_convert_to_authentication(web_request)
-            .map(|auth| {
-                request_context.as_mut().map(|mut request_context| {
-                    request_context.request_context.http_session.security_context_holder.auth_token = Some(auth.to_owned());
-                });
-                auth
-            })
-            .expect("Panic experienced while authenticating user.");
+        // If the request has no credentials it stays anonymous, and the AuthorizationFilter
+        // decides if that is allowed.
+        if let Ok(auth) = self.try_convert_to_authentication(web_request).await {
+            request_context.as_mut().map(|mut request_context| {
+                let security_context_holder = &mut request_context.request_context.http_session.security_context_holder;
+                security_context_holder.auth_token = Some(auth);
+                security_context_holder.set_current();
+            });
+        }
 
         None
     }
@@ -123,7 +130,93 @@ where
     }
 
     fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
-        todo!()
+        true
+    }
+
+}
+
+/// Starts authentication when an anonymous request is denied, for example by sending the
+/// WWW-Authenticate challenge for HTTP Basic or redirecting to the login page.
+pub trait AuthenticationEntryPoint: Send + Sync {
+    fn commence(&self, web_request: &WebRequest, response: &mut WebResponse);
+}
+
+/// Checks the authentication of the SecurityContextHolder, or anonymous if there is none, against
+/// the request matchers, setting 401 if authentication is required and 403 if access is denied.
+pub struct AuthorizationFilter
+{
+    authorization_manager: Arc<RequestMatcherDelegatingAuthorizationManager>,
+    /// Called instead of setting 401 when authentication is required.
+    entry_point: Option<Arc<dyn AuthenticationEntryPoint>>
+}
+
+impl AuthorizationFilter
+{
+    pub fn new(authorization_manager: Arc<RequestMatcherDelegatingAuthorizationManager>) -> Self {
+        Self { authorization_manager, entry_point: None }
     }
 
+    pub fn with_entry_point(mut self, entry_point: Arc<dyn AuthenticationEntryPoint>) -> Self {
+        self.entry_point = Some(entry_point);
+        self
+    }
+
+    pub fn authorization_filter<Request, Response>(
+        authorization_manager: Arc<RequestMatcherDelegatingAuthorizationManager>,
+        entry_point: Option<Arc<dyn AuthenticationEntryPoint>>,
+        dispatcher: Arc<FilterExecutor>,
+        order: Option<u8>
+    ) -> Filter<Request, Response>
+        where
+            Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+            Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    {
+        Filter::new(
+            Arc::new(Self { authorization_manager, entry_point }),
+            order,
+            dispatcher
+        )
+    }
+
+    pub fn authorize(&self, web_request: &WebRequest, response: &mut WebResponse, auth_token: Option<&AuthenticationToken>) {
+        let anonymous = AuthenticationToken::default();
+        let decision = self.authorization_manager.check(auth_token.or(Some(&anonymous)).unwrap(), web_request);
+        match (decision.status(), self.entry_point.as_ref()) {
+            (Some(_), Some(entry_point)) if decision.authentication_required => {
+                entry_point.commence(web_request, response);
+            }
+            (Some(status), _) => {
+                response.set_status(status);
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+#[async_trait]
+impl <Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for AuthorizationFilter
+where
+    Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+    Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        let auth_token = request_context.as_ref()
+            .and_then(|request_context| request_context.request_context.http_session.security_context_holder.auth_token.as_ref());
+        self.authorize(web_request, response, auth_token);
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
 }
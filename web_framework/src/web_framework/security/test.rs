@@ -5,12 +5,14 @@ mod test_security {
     use std::any::Any;
     use std::ops::DerefMut;
     use std::sync::{Arc, Mutex};
+    use async_trait::async_trait;
     use serde::{Deserialize, Serialize};
     use knockoff_security::knockoff_security::authentication_type::{Anonymous, AuthenticationConversionError, JwtToken, UsernamePassword};
     use authentication_gen::{AuthenticationType, AuthenticationTypeConverter};
     use web_framework_shared::authority::GrantedAuthority;
     use web_framework_shared::convert::Converter;
-    use crate::web_framework::filter::filter::FilterChain;
+    use crate::web_framework::filter::filter::{Filter, FilterChain};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use web_framework_shared::request::WebResponse;
     use crate::web_framework::security::security_filter::{AuthenticationFilter, UsernamePasswordAuthenticationFilter};
     use web_framework_shared::request::WebRequest;
@@ -18,6 +20,36 @@ mod test_security {
     use crate::web_framework::convert::Registration;
     use crate::web_framework::security::authentication::{Authentication, AuthenticationConverter, AuthenticationProvider, AuthenticationToken, DelegatingAuthenticationManager};
     use crate::web_framework::security::http_security::HttpSecurity;
+    use crate::web_framework::security::jwt::{BearerTokenAuthenticationConverter, JwtAuthenticationProvider, JwtDecoder, JwtKey, JwtProperties};
+    use crate::web_framework::security::token_endpoint::{JwtTokenEndpoint, JwtTokenProperties, JwtTokenService, LoginRequest, RefreshToken, TokenError, TokenResponse};
+    use crate::web_framework::security::jwt::JwtSigningKey;
+    use data_framework::Repo;
+    use std::collections::{HashMap, LinkedList};
+    use crate::web_framework::security::security_context_holder::SecurityContextHolder;
+    use crate::web_framework::security::authorization::AuthorizationManager;
+    use web_framework_shared::http_method::HttpMethod;
+    use web_framework_shared::authority::{RoleHierarchy, SimpleAuthorityMapper};
+    use crate::web_framework::security::basic::{BasicAuthenticationEntryPoint, BasicAuthenticationFilter};
+    use crate::web_framework::security::form_login::{FormLoginFilter, FormLoginProperties};
+    use crate::web_framework::security::security_filter::AuthorizationFilter;
+    use crate::web_framework::session::session::{HttpSession, SessionFilter, SessionManager, SessionProperties};
+    use crate::web_framework::session::repo::InMemorySessionRepo;
+    use crate::web_framework::security::csrf::{CsrfFilter, CsrfProperties, HttpSessionCsrfTokenRepository};
+    use crate::web_framework::context_builder::FilterRegistrarBuilder;
+    use crate::web_framework::dispatch::FilterExecutor;
+    use web_framework_shared::csrf::CsrfToken;
+    use web_framework_shared::cors::CorsConfiguration;
+    use crate::web_framework::security::cors::{CorsFilter, CorsProperties};
+    use web_framework_shared::request::StatusCode;
+    use web_framework_shared::method_security::MethodSecurity;
+    use crate::web_framework::context::{Context, RequestContextData, UserRequestContext};
+    use web_framework_shared::dispatch_server::{Handler, RequestDispatcher};
+    use web_framework_shared::request::EndpointMetadata;
+    use crate::web_framework::security::authentication::DaoAuthenticationProvider;
+    use crate::web_framework::security::user_details::{InMemoryUserDetailsService, UserDetailsService};
+    use knockoff_security::knockoff_security::user_request_account::{AccountData, UserAccount};
+    use data_framework::Entity;
+    use crate::web_framework::security::password::{Argon2PasswordEncoder, BCryptPasswordEncoder, DelegatingPasswordEncoder, NoOpPasswordEncoder, PasswordEncoder, Pbkdf2PasswordEncoder};
 
     #[test]
     fn test_split() {
@@ -30,7 +62,7 @@ mod test_security {
             String::from("Basic faslkjaf:as;dljfkas"),
         );
 
-        let converted = username_password_auth_filter.try_convert_to_authentication(&request);
+        let converted = tokio_test::block_on(username_password_auth_filter.try_convert_to_authentication(&request));
         assert!(converted.is_ok());
 
         converted.iter()
@@ -51,7 +83,7 @@ mod test_security {
         let password = "".to_string();
         let username_type_id = AuthenticationType::Password(UsernamePassword{username, password})
             .type_id();
-        let jwt_type_id = AuthenticationType::Jwt( JwtToken{ token: "".to_string() } )
+        let jwt_type_id = AuthenticationType::Jwt( JwtToken::new("".to_string()) )
             .type_id();
         assert_ne!(username_type_id, jwt_type_id)
     }
@@ -63,6 +95,260 @@ mod test_security {
         assert_eq!("", auth)
     }
 
+    #[test]
+    fn test_password_encoders() {
+        let bcrypt = BCryptPasswordEncoder { cost: 4 };
+        let encoded = bcrypt.encode_password("password").unwrap();
+        assert!(bcrypt.matches("password", &encoded));
+        assert!(!bcrypt.matches("other", &encoded));
+        assert!(!bcrypt.upgrade_encoding(&encoded));
+        assert!(BCryptPasswordEncoder { cost: 5 }.upgrade_encoding(&encoded));
+
+        let pbkdf2 = Pbkdf2PasswordEncoder { rounds: 1000, output_length: 32 };
+        let encoded = pbkdf2.encode_password("password").unwrap();
+        assert!(pbkdf2.matches("password", &encoded));
+        assert!(Pbkdf2PasswordEncoder::default().upgrade_encoding(&encoded));
+
+        assert!(Argon2PasswordEncoder { memory_cost: 1024, iterations: 1, parallelism: 0 }.encode_password("password").is_none());
+        assert!(BCryptPasswordEncoder { cost: 1 }.encode_password("password").is_none());
+    }
+
+    #[test]
+    fn test_delegating_password_encoder() {
+        let delegating = DelegatingPasswordEncoder::create_delegating_password_encoder();
+        assert!(delegating.matches("password", "{noop}password"));
+        assert!(delegating.upgrade_encoding("{noop}password"));
+        assert!(!delegating.matches("password", "{unknown}password"));
+        assert!(!delegating.matches("password", "password"));
+
+        let legacy = Pbkdf2PasswordEncoder { rounds: 1000, output_length: 32 }.encode_password("password").unwrap();
+        assert!(delegating.matches("password", format!("{{pbkdf2}}{}", legacy).as_str()));
+
+        let delegating = delegating.default_for_matches(Box::new(NoOpPasswordEncoder {}));
+        assert!(delegating.matches("password", "password"));
+        assert!(delegating.upgrade_encoding("password"));
+
+        assert!(DelegatingPasswordEncoder::new("bcrypt", HashMap::new()).is_none());
+        let mut encoders: HashMap<String, Box<dyn PasswordEncoder>> = HashMap::new();
+        encoders.insert("noop".to_string(), Box::new(NoOpPasswordEncoder {}));
+        let noop = DelegatingPasswordEncoder::new("noop", encoders).unwrap();
+        assert_eq!(noop.encode_password("password"), Some("{noop}password".to_string()));
+        assert!(!noop.upgrade_encoding("{noop}password"));
+    }
+
+    pub struct CountingPasswordEncoder {
+        matched: Arc<AtomicUsize>
+    }
+
+    impl PasswordEncoder for CountingPasswordEncoder {
+        fn encode_password(&self, unencoded: &str) -> Option<String> {
+            Some(unencoded.to_string())
+        }
+
+        fn matches(&self, raw: &str, encoded: &str) -> bool {
+            self.matched.fetch_add(1, Ordering::SeqCst);
+            raw == encoded
+        }
+    }
+
+    #[derive(Default)]
+    pub struct UpdatingUserDetailsService {
+        user_accounts: Mutex<HashMap<String, TestUserAccount>>
+    }
+
+    #[async_trait]
+    impl UserDetailsService<TestUserAccount, String> for UpdatingUserDetailsService {
+        async fn load_by_username(&self, id: &String) -> Option<TestUserAccount> {
+            self.user_accounts.lock().unwrap().get(id).cloned()
+        }
+
+        async fn update_password(&self, user: &TestUserAccount, encoded_password: &str) {
+            let mut updated = user.clone();
+            updated.password = encoded_password.to_string();
+            self.user_accounts.lock().unwrap().insert(updated.username.clone(), updated);
+        }
+    }
+
+    #[test]
+    fn test_dao_authentication_provider() {
+        let user_service = UpdatingUserDetailsService::default();
+        user_service.user_accounts.lock().unwrap().insert("user".to_string(), TestUserAccount {
+            username: "user".to_string(),
+            password: "password".to_string(),
+            authorities: vec![],
+        });
+        let matched = Arc::new(AtomicUsize::new(0));
+        let provider = DaoAuthenticationProvider::<TestUserAccount, _>::new(user_service, Arc::new(CountingPasswordEncoder { matched: matched.clone() }));
+
+        // an unknown user is still matched, so it takes as long as a user that exists.
+        let unknown = tokio_test::block_on(provider.authenticate(&mut AuthenticationToken::username_password("unknown", "password")));
+        assert!(!unknown.authenticated);
+        assert_eq!(matched.load(Ordering::SeqCst), 1);
+        let wrong = tokio_test::block_on(provider.authenticate(&mut AuthenticationToken::username_password("user", "other")));
+        assert!(!wrong.authenticated);
+        assert_eq!(matched.load(Ordering::SeqCst), 2);
+
+        let mut encoders: HashMap<String, Box<dyn PasswordEncoder>> = HashMap::new();
+        encoders.insert("bcrypt".to_string(), Box::new(BCryptPasswordEncoder { cost: 4 }));
+        encoders.insert("noop".to_string(), Box::new(NoOpPasswordEncoder {}));
+        let user_service = UpdatingUserDetailsService::default();
+        user_service.user_accounts.lock().unwrap().insert("user".to_string(), TestUserAccount {
+            username: "user".to_string(),
+            password: "{noop}password".to_string(),
+            authorities: vec![],
+        });
+        let provider = DaoAuthenticationProvider::<TestUserAccount, _>::new(user_service, Arc::new(DelegatingPasswordEncoder::new("bcrypt", encoders).unwrap()));
+
+        let authenticated = tokio_test::block_on(provider.authenticate(&mut AuthenticationToken::username_password("user", "password")));
+        assert!(authenticated.authenticated);
+        // the noop password was encoded again with bcrypt after the login.
+        let upgraded = tokio_test::block_on(provider.user_details_service.load_by_username(&"user".to_string())).unwrap();
+        assert!(upgraded.password.starts_with("{bcrypt}"));
+        assert!(provider.password_encoder.matches("password", &upgraded.password));
+        let authenticated = tokio_test::block_on(provider.authenticate(&mut AuthenticationToken::username_password("user", "password")));
+        assert!(authenticated.authenticated);
+    }
+
+    #[test]
+    fn test_jwt_bearer_authentication() {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let claims = serde_json::json!({"sub": "user", "iss": "issuer", "exp": now + 60, "scope": "read write"});
+        let token = jsonwebtoken::encode(&jsonwebtoken::Header::default(), &claims, &jsonwebtoken::EncodingKey::from_secret(b"secret")).unwrap();
+
+        let mut request = WebRequest::default();
+        request.headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        let mut authentication = BearerTokenAuthenticationConverter::default().convert(&request).unwrap();
+        assert_eq!(authentication.principal, "user");
+        assert!(BearerTokenAuthenticationConverter::default().convert(&WebRequest::default()).is_err());
+
+        let mut properties = JwtProperties::new(JwtKey::Hs256 { secret: "secret".to_string() });
+        properties.issuer = Some("issuer".to_string());
+        let provider = JwtAuthenticationProvider::new(JwtDecoder::new(properties.clone()).unwrap());
+        assert!(provider.supports(&authentication.credentials.auth));
+        let authenticated = tokio_test::block_on(provider.authenticate(&mut authentication.credentials));
+        assert!(authenticated.authenticated);
+        assert_eq!(authenticated.name, "user");
+        assert!(matches!(&authenticated.auth, AuthenticationType::Jwt(jwt) if jwt.principal == Some("user".to_string())));
+        let authorities = authenticated.authorities.iter()
+            .map(|a| a.get_authority())
+            .collect::<Vec<&str>>();
+        assert_eq!(authorities, vec!["SCOPE_read", "SCOPE_write"]);
+
+        properties.issuer = Some("other".to_string());
+        let provider = JwtAuthenticationProvider::new(JwtDecoder::new(properties).unwrap());
+        let mut authentication = BearerTokenAuthenticationConverter::default().convert(&request).unwrap();
+        assert!(!tokio_test::block_on(provider.authenticate(&mut authentication.credentials)).authenticated);
+    }
+
+    #[derive(Default)]
+    pub struct InMemoryRefreshTokenRepo {
+        tokens: Mutex<HashMap<String, RefreshToken>>
+    }
+    #[async_trait]
+    impl <'a> Repo<'a, RefreshToken, String> for InMemoryRefreshTokenRepo {
+        type Data = ();
+        async fn find_all(&self) -> LinkedList<RefreshToken> {
+            self.tokens.lock().unwrap().values().cloned().collect()
+        }
+        async fn find_by_id(&self, id: &String) -> Option<RefreshToken> {
+            // yields as a database would, so that concurrent refreshes are interleaved.
+            async_std::task::yield_now().await;
+            self.tokens.lock().unwrap().get(id).cloned()
+        }
+        async fn save(&self, to_save: &'a RefreshToken) -> String {
+            self.tokens.lock().unwrap().insert(to_save.id.clone(), to_save.clone());
+            to_save.id.clone()
+        }
+        async fn delete_by_id(&self, id: &String) -> bool {
+            self.tokens.lock().unwrap().remove(id).is_some()
+        }
+        fn get(data: Option<Self::Data>) -> Self {
+            Self::default()
+        }
+    }
+
+    pub struct TestPasswordProvider;
+    #[async_trait]
+    impl AuthenticationProvider for TestPasswordProvider {
+        fn supports(&self, authentication_token: &AuthenticationType) -> bool {
+            true
+        }
+        async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+            if let AuthenticationType::Password(username_password) = &auth_token.auth {
+                auth_token.authenticated = username_password.password == "password";
+            }
+            auth_token.to_owned()
+        }
+    }
+
+    fn test_token_service() -> JwtTokenService<InMemoryRefreshTokenRepo> {
+        let mut d = DelegatingAuthenticationManagerBuilder::new();
+        d.register(Box::new(TestPasswordProvider{}));
+        JwtTokenService::new(
+            JwtTokenProperties::new(JwtSigningKey::Hs256 { secret: "secret".to_string() }),
+            Arc::new(d.build()),
+            Box::new(InMemoryRefreshTokenRepo::default())
+        ).ok().unwrap()
+    }
+
+    #[test]
+    fn test_jwt_token_service_rotates_refresh_tokens() {
+        let token_service = test_token_service();
+
+        let login = |password: &str| LoginRequest { username: "user".to_string(), password: password.to_string() };
+        assert_eq!(tokio_test::block_on(token_service.login(&login("other"))), Err(TokenError::InvalidCredentials));
+
+        let first = tokio_test::block_on(token_service.login(&login("password"))).unwrap();
+        let decoder = JwtDecoder::new(JwtProperties::new(JwtKey::Hs256 { secret: "secret".to_string() })).unwrap();
+        assert_eq!(decoder.decode(&first.access_token).unwrap()["sub"], "user");
+
+        let second = tokio_test::block_on(token_service.refresh(&first.refresh_token)).unwrap();
+        assert_ne!(first.refresh_token, second.refresh_token);
+        // reusing the rotated token revokes the token that replaced it.
+        assert_eq!(tokio_test::block_on(token_service.refresh(&first.refresh_token)), Err(TokenError::RefreshTokenReused));
+        assert_eq!(tokio_test::block_on(token_service.refresh(&second.refresh_token)), Err(TokenError::InvalidRefreshToken));
+    }
+
+    #[test]
+    fn test_jwt_token_service_concurrent_refresh() {
+        let token_service = test_token_service();
+        let login = LoginRequest { username: "user".to_string(), password: "password".to_string() };
+        let first = tokio_test::block_on(token_service.login(&login)).unwrap();
+
+        let refreshed = tokio_test::block_on(futures::future::join_all(
+            (0..4).map(|_| token_service.refresh(&first.refresh_token))
+        ));
+
+        assert_eq!(refreshed.iter().filter(|r| r.is_ok()).count(), 1);
+        assert_eq!(refreshed.iter().filter(|r| r == &&Err(TokenError::RefreshTokenReused)).count(), 3);
+        let issued = refreshed.into_iter().find(|r| r.is_ok()).unwrap().unwrap();
+        // the reuse revoked the token that was issued.
+        assert_eq!(tokio_test::block_on(token_service.refresh(&issued.refresh_token)), Err(TokenError::InvalidRefreshToken));
+    }
+
+    #[test]
+    fn test_security_context_holder_sets_method_security_principal() {
+        let security_context_holder = SecurityContextHolder {
+            auth_token: Some(AuthenticationToken {
+                name: "admin".to_string(),
+                authenticated: true,
+                authorities: vec![GrantedAuthority { authority: "ROLE_ADMIN".to_string() }],
+                ..AuthenticationToken::default()
+            })
+        };
+        tokio_test::block_on(MethodSecurity::scope(async {
+            security_context_holder.set_current();
+            assert!(MethodSecurity::check_secured(&["ROLE_ADMIN"]).is_ok());
+            assert!(MethodSecurity::check_pre_authorize("principal == #id", &[("id", "admin".to_string())]).is_ok());
+            assert!(MethodSecurity::check_pre_authorize("principal == #id", &[("id", "other".to_string())]).is_err());
+            SecurityContextHolder::clear_current();
+            assert!(MethodSecurity::check_secured(&["ROLE_ADMIN"]).is_err());
+            security_context_holder.set_current();
+        }));
+        // the principal does not outlive the request.
+        assert!(MethodSecurity::check_secured(&["ROLE_ADMIN"]).is_err());
+    }
+
     #[test]
     fn test_http_security() {
         #[derive(Serialize, Deserialize, Clone, Default)]
@@ -75,16 +361,60 @@ mod test_security {
         assert!(http.authentication_manager.lock().unwrap().is_some());
     }
 
+    #[test]
+    fn test_http_security_authorize_requests() {
+        let http = HttpSecurity::<String, String>::default();
+        http.path("/health").permit_all()
+            .path("/login").anonymous()
+            .path("/api/**").method(HttpMethod::Post).has_any_authority(["SCOPE_write"])
+            .path("/admin/**").has_role("ADMIN")
+            .any_request().authenticated();
+        let authorization_manager = http.authorization_manager.lock().unwrap().as_ref().unwrap().build();
+
+        let request = |method: &str, path: &str| {
+            let mut request = WebRequest::default();
+            request.method = method.parse().unwrap();
+            request.uri = path.parse().unwrap();
+            request
+        };
+        let status = |auth_token: &AuthenticationToken, method: &str, path: &str| {
+            authorization_manager.check(auth_token, &request(method, path)).status()
+        };
+        let anonymous = AuthenticationToken::default();
+        let user = AuthenticationToken {
+            name: "user".to_string(),
+            authenticated: true,
+            authorities: vec![GrantedAuthority { authority: "SCOPE_read".to_string() }],
+            ..AuthenticationToken::default()
+        };
+
+        assert_eq!(status(&anonymous, "GET", "/health"), None);
+        assert_eq!(status(&anonymous, "GET", "/login"), None);
+        assert_eq!(status(&user, "GET", "/login"), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(&anonymous, "POST", "/api/items"), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(&user, "POST", "/api/items"), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(&user, "GET", "/api/items"), None);
+        assert_eq!(status(&user, "GET", "/admin/users"), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(&anonymous, "GET", "/other"), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(&user, "GET", "/other"), None);
+
+        // building again, as for the authorization filter, keeps the rules.
+        let rebuilt = http.authorization_manager.lock().unwrap().as_ref().unwrap().build();
+        assert_eq!(rebuilt.authority_authorization_managers.len(), authorization_manager.authority_authorization_managers.len());
+        assert_eq!(rebuilt.check(&anonymous, &request("GET", "/other")).status(), Some(StatusCode::UNAUTHORIZED));
+    }
+
     #[test]
     fn test_delegating_authentication_manager() {
 
         pub struct TestAuthProvider;
+        #[async_trait]
         impl AuthenticationProvider for TestAuthProvider {
             fn supports(&self, authentication_token: &AuthenticationType) -> bool {
                 true
             }
 
-            fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+            async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
                 auth_token.authenticated = true;
                 auth_token.to_owned()
             }
@@ -93,10 +423,581 @@ mod test_security {
         let mut d = DelegatingAuthenticationManagerBuilder::new();
         d.register(Box::new(TestAuthProvider{}));
         let d = d.build();
-        let out = d.authenticate(&mut AuthenticationToken::default());
+        let out = tokio_test::block_on(d.authenticate(&mut AuthenticationToken::default()));
         assert!(out.authenticated);
     }
 
+    #[test]
+    fn test_role_hierarchy_maps_authenticated_authorities() {
+        pub struct TestAdminProvider;
+        #[async_trait]
+        impl AuthenticationProvider for TestAdminProvider {
+            fn supports(&self, authentication_token: &AuthenticationType) -> bool {
+                true
+            }
+
+            async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+                auth_token.authenticated = auth_token.name == "admin";
+                auth_token.authorities = vec![GrantedAuthority { authority: "ROLE_ADMIN".to_string() }];
+                auth_token.to_owned()
+            }
+        }
+
+        let http = HttpSecurity::<String, String>::default();
+        http.authentication_provider(Box::new(TestAdminProvider {}));
+        http.role_hierarchy(RoleHierarchy::parse("ROLE_ADMIN > ROLE_STAFF > ROLE_USER").unwrap());
+        http.path("/users/**").has_role("USER");
+        let authentication_manager = http.authentication_manager.lock().unwrap().as_ref().unwrap().build();
+        let authorization_manager = http.authorization_manager.lock().unwrap().as_ref().unwrap().build();
+
+        let mut admin = AuthenticationToken { name: "admin".to_string(), ..AuthenticationToken::default() };
+        let admin = tokio_test::block_on(authentication_manager.authenticate(&mut admin));
+        let authorities = admin.authorities.iter()
+            .map(|a| a.get_authority())
+            .collect::<Vec<&str>>();
+        assert_eq!(authorities, vec!["ROLE_ADMIN", "ROLE_STAFF", "ROLE_USER"]);
+        let mut request = WebRequest::default();
+        request.uri = "/users/1".parse().unwrap();
+        assert!(authorization_manager.check(&admin, &request).granted);
+
+        let mut other = AuthenticationToken { name: "other".to_string(), ..AuthenticationToken::default() };
+        let other = tokio_test::block_on(authentication_manager.authenticate(&mut other));
+        assert_eq!(other.authorities.len(), 1);
+    }
+
+    #[test]
+    fn test_authorities_mapper_is_chained_with_role_hierarchy() {
+        pub struct TestIdpProvider;
+        #[async_trait]
+        impl AuthenticationProvider for TestIdpProvider {
+            fn supports(&self, authentication_token: &AuthenticationType) -> bool {
+                true
+            }
+
+            async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+                auth_token.authenticated = true;
+                auth_token.authorities = vec![GrantedAuthority { authority: "administrators".to_string() }];
+                auth_token.to_owned()
+            }
+        }
+
+        let mut renames = HashMap::new();
+        renames.insert("administrators".to_string(), "admin".to_string());
+        let http = HttpSecurity::<String, String>::default();
+        http.authentication_provider(Box::new(TestIdpProvider {}));
+        http.role_hierarchy(RoleHierarchy::parse("ROLE_ADMIN > ROLE_STAFF > ROLE_USER").unwrap());
+        http.authorities_mapper(Arc::new(SimpleAuthorityMapper {
+            convert_to_upper_case: true,
+            renames,
+            ..SimpleAuthorityMapper::default()
+        }));
+        let authentication_manager = http.authentication_manager.lock().unwrap().as_ref().unwrap().build();
+
+        let admin = tokio_test::block_on(authentication_manager.authenticate(&mut AuthenticationToken::default()));
+        let authorities = admin.authorities.iter()
+            .map(|a| a.get_authority())
+            .collect::<Vec<&str>>();
+        assert_eq!(authorities, vec!["ROLE_ADMIN", "ROLE_STAFF", "ROLE_USER"]);
+    }
+
+    pub struct TestUserPasswordProvider;
+    #[async_trait]
+    impl AuthenticationProvider for TestUserPasswordProvider {
+        fn supports(&self, authentication_token: &AuthenticationType) -> bool {
+            true
+        }
+
+        async fn authenticate(&self, auth_token: &mut AuthenticationToken) -> AuthenticationToken {
+            if let AuthenticationType::Password(username_password) = &auth_token.auth {
+                auth_token.authenticated = username_password.username == "user" && username_password.password == "pass word";
+            }
+            auth_token.to_owned()
+        }
+    }
+
+    fn test_user_authentication_manager() -> Arc<DelegatingAuthenticationManager> {
+        let builder = DelegatingAuthenticationManagerBuilder::new();
+        builder.register(Box::new(TestUserPasswordProvider {}));
+        Arc::new(builder.build())
+    }
+
+    #[test]
+    fn test_http_basic_authentication() {
+        let filter = BasicAuthenticationFilter::new(test_user_authentication_manager(), "test");
+        let basic_request = |credentials: &str| {
+            let mut request = WebRequest::default();
+            request.headers.insert("authorization".to_string(), format!("Basic {}", base64::encode(credentials)));
+            request
+        };
+
+        let mut response = WebResponse::default();
+        let authenticated = tokio_test::block_on(filter.authenticate(&basic_request("user:pass word"), &mut response)).unwrap();
+        assert!(authenticated.authenticated);
+        assert_eq!(authenticated.name, "user");
+        assert!(!response.is_error());
+        if let AuthenticationType::Password(username_password) = &authenticated.auth {
+            assert_eq!(username_password.password, "");
+        }
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.authenticate(&basic_request("user:wrong"), &mut response)).is_none());
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers.get("WWW-Authenticate").unwrap(), "Basic realm=\"test\", charset=\"UTF-8\"");
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.authenticate(&WebRequest::default(), &mut response)).is_none());
+        assert!(!response.is_error());
+
+        let http = HttpSecurity::<String, String>::default();
+        http.any_request().authenticated();
+        let authorization_filter = AuthorizationFilter::new(Arc::new(http.authorization_manager.lock().unwrap().as_ref().unwrap().build()))
+            .with_entry_point(Arc::new(BasicAuthenticationEntryPoint::new("test")));
+        let mut response = WebResponse::default();
+        authorization_filter.authorize(&WebRequest::default(), &mut response, None);
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert!(response.headers.get("WWW-Authenticate").is_some());
+        let mut response = WebResponse::default();
+        authorization_filter.authorize(&WebRequest::default(), &mut response, Some(&authenticated));
+        assert!(!response.is_error());
+    }
+
+    fn test_session_manager() -> (Arc<InMemorySessionRepo>, Arc<SessionManager<InMemorySessionRepo>>) {
+        let session_repo = Arc::new(InMemorySessionRepo::default());
+        let session_manager = Arc::new(SessionManager::new(session_repo.clone(), SessionProperties::default()));
+        (session_repo, session_manager)
+    }
+
+    #[test]
+    fn test_form_login_saves_authentication_in_session() {
+        let (session_repo, session_manager) = test_session_manager();
+        let filter = FormLoginFilter::new(FormLoginProperties::default(), test_user_authentication_manager(), session_manager);
+        let login_request = |body: &str| {
+            let mut request = WebRequest::default();
+            request.method = "POST".parse().unwrap();
+            request.uri = "/login".parse().unwrap();
+            request.body = body.to_string();
+            request
+        };
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.login(&login_request("username=user&password=wrong"), &mut response, None)).is_none());
+        assert_eq!(response.status, StatusCode::FOUND);
+        assert_eq!(response.headers.get("Location").unwrap(), "/login?error");
+        assert!(response.is_committed());
+        assert!(tokio_test::block_on(session_repo.find_all()).is_empty());
+
+        let previous = HttpSession::new("previous".to_string(), None, Default::default());
+        tokio_test::block_on(session_repo.save(&previous));
+        let mut response = WebResponse::default();
+        let session = tokio_test::block_on(filter.login(&login_request("username=user&password=pass+word"), &mut response, Some(&previous))).unwrap();
+        assert_eq!(response.status, StatusCode::FOUND);
+        assert_eq!(response.headers.get("Location").unwrap(), "/");
+        assert!(response.is_committed());
+        let session_id = session.id.clone().unwrap();
+        assert_ne!(session_id, "previous");
+        let cookie = response.headers.get("Set-Cookie").unwrap().to_str().unwrap();
+        assert!(cookie.starts_with(format!("SESSION={};", session_id).as_str()));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(cookie.contains("Secure"));
+
+        assert!(tokio_test::block_on(session_repo.find_by_id(&"previous".to_string())).is_none());
+        let saved = tokio_test::block_on(session_repo.find_by_id(&session_id)).unwrap()
+            .security_context_holder.auth_token.clone().unwrap();
+        assert!(saved.authenticated);
+        assert_eq!(saved.name, "user");
+
+        let mut response = WebResponse::default();
+        let mut get_login = login_request("");
+        get_login.method = "GET".parse().unwrap();
+        assert!(tokio_test::block_on(filter.login(&get_login, &mut response, None)).is_none());
+        assert!(response.headers.get("Location").is_none());
+        assert!(!response.is_committed());
+    }
+
+    pub struct SessionDataFilter;
+    #[async_trait]
+    impl Handler<String, String, UserRequestContext<String>, RequestContextData<String, String>> for SessionDataFilter {
+        async fn do_action(
+            &self,
+            web_request: &WebRequest,
+            response: &mut WebResponse,
+            context: &RequestContextData<String, String>,
+            request_context: &mut Option<Box<UserRequestContext<String>>>
+        ) -> Option<String> {
+            request_context.as_mut().map(|request_context| request_context.request_context.http_session.session_data.insert("key", "value"));
+            None
+        }
+
+        fn authentication_granted(&self, token: &Option<Box<UserRequestContext<String>>>) -> bool {
+            true
+        }
+
+        fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_session_filter_saves_changed_session() {
+        let (session_repo, session_manager) = test_session_manager();
+        let session = HttpSession::new("session".to_string(), None, Default::default());
+        tokio_test::block_on(session_repo.save(&session));
+        let filter_chain = FilterChain::<String, String>::new(vec![
+            Filter::new(Arc::new(SessionFilter::new(session_manager.clone())), Some(0), Arc::new(FilterExecutor {})),
+            Filter::new(Arc::new(SessionDataFilter {}), Some(1), Arc::new(FilterExecutor {}))
+        ]);
+        let context = RequestContextData { request_context_data: Context::new() };
+        let dispatcher = CountingDispatcher::default();
+        let mut request = WebRequest::default();
+        request.headers.insert("Cookie".to_string(), "SESSION=session".to_string());
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            request, &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert_eq!(dispatcher.dispatched.load(Ordering::SeqCst), 1);
+        let saved = tokio_test::block_on(session_repo.find_by_id(&"session".to_string())).unwrap();
+        assert_eq!(saved.session_data.get("key"), Some(&"value".to_string()));
+
+        assert!(!tokio_test::block_on(session_manager.save_if_changed(&saved, &saved)));
+        assert!(!tokio_test::block_on(session_manager.save_if_changed(&HttpSession::default(), &saved)));
+    }
+
+    #[test]
+    fn test_form_login_redirect_is_not_dispatched() {
+        let (_, session_manager) = test_session_manager();
+        let filter_chain = FilterChain::<String, String>::new(vec![
+            FormLoginFilter::form_login_filter(FormLoginProperties::default(), test_user_authentication_manager(),
+                                               session_manager, Arc::new(FilterExecutor {}), Some(0))
+        ]);
+        let context = RequestContextData { request_context_data: Context::new() };
+        let dispatcher = CountingDispatcher::default();
+        let mut request = WebRequest::default();
+        request.method = "POST".parse().unwrap();
+        request.uri = "/login".parse().unwrap();
+        request.body = "username=user&password=pass+word".to_string();
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            request, &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert_eq!(response.status, StatusCode::FOUND);
+        assert_eq!(dispatcher.dispatched.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_csrf_filter_synchronizer_token() {
+        let (session_repo, session_manager) = test_session_manager();
+        let http = HttpSecurity::<String, String>::http();
+        http.csrf_ignoring(vec!["/api/**"]);
+        let properties = CsrfProperties::default();
+        let mut registrar = FilterRegistrarBuilder::<String, String>::new();
+        registrar.register(http.csrf_filter(
+            properties.clone(),
+            Arc::new(HttpSessionCsrfTokenRepository::new(&properties, session_manager.clone())),
+            Arc::new(FilterExecutor {}),
+            Some(1)
+        ));
+        assert_eq!(registrar.get_filters().len(), 1);
+
+        let filter = CsrfFilter::synchronizer_token(properties, session_manager, vec!["/api/**"]);
+        let request = |method: &str, path: &str| {
+            let mut request = WebRequest::default();
+            request.method = method.parse().unwrap();
+            request.uri = path.parse().unwrap();
+            request
+        };
+
+        let mut session = HttpSession::default();
+        let mut get = request("GET", "/form");
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.protect(&mut get, &mut response, &mut session)));
+        let token = CsrfToken::from_request(&get).unwrap();
+        assert_eq!(token.header_name, "X-CSRF-TOKEN");
+        assert_eq!(token.parameter_name, "_csrf");
+        let session_id = session.id.clone().unwrap();
+        assert!(response.headers.get("Set-Cookie").unwrap().to_str().unwrap().starts_with(format!("SESSION={};", session_id).as_str()));
+        let saved = tokio_test::block_on(session_repo.find_by_id(&session_id)).unwrap();
+        assert_eq!(saved.session_data.get("CSRF_TOKEN"), Some(&token.token));
+
+        let mut response = WebResponse::default();
+        assert!(!tokio_test::block_on(filter.protect(&mut request("POST", "/form"), &mut response, &mut session)));
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+        let mut with_header = request("POST", "/form");
+        with_header.headers.insert("X-CSRF-TOKEN".to_string(), token.token.clone());
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.protect(&mut with_header, &mut response, &mut session)));
+        assert!(!response.is_error());
+
+        let mut with_parameter = request("POST", "/form");
+        with_parameter.body = format!("name=one&_csrf={}", token.token);
+        assert!(tokio_test::block_on(filter.protect(&mut with_parameter, &mut WebResponse::default(), &mut session)));
+
+        let mut wrong = request("DELETE", "/form");
+        wrong.headers.insert("X-CSRF-TOKEN".to_string(), "wrong".to_string());
+        assert!(!tokio_test::block_on(filter.protect(&mut wrong, &mut WebResponse::default(), &mut session)));
+
+        let mut exempt = request("POST", "/api/one");
+        assert!(tokio_test::block_on(filter.protect(&mut exempt, &mut WebResponse::default(), &mut session)));
+    }
+
+    #[test]
+    fn test_csrf_filter_double_submit_cookie() {
+        let filter = CsrfFilter::double_submit_cookie(CsrfProperties {
+            header_name: "X-XSRF-TOKEN".to_string(),
+            ..CsrfProperties::default()
+        }, vec![]);
+        let mut session = HttpSession::default();
+
+        let mut get = WebRequest::default();
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.protect(&mut get, &mut response, &mut session)));
+        let token = CsrfToken::from_request(&get).unwrap();
+        let cookie = response.headers.get("Set-Cookie").unwrap().to_str().unwrap();
+        assert!(cookie.starts_with(format!("XSRF-TOKEN={};", token.token).as_str()));
+        assert!(!cookie.contains("HttpOnly"));
+        assert!(session.id.is_none());
+
+        let post = |header: &str| {
+            let mut request = WebRequest::default();
+            request.method = "POST".parse().unwrap();
+            request.headers.insert("Cookie".to_string(), format!("XSRF-TOKEN={}", token.token));
+            request.headers.insert("X-XSRF-TOKEN".to_string(), header.to_string());
+            request
+        };
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(filter.protect(&mut post(&token.token), &mut response, &mut session)));
+        assert!(response.headers.get("Set-Cookie").is_none());
+        let mut response = WebResponse::default();
+        assert!(!tokio_test::block_on(filter.protect(&mut post("other"), &mut response, &mut session)));
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+    }
+
+    struct CsrfTokenDispatcher {
+        token: Mutex<Option<CsrfToken>>
+    }
+
+    #[async_trait]
+    impl RequestDispatcher for CsrfTokenDispatcher {
+        async fn dispatch(&self, request: &WebRequest, response: &mut WebResponse) {
+            *self.token.lock().unwrap() = CsrfToken::from_request(request);
+        }
+    }
+
+    #[test]
+    fn test_csrf_token_reaches_dispatch() {
+        let filter = CsrfFilter::double_submit_cookie(CsrfProperties::default(), vec![]);
+        let filter_chain = FilterChain::<String, String>::new(vec![
+            CsrfFilter::csrf_filter(filter, Arc::new(FilterExecutor {}), Some(0))
+        ]);
+        let context = RequestContextData { request_context_data: Context::new() };
+        let dispatcher = CsrfTokenDispatcher { token: Mutex::new(None) };
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            WebRequest::default(), &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        let token = dispatcher.token.lock().unwrap().take().unwrap();
+        assert_eq!(token.parameter_name, "_csrf");
+        let cookie = response.headers.get("Set-Cookie").unwrap().to_str().unwrap();
+        assert!(cookie.starts_with(format!("XSRF-TOKEN={};", token.token).as_str()));
+
+        let mut post = WebRequest::default();
+        post.method = "POST".parse().unwrap();
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            post, &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert!(dispatcher.token.lock().unwrap().is_none());
+    }
+
+    #[derive(Default)]
+    struct CountingDispatcher {
+        dispatched: AtomicUsize
+    }
+
+    #[async_trait]
+    impl RequestDispatcher for CountingDispatcher {
+        async fn dispatch(&self, request: &WebRequest, response: &mut WebResponse) {
+            self.dispatched.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_jwt_token_endpoint_commits_response() {
+        let endpoint = JwtTokenEndpoint::new(Arc::new(test_token_service()));
+        let filter_chain = FilterChain::<String, String>::new(vec![
+            Filter::new(Arc::new(endpoint), Some(0), Arc::new(FilterExecutor {}))
+        ]);
+        let context = RequestContextData { request_context_data: Context::new() };
+        let dispatcher = CountingDispatcher::default();
+        let login = |password: &str| {
+            let mut request = WebRequest::default();
+            request.method = "POST".parse().unwrap();
+            request.uri = "/login/token".parse().unwrap();
+            request.body = serde_json::to_string(&LoginRequest { username: "user".to_string(), password: password.to_string() }).unwrap();
+            request
+        };
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            login("password"), &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert!(response.is_committed());
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(serde_json::from_slice::<TokenResponse>(&response.response_bytes().unwrap()).is_ok());
+        assert_eq!(dispatcher.dispatched.load(Ordering::SeqCst), 0);
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            login("other"), &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(dispatcher.dispatched.load(Ordering::SeqCst), 0);
+
+        let mut response = WebResponse::default();
+        tokio_test::block_on(filter_chain.dispatch(
+            WebRequest::default(), &mut response, &context,
+            &mut Some(UserRequestContext::new_default().into()), &dispatcher
+        ));
+        assert!(!response.is_committed());
+        assert_eq!(dispatcher.dispatched.load(Ordering::SeqCst), 1);
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Default)]
+    pub struct TestUserAccount {
+        pub username: String,
+        pub password: String,
+        pub authorities: Vec<String>
+    }
+
+    impl Entity<String> for TestUserAccount {
+        fn get_id(&self) -> Option<String> {
+            Some(self.username.clone())
+        }
+
+        fn set_id(&mut self, id: String) {
+            self.username = id;
+        }
+    }
+
+    impl UserAccount for TestUserAccount {
+        fn get_account_data(&self) -> AccountData {
+            AccountData { user_sessions: vec![], id: self.username.clone() }
+        }
+
+        fn login(&self) {
+        }
+
+        fn get_password(&self) -> String {
+            self.password.clone()
+        }
+
+        fn get_authorities(&self) -> Vec<GrantedAuthority> {
+            self.authorities.iter()
+                .map(|authority| GrantedAuthority { authority: authority.clone() })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_jwt_token_endpoint_with_dao_authentication_provider() {
+        let mut user_accounts = HashMap::new();
+        user_accounts.insert("user".to_string(), TestUserAccount {
+            username: "user".to_string(),
+            password: "password".to_string(),
+            authorities: vec!["ROLE_USER".to_string()],
+        });
+        let builder = DelegatingAuthenticationManagerBuilder::new();
+        builder.register(Box::new(DaoAuthenticationProvider::<TestUserAccount, _>::new(
+            InMemoryUserDetailsService::new(user_accounts),
+            Arc::new(NoOpPasswordEncoder {})
+        )));
+        let token_service = JwtTokenService::new(
+            JwtTokenProperties::new(JwtSigningKey::Hs256 { secret: "secret".to_string() }),
+            Arc::new(builder.build()),
+            Box::new(InMemoryRefreshTokenRepo::default())
+        ).ok().unwrap();
+        let endpoint = JwtTokenEndpoint::new(Arc::new(token_service));
+        let login = |username: &str, password: &str| {
+            let mut request = WebRequest::default();
+            request.method = "POST".parse().unwrap();
+            request.uri = "/login/token".parse().unwrap();
+            request.body = serde_json::to_string(&LoginRequest { username: username.to_string(), password: password.to_string() }).unwrap();
+            request
+        };
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(endpoint.handle(&login("user", "password"), &mut response)));
+        assert_eq!(response.status, StatusCode::OK);
+        let token_response = serde_json::from_slice::<TokenResponse>(&response.response_bytes().unwrap()).unwrap();
+        let decoder = JwtDecoder::new(JwtProperties::new(JwtKey::Hs256 { secret: "secret".to_string() })).unwrap();
+        let claims = decoder.decode(&token_response.access_token).unwrap();
+        assert_eq!(claims["sub"], "user");
+        assert_eq!(claims["authorities"], serde_json::json!(["ROLE_USER"]));
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(endpoint.handle(&login("user", "other"), &mut response)));
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+        let mut response = WebResponse::default();
+        assert!(tokio_test::block_on(endpoint.handle(&login("unknown", "password"), &mut response)));
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_cors_filter_answers_preflight() {
+        let http = HttpSecurity::<String, String>::http();
+        http.cors("/api/**", CorsConfiguration::permit_default_values());
+        let mut registrar = FilterRegistrarBuilder::<String, String>::new();
+        registrar.register(http.cors_filter(Arc::new(FilterExecutor {}), Some(0)));
+        assert_eq!(registrar.get_filters().len(), 1);
+
+        let mut properties = CorsProperties::default();
+        properties.mappings.insert("/api/**".to_string(), CorsConfiguration {
+            allowed_origins: vec!["https://one.com".to_string()],
+            allowed_methods: vec!["GET".to_string(), "PUT".to_string()],
+            ..CorsConfiguration::default()
+        });
+        let filter = CorsFilter::from_properties(&properties);
+        let request = |method: &str, path: &str, origin: &str| {
+            let mut request = WebRequest::default();
+            request.method = method.parse().unwrap();
+            request.uri = path.parse().unwrap();
+            request.headers.insert("Origin".to_string(), origin.to_string());
+            request
+        };
+
+        let mut preflight = request("OPTIONS", "/api/one", "https://one.com");
+        preflight.headers.insert("Access-Control-Request-Method".to_string(), "PUT".to_string());
+        let mut response = WebResponse::default();
+        assert!(filter.apply(&preflight, &mut response));
+        assert!(response.is_committed());
+        assert_eq!(response.headers.get("Access-Control-Allow-Origin").unwrap(), "https://one.com");
+        assert_eq!(response.headers.get("Access-Control-Allow-Methods").unwrap(), "GET, PUT");
+
+        let mut response = WebResponse::default();
+        assert!(filter.apply(&request("PUT", "/api/one", "https://one.com"), &mut response));
+        assert!(!response.is_committed());
+        assert_eq!(response.headers.get("Access-Control-Allow-Origin").unwrap(), "https://one.com");
+
+        let mut response = WebResponse::default();
+        assert!(!filter.apply(&request("PUT", "/api/one", "https://two.com"), &mut response));
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+        let mut response = WebResponse::default();
+        assert!(filter.apply(&request("PUT", "/other", "https://two.com"), &mut response));
+        assert!(response.headers.is_empty());
+    }
+
     #[test]
     fn test_authentication_converter_builder() {
         pub struct TestAuthConverter;
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use async_trait::async_trait;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use authentication_gen::AuthenticationType;
+use data_framework::{Entity, Repo};
+use knockoff_security::knockoff_security::authentication_type::UsernamePassword;
+use web_framework_shared::dispatch_server::Handler;
+use web_framework_shared::request::{EndpointMetadata, ResponseWriter, StatusCode, WebRequest, WebResponse};
+use crate::web_framework::context::{RequestContextData, UserRequestContext};
+use crate::web_framework::security::authentication::{AuthenticationProvider, AuthenticationToken, DelegatingAuthenticationManager};
+use crate::web_framework::security::jwt::{JwtEncoder, JwtSigningKey};
+
+/// Configuration of the endpoint that issues access tokens and refresh tokens.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JwtTokenProperties {
+    pub signing_key: JwtSigningKey,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
+    pub audience: Vec<String>,
+    /// The claim the authorities are written to, as an array.
+    #[serde(default = "JwtTokenProperties::default_authorities_claim")]
+    pub authorities_claim: String,
+    #[serde(default = "JwtTokenProperties::default_login_path")]
+    pub login_path: String,
+    #[serde(default = "JwtTokenProperties::default_refresh_path")]
+    pub refresh_path: String,
+    /// Seconds the access token is valid for.
+    #[serde(default = "JwtTokenProperties::default_access_token_validity")]
+    pub access_token_validity: u64,
+    /// Seconds the refresh token is valid for.
+    #[serde(default = "JwtTokenProperties::default_refresh_token_validity")]
+    pub refresh_token_validity: u64
+}
+
+impl JwtTokenProperties {
+    pub fn new(signing_key: JwtSigningKey) -> Self {
+        Self {
+            signing_key,
+            issuer: None,
+            audience: vec![],
+            authorities_claim: Self::default_authorities_claim(),
+            login_path: Self::default_login_path(),
+            refresh_path: Self::default_refresh_path(),
+            access_token_validity: Self::default_access_token_validity(),
+            refresh_token_validity: Self::default_refresh_token_validity(),
+        }
+    }
+
+    fn default_authorities_claim() -> String {
+        "authorities".to_string()
+    }
+
+    fn default_login_path() -> String {
+        "/login/token".to_string()
+    }
+
+    fn default_refresh_path() -> String {
+        "/login/token/refresh".to_string()
+    }
+
+    fn default_access_token_validity() -> u64 {
+        300
+    }
+
+    fn default_refresh_token_validity() -> u64 {
+        60 * 60 * 24 * 14
+    }
+}
+
+/// A refresh token that was issued. When it is used it is replaced by a new token in the same
+/// family, and keeps the id of the token that replaced it, so that if it is used again the tokens
+/// issued after it can be revoked.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RefreshToken {
+    pub id: String,
+    pub family_id: String,
+    pub username: String,
+    pub authorities: Vec<String>,
+    pub expires_at: u64,
+    pub replaced_by: Option<String>
+}
+
+impl Entity<String> for RefreshToken {
+    fn get_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub refresh_token: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RefreshRequest {
+    pub refresh_token: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TokenError {
+    InvalidRequest,
+    InvalidCredentials,
+    InvalidRefreshToken,
+    ExpiredRefreshToken,
+    /// A refresh token that was already rotated was used again, so the family was revoked.
+    RefreshTokenReused,
+    Signing(String)
+}
+
+impl TokenError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            TokenError::InvalidRequest => StatusCode::BAD_REQUEST,
+            TokenError::Signing(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED
+        }
+    }
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::InvalidRequest => write!(f, "The token request could not be read."),
+            TokenError::InvalidCredentials => write!(f, "The credentials were not valid."),
+            TokenError::InvalidRefreshToken => write!(f, "The refresh token was not valid."),
+            TokenError::ExpiredRefreshToken => write!(f, "The refresh token has expired."),
+            TokenError::RefreshTokenReused => write!(f, "The refresh token was already used."),
+            TokenError::Signing(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+/// Issues a signed access token and a refresh token when the DelegatingAuthenticationManager
+/// authenticates the login, and rotates the refresh token each time it is used.
+pub struct JwtTokenService<R>
+    where
+        R: for<'b> Repo<'b, RefreshToken, String>
+{
+    properties: JwtTokenProperties,
+    encoder: JwtEncoder,
+    authentication_manager: Arc<DelegatingAuthenticationManager>,
+    repo: Box<R>,
+    /// Held while a token of the family is rotated, so that concurrent refreshes of the same
+    /// token cannot both be issued a replacement.
+    family_locks: Mutex<HashMap<String, Arc<futures::lock::Mutex<()>>>>
+}
+
+impl <R> JwtTokenService<R>
+    where
+        R: for<'b> Repo<'b, RefreshToken, String>
+{
+    pub fn new(
+        properties: JwtTokenProperties,
+        authentication_manager: Arc<DelegatingAuthenticationManager>,
+        repo: Box<R>
+    ) -> Result<Self, TokenError> {
+        let encoder = JwtEncoder::new(&properties.signing_key)
+            .map_err(|e| TokenError::Signing(e.message))?;
+        Ok(Self { properties, encoder, authentication_manager, repo, family_locks: Mutex::new(HashMap::new()) })
+    }
+
+    pub fn properties(&self) -> &JwtTokenProperties {
+        &self.properties
+    }
+
+    pub async fn login(&self, login: &LoginRequest) -> Result<TokenResponse, TokenError> {
+        let mut auth_token = AuthenticationToken {
+            name: login.username.clone(),
+            auth: AuthenticationType::Password(UsernamePassword {
+                username: login.username.clone(),
+                password: login.password.clone(),
+            }),
+            authenticated: false,
+            authorities: vec![],
+        };
+        let authenticated = self.authentication_manager.authenticate(&mut auth_token).await;
+        if !authenticated.authenticated {
+            return Err(TokenError::InvalidCredentials);
+        }
+        let username = Some(authenticated.name.clone())
+            .filter(|name| name.len() != 0)
+            .or(Some(login.username.clone()))
+            .unwrap();
+        let authorities = authenticated.authorities.iter()
+            .map(|a| a.authority.clone())
+            .collect::<Vec<String>>();
+        self.issue(username, authorities, Self::generate_token_value(), Self::generate_token_value()).await
+    }
+
+    /// Replaces the refresh token with a new one. If the refresh token was already replaced then
+    /// it was stolen or replayed, so every token issued after it is revoked.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenResponse, TokenError> {
+        let family_id = self.repo.find_by_id(&refresh_token.to_string()).await
+            .map(|found: RefreshToken| found.family_id)
+            .ok_or(TokenError::InvalidRefreshToken)?;
+        let family_lock = self.family_lock(&family_id);
+        let rotated = {
+            let _rotating = family_lock.lock().await;
+            self.rotate(refresh_token).await
+        };
+        self.release_family_lock(&family_id, family_lock);
+        rotated
+    }
+
+    /// The token is read again while the lock of its family is held, and is marked as replaced
+    /// before the new token is issued, so only the first of several concurrent refreshes with
+    /// the same token is issued a new one, and the others revoke the family.
+    async fn rotate(&self, refresh_token: &str) -> Result<TokenResponse, TokenError> {
+        let mut found: RefreshToken = self.repo.find_by_id(&refresh_token.to_string()).await
+            .ok_or(TokenError::InvalidRefreshToken)?;
+        if found.replaced_by.is_some() {
+            self.revoke_replacements(found).await;
+            return Err(TokenError::RefreshTokenReused);
+        }
+        if found.expires_at <= Self::now() {
+            self.repo.delete_by_id(&found.id).await;
+            return Err(TokenError::ExpiredRefreshToken);
+        }
+        let replacement_id = Self::generate_token_value();
+        found.replaced_by = Some(replacement_id.clone());
+        self.repo.save(&found).await;
+        self.issue(found.username.clone(), found.authorities.clone(), found.family_id.clone(), replacement_id).await
+    }
+
+    fn family_lock(&self, family_id: &String) -> Arc<futures::lock::Mutex<()>> {
+        let mut family_locks = self.family_locks.lock().unwrap();
+        family_locks.entry(family_id.clone())
+            .or_insert_with(|| Arc::new(futures::lock::Mutex::new(())))
+            .clone()
+    }
+
+    /// Removes the lock of the family once no other refresh is waiting on it.
+    fn release_family_lock(&self, family_id: &String, family_lock: Arc<futures::lock::Mutex<()>>) {
+        let mut family_locks = self.family_locks.lock().unwrap();
+        if Arc::strong_count(&family_lock) == 2 {
+            family_locks.remove(family_id);
+        }
+    }
+
+    async fn issue(&self, username: String, authorities: Vec<String>, family_id: String, refresh_token_id: String) -> Result<TokenResponse, TokenError> {
+        let now = Self::now();
+        let mut claims: HashMap<String, Value> = HashMap::new();
+        claims.insert("sub".to_string(), Value::from(username.clone()));
+        claims.insert("iat".to_string(), Value::from(now));
+        claims.insert("exp".to_string(), Value::from(now + self.properties.access_token_validity));
+        claims.insert(self.properties.authorities_claim.clone(), Value::from(authorities.clone()));
+        self.properties.issuer.as_ref()
+            .map(|issuer| claims.insert("iss".to_string(), Value::from(issuer.clone())));
+        if self.properties.audience.len() != 0 {
+            claims.insert("aud".to_string(), Value::from(self.properties.audience.clone()));
+        }
+        let access_token = self.encoder.encode(&claims)
+            .map_err(|e| TokenError::Signing(e.message))?;
+
+        let refresh_token = RefreshToken {
+            id: refresh_token_id,
+            family_id,
+            username,
+            authorities,
+            expires_at: now + self.properties.refresh_token_validity,
+            replaced_by: None,
+        };
+        self.repo.save(&refresh_token).await;
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.properties.access_token_validity,
+            refresh_token: refresh_token.id,
+        })
+    }
+
+    /// Follows the tokens that replaced the reused token, deleting each of them, so that the
+    /// current token of the family can no longer be used.
+    async fn revoke_replacements(&self, reused: RefreshToken) {
+        let mut next = reused.replaced_by;
+        while let Some(id) = next {
+            next = self.repo.find_by_id(&id).await
+                .and_then(|replacement: RefreshToken| replacement.replaced_by);
+            self.repo.delete_by_id(&id).await;
+        }
+    }
+
+    fn generate_token_value() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Handles POST to the login path, with a json LoginRequest, and POST to the refresh path, with a
+/// json RefreshRequest, writing a TokenResponse, or the TokenError with its status. The response
+/// is committed, so the request is not dispatched.
+pub struct JwtTokenEndpoint<R>
+    where
+        R: for<'b> Repo<'b, RefreshToken, String>
+{
+    token_service: Arc<JwtTokenService<R>>
+}
+
+impl <R> JwtTokenEndpoint<R>
+    where
+        R: for<'b> Repo<'b, RefreshToken, String>
+{
+    pub fn new(token_service: Arc<JwtTokenService<R>>) -> Self {
+        Self { token_service }
+    }
+
+    pub async fn handle(&self, web_request: &WebRequest, response: &mut WebResponse) -> bool {
+        if web_request.method.as_str() != "POST" {
+            return false;
+        }
+        let path = web_request.uri.path();
+        let properties = self.token_service.properties();
+        let issued = if path == properties.login_path {
+            match serde_json::from_str::<LoginRequest>(&web_request.body) {
+                Ok(login) => self.token_service.login(&login).await,
+                Err(_) => Err(TokenError::InvalidRequest)
+            }
+        } else if path == properties.refresh_path {
+            match serde_json::from_str::<RefreshRequest>(&web_request.body) {
+                Ok(refresh) => self.token_service.refresh(&refresh.refresh_token).await,
+                Err(_) => Err(TokenError::InvalidRequest)
+            }
+        } else {
+            return false;
+        };
+        let body = match issued {
+            Ok(token_response) => serde_json::to_string(&token_response),
+            Err(e) => {
+                response.set_status(e.status());
+                serde_json::to_string(&e)
+            }
+        };
+        response.set_header("Cache-Control", "no-store");
+        response.set_content_type_if_absent("application/json");
+        body.map(|body| response.write(body.as_bytes())).ok();
+        true
+    }
+}
+
+#[async_trait]
+impl <R, Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for JwtTokenEndpoint<R>
+    where
+        R: for<'b> Repo<'b, RefreshToken, String>,
+        Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+        Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
+{
+    async fn do_action(
+        &self,
+        web_request: &WebRequest,
+        response: &mut WebResponse,
+        application_context: &RequestContextData<Request, Response>,
+        request_context: &mut Option<Box<UserRequestContext<Request>>>
+    ) -> Option<Response> {
+        if self.handle(web_request, response).await {
+            response.commit();
+        }
+        None
+    }
+
+    fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
+        true
+    }
+
+    fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool {
+        true
+    }
+}
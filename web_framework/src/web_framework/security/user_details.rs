@@ -5,12 +5,19 @@ use std::marker::PhantomData;
 use std::any::Any;
 use std::collections::HashMap;
 use std::hash::Hash;
+use async_trait::async_trait;
 
+#[async_trait]
 pub trait UserDetailsService<U, ID>: Send + Sync
     where
         U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync
 {
     async fn load_by_username(&self, id: &ID) -> Option<U>;
+
+    /// Called with the password encoded again after a login, when the stored password was encoded
+    /// with weaker parameters or another algorithm, so that it can be stored instead.
+    async fn update_password(&self, _user: &U, _encoded_password: &str) {
+    }
 }
 
 pub struct PersistenceUserDetailsService<'a, R, U>
@@ -22,6 +29,7 @@ pub struct PersistenceUserDetailsService<'a, R, U>
     pub repo: Box<R>,
 }
 
+#[async_trait]
 impl <'a, R, U> UserDetailsService<U, String> for PersistenceUserDetailsService<'a, R, U>
     where
         U: UserAccount + Serialize + for<'de> Deserialize<'de> + Send + Sync,
@@ -39,6 +47,17 @@ pub struct InMemoryUserDetailsService<U, ID>
     user_accounts: HashMap<ID, U>
 }
 
+impl <U, ID> InMemoryUserDetailsService<U, ID>
+    where
+        U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync,
+        ID: Send + Sync
+{
+    pub fn new(user_accounts: HashMap<ID, U>) -> Self {
+        Self { user_accounts }
+    }
+}
+
+#[async_trait]
 impl <U, ID> UserDetailsService<U,ID> for InMemoryUserDetailsService<U,ID>
     where
         U: UserAccount + Serialize + for<'a> Deserialize<'a> + Send + Sync,
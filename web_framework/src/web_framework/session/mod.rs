@@ -10,23 +10,34 @@ pub mod session {
     use crate::web_framework::security::authentication::AuthenticationToken;
     use crate::web_framework::security::security_context_holder::SecurityContextHolder;
     use alloc::string::String;
+    use async_trait::async_trait;
     use core::borrow::Borrow;
     use data_framework::{Entity, Repo};
-    use futures::executor;
     use knockoff_security::knockoff_security::user_request_account::SessionData;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use std::any::Any;
-    use std::marker::PhantomData;
+    use async_std::task::JoinHandle;
+    use rand::RngCore;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
     use web_framework_shared::dispatch_server::Handler;
     use web_framework_shared::request::WebResponse;
     use web_framework_shared::request::{EndpointMetadata, WebRequest};
 
 
+    /// The default name of the cookie the session id is sent in.
+    pub const SESSION_COOKIE_NAME: &str = "SESSION";
+
     #[derive(Serialize, Deserialize, Debug, Clone, Default)]
     pub struct HttpSession {
         pub session_data: SessionData,
         pub security_context_holder: SecurityContextHolder,
         pub id: Option<String>,
+        /// Seconds since the epoch, for the absolute timeout.
+        #[serde(default)]
+        pub created_at: u64,
+        /// Seconds since the epoch, for the idle timeout.
+        #[serde(default)]
+        pub last_accessed_at: u64,
     }
 
     impl HttpSession {
@@ -35,14 +46,29 @@ pub mod session {
             authentication_token: Option<AuthenticationToken>,
             session_data: SessionData,
         ) -> HttpSession {
+            let now = Self::now_seconds();
             Self {
                 session_data,
                 security_context_holder: SecurityContextHolder {
                     auth_token: authentication_token
                 },
                 id: Some(id),
+                created_at: now,
+                last_accessed_at: now,
             }
         }
+
+        pub fn now_seconds() -> u64 {
+            SystemTime::now().duration_since(UNIX_EPOCH)
+                .map(|since_epoch| since_epoch.as_secs())
+                .unwrap_or(0)
+        }
+
+        /// A timeout of 0 is not applied.
+        pub fn is_expired(&self, idle_timeout_seconds: u64, absolute_timeout_seconds: u64, now: u64) -> bool {
+            (idle_timeout_seconds != 0 && now.saturating_sub(self.last_accessed_at) >= idle_timeout_seconds)
+                || (absolute_timeout_seconds != 0 && now.saturating_sub(self.created_at) >= absolute_timeout_seconds)
+        }
     }
 
     impl Entity<String> for HttpSession {
@@ -54,39 +80,280 @@ pub mod session {
         }
     }
 
-    pub struct SessionFilter<'a, R>
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    pub enum SessionStoreType {
+        Memory, File, Mongo
+    }
+
+    impl Default for SessionStoreType {
+        fn default() -> Self {
+            SessionStoreType::Memory
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct SessionCookieProperties {
+        pub name: String,
+        pub path: String,
+        pub secure: bool,
+        pub http_only: bool,
+        /// Strict, Lax or None. None requires the cookie to be secure.
+        pub same_site: String
+    }
+
+    impl Default for SessionCookieProperties {
+        fn default() -> Self {
+            Self {
+                name: SESSION_COOKIE_NAME.to_string(),
+                path: "/".to_string(),
+                secure: true,
+                http_only: true,
+                same_site: "Lax".to_string(),
+            }
+        }
+    }
+
+    /// Configuration of the session store, the timeouts and the session cookie.
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct SessionProperties {
+        pub store: SessionStoreType,
+        /// The directory each session is written to as json, for SessionStoreType::File.
+        pub file_directory: String,
+        pub mongo_uri: String,
+        pub mongo_database: String,
+        pub mongo_collection: String,
+        /// The session expires if it is not used for this many seconds.
+        pub idle_timeout_seconds: u64,
+        /// The session expires this many seconds after it was created, even if it is in use.
+        pub absolute_timeout_seconds: u64,
+        /// How often the expired sessions are removed from the store.
+        pub purge_interval_seconds: u64,
+        pub cookie: SessionCookieProperties
+    }
+
+    impl Default for SessionProperties {
+        fn default() -> Self {
+            Self {
+                store: SessionStoreType::default(),
+                file_directory: "sessions".to_string(),
+                mongo_uri: "mongodb://localhost:27017".to_string(),
+                mongo_database: "http_session".to_string(),
+                mongo_collection: "http_session".to_string(),
+                idle_timeout_seconds: 30 * 60,
+                absolute_timeout_seconds: 8 * 60 * 60,
+                purge_interval_seconds: 60,
+                cookie: SessionCookieProperties::default(),
+            }
+        }
+    }
+
+    /// Loads, saves and expires the sessions in the store, and writes the session cookie. The
+    /// store is any Repo for HttpSession, such as the SessionStore created from the
+    /// SessionProperties.
+    pub struct SessionManager<R> {
+        repo: Arc<R>,
+        properties: SessionProperties
+    }
+
+    impl<R> SessionManager<R>
+    where
+        R: for<'b> Repo<'b, HttpSession, String>,
+    {
+        pub fn new(repo: Arc<R>, properties: SessionProperties) -> Self {
+            Self { repo, properties }
+        }
+
+        pub fn properties(&self) -> &SessionProperties {
+            &self.properties
+        }
+
+        pub fn generate_session_id() -> String {
+            let mut bytes = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+        }
+
+        /// The session id from the session cookie.
+        pub fn session_id(&self, web_request: &WebRequest) -> Option<String> {
+            web_request.cookie(&self.properties.cookie.name)
+        }
+
+        pub fn session_cookie(&self, session_id: &str) -> String {
+            let cookie = &self.properties.cookie;
+            let mut session_cookie = format!("{}={}; Path={}", cookie.name, session_id, cookie.path);
+            if cookie.secure {
+                session_cookie.push_str("; Secure");
+            }
+            if cookie.http_only {
+                session_cookie.push_str("; HttpOnly");
+            }
+            if cookie.same_site.len() != 0 {
+                session_cookie.push_str(format!("; SameSite={}", cookie.same_site).as_str());
+            }
+            session_cookie
+        }
+
+        fn expired_cookie(&self) -> String {
+            format!("{}; Max-Age=0", self.session_cookie(""))
+        }
+
+        /// The session for the id, or None if there is none or it has expired, in which case it is
+        /// removed. The last accessed time of the session is updated.
+        pub async fn load(&self, session_id: &String) -> Option<HttpSession> {
+            let now = HttpSession::now_seconds();
+            let mut session = self.repo.find_by_id(session_id).await?;
+            if self.is_expired(&session, now) {
+                self.repo.delete_by_id(session_id).await;
+                return None;
+            }
+            session.last_accessed_at = now;
+            self.repo.save(&session).await;
+            Some(session)
+        }
+
+        pub async fn save(&self, session: &HttpSession) {
+            self.repo.save(session).await;
+        }
+
+        /// Saves the session if it is different from the session that was loaded, returning if it
+        /// was saved. A session without an id was invalidated or never created, so is not saved.
+        pub async fn save_if_changed(&self, session: &HttpSession, loaded: &HttpSession) -> bool {
+            if session.id.is_none()
+                || serde_json::to_value(session).ok() == serde_json::to_value(loaded).ok() {
+                return false;
+            }
+            self.repo.save(session).await;
+            true
+        }
+
+        /// Creates the session if it does not have an id yet, writing the session cookie.
+        pub async fn save_new(&self, session: &mut HttpSession, response: &mut WebResponse) {
+            if session.id.is_none() {
+                let now = HttpSession::now_seconds();
+                session.id = Some(Self::generate_session_id());
+                session.created_at = now;
+                session.last_accessed_at = now;
+                response.add_cookie(self.session_cookie(session.id.as_ref().unwrap()).as_str());
+            }
+            self.repo.save(session).await;
+        }
+
+        /// Moves the session to a new id, removing the previous one, so that an id set before the
+        /// login cannot be used to take over the authenticated session.
+        pub async fn rotate_id(&self, session: &mut HttpSession, response: &mut WebResponse) -> String {
+            if let Some(previous_id) = session.id.take() {
+                self.repo.delete_by_id(&previous_id).await;
+            }
+            self.save_new(session, response).await;
+            session.id.clone().unwrap()
+        }
+
+        pub async fn invalidate(&self, session: &mut HttpSession, response: &mut WebResponse) {
+            if let Some(session_id) = session.id.take() {
+                self.repo.delete_by_id(&session_id).await;
+            }
+            response.add_cookie(self.expired_cookie().as_str());
+            *session = HttpSession::default();
+        }
+
+        pub fn is_expired(&self, session: &HttpSession, now: u64) -> bool {
+            session.is_expired(self.properties.idle_timeout_seconds, self.properties.absolute_timeout_seconds, now)
+        }
+
+        /// Removes the expired sessions, returning how many were removed.
+        pub async fn purge_expired(&self) -> usize {
+            let now = HttpSession::now_seconds();
+            let mut purged = 0;
+            for session in self.repo.find_all().await.iter()
+                .filter(|session| self.is_expired(session, now)) {
+                if let Some(session_id) = session.id.as_ref() {
+                    if self.repo.delete_by_id(session_id).await {
+                        purged += 1;
+                    }
+                }
+            }
+            purged
+        }
+
+        /// Purges the expired sessions every purge_interval_seconds.
+        pub fn schedule_purge(session_manager: Arc<Self>) -> JoinHandle<()>
+        where
+            R: 'static,
+        {
+            let purge_interval = Duration::from_secs(session_manager.properties.purge_interval_seconds.max(1));
+            async_std::task::spawn(async move {
+                loop {
+                    async_std::task::sleep(purge_interval).await;
+                    session_manager.purge_expired().await;
+                }
+            })
+        }
+    }
+
+    /// Loads the session for the session cookie into the request context, and adds its
+    /// authentication to the SecurityContextHolder. Once the request was dispatched, the session
+    /// is saved if it was changed.
+    pub struct SessionFilter<R> {
+        session_manager: Arc<SessionManager<R>>,
+    }
+
+    impl<R> SessionFilter<R>
     where
-        R: Repo<'a, HttpSession, String>,
+        R: for<'b> Repo<'b, HttpSession, String>,
     {
-        p: &'a PhantomData<dyn Any + Send + Sync>,
-        repo: Box<R>,
+        pub fn new(session_manager: Arc<SessionManager<R>>) -> Self {
+            Self { session_manager }
+        }
+
+        pub fn session_id(&self, web_request: &WebRequest) -> Option<String> {
+            self.session_manager.session_id(web_request)
+        }
     }
 
-    impl<'a, R, Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for SessionFilter<'a, R>
+    #[async_trait]
+    impl<R, Request, Response> Handler<Request, Response, UserRequestContext<Request>, RequestContextData<Request, Response>> for SessionFilter<R>
     where
-        R: Repo<'a, HttpSession, String>,
+        R: for<'b> Repo<'b, HttpSession, String>,
         Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
         Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
     {
 
-        fn do_action(
+        async fn do_action(
             &self,
             mut web_request: &WebRequest,
             mut response: &mut WebResponse,
             application_context: &RequestContextData<Request, Response>,
             request_context: &mut Option<Box<UserRequestContext<Request>>>
         ) -> Option<Response> {
-            if let Some(session) = web_request
-                .headers
-                .get("R_SESSION_ID")
-                .and_then(|session_id| executor::block_on(self.repo.find_by_id(session_id))) {
-                    request_context.as_mut().map(|mut request_context| {
-                        request_context.request_context.http_session = session;
-                    });
+            let session = match self.session_id(web_request) {
+                Some(session_id) => self.session_manager.load(&session_id).await,
+                None => None
+            };
+            if let Some(session) = session {
+                request_context.as_mut().map(|mut request_context| {
+                    request_context.request_context.loaded_http_session = Some(session.clone());
+                    request_context.request_context.http_session = session;
+                    request_context.request_context.http_session.security_context_holder.set_current();
+                });
             }
             None
         }
 
+        async fn after_dispatch(
+            &self,
+            web_request: &WebRequest,
+            response: &mut WebResponse,
+            application_context: &RequestContextData<Request, Response>,
+            request_context: &mut Option<Box<UserRequestContext<Request>>>
+        ) {
+            if let Some(request_context) = request_context.as_ref() {
+                let session_context = &request_context.request_context;
+                if let Some(loaded) = session_context.loaded_http_session.as_ref() {
+                    self.session_manager.save_if_changed(&session_context.http_session, loaded).await;
+                }
+            }
+        }
+
         fn authentication_granted(&self, token: &Option<Box<UserRequestContext<Request>>>) -> bool {
             true
         }
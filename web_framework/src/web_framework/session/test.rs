@@ -1,4 +1,6 @@
-use crate::web_framework::session::session::{HttpSession};
+use crate::web_framework::session::session::{HttpSession, SessionCookieProperties, SessionManager, SessionProperties};
+use crate::web_framework::session::repo::{FileSessionRepo, InMemorySessionRepo, SessionStore};
+use web_framework_shared::request::{WebRequest, WebResponse};
 use data_framework::{Entity, Repo, RepoDelegate};
 use lazy_static::lazy_static;
 use mongo_repo::Db;
@@ -37,3 +39,103 @@ async fn test_insert_save() {
 fn test_repo_delegate() {
     println!("hello")
 }
+
+fn expire(session: &mut HttpSession, seconds_ago: u64) {
+    session.created_at = HttpSession::now_seconds() - seconds_ago;
+    session.last_accessed_at = HttpSession::now_seconds() - seconds_ago;
+}
+
+#[test]
+fn test_session_manager_timeouts_and_purge() {
+    let repo = Arc::new(InMemorySessionRepo::default());
+    let properties = SessionProperties {
+        idle_timeout_seconds: 60,
+        absolute_timeout_seconds: 600,
+        ..SessionProperties::default()
+    };
+    let session_manager = Arc::new(SessionManager::new(repo.clone(), properties));
+
+    let active = HttpSession::new("active".to_string(), None, SessionData::default());
+    let mut idle = HttpSession::new("idle".to_string(), None, SessionData::default());
+    expire(&mut idle, 61);
+    let mut absolute = HttpSession::new("absolute".to_string(), None, SessionData::default());
+    absolute.created_at = HttpSession::now_seconds() - 601;
+    block_on(repo.save(&active));
+    block_on(repo.save(&idle));
+    block_on(repo.save(&absolute));
+
+    assert!(block_on(session_manager.load(&"active".to_string())).is_some());
+    assert!(block_on(session_manager.load(&"idle".to_string())).is_none());
+    assert!(block_on(repo.find_by_id(&"idle".to_string())).is_none());
+    assert_eq!(block_on(session_manager.purge_expired()), 1);
+    let remaining = block_on(repo.find_all());
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining.front().unwrap().get_id().unwrap(), "active");
+}
+
+#[test]
+fn test_session_manager_rotates_id_with_cookie() {
+    let repo = Arc::new(InMemorySessionRepo::default());
+    let session_manager = SessionManager::new(repo.clone(), SessionProperties {
+        cookie: SessionCookieProperties {
+            name: "ID".to_string(),
+            same_site: "Strict".to_string(),
+            ..SessionCookieProperties::default()
+        },
+        ..SessionProperties::default()
+    });
+
+    let mut session = HttpSession::default();
+    session.session_data.insert("cart", "one");
+    let mut response = WebResponse::default();
+    block_on(session_manager.save_new(&mut session, &mut response));
+    let first_id = session.id.clone().unwrap();
+    assert_eq!(
+        response.headers.get("Set-Cookie").unwrap().to_str().unwrap(),
+        format!("ID={}; Path=/; Secure; HttpOnly; SameSite=Strict", first_id)
+    );
+
+    let mut request = WebRequest::default();
+    request.headers.insert("Cookie".to_string(), format!("ID={}", first_id));
+    request.headers.insert("R_SESSION_ID".to_string(), "other".to_string());
+    assert_eq!(session_manager.session_id(&request), Some(first_id.clone()));
+
+    let mut response = WebResponse::default();
+    let second_id = block_on(session_manager.rotate_id(&mut session, &mut response));
+    assert_ne!(first_id, second_id);
+    assert!(block_on(repo.find_by_id(&first_id)).is_none());
+    let rotated = block_on(repo.find_by_id(&second_id)).unwrap();
+    assert_eq!(rotated.session_data.get("cart"), Some(&"one".to_string()));
+
+    let mut response = WebResponse::default();
+    block_on(session_manager.invalidate(&mut session, &mut response));
+    assert!(session.id.is_none());
+    assert!(block_on(repo.find_by_id(&second_id)).is_none());
+    assert!(response.headers.get("Set-Cookie").unwrap().to_str().unwrap().starts_with("ID=; Path=/"));
+}
+
+#[test]
+fn test_file_session_repo() {
+    let directory = std::env::temp_dir().join(format!("sessions_{}", SessionManager::<InMemorySessionRepo>::generate_session_id()));
+    let repo = FileSessionRepo::new(directory.to_str().unwrap());
+    let session = HttpSession::new("one".to_string(), None, SessionData::default());
+    assert_eq!(block_on(repo.save(&session)), "one");
+    assert_eq!(block_on(repo.find_by_id(&"one".to_string())).unwrap().get_id().unwrap(), "one");
+    assert_eq!(block_on(repo.find_all()).len(), 1);
+
+    assert!(!FileSessionRepo::is_valid_id("../one"));
+    assert!(block_on(repo.find_by_id(&"../one".to_string())).is_none());
+
+    assert!(block_on(repo.delete_by_id(&"one".to_string())));
+    assert!(block_on(repo.find_by_id(&"one".to_string())).is_none());
+    std::fs::remove_dir_all(directory).ok();
+}
+
+#[test]
+fn test_session_store_from_properties() {
+    let store = SessionStore::from_properties(&SessionProperties::default());
+    assert!(matches!(store, SessionStore::Memory(_)));
+    let session = HttpSession::new("one".to_string(), None, SessionData::default());
+    block_on(store.save(&session));
+    assert!(block_on(store.find_by_id(&"one".to_string())).is_some());
+}
@@ -0,0 +1,19 @@
+use syn::ImplItemMethod;
+use crate::argument_resolver::{NamedValueInfo, ResolveArguments};
+
+pub struct CookieValueMethodArgument {
+    pub inner: NamedValueInfo
+}
+
+impl ResolveArguments for CookieValueMethodArgument {
+    fn resolve_argument_methods(method: &ImplItemMethod) -> Vec<Self> where Self: Sized {
+        Self::resolve_fn_arg_fn_arg_ident_tuple("cookie_value", method)
+            .iter()
+            .map(|method_arg_name| {
+                Self {
+                    inner: NamedValueInfo::parse("cookie value", method_arg_name.0, method_arg_name.1.as_str())
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,22 @@
+use syn::ImplItemMethod;
+use crate::argument_resolver::{NamedValueInfo, ResolveArguments};
+use crate::csrf::CSRF_TOKEN_ATTRIBUTE;
+
+/// #[csrf_token] resolves the CsrfToken the CsrfFilter added to the request attributes, so the
+/// name is always the CSRF_TOKEN_ATTRIBUTE rather than the ident of the argument.
+pub struct CsrfTokenMethodArgument {
+    pub inner: NamedValueInfo
+}
+
+impl ResolveArguments for CsrfTokenMethodArgument {
+    fn resolve_argument_methods(method: &ImplItemMethod) -> Vec<Self> where Self: Sized {
+        Self::resolve_fn_arg_fn_arg_ident_tuple("csrf_token", method)
+            .iter()
+            .map(|method_arg_name| {
+                Self {
+                    inner: NamedValueInfo::parse("csrf token", method_arg_name.0, CSRF_TOKEN_ATTRIBUTE)
+                }
+            })
+            .collect()
+    }
+}
@@ -5,15 +5,28 @@ use codegen_utils::syn_helper::SynHelper;
 use crate::argument_resolver::path_variable_argument_resolver::PathVariableMethodArgument;
 use crate::argument_resolver::query_param_argument_resolver::QueryParamMethodArgument;
 use crate::argument_resolver::request_body_argument_resolver::RequestBodyArgumentResolver;
+use crate::argument_resolver::request_header_argument_resolver::RequestHeaderMethodArgument;
+use crate::argument_resolver::cookie_value_argument_resolver::CookieValueMethodArgument;
+use crate::argument_resolver::request_attribute_argument_resolver::RequestAttributeMethodArgument;
+use crate::argument_resolver::csrf_token_argument_resolver::CsrfTokenMethodArgument;
 
 pub mod path_variable_argument_resolver;
 pub mod query_param_argument_resolver;
 pub mod request_body_argument_resolver;
+pub mod request_header_argument_resolver;
+pub mod cookie_value_argument_resolver;
+pub mod request_attribute_argument_resolver;
+pub mod csrf_token_argument_resolver;
+pub mod request_value_resolver;
 
 pub struct ArgumentResolver {
     pub path_variable_arguments: Vec<PathVariableMethodArgument>,
     pub query_param_arguments: Vec<QueryParamMethodArgument>,
-    pub request_body_arguments: Vec<RequestBodyArgumentResolver>
+    pub request_body_arguments: Vec<RequestBodyArgumentResolver>,
+    pub request_header_arguments: Vec<RequestHeaderMethodArgument>,
+    pub cookie_value_arguments: Vec<CookieValueMethodArgument>,
+    pub request_attribute_arguments: Vec<RequestAttributeMethodArgument>,
+    pub csrf_token_arguments: Vec<CsrfTokenMethodArgument>
 }
 
 #[derive(Clone,Default)]
@@ -22,7 +35,79 @@ pub struct NamedValueInfo {
     pub required: bool,
     pub default_value: String,
     pub label: String,
-    pub multi_valued: bool
+    pub multi_valued: bool,
+    /// The ident and type of the method argument, used to generate the call to the controller.
+    pub arg_ident: Option<Ident>,
+    pub arg_type: Option<Type>
+}
+
+impl NamedValueInfo {
+    pub fn has_default_value(&self) -> bool {
+        self.default_value.len() != 0
+    }
+
+    /// Parses #[request_param(name, default_value = "1", required = false)]. The name defaults to
+    /// the ident of the argument. Option<T> and arguments with a default value are not required,
+    /// and Vec<T> collects all of the values provided for the name.
+    pub fn parse(label: &str, typed_arg: &PatType, attr_item: &str) -> Self {
+        let arg_ident = SynHelper::get_fn_arg_ident_type(typed_arg).map(|t| t.0);
+        let arg_type = typed_arg.ty.deref().clone();
+
+        let mut name = None;
+        let mut default_value = String::default();
+        let mut required = None;
+
+        attr_item.split(",")
+            .map(|item| item.trim())
+            .filter(|item| item.len() != 0)
+            .for_each(|item| {
+                match item.split_once("=") {
+                    Some((key, value)) => {
+                        let value = value.trim().trim_matches('"').to_string();
+                        match key.trim() {
+                            "name" | "value" => name = Some(value),
+                            "default_value" => default_value = value,
+                            "required" => required = Some(value == "true"),
+                            _ => {}
+                        }
+                    }
+                    None => name = Some(item.replace(" ", ""))
+                }
+            });
+
+        let optional = Self::is_type(&arg_type, "Option");
+        let has_default = default_value.len() != 0;
+
+        Self {
+            name: name.or(arg_ident.as_ref().map(|i| i.to_string()))
+                .or(Some(String::default()))
+                .unwrap(),
+            required: required.or(Some(!optional && !has_default)).unwrap(),
+            default_value,
+            label: label.to_string(),
+            multi_valued: Self::is_type(&arg_type, "Vec"),
+            arg_ident,
+            arg_type: Some(arg_type),
+        }
+    }
+
+    /// Matches the last segment of the type, ignoring references.
+    pub fn is_type(ty: &Type, ident: &str) -> bool {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.path.segments.last()
+                    .map(|s| s.ident.to_string() == ident)
+                    .or(Some(false))
+                    .unwrap()
+            }
+            Type::Reference(reference) => {
+                Self::is_type(reference.elem.deref(), ident)
+            }
+            _ => {
+                false
+            }
+        }
+    }
 }
 
 pub trait ResolveArguments {
@@ -88,6 +173,10 @@ impl ResolveArguments for ArgumentResolver {
             path_variable_arguments: PathVariableMethodArgument::resolve_argument_methods(method),
             query_param_arguments: QueryParamMethodArgument::resolve_argument_methods(method),
             request_body_arguments: RequestBodyArgumentResolver::resolve_argument_methods(method),
+            request_header_arguments: RequestHeaderMethodArgument::resolve_argument_methods(method),
+            cookie_value_arguments: CookieValueMethodArgument::resolve_argument_methods(method),
+            request_attribute_arguments: RequestAttributeMethodArgument::resolve_argument_methods(method),
+            csrf_token_arguments: CsrfTokenMethodArgument::resolve_argument_methods(method),
         }]
     }
 }
\ No newline at end of file
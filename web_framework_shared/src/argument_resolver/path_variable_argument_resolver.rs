@@ -13,15 +13,9 @@ impl ResolveArguments for PathVariableMethodArgument {
             .iter()
             .map(|method_arg_name| {
                 Self {
-                    inner: NamedValueInfo {
-                        name: method_arg_name.1.clone(),
-                        required: false,
-                        default_value: "".to_string(),
-                        label: "path variable".to_string(),
-                        multi_valued: false,
-                    }
+                    inner: NamedValueInfo::parse("path variable", method_arg_name.0, method_arg_name.1.as_str())
                 }
             })
             .collect()
     }
-}
+}
\ No newline at end of file
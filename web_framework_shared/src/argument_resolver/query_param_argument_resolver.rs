@@ -11,13 +11,7 @@ impl ResolveArguments for QueryParamMethodArgument {
             .iter()
             .map(|method_arg_name| {
                 Self {
-                    inner: NamedValueInfo {
-                        name: method_arg_name.1.clone(),
-                        required: false,
-                        default_value: "".to_string(),
-                        label: "request param".to_string(),
-                        multi_valued: false,
-                    }
+                    inner: NamedValueInfo::parse("request param", method_arg_name.0, method_arg_name.1.as_str())
                 }
             })
             .collect()
@@ -0,0 +1,19 @@
+use syn::ImplItemMethod;
+use crate::argument_resolver::{NamedValueInfo, ResolveArguments};
+
+pub struct RequestAttributeMethodArgument {
+    pub inner: NamedValueInfo
+}
+
+impl ResolveArguments for RequestAttributeMethodArgument {
+    fn resolve_argument_methods(method: &ImplItemMethod) -> Vec<Self> where Self: Sized {
+        Self::resolve_fn_arg_fn_arg_ident_tuple("request_attribute", method)
+            .iter()
+            .map(|method_arg_name| {
+                Self {
+                    inner: NamedValueInfo::parse("request attribute", method_arg_name.0, method_arg_name.1.as_str())
+                }
+            })
+            .collect()
+    }
+}
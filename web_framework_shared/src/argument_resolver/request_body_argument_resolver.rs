@@ -7,7 +7,9 @@ use crate::argument_resolver::{NamedValueInfo, ResolveArguments};
 pub struct RequestBodyArgumentResolver {
     pub inner: NamedValueInfo,
     pub request_serialize_type: syn::Type,
-    pub output_type: Option<syn::Type>
+    pub output_type: Option<syn::Type>,
+    /// #[request_body] #[validated] arguments are validated before the controller is called.
+    pub validated: bool
 }
 
 impl ResolveArguments for RequestBodyArgumentResolver {
@@ -24,9 +26,12 @@ impl ResolveArguments for RequestBodyArgumentResolver {
                         default_value: "".to_string(),
                         label: "request body".to_string(),
                         multi_valued: false,
+                        arg_ident: Self::get_method_arg_ident(method_arg_name.0),
+                        arg_type: Some(method_arg_name.0.ty.deref().clone()),
                     },
                     request_serialize_type: method_arg_name.0.ty.deref().clone(),
                     output_type: Self::resolve_fn_arg_fn_output(method),
+                    validated: SynHelper::get_attr_from_vec(&method_arg_name.0.attrs, &vec!["validated"]).is_some(),
                 }
             })
             .collect()
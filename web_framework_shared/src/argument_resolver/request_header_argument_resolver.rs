@@ -0,0 +1,19 @@
+use syn::ImplItemMethod;
+use crate::argument_resolver::{NamedValueInfo, ResolveArguments};
+
+pub struct RequestHeaderMethodArgument {
+    pub inner: NamedValueInfo
+}
+
+impl ResolveArguments for RequestHeaderMethodArgument {
+    fn resolve_argument_methods(method: &ImplItemMethod) -> Vec<Self> where Self: Sized {
+        Self::resolve_fn_arg_fn_arg_ident_tuple("request_header", method)
+            .iter()
+            .map(|method_arg_name| {
+                Self {
+                    inner: NamedValueInfo::parse("request header", method_arg_name.0, method_arg_name.1.as_str())
+                }
+            })
+            .collect()
+    }
+}
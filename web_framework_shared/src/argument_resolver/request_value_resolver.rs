@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::request::WebRequest;
+
+/// Where the generated code resolves the value of a controller argument from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestValueSource {
+    QueryParam, Header, Cookie, Attribute, PathVariable, RequestBody
+}
+
+impl RequestValueSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestValueSource::QueryParam => "request param",
+            RequestValueSource::Header => "request header",
+            RequestValueSource::Cookie => "cookie value",
+            RequestValueSource::Attribute => "request attribute",
+            RequestValueSource::PathVariable => "path variable",
+            RequestValueSource::RequestBody => "request body"
+        }
+    }
+}
+
+/// Written to the response with 400 when an argument is missing or could not be converted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ArgumentResolutionError {
+    pub name: String,
+    pub source: String,
+    pub message: String
+}
+
+impl ArgumentResolutionError {
+    pub fn new(name: &str, source: RequestValueSource, message: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source: source.label().to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ArgumentResolutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not resolve {} {}: {}", self.source, self.name, self.message)
+    }
+}
+
+pub struct RequestValueResolver;
+
+impl RequestValueResolver {
+
+    /// A header is a single value, even if it contains ',', see resolve_multi.
+    pub fn resolve_values(request: &WebRequest, source: RequestValueSource, name: &str) -> Vec<String> {
+        match source {
+            RequestValueSource::QueryParam => request.query_param_values(name),
+            RequestValueSource::Header => request.header(name).cloned().into_iter().collect(),
+            RequestValueSource::Cookie => request.cookie(name).into_iter().collect(),
+            RequestValueSource::Attribute => request.attribute(name).cloned().into_iter().collect(),
+            // the names of path variables are in the matched pattern, see resolve_path_variable.
+            RequestValueSource::PathVariable | RequestValueSource::RequestBody => vec![]
+        }
+    }
+
+    /// Resolves a value for T, using the default value if it is not provided.
+    pub fn resolve_required<T: FromStr>(request: &WebRequest, source: RequestValueSource,
+                                        name: &str, default_value: Option<&str>) -> Result<T, ArgumentResolutionError> {
+        Self::resolve_optional(request, source, name, default_value)
+            .and_then(|value| value.ok_or(ArgumentResolutionError::new(name, source, "required value was not provided.")))
+    }
+
+    /// None if the value was not provided. An Option<T> that is required = true is resolved with
+    /// resolve_required instead.
+    pub fn resolve_optional<T: FromStr>(request: &WebRequest, source: RequestValueSource,
+                                        name: &str, default_value: Option<&str>) -> Result<Option<T>, ArgumentResolutionError> {
+        Self::resolve_values(request, source, name).first()
+            .map(|v| v.as_str())
+            .or(default_value)
+            .map(|value| Self::convert::<T>(value, source, name).map(Some))
+            .or(Some(Ok(None)))
+            .unwrap()
+    }
+
+    /// Collects all of the values for the name, e.g. ?id=1&id=2, or a header split on ',', using
+    /// the default value if none were provided. If none were provided and the values are
+    /// required, this is an error.
+    pub fn resolve_multi<T: FromStr>(request: &WebRequest, source: RequestValueSource, name: &str,
+                                     default_value: Option<&str>, required: bool) -> Result<Vec<T>, ArgumentResolutionError> {
+        let mut values = match source {
+            RequestValueSource::Header => Self::resolve_values(request, source, name).iter()
+                .flat_map(|header| header.split(",").map(|v| v.trim().to_string()).collect::<Vec<String>>())
+                .filter(|value| value.len() != 0)
+                .collect::<Vec<String>>(),
+            _ => Self::resolve_values(request, source, name)
+        };
+        if values.len() == 0 {
+            default_value.map(|d| values.push(d.to_string()));
+        }
+        if values.len() == 0 && required {
+            return Err(ArgumentResolutionError::new(name, source, "required value was not provided."));
+        }
+        values.iter()
+            .map(|value| Self::convert::<T>(value.as_str(), source, name))
+            .collect::<Result<Vec<T>, ArgumentResolutionError>>()
+    }
+
+    /// Path variables are extracted by the AntPathRequestMatcher that matched the request, as
+    /// their names are in the pattern rather than the request.
+    pub fn resolve_path_variable<T: FromStr>(path_variables: &HashMap<String, String>, name: &str) -> Result<T, ArgumentResolutionError> {
+        path_variables.get(name)
+            .ok_or(ArgumentResolutionError::new(name, RequestValueSource::PathVariable, "path variable was not in the matched pattern."))
+            .and_then(|value| Self::convert::<T>(value.as_str(), RequestValueSource::PathVariable, name))
+    }
+
+    fn convert<T: FromStr>(value: &str, source: RequestValueSource, name: &str) -> Result<T, ArgumentResolutionError> {
+        T::from_str(value)
+            .map_err(|_| ArgumentResolutionError::new(
+                name, source, format!("could not convert {} to {}.", value, std::any::type_name::<T>()).as_str()
+            ))
+    }
+}
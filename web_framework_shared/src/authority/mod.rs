@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -10,3 +13,172 @@ impl GrantedAuthority {
         &self.authority
     }
 }
+
+/// Runs after authentication to expand or rename the authorities of the authentication, so that
+/// the authorization rules only need the authorities after mapping.
+pub trait GrantedAuthoritiesMapper: Send + Sync {
+    fn map_authorities(&self, authorities: Vec<GrantedAuthority>) -> Vec<GrantedAuthority>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RoleHierarchyError {
+    pub message: String
+}
+
+impl Display for RoleHierarchyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RoleHierarchyError {}
+
+/// The role hierarchy as it is configured in properties, one chain per line, for example
+/// "ROLE_ADMIN > ROLE_STAFF > ROLE_USER\nROLE_STAFF > ROLE_AUDITOR".
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RoleHierarchyProperties {
+    pub hierarchy: String
+}
+
+/// Each role reaches the roles below it, so an authentication with ROLE_ADMIN in
+/// ROLE_ADMIN > ROLE_STAFF > ROLE_USER is mapped to all three.
+#[derive(Clone, Debug, Default)]
+pub struct RoleHierarchy {
+    reachable: HashMap<String, Vec<String>>
+}
+
+impl RoleHierarchy {
+
+    pub fn from_properties(properties: &RoleHierarchyProperties) -> Result<Self, RoleHierarchyError> {
+        Self::parse(&properties.hierarchy)
+    }
+
+    /// Chains are separated by new lines or ;, and a role cannot reach itself.
+    pub fn parse(hierarchy: &str) -> Result<Self, RoleHierarchyError> {
+        let mut below: HashMap<String, Vec<String>> = HashMap::new();
+        for chain in hierarchy.split(|c| c == '\n' || c == ';').map(|chain| chain.trim()).filter(|chain| chain.len() != 0) {
+            let roles = chain.split('>')
+                .map(|role| role.trim().to_string())
+                .collect::<Vec<String>>();
+            if roles.len() < 2 || roles.iter().any(|role| role.len() == 0) {
+                return Err(RoleHierarchyError { message: format!("Invalid role hierarchy {}.", chain) });
+            }
+            for higher_lower in roles.windows(2) {
+                let lower = below.entry(higher_lower[0].clone()).or_insert(vec![]);
+                if !lower.contains(&higher_lower[1]) {
+                    lower.push(higher_lower[1].clone());
+                }
+            }
+        }
+
+        let mut reachable = HashMap::new();
+        for role in below.keys() {
+            let mut found: Vec<String> = vec![];
+            let mut to_visit = below.get(role).cloned().or(Some(vec![])).unwrap();
+            while let Some(next) = to_visit.pop() {
+                if &next == role {
+                    return Err(RoleHierarchyError { message: format!("Cycle in role hierarchy at {}.", role) });
+                }
+                if found.contains(&next) {
+                    continue;
+                }
+                below.get(&next).map(|lower| to_visit.extend(lower.iter().cloned()));
+                found.push(next);
+            }
+            reachable.insert(role.clone(), found);
+        }
+        Ok(Self { reachable })
+    }
+
+    /// The authorities, followed by the roles reachable from them.
+    pub fn reachable_authorities(&self, authorities: &Vec<GrantedAuthority>) -> Vec<GrantedAuthority> {
+        let mut found = authorities.iter()
+            .map(|authority| authority.authority.clone())
+            .collect::<Vec<String>>();
+        for authority in authorities.iter() {
+            self.reachable.get(&authority.authority).map(|reachable| {
+                reachable.iter().for_each(|role| if !found.contains(role) {
+                    found.push(role.clone());
+                })
+            });
+        }
+        found.into_iter()
+            .map(|authority| GrantedAuthority { authority })
+            .collect()
+    }
+}
+
+impl GrantedAuthoritiesMapper for RoleHierarchy {
+    fn map_authorities(&self, authorities: Vec<GrantedAuthority>) -> Vec<GrantedAuthority> {
+        self.reachable_authorities(&authorities)
+    }
+}
+
+/// Renames authorities, then adds the prefix to those without it, for example mapping admin from
+/// an identity provider to ROLE_ADMIN, and adds the default authority if there is one.
+#[derive(Clone, Debug)]
+pub struct SimpleAuthorityMapper {
+    pub prefix: String,
+    pub convert_to_upper_case: bool,
+    pub renames: HashMap<String, String>,
+    pub default_authority: Option<String>
+}
+
+impl Default for SimpleAuthorityMapper {
+    fn default() -> Self {
+        Self {
+            prefix: "ROLE_".to_string(),
+            convert_to_upper_case: false,
+            renames: HashMap::new(),
+            default_authority: None,
+        }
+    }
+}
+
+impl SimpleAuthorityMapper {
+    fn map_authority(&self, authority: &str) -> String {
+        let authority = self.renames.get(authority)
+            .cloned()
+            .or(Some(authority.to_string()))
+            .unwrap();
+        let authority = if self.convert_to_upper_case {
+            authority.to_uppercase()
+        } else {
+            authority
+        };
+        if authority.starts_with(&self.prefix) {
+            authority
+        } else {
+            format!("{}{}", self.prefix, authority)
+        }
+    }
+}
+
+impl GrantedAuthoritiesMapper for SimpleAuthorityMapper {
+    fn map_authorities(&self, authorities: Vec<GrantedAuthority>) -> Vec<GrantedAuthority> {
+        let mut mapped: Vec<String> = vec![];
+        authorities.iter()
+            .map(|authority| self.map_authority(&authority.authority))
+            .chain(self.default_authority.iter().cloned())
+            .for_each(|authority| if !mapped.contains(&authority) {
+                mapped.push(authority);
+            });
+        mapped.into_iter()
+            .map(|authority| GrantedAuthority { authority })
+            .collect()
+    }
+}
+
+/// Applies each of the mappers in order, for example a SimpleAuthorityMapper followed by the
+/// RoleHierarchy.
+#[derive(Clone, Default)]
+pub struct DelegatingAuthoritiesMapper {
+    pub mappers: Vec<Arc<dyn GrantedAuthoritiesMapper>>
+}
+
+impl GrantedAuthoritiesMapper for DelegatingAuthoritiesMapper {
+    fn map_authorities(&self, authorities: Vec<GrantedAuthority>) -> Vec<GrantedAuthority> {
+        self.mappers.iter()
+            .fold(authorities, |authorities, mapper| mapper.map_authorities(authorities))
+    }
+}
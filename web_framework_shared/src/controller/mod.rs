@@ -1,11 +1,14 @@
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::task::Context;
-use http::Request;
+use async_trait::async_trait;
+use http::{Method, Request};
+use http::header::ALLOW;
 use serde::{Deserialize, Serialize};
 use crate::Handler;
-use crate::matcher::{AntPathRequestMatcher, Matcher};
-use crate::request::{EndpointMetadata, WebRequest, WebResponse};
+use crate::http_method::HttpMethod;
+use crate::matcher::{AntPathRequestMatcher, Matcher, PathSpecificity};
+use crate::request::{EndpointMetadata, StatusCode, WebRequest, WebResponse};
 
 /// will be impl Action<> for HandlerMethod and add metadata about it
 pub struct HandlerMethod<RequestCtxData: Data + ?Sized>
@@ -60,15 +63,16 @@ pub struct HandlerExecutionChain<T: Data + ?Sized, Ctx: ContextData + ?Sized, Re
     pub handler_executor: Arc<HandlerExecutorStruct<H, T, Ctx, Request, Response>>
 }
 
+#[async_trait]
 impl<T: Data + ?Sized, Ctx: ContextData + ?Sized, Request, Response, H: HandlerExecutor<T, Ctx, Request, Response>> Handler<Request, Response, T, Ctx>
 for HandlerExecutionChain<T, Ctx, Request, Response, H>
     where
         Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
         Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync
 {
-    fn do_action(&self, web_request: &WebRequest, response: &mut WebResponse,
+    async fn do_action(&self, web_request: &WebRequest, response: &mut WebResponse,
                  context: &Ctx, request_context: &mut Option<Box<T>>) -> Option<Response> {
-        self.handler_executor.handler_executor.do_action(web_request, response, context, request_context)
+        self.handler_executor.handler_executor.do_action(web_request, response, context, request_context).await
     }
 
     fn authentication_granted(&self, token: &Option<Box<T>>) -> bool {
@@ -101,6 +105,42 @@ where
     pub fn matches(&self, request: &WebRequest) -> bool {
         self.request_matchers.iter().any(|r| r.matches(request))
     }
+
+    /// Matches the path only, so that a request with the wrong method can be answered with 405.
+    pub fn matches_path(&self, request: &WebRequest) -> bool {
+        self.request_matchers.iter().any(|r| r.matches_path(request))
+    }
+
+    /// The specificity of the most specific pattern matching the path, used to choose between
+    /// handlers whose patterns overlap.
+    pub fn specificity(&self, request: &WebRequest) -> Option<PathSpecificity> {
+        self.request_matchers.iter()
+            .flat_map(|r| r.specificity(request))
+            .max()
+    }
+
+    pub fn allowed_methods(&self, request: &WebRequest) -> Vec<HttpMethod> {
+        self.request_matchers.iter()
+            .filter(|r| r.matches_path(request))
+            .flat_map(|r| r.methods.iter().map(|m| *m))
+            .collect::<Vec<HttpMethod>>()
+    }
+}
+
+/// Writes the response when no handler matched both the path and the method of the request. If
+/// the path matched but the method did not, then 405 is returned with the Allow header, or 204 if it
+/// was an OPTIONS request. Otherwise, 404.
+pub fn write_unmatched_response(request: &WebRequest, response: &mut WebResponse, allowed_methods: &Vec<HttpMethod>) {
+    if allowed_methods.len() == 0 {
+        response.set_status(StatusCode::NOT_FOUND);
+        return;
+    }
+    response.set_header(ALLOW.as_str(), HttpMethod::allow_header(allowed_methods).as_str());
+    if request.method == Method::OPTIONS {
+        response.set_status(StatusCode::NO_CONTENT);
+    } else {
+        response.set_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
 }
 
 impl<T, Ctx, Request, Response, H: HandlerExecutor<T, Ctx, Request, Response>> HandlerExecutionChain<T, Ctx, Request, Response, H>
@@ -110,14 +150,14 @@ where
     Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync
 
 {
-    fn do_request_inner(&self, web_request: &mut WebRequest, mut response: &mut WebResponse, mut handler: HandlerMethod<T>) {
+    async fn do_request_inner(&self, web_request: &mut WebRequest, mut response: &mut WebResponse, mut handler: HandlerMethod<T>) {
         // self.interceptors
         //     .iter()
         //     .for_each(|i|
         //         i.pre_handle(&web_request, &mut response, &mut handler, &self.context));
 
         self.handler_executor.handler_executor
-            .execute_handler(handler, &mut response, &web_request);
+            .execute_handler(handler, &mut response, &web_request).await;
 
         // self.interceptors
         //     .iter()
@@ -126,12 +166,13 @@ where
     }
 }
 
+#[async_trait]
 pub trait HandlerExecutor<D: Data + Send + Sync + ?Sized, Ctx: ContextData + Send + Sync + ?Sized, Request, Response>: Send + Sync + Handler<Request, Response, D, Ctx>
 where
     Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
     Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
 {
-    fn execute_handler(&self, handler: HandlerMethod<D>, response: &mut WebResponse, request: &WebRequest) -> Option<Response>;
+    async fn execute_handler(&self, handler: HandlerMethod<D>, response: &mut WebResponse, request: &WebRequest) -> Option<Response>;
 }
 
 pub trait HandlerMethodFactory<RequestCtxData: Data + ?Sized> {
@@ -145,19 +186,22 @@ where
     Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
 {
 
-    pub fn pre_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
-        self.interceptors.iter()
-            .for_each(|i| i.pre_handle(request, response, data, ctx));
+    pub async fn pre_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
+        for i in self.interceptors.iter() {
+            i.pre_handle(request, response, data, ctx).await;
+        }
     }
 
-    fn post_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
-        self.interceptors.iter()
-            .for_each(|i| i.post_handle(request, response, data, ctx));
+    async fn post_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
+        for i in self.interceptors.iter() {
+            i.post_handle(request, response, data, ctx).await;
+        }
     }
 
-    fn after_completion(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
-        self.interceptors.iter()
-            .for_each(|i| i.after_completion(request, response, data, ctx));
+    async fn after_completion(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx) {
+        for i in self.interceptors.iter() {
+            i.after_completion(request, response, data, ctx).await;
+        }
     }
 }
 
@@ -166,10 +210,11 @@ pub trait HandlerAdapter<T, Ctx, RequestCtx> {
               ctx: Ctx, request_ctx: &mut RequestCtx, handler: T);
 }
 
+#[async_trait]
 pub trait HandlerInterceptor<T: Data + ?Sized, Ctx: ContextData + ?Sized>: Send + Sync {
-    fn pre_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
-    fn post_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
-    fn after_completion(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
+    async fn pre_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
+    async fn post_handle(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
+    async fn after_completion(&self, request: &WebRequest, response: &mut WebResponse, data: &mut HandlerMethod<T>, ctx: &Ctx);
 }
 
 #[test]
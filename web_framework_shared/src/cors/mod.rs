@@ -0,0 +1,293 @@
+use http::header::{ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY};
+use http::Method;
+use serde::{Deserialize, Serialize};
+use crate::http_method::HttpMethod;
+use crate::matcher::AntPathRequestMatcher;
+use crate::request::{StatusCode, WebRequest, WebResponse};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("cors.rs");
+
+/// The cross origin requests allowed for a path. An allowed origin, method or header of "*"
+/// allows any. An allowed origin of "*" is rejected when credentials are allowed, so with
+/// credentials the origins or origin patterns must be listed, and the matching request origin is
+/// written back.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CorsConfiguration {
+    pub allowed_origins: Vec<String>,
+    /// Origins with * wildcards, as in https://*.example.com or http://localhost:*.
+    pub allowed_origin_patterns: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Seconds the browser may cache the preflight response for.
+    pub max_age: Option<u64>
+}
+
+impl Default for CorsConfiguration {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            allowed_origin_patterns: vec![],
+            allowed_methods: vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()],
+            allowed_headers: vec!["*".to_string()],
+            exposed_headers: vec![],
+            allow_credentials: false,
+            max_age: Some(1800),
+        }
+    }
+}
+
+impl CorsConfiguration {
+    /// Any origin, with the default methods and headers.
+    pub fn permit_default_values() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            ..Self::default()
+        }
+    }
+
+    /// Parses #[cross_origin(origins = "https://one.com, https://two.com", methods = "GET, PUT",
+    /// allowed_headers = "*", exposed_headers = "X-Total", allow_credentials = true,
+    /// max_age = 3600)]. Values not provided are the defaults, and if neither origins nor
+    /// origin_patterns are provided any origin is allowed, so #[cross_origin] allows any origin.
+    pub fn parse(attr: &str) -> Self {
+        let mut configuration = Self::default();
+        let mut origins_provided = false;
+        Self::split_attr(attr).iter()
+            .flat_map(|item| item.split_once("="))
+            .for_each(|(key, value)| {
+                let value = value.trim().trim_matches('"');
+                let values = value.split(",")
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| v.len() != 0)
+                    .collect::<Vec<String>>();
+                match key.trim() {
+                    "origins" | "allowed_origins" => {
+                        origins_provided = true;
+                        configuration.allowed_origins = values;
+                    }
+                    "origin_patterns" | "allowed_origin_patterns" => {
+                        origins_provided = true;
+                        configuration.allowed_origin_patterns = values;
+                    }
+                    "methods" | "allowed_methods" => configuration.allowed_methods = values,
+                    "allowed_headers" => configuration.allowed_headers = values,
+                    "exposed_headers" => configuration.exposed_headers = values,
+                    "allow_credentials" => configuration.allow_credentials = value == "true",
+                    "max_age" => configuration.max_age = value.parse::<u64>().ok(),
+                    _ => {}
+                }
+            });
+        if !origins_provided {
+            configuration.allowed_origins = vec!["*".to_string()];
+        }
+        if configuration.allows_any_origin_with_credentials() {
+            error!("#[cross_origin({})] allows credentials from any origin, so every cross origin request will be rejected. Provide the origins or origin_patterns.", attr);
+        }
+        configuration
+    }
+
+    /// As the browser does not accept * with credentials, this would require reflecting any origin
+    /// with credentials allowed, so cross origin requests are rejected instead.
+    pub fn allows_any_origin_with_credentials(&self) -> bool {
+        self.allow_credentials && self.allowed_origins.iter().any(|allowed| allowed == "*")
+    }
+
+    /// Splits on the commas that are not within a quoted value.
+    fn split_attr(attr: &str) -> Vec<String> {
+        let mut items = vec![];
+        let mut current = String::default();
+        let mut quoted = false;
+        for c in attr.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ',' if !quoted => {
+                    items.push(current.clone());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        items.push(current);
+        items.into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| item.len() != 0)
+            .collect()
+    }
+
+    /// The value of Access-Control-Allow-Origin for the origin, or None if it is not allowed.
+    pub fn check_origin(&self, origin: &str) -> Option<String> {
+        let origin = origin.trim().trim_end_matches("/");
+        if self.allows_any_origin_with_credentials() {
+            return None;
+        }
+        if self.allowed_origins.iter().any(|allowed| allowed == "*") {
+            return Some("*".to_string());
+        }
+        self.allowed_origins.iter()
+            .any(|allowed| allowed.trim_end_matches("/").eq_ignore_ascii_case(origin))
+            .then(|| origin.to_string())
+            .or_else(|| self.allowed_origin_patterns.iter()
+                .any(|pattern| Self::matches_origin_pattern(pattern, origin))
+                .then(|| origin.to_string())
+            )
+    }
+
+    /// The methods to write to Access-Control-Allow-Methods, or None if the method is not allowed.
+    pub fn check_method(&self, method: &str) -> Option<Vec<String>> {
+        if self.allowed_methods.iter().any(|allowed| allowed == "*") {
+            return Some(vec![method.to_uppercase()]);
+        }
+        self.allowed_methods.iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+            .then(|| self.allowed_methods.iter().map(|allowed| allowed.to_uppercase()).collect())
+    }
+
+    /// The headers to write to Access-Control-Allow-Headers, or None if any of the requested
+    /// headers is not allowed.
+    pub fn check_headers(&self, requested_headers: &Vec<String>) -> Option<Vec<String>> {
+        if self.allowed_headers.iter().any(|allowed| allowed == "*") {
+            return Some(requested_headers.clone());
+        }
+        requested_headers.iter()
+            .all(|requested| self.allowed_headers.iter().any(|allowed| allowed.eq_ignore_ascii_case(requested)))
+            .then(|| requested_headers.clone())
+    }
+
+    /// Matches the origin against the pattern, where * matches any characters.
+    pub fn matches_origin_pattern(pattern: &str, origin: &str) -> bool {
+        let pattern = pattern.trim().trim_end_matches("/").to_lowercase();
+        let origin = origin.to_lowercase();
+        let parts = pattern.split("*").collect::<Vec<&str>>();
+        if parts.len() == 1 {
+            return pattern == origin;
+        }
+        let first = parts[0];
+        let last = parts[parts.len() - 1];
+        if !origin.starts_with(first) || !origin[first.len()..].ends_with(last) {
+            return false;
+        }
+        let mut remaining = &origin[first.len()..origin.len() - last.len()];
+        for part in parts[1..parts.len() - 1].iter() {
+            match remaining.find(part) {
+                Some(i) => remaining = &remaining[i + part.len()..],
+                None => return false
+            }
+        }
+        true
+    }
+}
+
+/// The CorsConfiguration for the most specific path matching the request.
+#[derive(Clone, Default)]
+pub struct UrlBasedCorsConfigurationSource {
+    configurations: Vec<(AntPathRequestMatcher, CorsConfiguration)>
+}
+
+impl UrlBasedCorsConfigurationSource {
+    pub fn register(&mut self, path: &str, configuration: CorsConfiguration) {
+        self.configurations.push((AntPathRequestMatcher::new(path, "/"), configuration));
+    }
+
+    pub fn configuration(&self, web_request: &WebRequest) -> Option<&CorsConfiguration> {
+        self.configurations.iter()
+            .flat_map(|(matcher, configuration)| matcher.specificity(web_request)
+                .map(|specificity| (specificity, configuration))
+            )
+            .max_by(|first, second| first.0.cmp(&second.0))
+            .map(|(_, configuration)| configuration)
+    }
+}
+
+pub struct CorsProcessor;
+
+impl CorsProcessor {
+    pub fn is_cors_request(web_request: &WebRequest) -> bool {
+        web_request.header(ORIGIN.as_str()).is_some()
+    }
+
+    pub fn is_preflight(web_request: &WebRequest) -> bool {
+        web_request.method == Method::OPTIONS
+            && Self::is_cors_request(web_request)
+            && web_request.header(ACCESS_CONTROL_REQUEST_METHOD.as_str()).is_some()
+    }
+
+    /// The Access-Control-Request-Method of a preflight.
+    pub fn preflight_method(web_request: &WebRequest) -> Option<HttpMethod> {
+        Self::is_preflight(web_request)
+            .then(|| web_request.header(ACCESS_CONTROL_REQUEST_METHOD.as_str()))
+            .flatten()
+            .and_then(|method| HttpMethod::from_str(method.trim()))
+    }
+
+    /// Writes the CORS headers for the request, or 403 if the origin, or for a preflight the
+    /// requested method or headers, are not allowed. Returns false if the request was rejected.
+    /// Requests without an Origin, and responses already processed, are left as they are.
+    pub fn process_request(configuration: &CorsConfiguration, web_request: &WebRequest, response: &mut WebResponse) -> bool {
+        let origin = match web_request.header(ORIGIN.as_str()) {
+            Some(origin) => origin,
+            None => return true
+        };
+        if response.headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) {
+            return true;
+        }
+        let preflight = Self::is_preflight(web_request);
+        response.add_header(VARY.as_str(), "Origin");
+        if preflight {
+            response.add_header(VARY.as_str(), "Access-Control-Request-Method");
+            response.add_header(VARY.as_str(), "Access-Control-Request-Headers");
+        }
+
+        let allow_origin = match configuration.check_origin(origin) {
+            Some(allow_origin) => allow_origin,
+            None => return Self::reject(response)
+        };
+
+        if preflight {
+            let allow_methods = match web_request.header(ACCESS_CONTROL_REQUEST_METHOD.as_str())
+                .and_then(|method| configuration.check_method(method.trim())) {
+                Some(allow_methods) => allow_methods,
+                None => return Self::reject(response)
+            };
+            let requested_headers = web_request.header(ACCESS_CONTROL_REQUEST_HEADERS.as_str())
+                .map(|headers| headers.split(",")
+                    .map(|header| header.trim().to_string())
+                    .filter(|header| header.len() != 0)
+                    .collect::<Vec<String>>())
+                .or(Some(vec![]))
+                .unwrap();
+            let allow_headers = match configuration.check_headers(&requested_headers) {
+                Some(allow_headers) => allow_headers,
+                None => return Self::reject(response)
+            };
+            response.set_header(ACCESS_CONTROL_ALLOW_ORIGIN.as_str(), allow_origin.as_str());
+            response.set_header(ACCESS_CONTROL_ALLOW_METHODS.as_str(), allow_methods.join(", ").as_str());
+            if allow_headers.len() != 0 {
+                response.set_header(ACCESS_CONTROL_ALLOW_HEADERS.as_str(), allow_headers.join(", ").as_str());
+            }
+            configuration.max_age.map(|max_age| response.set_header(ACCESS_CONTROL_MAX_AGE.as_str(), max_age.to_string().as_str()));
+        } else {
+            response.set_header(ACCESS_CONTROL_ALLOW_ORIGIN.as_str(), allow_origin.as_str());
+            if configuration.exposed_headers.len() != 0 {
+                response.set_header(ACCESS_CONTROL_EXPOSE_HEADERS.as_str(), configuration.exposed_headers.join(", ").as_str());
+            }
+        }
+        if configuration.allow_credentials {
+            response.set_header(ACCESS_CONTROL_ALLOW_CREDENTIALS.as_str(), "true");
+        }
+        true
+    }
+
+    fn reject(response: &mut WebResponse) -> bool {
+        response.set_status(StatusCode::FORBIDDEN);
+        false
+    }
+}
@@ -0,0 +1,45 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::request::WebRequest;
+
+/// The request attribute the CsrfFilter adds the token to, resolved with #[csrf_token].
+pub const CSRF_TOKEN_ATTRIBUTE: &str = "_csrf";
+
+/// The token that has to be sent back with each unsafe request, either in the header or in the
+/// form parameter.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CsrfToken {
+    pub header_name: String,
+    pub parameter_name: String,
+    pub token: String
+}
+
+impl CsrfToken {
+    pub fn new(header_name: &str, parameter_name: &str, token: &str) -> Self {
+        Self {
+            header_name: header_name.to_string(),
+            parameter_name: parameter_name.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Adds the token to the request attributes, so that the controller can render it in a form.
+    pub fn expose(&self, web_request: &mut WebRequest) {
+        serde_json::to_string(self)
+            .map(|token| web_request.set_attribute(CSRF_TOKEN_ATTRIBUTE, token.as_str()))
+            .ok();
+    }
+
+    pub fn from_request(web_request: &WebRequest) -> Option<Self> {
+        web_request.attribute(CSRF_TOKEN_ATTRIBUTE)
+            .and_then(|token| Self::from_str(token).ok())
+    }
+}
+
+impl FromStr for CsrfToken {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
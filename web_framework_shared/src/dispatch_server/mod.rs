@@ -1,3 +1,4 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use crate::authority::GrantedAuthority;
 use crate::controller::{HandlerInterceptor};
@@ -34,12 +35,15 @@ use crate::request::{EndpointMetadata, WebRequest, WebResponse};
 //     }
 // }
 
+/// do_action is async so that filters and controllers awaiting a Repo or other IO do not block
+/// a worker of the runtime.
+#[async_trait]
 pub trait Handler<Request, Response, RequestData: ?Sized, Ctx: ?Sized>: Send + Sync
 where
     Response: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync,
     Request: Serialize + for<'b> Deserialize<'b> + Clone + Default + Send + Sync
 {
-    fn do_action(
+    async fn do_action(
         &self,
         web_request: &WebRequest,
         response: &mut WebResponse,
@@ -47,6 +51,20 @@ where
         request_context: &mut Option<Box<RequestData>>
     ) -> Option<Response>;
 
+    /// Called once the request was dispatched, or a filter wrote the response, so that a filter
+    /// can persist what the controller changed, such as the session.
+    async fn after_dispatch(
+        &self,
+        _web_request: &WebRequest,
+        _response: &mut WebResponse,
+        _context: &Ctx,
+        _request_context: &mut Option<Box<RequestData>>
+    ) where
+        Ctx: Sync,
+        RequestData: Send
+    {
+    }
+
     /**
     For method level annotations (could also be done via Aspect though).
     */
@@ -58,3 +76,10 @@ where
     fn matches(&self, endpoint_metadata: &EndpointMetadata) -> bool;
 
 }
+
+/// Routes the request to the matching controller and writes its response, implemented by the
+/// generated AttributeHandlerMapping. Called by the http server after the filter chain.
+#[async_trait]
+pub trait RequestDispatcher: Send + Sync {
+    async fn dispatch(&self, request: &WebRequest, response: &mut WebResponse);
+}
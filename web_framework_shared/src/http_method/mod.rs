@@ -1,6 +1,7 @@
+use http::Method;
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
     Get, Options, Head, Post, Put, Patch, Delete
 }
@@ -9,4 +10,64 @@ impl Default for HttpMethod {
     fn default() -> Self {
         HttpMethod::Get
     }
-}
\ No newline at end of file
+}
+
+impl HttpMethod {
+    pub fn from_method(method: &Method) -> Option<HttpMethod> {
+        Self::from_str(method.as_str())
+    }
+
+    pub fn from_str(method: &str) -> Option<HttpMethod> {
+        match method.to_uppercase().as_str() {
+            "GET" => Some(HttpMethod::Get),
+            "OPTIONS" => Some(HttpMethod::Options),
+            "HEAD" => Some(HttpMethod::Head),
+            "POST" => Some(HttpMethod::Post),
+            "PUT" => Some(HttpMethod::Put),
+            "PATCH" => Some(HttpMethod::Patch),
+            "DELETE" => Some(HttpMethod::Delete),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE"
+        }
+    }
+
+    /// A HEAD request is handled by the GET handler, with the body removed.
+    pub fn matches(&self, method: &Method) -> bool {
+        Self::from_method(method)
+            .map(|m| m == *self || (m == HttpMethod::Head && *self == HttpMethod::Get))
+            .or(Some(false))
+            .unwrap()
+    }
+
+    /// The value of the Allow header for the methods, adding HEAD for GET and OPTIONS, which are
+    /// handled automatically.
+    pub fn allow_header(methods: &Vec<HttpMethod>) -> String {
+        let mut allowed: Vec<HttpMethod> = vec![];
+        for method in methods.iter() {
+            if !allowed.contains(method) {
+                allowed.push(*method);
+            }
+            if *method == HttpMethod::Get && !allowed.contains(&HttpMethod::Head) {
+                allowed.push(HttpMethod::Head);
+            }
+        }
+        if !allowed.contains(&HttpMethod::Options) {
+            allowed.push(HttpMethod::Options);
+        }
+        allowed.iter()
+            .map(|m| m.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
@@ -17,8 +17,28 @@ pub mod authority;
 pub use authority::*;
 pub mod argument_resolver;
 pub use argument_resolver::*;
+pub mod validation;
+pub use validation::*;
+pub mod media_type;
+pub use media_type::*;
+pub mod streaming;
+pub use streaming::*;
+pub mod method_security;
+pub use method_security::*;
+pub mod csrf;
+pub use csrf::*;
+pub mod cors;
+pub use cors::*;
 pub mod test;
 
+/// So the code generated by #[derive(Validate)] in the tests resolves.
+#[cfg(test)]
+extern crate self as web_framework_shared;
+
+/// Re-exported for the generated HandlerExecutorImpl, so the user's crate does not need the
+/// dependency.
+pub use async_trait::async_trait;
+
 use knockoff_logging::*;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
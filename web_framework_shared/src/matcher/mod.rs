@@ -1,8 +1,12 @@
-use std::collections::LinkedList;
+use std::cmp::Ordering;
+use std::collections::{HashMap, LinkedList};
 use derive_syn_parse::Parse;
 use proc_macro2::TokenStream;
 use syn::__private::quote::quote;
 use syn::__private::ToTokens;
+use http::Method;
+use regex::Regex;
+use crate::http_method::HttpMethod;
 use crate::request::WebRequest;
 
 use knockoff_logging::*;
@@ -60,7 +64,12 @@ impl AntStringRequestMatcher {
     }
 
     fn match_value(to_match: &str, matcher: &str) -> bool {
-        if matcher.contains("*") && matcher != "*" {
+        if PathVariableSegment::is_path_variable(matcher) {
+            PathVariableSegment::parse(matcher)
+                .map(|segment| segment.matches(to_match))
+                .or(Some(false))
+                .unwrap()
+        } else if matcher.contains("*") && matcher != "*" {
             /// Cover cases where glob is in middle of word.
             let matcher = AntStringRequestMatcher::new(matcher.to_string(), "".to_string()) ;
             matcher.matches(to_match)
@@ -105,11 +114,15 @@ impl Matcher<&str> for AntStringRequestMatcher {
                 _ => {}
             }
 
-            let matcher_value = self_to_match.or(Some(&""))
-                .unwrap()
-                .split("|")
-                .filter(|s| s.len() != 0)
-                .collect::<Vec<&str>>();
+            let self_to_match_value = self_to_match.or(Some(&"")).unwrap();
+            let matcher_value = if PathVariableSegment::is_path_variable(self_to_match_value) {
+                vec![*self_to_match_value]
+            } else {
+                self_to_match_value
+                    .split("|")
+                    .filter(|s| s.len() != 0)
+                    .collect::<Vec<&str>>()
+            };
 
             if matcher_value.len() > 1 {
                 if Self::do_match(&to_match_value, i, matcher_value) {
@@ -137,7 +150,11 @@ impl Matcher<&str> for AntStringRequestMatcher {
             }
         } else {
             if split_self_match.len() == to_match_value.len() {
-                split_self_match.last() == to_match_value.last()
+                match (split_self_match.last(), to_match_value.last()) {
+                    (Some(matcher), Some(to_match)) => Self::match_value(to_match, matcher),
+                    (None, None) => true,
+                    _ => false
+                }
             } else {
                 false
             }
@@ -151,34 +168,178 @@ impl Matcher<&str> for AntStringRequestMatcher {
 impl StringMatcher<'_> for AntStringRequestMatcher {
 }
 
+impl AntStringRequestMatcher {
+
+    /// Extracts the named path variables, /users/{id:[0-9]+}, if the path matches.
+    pub fn extract_path_variables(&self, path: &str) -> Option<HashMap<String, String>> {
+        if !self.matches(path) {
+            return None;
+        }
+        let split_self_match = self.split_for_match(&self.to_match);
+        let to_match_value = self.split_for_match(path);
+        Some(split_self_match.iter()
+            .zip(to_match_value.iter())
+            .flat_map(|(matcher, value)| PathVariableSegment::parse(matcher)
+                .map(|segment| (segment.name, value.to_string()))
+            )
+            .collect::<HashMap<String, String>>())
+    }
+
+    /// When several patterns match the same path, the one with the highest specificity is
+    /// used. Literal segments are more specific than constrained path variables, which are more
+    /// specific than path variables and globs, which are more specific than wildcards.
+    pub fn specificity(&self) -> PathSpecificity {
+        let split_self_match = self.split_for_match(&self.to_match);
+        let score = split_self_match.iter()
+            .map(|segment| {
+                if PathVariableSegment::is_path_variable(segment) {
+                    PathVariableSegment::parse(segment)
+                        .map(|s| if s.pattern.is_some() { 3 } else { 1 })
+                        .or(Some(1))
+                        .unwrap()
+                } else if *segment == "**" {
+                    0
+                } else if *segment == "*" {
+                    1
+                } else if segment.contains("*") || segment.contains("|") {
+                    2
+                } else {
+                    4
+                }
+            })
+            .sum::<usize>();
+        PathSpecificity {
+            score,
+            double_wildcards: split_self_match.iter().filter(|s| **s == "**").count(),
+            length: self.to_match.len(),
+        }
+    }
+}
+
+/// Compared to determine which of the matching patterns is used, greater being more specific.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathSpecificity {
+    pub score: usize,
+    pub double_wildcards: usize,
+    pub length: usize
+}
+
+impl PartialOrd for PathSpecificity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PathSpecificity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score)
+            .then(other.double_wildcards.cmp(&self.double_wildcards))
+            .then(self.length.cmp(&other.length))
+    }
+}
+
+/// A segment of the path such as {slug} or {id:[0-9]+}, where the regex must match the whole
+/// segment.
+#[derive(Clone, Debug)]
+pub struct PathVariableSegment {
+    pub name: String,
+    pub pattern: Option<Regex>
+}
+
+impl PathVariableSegment {
+    pub fn is_path_variable(segment: &str) -> bool {
+        segment.starts_with("{") && segment.ends_with("}")
+    }
+
+    pub fn parse(segment: &str) -> Option<Self> {
+        if !Self::is_path_variable(segment) {
+            return None;
+        }
+        let inner = &segment[1..segment.len() - 1];
+        match inner.split_once(":") {
+            None => Some(Self { name: inner.to_string(), pattern: None }),
+            Some((name, pattern)) => {
+                Regex::new(format!("^(?:{})$", pattern).as_str())
+                    .map_err(|e| {
+                        error!("Could not parse regex for path variable {}: {:?}", name, e);
+                    })
+                    .ok()
+                    .map(|pattern| Self { name: name.to_string(), pattern: Some(pattern) })
+            }
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        value.len() != 0 && self.pattern.as_ref()
+            .map(|p| p.is_match(value))
+            .or(Some(true))
+            .unwrap()
+    }
+}
+
 impl Matcher<&'_ WebRequest> for AntPathRequestMatcher {
     fn matches(&self, to_match: &WebRequest) -> bool {
-        self.request_matchers.iter()
-            .any(|r| r.matches(to_match.uri.path()))
+        self.matches_path(to_match) && self.matches_method(&to_match.method)
     }
 }
 
 #[derive(Clone)]
 pub struct AntPathRequestMatcher {
     //TODO: add bloom filter and contains
-    pub request_matchers: Vec<AntStringRequestMatcher>
+    pub request_matchers: Vec<AntStringRequestMatcher>,
+    /// If empty, matches any method.
+    pub methods: Vec<HttpMethod>
 }
 
 impl AntPathRequestMatcher {
     pub fn new(to_match: &str, splitter: &str) -> Self {
-        let request_matchers = to_match.split(splitter).map(|to_match|
-            AntStringRequestMatcher::new(
-                to_match.to_string(),
-                " ".to_string(),
-            ))
-            .collect::<Vec<AntStringRequestMatcher>>();
         Self {
-            request_matchers
+            request_matchers: vec![AntStringRequestMatcher::new(to_match.to_string(), splitter.to_string())],
+            methods: vec![]
         }
     }
+
+    pub fn new_with_methods(to_match: &str, splitter: &str, methods: Vec<HttpMethod>) -> Self {
+        let mut matcher = Self::new(to_match, splitter);
+        matcher.methods = methods;
+        matcher
+    }
+
     pub fn new_from_request_matcher(request_matchers: Vec<AntStringRequestMatcher> ) -> Self {
         Self {
-            request_matchers
+            request_matchers,
+            methods: vec![]
         }
     }
+
+    pub fn new_from_request_matcher_with_methods(request_matchers: Vec<AntStringRequestMatcher>, methods: Vec<HttpMethod>) -> Self {
+        Self {
+            request_matchers,
+            methods
+        }
+    }
+
+    pub fn matches_path(&self, to_match: &WebRequest) -> bool {
+        self.request_matchers.iter()
+            .any(|r| r.matches(to_match.uri.path()))
+    }
+
+    pub fn extract_path_variables(&self, to_match: &WebRequest) -> Option<HashMap<String, String>> {
+        self.request_matchers.iter()
+            .filter(|r| r.matches(to_match.uri.path()))
+            .max_by_key(|r| r.specificity())
+            .and_then(|r| r.extract_path_variables(to_match.uri.path()))
+    }
+
+    /// The specificity of the most specific pattern matching the path.
+    pub fn specificity(&self, to_match: &WebRequest) -> Option<PathSpecificity> {
+        self.request_matchers.iter()
+            .filter(|r| r.matches(to_match.uri.path()))
+            .map(|r| r.specificity())
+            .max()
+    }
+
+    pub fn matches_method(&self, method: &Method) -> bool {
+        self.methods.len() == 0 || self.methods.iter().any(|m| m.matches(method))
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::LinkedList;
 use std::str::FromStr;
-use http::Uri;
+use http::{Method, Uri};
+use crate::http_method::HttpMethod;
 use crate::matcher::{AntPathRequestMatcher, AntStringRequestMatcher, Matcher};
 use crate::request::WebRequest;
 
@@ -55,6 +56,60 @@ fn test_ant_path_matcher() {
     assert!(!second.matches("/v1/test_one/oknnnn"));
 }
 
+#[test]
+fn test_ant_path_request_matcher_methods() {
+    let request_matcher = AntPathRequestMatcher::new_from_request_matcher_with_methods(
+        vec![create_request_matcher("/v1/test_one".to_string(), "/".to_string())],
+        vec![HttpMethod::Get, HttpMethod::Put]
+    );
+
+    let mut request = test_web_request("/v1/test_one".to_string());
+    assert!(request_matcher.matches(&request));
+    request.method = Method::HEAD;
+    assert!(request_matcher.matches(&request));
+    request.method = Method::PUT;
+    assert!(request_matcher.matches(&request));
+    request.method = Method::DELETE;
+    assert!(!request_matcher.matches(&request));
+    assert!(request_matcher.matches_path(&request));
+
+    assert_eq!(HttpMethod::allow_header(&request_matcher.methods), "GET, HEAD, PUT, OPTIONS");
+
+    let any_method = create_request_matchers(vec![create_request_matcher("/v1/test_one".to_string(), "/".to_string())]);
+    assert!(any_method.matches(&request));
+}
+
+#[test]
+fn test_path_variable_template() {
+    let matcher = create_request_matcher("/users/{id:[0-9]+}/posts/{slug}".to_string(), "/".to_string());
+    assert!(matcher.matches("/users/10/posts/hello"));
+    assert!(!matcher.matches("/users/ten/posts/hello"));
+    assert!(!matcher.matches("/users/10/posts"));
+    assert!(!matcher.matches("/users/10/comments/hello"));
+
+    let path_variables = matcher.extract_path_variables("/users/10/posts/hello").unwrap();
+    assert_eq!(path_variables.get("id").unwrap(), "10");
+    assert_eq!(path_variables.get("slug").unwrap(), "hello");
+    assert!(matcher.extract_path_variables("/users/ten/posts/hello").is_none());
+}
+
+#[test]
+fn test_most_specific_pattern() {
+    let literal = create_request_matcher("/users/new".to_string(), "/".to_string());
+    let constrained = create_request_matcher("/users/{id:[0-9]+}".to_string(), "/".to_string());
+    let variable = create_request_matcher("/users/{name}".to_string(), "/".to_string());
+    let wildcard = create_request_matcher("/users/**".to_string(), "/".to_string());
+
+    assert!(literal.specificity() > constrained.specificity());
+    assert!(constrained.specificity() > variable.specificity());
+    assert!(variable.specificity() > wildcard.specificity());
+
+    let request_matcher = create_request_matchers(vec![wildcard, variable, constrained]);
+    let request = test_web_request("/users/10".to_string());
+    assert_eq!(request_matcher.specificity(&request), Some(create_request_matcher("/users/{id:[0-9]+}".to_string(), "/".to_string()).specificity()));
+    assert_eq!(request_matcher.extract_path_variables(&request).unwrap().get("id").unwrap(), "10");
+}
+
 #[test]
 fn test_request_matcher() {
     let first = create_request_matcher("o*e".to_string(), "".to_string());
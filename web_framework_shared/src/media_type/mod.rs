@@ -0,0 +1,248 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use http::header::{ACCEPT, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use crate::request::{ResponseWriter, StatusCode, WebRequest, WebResponse};
+
+/// A media type from the Accept or Content-Type header, or from the produces and consumes of a
+/// mapping, e.g. application/json;q=0.8. The parameters other than q are kept, but are not used
+/// when matching.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaType {
+    pub main_type: String,
+    pub sub_type: String,
+    pub quality: f32,
+    pub parameters: Vec<(String, String)>
+}
+
+impl MediaType {
+
+    pub fn parse(value: &str) -> Option<MediaType> {
+        let mut parts = value.split(";");
+        let (main_type, sub_type) = parts.next()
+            .map(|t| t.trim())
+            .and_then(|t| t.split_once("/"))
+            .filter(|(main_type, sub_type)| main_type.len() != 0 && sub_type.len() != 0)
+            .map(|(main_type, sub_type)| (main_type.trim().to_lowercase(), sub_type.trim().to_lowercase()))?;
+        let mut quality = 1.0;
+        let mut parameters = vec![];
+        for param in parts {
+            if let Some((name, value)) = param.split_once("=") {
+                let name = name.trim().to_lowercase();
+                let value = value.trim().trim_matches('"').to_string();
+                if name == "q" {
+                    quality = value.parse::<f32>().ok()
+                        .filter(|q| *q >= 0.0 && *q <= 1.0)
+                        .or(Some(0.0))
+                        .unwrap();
+                } else {
+                    parameters.push((name, value));
+                }
+            }
+        }
+        Some(MediaType { main_type, sub_type, quality, parameters })
+    }
+
+    /// Parses the comma separated media types of an Accept header, most preferred first. Media
+    /// types with the same quality are ordered by specificity, so that text/html is before text/*.
+    pub fn parse_accept(accept: &str) -> Vec<MediaType> {
+        let mut media_types = accept.split(",")
+            .flat_map(|m| Self::parse(m).into_iter())
+            .collect::<Vec<MediaType>>();
+        media_types.sort_by(|first, second| second.quality.partial_cmp(&first.quality)
+            .unwrap_or(Ordering::Equal)
+            .then(second.specificity().cmp(&first.specificity())));
+        media_types
+    }
+
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.main_type, self.sub_type)
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.main_type == "*" || self.sub_type == "*"
+    }
+
+    /// If this media type, which may contain wildcards, includes the other, e.g. text/* includes
+    /// text/html.
+    pub fn includes(&self, other: &MediaType) -> bool {
+        (self.main_type == "*" || self.main_type == other.main_type)
+            && (self.sub_type == "*" || self.sub_type == other.sub_type || Self::suffix_matches(&self.sub_type, &other.sub_type))
+    }
+
+    /// application/*+json includes application/problem+json.
+    fn suffix_matches(wildcard: &str, sub_type: &str) -> bool {
+        wildcard.strip_prefix("*+")
+            .map(|suffix| sub_type.ends_with(format!("+{}", suffix).as_str()))
+            .or(Some(false))
+            .unwrap()
+    }
+
+    fn specificity(&self) -> u8 {
+        if self.main_type == "*" {
+            0
+        } else if self.sub_type == "*" {
+            1
+        } else {
+            2 + self.parameters.len().min(1) as u8
+        }
+    }
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.essence())
+    }
+}
+
+/// Written to the response when no converter can read the body of the request, 415, or none can
+/// write a media type the client accepts, 406.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum NegotiationError {
+    NotAcceptable { accept: String, supported: Vec<String> },
+    UnsupportedMediaType { content_type: String, supported: Vec<String> }
+}
+
+impl NegotiationError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            NegotiationError::NotAcceptable { .. } => StatusCode::NOT_ACCEPTABLE,
+            NegotiationError::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE
+        }
+    }
+
+    pub fn supported(&self) -> &Vec<String> {
+        match self {
+            NegotiationError::NotAcceptable { supported, .. } => supported,
+            NegotiationError::UnsupportedMediaType { supported, .. } => supported
+        }
+    }
+}
+
+impl Display for NegotiationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NegotiationError::NotAcceptable { accept, supported } =>
+                write!(f, "Could not produce any of {}, supported are {}.", accept, supported.join(", ")),
+            NegotiationError::UnsupportedMediaType { content_type, supported } =>
+                write!(f, "Content type {} is not supported, supported are {}.", content_type, supported.join(", "))
+        }
+    }
+}
+
+/// Chooses the media type to read the request with from the Content-Type header, and the media
+/// type to write the response with from the Accept header, among the media types supported by the
+/// registered MessageConverters, or the consumes and produces of the mapping.
+pub struct ContentNegotiator;
+
+impl ContentNegotiator {
+
+    /// The media type of the response, most preferred by the Accept header. If there is no Accept
+    /// header then the first producible media type is used.
+    pub fn negotiate_produces(request: &WebRequest, producible: &Vec<String>) -> Result<String, NegotiationError> {
+        let producible_types = producible.iter()
+            .flat_map(|p| MediaType::parse(p).into_iter())
+            .collect::<Vec<MediaType>>();
+        let accept = request.header(ACCEPT.as_str())
+            .filter(|accept| accept.trim().len() != 0);
+        let accepted = accept
+            .map(|accept| MediaType::parse_accept(accept))
+            .or(Some(vec![MediaType::parse("*/*").unwrap()]))
+            .unwrap();
+        for accepted_type in accepted.iter().filter(|a| a.quality > 0.0) {
+            let found = producible_types.iter()
+                .filter(|p| !p.is_wildcard())
+                .find(|p| accepted_type.includes(p) && !Self::excluded(&accepted, p));
+            if let Some(found) = found {
+                return Ok(found.essence());
+            }
+        }
+        Err(NegotiationError::NotAcceptable {
+            accept: accept.cloned().or(Some("*/*".to_string())).unwrap(),
+            supported: producible.clone(),
+        })
+    }
+
+    /// The media type to read the body with. A request without a body does not need a
+    /// Content-Type, and a body without a Content-Type is read as the first consumable media type.
+    pub fn negotiate_consumes(request: &WebRequest, consumable: &Vec<String>) -> Result<Option<String>, NegotiationError> {
+        let content_type = request.header(CONTENT_TYPE.as_str())
+            .filter(|c| c.trim().len() != 0);
+        if content_type.is_none() {
+            return Ok(if request.body.len() == 0 {
+                None
+            } else {
+                consumable.first().cloned()
+            });
+        }
+        let content_type = content_type.unwrap();
+        MediaType::parse(content_type)
+            .and_then(|c| consumable.iter()
+                .flat_map(|m| MediaType::parse(m).into_iter())
+                .find(|m| m.includes(&c))
+                .map(|_| c.essence()))
+            .map(|found| Ok(Some(found)))
+            .or(Some(Err(NegotiationError::UnsupportedMediaType {
+                content_type: content_type.clone(),
+                supported: consumable.clone(),
+            })))
+            .unwrap()
+    }
+
+    /// The media types supported by the converters that are included in the produces or consumes
+    /// of the mapping, or all of them if the mapping does not declare any.
+    pub fn restrict(supported: &Vec<String>, declared: &Vec<String>) -> Vec<String> {
+        if declared.len() == 0 {
+            return supported.clone();
+        }
+        let declared = declared.iter()
+            .flat_map(|d| MediaType::parse(d).into_iter())
+            .collect::<Vec<MediaType>>();
+        supported.iter()
+            .filter(|s| MediaType::parse(s)
+                .map(|s| declared.iter().any(|d| d.includes(&s)))
+                .or(Some(false))
+                .unwrap())
+            .cloned()
+            .collect()
+    }
+
+    /// A StreamingResponseBody is not written by a converter, so its media type is the one of the
+    /// stream, or the one negotiated from the produces of the mapping if the stream does not
+    /// provide one. If the Accept header does not allow it, the stream is dropped so that the
+    /// error can be written instead.
+    pub fn negotiate_stream(request: &WebRequest, response: &mut WebResponse, media_type: Option<&String>) -> Result<(), NegotiationError> {
+        if response.content_type().is_none() {
+            media_type.map(|media_type| response.set_content_type_if_absent(media_type));
+        }
+        let content_type = response.content_type().map(|c| c.to_string());
+        if let Some(content_type) = content_type {
+            if let Err(e) = Self::negotiate_produces(request, &vec![content_type]) {
+                response.clear_body();
+                response.headers.remove(CONTENT_TYPE);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the status, with Accept for 415, and the error as the body.
+    pub fn write_negotiation_error(response: &mut WebResponse, error: &NegotiationError) {
+        response.set_status(error.status());
+        if let NegotiationError::UnsupportedMediaType { supported, .. } = error {
+            response.set_header(ACCEPT.as_str(), supported.join(", ").as_str());
+        }
+        response.set_content_type_if_absent("application/json");
+        serde_json::to_string(error)
+            .map(|body| response.write(body.as_bytes()))
+            .ok();
+    }
+
+    /// A media type with q=0 in the Accept header is not acceptable, even if a wildcard includes
+    /// it.
+    fn excluded(accepted: &Vec<MediaType>, media_type: &MediaType) -> bool {
+        accepted.iter()
+            .any(|a| a.quality == 0.0 && !a.is_wildcard() && a.includes(media_type))
+    }
+
+}
@@ -0,0 +1,379 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use serde::{Deserialize, Serialize};
+use crate::authority::GrantedAuthority;
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("method_security.rs");
+
+/// Returned from a #[secured] or #[pre_authorize] method when the current authentication does not
+/// have access. The method must return a Result with an error that implements
+/// From<AccessDeniedError>.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccessDeniedError {
+    pub message: String,
+    /// The authorities or the expression that was not satisfied.
+    pub required: String
+}
+
+impl AccessDeniedError {
+    pub fn new(required: &str) -> Self {
+        Self {
+            message: "Access is denied.".to_string(),
+            required: required.to_string(),
+        }
+    }
+}
+
+impl Display for AccessDeniedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} Required {}.", self.message, self.required)
+    }
+}
+
+impl std::error::Error for AccessDeniedError {}
+
+/// The authentication the method security checks are evaluated against, set from the
+/// SecurityContextHolder when the request is authenticated.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SecuredPrincipal {
+    pub principal: String,
+    pub authorities: Vec<GrantedAuthority>,
+    pub authenticated: bool
+}
+
+impl SecuredPrincipal {
+    fn has_authority(&self, authority: &str) -> bool {
+        self.authorities.iter().any(|a| a.authority == authority)
+    }
+}
+
+tokio::task_local! {
+    /// Scoped to the task handling the request rather than the thread, so that it is kept across
+    /// .await and is not seen by the next request handled by the same worker.
+    static CURRENT_PRINCIPAL: RefCell<Option<SecuredPrincipal>>;
+}
+
+/// The checks called from the methods woven by the aspect provider for #[secured] and
+/// #[pre_authorize].
+pub struct MethodSecurity;
+
+impl MethodSecurity {
+
+    /// Runs the request with no current authentication. The principal set by the authentication
+    /// filters while it runs is dropped when it completes.
+    pub async fn scope<F: Future>(request: F) -> F::Output {
+        CURRENT_PRINCIPAL.scope(RefCell::new(None), request).await
+    }
+
+    /// Only has an effect within MethodSecurity::scope.
+    pub fn set_current(principal: Option<SecuredPrincipal>) {
+        if CURRENT_PRINCIPAL.try_with(|current| *current.borrow_mut() = principal).is_err() {
+            error!("The current principal was set outside of a request scope and was ignored.");
+        }
+    }
+
+    pub fn current() -> Option<SecuredPrincipal> {
+        CURRENT_PRINCIPAL.try_with(|current| current.borrow().clone())
+            .ok()
+            .flatten()
+    }
+
+    /// Runs the function with the principal as the current authentication, restoring the previous
+    /// one afterwards.
+    pub fn with_principal<T>(principal: SecuredPrincipal, f: impl FnOnce() -> T) -> T {
+        CURRENT_PRINCIPAL.sync_scope(RefCell::new(Some(principal)), f)
+    }
+
+    /// Access is granted if the current authentication is authenticated and has any of the
+    /// authorities.
+    pub fn check_secured(authorities: &[&str]) -> Result<(), AccessDeniedError> {
+        Self::current()
+            .filter(|principal| principal.authenticated)
+            .filter(|principal| authorities.iter().any(|authority| principal.has_authority(authority)))
+            .map(|_| ())
+            .ok_or(AccessDeniedError::new(authorities.join(", ").as_str()))
+    }
+
+    /// Evaluates the expression, where #name refers to the method argument with that name.
+    pub fn check_pre_authorize(expression: &str, args: &[(&str, String)]) -> Result<(), AccessDeniedError> {
+        let parsed = SecurityExpression::parse(expression)
+            .map_err(|_| AccessDeniedError::new(expression))?;
+        let principal = Self::current()
+            .or(Some(SecuredPrincipal::default()))
+            .unwrap();
+        if parsed.evaluate(&principal, args) {
+            Ok(())
+        } else {
+            Err(AccessDeniedError::new(expression))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecurityOperand {
+    Principal,
+    Argument(String),
+    Literal(String)
+}
+
+/// A small expression language for #[pre_authorize], e.g.
+/// hasAuthority('x') and principal == #id. Supports hasAuthority, hasAnyAuthority, hasRole,
+/// hasAnyRole, isAuthenticated, isAnonymous, permitAll, denyAll, == and != between principal,
+/// arguments and quoted strings, and, or, not and parentheses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecurityExpression {
+    And(Box<SecurityExpression>, Box<SecurityExpression>),
+    Or(Box<SecurityExpression>, Box<SecurityExpression>),
+    Not(Box<SecurityExpression>),
+    HasAnyAuthority(Vec<String>),
+    IsAuthenticated,
+    IsAnonymous,
+    Constant(bool),
+    Equals(SecurityOperand, SecurityOperand),
+    NotEquals(SecurityOperand, SecurityOperand)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Argument(String),
+    Literal(String),
+    OpenParen,
+    CloseParen,
+    Comma,
+    Equals,
+    NotEquals,
+    Not
+}
+
+impl SecurityExpression {
+
+    pub fn parse(expression: &str) -> Result<SecurityExpression, String> {
+        let tokens = Self::tokenize(expression)?;
+        let mut position = 0;
+        let parsed = Self::parse_or(&tokens, &mut position)?;
+        if position != tokens.len() {
+            return Err(format!("Unexpected {:?} in {}.", tokens[position], expression));
+        }
+        Ok(parsed)
+    }
+
+    /// The names of the arguments referred to with #name.
+    pub fn arguments(&self) -> Vec<String> {
+        let operand_arguments = |first: &SecurityOperand, second: &SecurityOperand| vec![first, second].into_iter()
+            .flat_map(|operand| match operand {
+                SecurityOperand::Argument(name) => vec![name.clone()],
+                _ => vec![]
+            })
+            .collect::<Vec<String>>();
+        match self {
+            SecurityExpression::And(first, second) | SecurityExpression::Or(first, second) => {
+                let mut arguments = first.arguments();
+                arguments.extend(second.arguments());
+                arguments
+            }
+            SecurityExpression::Not(inner) => inner.arguments(),
+            SecurityExpression::Equals(first, second) | SecurityExpression::NotEquals(first, second) => operand_arguments(first, second),
+            _ => vec![]
+        }
+    }
+
+    pub fn evaluate(&self, principal: &SecuredPrincipal, args: &[(&str, String)]) -> bool {
+        match self {
+            SecurityExpression::And(first, second) => first.evaluate(principal, args) && second.evaluate(principal, args),
+            SecurityExpression::Or(first, second) => first.evaluate(principal, args) || second.evaluate(principal, args),
+            SecurityExpression::Not(inner) => !inner.evaluate(principal, args),
+            SecurityExpression::HasAnyAuthority(authorities) => principal.authenticated
+                && authorities.iter().any(|authority| principal.has_authority(authority)),
+            SecurityExpression::IsAuthenticated => principal.authenticated,
+            SecurityExpression::IsAnonymous => !principal.authenticated,
+            SecurityExpression::Constant(value) => *value,
+            SecurityExpression::Equals(first, second) => Self::resolve(first, principal, args)
+                .zip(Self::resolve(second, principal, args))
+                .map(|(first, second)| first == second)
+                .or(Some(false))
+                .unwrap(),
+            SecurityExpression::NotEquals(first, second) => Self::resolve(first, principal, args)
+                .zip(Self::resolve(second, principal, args))
+                .map(|(first, second)| first != second)
+                .or(Some(false))
+                .unwrap()
+        }
+    }
+
+    fn resolve(operand: &SecurityOperand, principal: &SecuredPrincipal, args: &[(&str, String)]) -> Option<String> {
+        match operand {
+            SecurityOperand::Principal => Some(principal.principal.clone())
+                .filter(|_| principal.authenticated),
+            SecurityOperand::Argument(name) => args.iter()
+                .find(|(arg, _)| arg == name)
+                .map(|(_, value)| value.clone()),
+            SecurityOperand::Literal(value) => Some(value.clone())
+        }
+    }
+
+    fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+        let chars = expression.chars().collect::<Vec<char>>();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                ' ' | '\t' | '\n' => i += 1,
+                '(' => { tokens.push(Token::OpenParen); i += 1; }
+                ')' => { tokens.push(Token::CloseParen); i += 1; }
+                ',' => { tokens.push(Token::Comma); i += 1; }
+                '=' if chars.get(i + 1) == Some(&'=') => { tokens.push(Token::Equals); i += 2; }
+                '!' if chars.get(i + 1) == Some(&'=') => { tokens.push(Token::NotEquals); i += 2; }
+                '!' => { tokens.push(Token::Not); i += 1; }
+                '\'' | '"' => {
+                    let end = chars[i + 1..].iter().position(|next| *next == c)
+                        .ok_or(format!("Unterminated string in {}.", expression))?;
+                    tokens.push(Token::Literal(chars[i + 1..i + 1 + end].iter().collect()));
+                    i += end + 2;
+                }
+                '#' => {
+                    let ident = Self::read_ident(&chars, i + 1);
+                    if ident.len() == 0 {
+                        return Err(format!("Expected an argument name after # in {}.", expression));
+                    }
+                    i += ident.len() + 1;
+                    tokens.push(Token::Argument(ident));
+                }
+                _ if c.is_alphabetic() || c == '_' => {
+                    let ident = Self::read_ident(&chars, i);
+                    i += ident.len();
+                    tokens.push(match ident.as_str() {
+                        "not" => Token::Not,
+                        _ => Token::Ident(ident)
+                    });
+                }
+                _ => return Err(format!("Unexpected {} in {}.", c, expression))
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn read_ident(chars: &Vec<char>, start: usize) -> String {
+        chars[start..].iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .collect()
+    }
+
+    fn parse_or(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityExpression, String> {
+        let mut expression = Self::parse_and(tokens, position)?;
+        while tokens.get(*position) == Some(&Token::Ident("or".to_string())) {
+            *position += 1;
+            expression = SecurityExpression::Or(Box::new(expression), Box::new(Self::parse_and(tokens, position)?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityExpression, String> {
+        let mut expression = Self::parse_unary(tokens, position)?;
+        while tokens.get(*position) == Some(&Token::Ident("and".to_string())) {
+            *position += 1;
+            expression = SecurityExpression::And(Box::new(expression), Box::new(Self::parse_unary(tokens, position)?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityExpression, String> {
+        match tokens.get(*position) {
+            Some(Token::Not) => {
+                *position += 1;
+                Ok(SecurityExpression::Not(Box::new(Self::parse_unary(tokens, position)?)))
+            }
+            Some(Token::OpenParen) => {
+                *position += 1;
+                let inner = Self::parse_or(tokens, position)?;
+                Self::expect(tokens, position, Token::CloseParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(ident)) if Self::is_function(ident) => Self::parse_function(tokens, position),
+            _ => Self::parse_comparison(tokens, position)
+        }
+    }
+
+    fn is_function(ident: &str) -> bool {
+        vec!["hasAuthority", "hasAnyAuthority", "hasRole", "hasAnyRole", "isAuthenticated",
+             "isAnonymous", "permitAll", "denyAll"].contains(&ident)
+    }
+
+    fn parse_function(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityExpression, String> {
+        let name = match &tokens[*position] {
+            Token::Ident(name) => name.clone(),
+            _ => unreachable!()
+        };
+        *position += 1;
+        let mut args = vec![];
+        if tokens.get(*position) == Some(&Token::OpenParen) {
+            *position += 1;
+            while tokens.get(*position) != Some(&Token::CloseParen) {
+                match tokens.get(*position) {
+                    Some(Token::Literal(value)) => args.push(value.clone()),
+                    other => return Err(format!("Expected a quoted argument to {}, found {:?}.", name, other))
+                }
+                *position += 1;
+                if tokens.get(*position) == Some(&Token::Comma) {
+                    *position += 1;
+                }
+            }
+            *position += 1;
+        }
+        let with_role_prefix = |roles: Vec<String>| roles.into_iter()
+            .map(|role| if role.starts_with("ROLE_") { role } else { format!("ROLE_{}", role) })
+            .collect::<Vec<String>>();
+        match name.as_str() {
+            "hasAuthority" | "hasAnyAuthority" if args.len() != 0 => Ok(SecurityExpression::HasAnyAuthority(args)),
+            "hasRole" | "hasAnyRole" if args.len() != 0 => Ok(SecurityExpression::HasAnyAuthority(with_role_prefix(args))),
+            "isAuthenticated" => Ok(SecurityExpression::IsAuthenticated),
+            "isAnonymous" => Ok(SecurityExpression::IsAnonymous),
+            "permitAll" => Ok(SecurityExpression::Constant(true)),
+            "denyAll" => Ok(SecurityExpression::Constant(false)),
+            _ => Err(format!("{} requires at least one argument.", name))
+        }
+    }
+
+    fn parse_comparison(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityExpression, String> {
+        let first = Self::parse_operand(tokens, position)?;
+        let equals = match tokens.get(*position) {
+            Some(Token::Equals) => true,
+            Some(Token::NotEquals) => false,
+            other => return Err(format!("Expected == or !=, found {:?}.", other))
+        };
+        *position += 1;
+        let second = Self::parse_operand(tokens, position)?;
+        Ok(if equals {
+            SecurityExpression::Equals(first, second)
+        } else {
+            SecurityExpression::NotEquals(first, second)
+        })
+    }
+
+    fn parse_operand(tokens: &Vec<Token>, position: &mut usize) -> Result<SecurityOperand, String> {
+        let operand = match tokens.get(*position) {
+            Some(Token::Ident(ident)) if ident == "principal" => SecurityOperand::Principal,
+            Some(Token::Argument(name)) => SecurityOperand::Argument(name.clone()),
+            Some(Token::Literal(value)) => SecurityOperand::Literal(value.clone()),
+            other => return Err(format!("Expected principal, an argument or a quoted string, found {:?}.", other))
+        };
+        *position += 1;
+        Ok(operand)
+    }
+
+    fn expect(tokens: &Vec<Token>, position: &mut usize, expected: Token) -> Result<(), String> {
+        if tokens.get(*position) == Some(&expected) {
+            *position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected {:?}, found {:?}.", expected, tokens.get(*position)))
+        }
+    }
+}
@@ -6,14 +6,25 @@ use std::net::TcpStream;
 use std::ops::Deref;
 use async_std::io::ReadExt;
 use crate::http_method::HttpMethod;
+use crate::streaming::{BodyChunkStream, StreamingResponseBody};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use circular::Buffer;
 use http::{Method, Uri};
+pub use http::{HeaderMap, StatusCode};
+use http::header::{CONTENT_TYPE, COOKIE, HeaderName, HeaderValue, SET_COOKIE};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("request.rs");
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct EndpointMetadata {
     pub path_variables: HashMap<usize, String>,
-    pub query_params: HashMap<String, String>,
+    /// Repeated keys, ?id=1&id=2, are collected in the order they were provided.
+    pub query_params: HashMap<String, Vec<String>>,
     pub host: String
 }
 
@@ -22,6 +33,31 @@ impl EndpointMetadata {
     pub fn matches_vec(&self, path: Vec<(&str, &str)>) -> bool {
         true
     }
+
+    pub fn query_param(&self, name: &str) -> Option<&String> {
+        self.query_params.get(name)
+            .and_then(|values| values.first())
+    }
+
+    pub fn query_param_values(&self, name: &str) -> Vec<String> {
+        self.query_params.get(name)
+            .cloned()
+            .or(Some(vec![]))
+            .unwrap()
+    }
+
+    /// The keys and values are decoded as application/x-www-form-urlencoded, so ?name=a%20b+c
+    /// is "a b c".
+    pub fn split_query_params(query: &str) -> HashMap<String, Vec<String>> {
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        form_urlencoded::parse(query.as_bytes())
+            .for_each(|(key, value)| {
+                query_params.entry(key.into_owned())
+                    .or_insert(vec![])
+                    .push(value.into_owned());
+            });
+        query_params
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
@@ -34,7 +70,53 @@ pub struct WebRequest {
     pub method: Method,
     /// Having this in multiple places allows for the extraction by the http framework, and then
     /// the extraction provided by user.
-    pub endpoint_metadata: Option<EndpointMetadata>
+    pub endpoint_metadata: Option<EndpointMetadata>,
+    /// Values added to the request by filters, resolved with #[request_attribute].
+    #[serde(default)]
+    pub attributes: HashMap<String, String>
+}
+
+impl WebRequest {
+
+    /// Header names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&String> {
+        self.headers.iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .next()
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.header(COOKIE.as_str())
+            .and_then(|cookies| cookies.split(";")
+                .map(|cookie| cookie.trim())
+                .flat_map(|cookie| cookie.split_once("="))
+                .filter(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+                .next()
+            )
+    }
+
+    /// Uses the query params extracted into the EndpointMetadata if they exist, otherwise parses
+    /// them from the uri.
+    pub fn query_param_values(&self, name: &str) -> Vec<String> {
+        self.endpoint_metadata.as_ref()
+            .map(|e| e.query_param_values(name))
+            .or_else(|| self.uri.query()
+                .map(|query| EndpointMetadata::split_query_params(query))
+                .and_then(|mut query_params| query_params.remove(name))
+            )
+            .or(Some(vec![]))
+            .unwrap()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&String> {
+        self.attributes.get(name)
+    }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        self.attributes.insert(name.to_string(), value.to_string());
+    }
 }
 
 
@@ -48,14 +130,195 @@ trait HttpEntity {}
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct WebResponse {
     pub response: String,
+    #[serde(with = "http_serde::status_code")]
+    pub status: StatusCode,
+    #[serde(with = "http_serde::header_map")]
+    pub headers: HeaderMap,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub response_bytes: ResponseBytesBuffer,
+    /// If set, the body is written from the stream instead of the response bytes.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub body_stream: Option<StreamingResponseBody>,
+    /// Set by a filter that has written the whole response, as for a CORS preflight, so that the
+    /// rest of the chain and the controller are skipped.
     #[serde(skip_serializing, skip_deserializing)]
-    pub response_bytes: ResponseBytesBuffer
+    pub committed: bool
 }
 
 impl WebResponse {
+    pub fn builder() -> WebResponseBuilder {
+        WebResponseBuilder::default()
+    }
+
     pub fn response_bytes(&mut self) -> Result<Vec<u8>, String> {
         self.response_bytes.read_and_empty_buffer()
     }
+
+    pub fn set_status(&mut self, status: StatusCode) {
+        self.status = status;
+    }
+
+    /// A HEAD request is handled as a GET, but the body is not sent.
+    pub fn clear_body(&mut self) {
+        self.response.clear();
+        let _ = self.response_bytes.read_and_empty_buffer();
+        self.body_stream = None;
+    }
+
+    /// The body is written as the chunks of the stream are produced, rather than from the
+    /// response bytes.
+    pub fn set_body_stream(&mut self, body_stream: StreamingResponseBody) {
+        body_stream.content_type.as_ref()
+            .map(|content_type| self.set_content_type_if_absent(content_type));
+        self.body_stream = Some(body_stream);
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.body_stream.is_some()
+    }
+
+    pub fn take_body_stream(&mut self) -> Option<BodyChunkStream> {
+        self.body_stream.take()
+            .and_then(|body_stream| body_stream.take_stream())
+    }
+
+    /// If a filter has set an error status then the rest of the chain should not overwrite it.
+    pub fn is_error(&self) -> bool {
+        self.status.is_client_error() || self.status.is_server_error()
+    }
+
+    pub fn commit(&mut self) {
+        self.committed = true;
+    }
+
+    pub fn is_committed(&self) -> bool {
+        self.committed
+    }
+
+    /// Replaces any values for the header.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        Self::parse_header(name, value)
+            .map(|(name, value)| self.headers.insert(name, value));
+    }
+
+    /// Appends the value, keeping any existing values for the header, as in the case of Set-Cookie.
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        Self::parse_header(name, value)
+            .map(|(name, value)| self.headers.append(name, value));
+    }
+
+    pub fn add_cookie(&mut self, cookie: &str) {
+        self.add_header(SET_COOKIE.as_str(), cookie);
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+    }
+
+    pub fn set_content_type_if_absent(&mut self, media_type: &str) {
+        if !self.headers.contains_key(CONTENT_TYPE) {
+            self.set_header(CONTENT_TYPE.as_str(), media_type);
+        }
+    }
+
+    /// Copies the status and headers provided by the controller into the response.
+    pub fn apply_response_entity<T>(&mut self, response_entity: &ResponseEntity<T>) {
+        self.status = response_entity.status;
+        for (name, value) in response_entity.headers.iter() {
+            self.headers.append(name.clone(), value.clone());
+        }
+    }
+
+    fn parse_header(name: &str, value: &str) -> Option<(HeaderName, HeaderValue)> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| {
+                error!("Could not parse header name {}: {:?}", name, e);
+            })
+            .ok()?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| {
+                error!("Could not parse header value {} for {}: {:?}", value, name, e);
+            })
+            .ok()?;
+        Some((name, value))
+    }
+}
+
+#[derive(Default)]
+pub struct WebResponseBuilder {
+    status: StatusCode,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>
+}
+
+impl WebResponseBuilder {
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn content_type(self, media_type: &str) -> Self {
+        self.header(CONTENT_TYPE.as_str(), media_type)
+    }
+
+    pub fn cookie(self, cookie: &str) -> Self {
+        self.header(SET_COOKIE.as_str(), cookie)
+    }
+
+    pub fn body(mut self, body: &[u8]) -> Self {
+        self.body = body.to_vec();
+        self
+    }
+
+    pub fn build(self) -> WebResponse {
+        let mut web_response = WebResponse::default();
+        web_response.set_status(self.status);
+        for (name, value) in self.headers.iter() {
+            web_response.add_header(name, value);
+        }
+        if self.body.len() != 0 {
+            web_response.write(self.body.as_slice());
+        }
+        web_response
+    }
+}
+
+/// Returned from a controller method to provide the status and headers along with the body. Only
+/// the body is passed to the MessageConverter.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(transparent)]
+pub struct ResponseEntity<T> {
+    pub body: T,
+    #[serde(skip)]
+    pub status: StatusCode,
+    #[serde(skip)]
+    pub headers: HeaderMap
+}
+
+impl<T> ResponseEntity<T> {
+    pub fn ok(body: T) -> Self {
+        Self::with_status(body, StatusCode::OK)
+    }
+
+    pub fn with_status(body: T, status: StatusCode) -> Self {
+        Self {
+            body,
+            status,
+            headers: HeaderMap::default(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        WebResponse::parse_header(name, value)
+            .map(|(name, value)| self.headers.append(name, value));
+        self
+    }
 }
 
 #[derive(Clone)]
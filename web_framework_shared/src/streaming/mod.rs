@@ -0,0 +1,195 @@
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use async_std::io::ReadExt;
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("streaming.rs");
+
+pub type BodyChunk = Result<Vec<u8>, std::io::Error>;
+
+pub type BodyChunkStream = Pin<Box<dyn Stream<Item = BodyChunk> + Send>>;
+
+/// Returned from a controller method, or set on the WebResponse, to write the body as a stream of
+/// chunks rather than buffering it. The http layer writes it with chunked transfer encoding as the
+/// chunks are produced. The stream can only be taken once, and clones share it.
+#[derive(Clone, Default)]
+pub struct StreamingResponseBody {
+    pub content_type: Option<String>,
+    stream: Arc<Mutex<Option<BodyChunkStream>>>
+}
+
+impl StreamingResponseBody {
+
+    pub const CHUNK_SIZE: usize = 8192;
+
+    pub fn from_stream<S>(stream: S, content_type: Option<&str>) -> Self
+    where
+        S: Stream<Item = BodyChunk> + Send + 'static
+    {
+        Self {
+            content_type: content_type.map(|c| c.to_string()),
+            stream: Arc::new(Mutex::new(Some(Box::pin(stream)))),
+        }
+    }
+
+    /// Splits bytes already in memory into chunks of CHUNK_SIZE.
+    pub fn from_bytes(bytes: Vec<u8>, content_type: Option<&str>) -> Self {
+        let chunks = bytes.chunks(Self::CHUNK_SIZE)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect::<Vec<BodyChunk>>();
+        Self::from_stream(stream::iter(chunks), content_type)
+    }
+
+    /// Reads the file as it is written, for large downloads. If the file cannot be opened the
+    /// stream contains the error, which the http layer uses to abort the response.
+    pub fn from_file<P: Into<PathBuf>>(path: P, content_type: Option<&str>) -> Self {
+        let path = path.into();
+        let file_stream = stream::once(async move { async_std::fs::File::open(path).await })
+            .flat_map(|file| match file {
+                Ok(file) => Self::read_chunks(file).boxed(),
+                Err(e) => {
+                    error!("Could not open file for streaming response: {:?}", e);
+                    stream::iter(vec![Err(e)]).boxed()
+                }
+            });
+        Self::from_stream(file_stream, content_type.or(Some("application/octet-stream")))
+    }
+
+    /// Serializes each item as a line of json, application/x-ndjson.
+    pub fn ndjson<T, S>(items: S) -> Self
+    where
+        T: Serialize,
+        S: Stream<Item = T> + Send + 'static
+    {
+        let lines = items.map(|item| serde_json::to_vec(&item)
+            .map(|mut line| {
+                line.push(b'\n');
+                line
+            })
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)));
+        Self::from_stream(lines, Some("application/x-ndjson"))
+    }
+
+    /// Writes each event as it is produced, text/event-stream.
+    pub fn server_sent_events<S>(events: S) -> Self
+    where
+        S: Stream<Item = SseEvent> + Send + 'static
+    {
+        Self::from_stream(events.map(|event| Ok(event.to_bytes())), Some("text/event-stream"))
+    }
+
+    pub fn take_stream(&self) -> Option<BodyChunkStream> {
+        self.stream.lock()
+            .map(|mut s| s.take())
+            .map_err(|e| {
+                error!("Could not take stream from streaming response body: {:?}", e);
+            })
+            .ok()
+            .flatten()
+    }
+
+    fn read_chunks(file: async_std::fs::File) -> impl Stream<Item = BodyChunk> + Send {
+        stream::unfold(Some(file), |file| async move {
+            let mut file = file?;
+            let mut chunk = vec![0; Self::CHUNK_SIZE];
+            match file.read(chunk.as_mut_slice()).await {
+                Ok(0) => None,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    Some((Ok(chunk), Some(file)))
+                }
+                Err(e) => Some((Err(e), None))
+            }
+        })
+    }
+}
+
+impl Debug for StreamingResponseBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StreamingResponseBody {{ content_type: {:?} }}", self.content_type)
+    }
+}
+
+/// The body is written by the http layer rather than the MessageConverter, so it is not
+/// serialized.
+impl Serialize for StreamingResponseBody {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_none()
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamingResponseBody {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        serde::de::IgnoredAny::deserialize(deserializer)
+            .map(|_| StreamingResponseBody::default())
+    }
+}
+
+/// A Server-Sent Event. Data containing newlines is written as several data lines.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<u64>,
+    pub comment: Option<String>
+}
+
+impl SseEvent {
+    pub fn data(data: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn json<T: Serialize>(data: &T) -> serde_json::Result<Self> {
+        serde_json::to_string(data)
+            .map(|data| Self::data(data.as_str()))
+    }
+
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    pub fn retry(mut self, retry: u64) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::default();
+        self.comment.as_ref()
+            .map(|comment| comment.lines().for_each(|line| out.push_str(format!(": {}\n", line).as_str())));
+        self.id.as_ref()
+            .map(|id| out.push_str(format!("id: {}\n", id).as_str()));
+        self.event.as_ref()
+            .map(|event| out.push_str(format!("event: {}\n", event).as_str()));
+        self.retry.as_ref()
+            .map(|retry| out.push_str(format!("retry: {}\n", retry).as_str()));
+        if self.data.len() != 0 || self.comment.is_none() {
+            self.data.split('\n')
+                .for_each(|line| out.push_str(format!("data: {}\n", line).as_str()));
+        }
+        out.push('\n');
+        out.into_bytes()
+    }
+}
@@ -1 +1,9 @@
 pub mod test_argument_resolver;
+pub mod test_web_response;
+#[cfg(test)]
+pub mod test_validation;
+pub mod test_media_type;
+pub mod test_streaming;
+pub mod test_method_security;
+pub mod test_authority;
+pub mod test_cors;
@@ -4,6 +4,15 @@ use crate::argument_resolver::path_variable_argument_resolver::PathVariableMetho
 use crate::argument_resolver::query_param_argument_resolver::QueryParamMethodArgument;
 use crate::argument_resolver::request_body_argument_resolver::RequestBodyArgumentResolver;
 use crate::argument_resolver::ResolveArguments;
+use crate::argument_resolver::request_header_argument_resolver::RequestHeaderMethodArgument;
+use crate::argument_resolver::cookie_value_argument_resolver::CookieValueMethodArgument;
+use crate::argument_resolver::request_attribute_argument_resolver::RequestAttributeMethodArgument;
+use crate::argument_resolver::csrf_token_argument_resolver::CsrfTokenMethodArgument;
+use crate::argument_resolver::request_value_resolver::{RequestValueResolver, RequestValueSource};
+use crate::request::WebRequest;
+use crate::csrf::{CSRF_TOKEN_ATTRIBUTE, CsrfToken};
+use std::str::FromStr;
+use http::Uri;
 
 #[test]
 fn test_request_body_fn_arg_resolver() {
@@ -35,6 +44,98 @@ fn test_request_body_request_param() {
     assert_for_all(resolved, &|r| r.inner.name == "test_request_param");
 }
 
+#[test]
+fn test_named_value_fn_arg_resolvers() {
+    let methods = parse_impl_item_method("codegen_resources/test_argument_resolver_named_values.rs");
+    let query_params = methods.iter()
+        .flat_map(|i| QueryParamMethodArgument::resolve_argument_methods(i))
+        .collect::<Vec<QueryParamMethodArgument>>();
+    assert_eq!(query_params.len(), 2);
+    assert_eq!(query_params[0].inner.name, "page");
+    assert_eq!(query_params[0].inner.default_value, "1");
+    assert!(!query_params[0].inner.required);
+    assert!(!query_params[0].inner.multi_valued);
+    assert_eq!(query_params[1].inner.name, "ids");
+    assert!(query_params[1].inner.multi_valued);
+    assert!(query_params[1].inner.required);
+
+    let headers = methods.iter()
+        .flat_map(|i| RequestHeaderMethodArgument::resolve_argument_methods(i))
+        .collect::<Vec<RequestHeaderMethodArgument>>();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[0].inner.name, "X-Request-Id");
+    assert_eq!(headers[0].inner.arg_ident.as_ref().unwrap().to_string(), "request_id");
+
+    let cookies = methods.iter()
+        .flat_map(|i| CookieValueMethodArgument::resolve_argument_methods(i))
+        .collect::<Vec<CookieValueMethodArgument>>();
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].inner.name, "session");
+    assert!(!cookies[0].inner.required);
+
+    let attributes = methods.iter()
+        .flat_map(|i| RequestAttributeMethodArgument::resolve_argument_methods(i))
+        .collect::<Vec<RequestAttributeMethodArgument>>();
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].inner.name, "user");
+
+    let csrf_tokens = methods.iter()
+        .flat_map(|i| CsrfTokenMethodArgument::resolve_argument_methods(i))
+        .collect::<Vec<CsrfTokenMethodArgument>>();
+    assert_eq!(csrf_tokens.len(), 1);
+    assert_eq!(csrf_tokens[0].inner.name, CSRF_TOKEN_ATTRIBUTE);
+    assert_eq!(csrf_tokens[0].inner.arg_ident.as_ref().unwrap().to_string(), "csrf");
+    assert!(csrf_tokens[0].inner.required);
+}
+
+#[test]
+fn test_csrf_token_resolved_from_attribute() {
+    let mut request = WebRequest::default();
+    let csrf_token = CsrfToken::new("X-CSRF-TOKEN", "_csrf", "abc,def");
+    csrf_token.expose(&mut request);
+
+    let resolved = RequestValueResolver::resolve_required::<CsrfToken>(&request, RequestValueSource::Attribute, CSRF_TOKEN_ATTRIBUTE, None);
+    assert_eq!(resolved.unwrap(), csrf_token);
+    assert_eq!(CsrfToken::from_request(&request), Some(csrf_token));
+    assert_eq!(CsrfToken::from_request(&WebRequest::default()), None);
+}
+
+#[test]
+fn test_request_value_resolver() {
+    let mut request = WebRequest::default();
+    request.uri = Uri::from_str("https://test/v1/api/test/one?ids=1&ids=2&page=&q=a%20b+c%2C").unwrap();
+    request.headers.insert("x-request-id".to_string(), "abc".to_string());
+    request.headers.insert("Accept-Language".to_string(), "en, fr".to_string());
+    request.headers.insert("Cookie".to_string(), "one=two; session=three".to_string());
+    request.set_attribute("user", "four");
+
+    let ids = RequestValueResolver::resolve_multi::<u64>(&request, RequestValueSource::QueryParam, "ids", None, true);
+    assert_eq!(ids.unwrap(), vec![1, 2]);
+    let missing = RequestValueResolver::resolve_multi::<u64>(&request, RequestValueSource::QueryParam, "other", None, true);
+    assert!(missing.is_err());
+    let missing = RequestValueResolver::resolve_multi::<u64>(&request, RequestValueSource::QueryParam, "other", None, false);
+    assert_eq!(missing.unwrap(), Vec::<u64>::new());
+    let decoded = RequestValueResolver::resolve_required::<String>(&request, RequestValueSource::QueryParam, "q", None);
+    assert_eq!(decoded.unwrap(), "a b c,");
+    let missing = RequestValueResolver::resolve_optional::<u32>(&request, RequestValueSource::QueryParam, "size", Some("10"));
+    assert_eq!(missing.unwrap(), Some(10));
+    let invalid = RequestValueResolver::resolve_required::<u32>(&request, RequestValueSource::QueryParam, "page", None);
+    assert!(invalid.is_err());
+
+    let header = RequestValueResolver::resolve_required::<String>(&request, RequestValueSource::Header, "X-Request-Id", None);
+    assert_eq!(header.unwrap(), "abc");
+    let header = RequestValueResolver::resolve_required::<String>(&request, RequestValueSource::Header, "Accept-Language", None);
+    assert_eq!(header.unwrap(), "en, fr");
+    let headers = RequestValueResolver::resolve_multi::<String>(&request, RequestValueSource::Header, "Accept-Language", None, true);
+    assert_eq!(headers.unwrap(), vec!["en".to_string(), "fr".to_string()]);
+    let cookie = RequestValueResolver::resolve_optional::<String>(&request, RequestValueSource::Cookie, "session", None);
+    assert_eq!(cookie.unwrap(), Some("three".to_string()));
+    let attribute = RequestValueResolver::resolve_required::<String>(&request, RequestValueSource::Attribute, "user", None);
+    assert_eq!(attribute.unwrap(), "four");
+    let missing = RequestValueResolver::resolve_required::<String>(&request, RequestValueSource::Attribute, "other", None);
+    assert!(missing.is_err());
+}
+
 fn assert_for_all<T>(items: Vec<T>, to_do: &dyn Fn(&T) -> bool) {
     items.iter().for_each(|i| assert!(to_do(i)));
 }
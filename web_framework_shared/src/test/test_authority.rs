@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::authority::{DelegatingAuthoritiesMapper, GrantedAuthoritiesMapper, GrantedAuthority, RoleHierarchy, RoleHierarchyProperties, SimpleAuthorityMapper};
+
+fn authorities(authorities: Vec<&str>) -> Vec<GrantedAuthority> {
+    authorities.into_iter()
+        .map(|authority| GrantedAuthority { authority: authority.to_string() })
+        .collect()
+}
+
+fn names(authorities: &Vec<GrantedAuthority>) -> Vec<&str> {
+    authorities.iter().map(|authority| authority.get_authority()).collect()
+}
+
+#[test]
+fn test_role_hierarchy() {
+    let role_hierarchy = RoleHierarchy::from_properties(&RoleHierarchyProperties {
+        hierarchy: "ROLE_ADMIN > ROLE_STAFF > ROLE_USER\nROLE_STAFF > ROLE_AUDITOR".to_string()
+    }).unwrap();
+    let mut admin = role_hierarchy.map_authorities(authorities(vec!["ROLE_ADMIN", "SCOPE_read"]));
+    assert_eq!(names(&admin)[..2], ["ROLE_ADMIN", "SCOPE_read"]);
+    admin.sort_by(|first, second| first.authority.cmp(&second.authority));
+    assert_eq!(names(&admin), vec!["ROLE_ADMIN", "ROLE_AUDITOR", "ROLE_STAFF", "ROLE_USER", "SCOPE_read"]);
+    assert_eq!(names(&role_hierarchy.map_authorities(authorities(vec!["ROLE_USER"]))), vec!["ROLE_USER"]);
+
+    assert!(RoleHierarchy::parse("ROLE_ADMIN > ROLE_USER; ROLE_USER > ROLE_ADMIN").is_err());
+    assert!(RoleHierarchy::parse("ROLE_ADMIN >").is_err());
+    assert!(RoleHierarchy::parse("ROLE_ADMIN").is_err());
+}
+
+#[test]
+fn test_authorities_mapper() {
+    let mut renames = HashMap::new();
+    renames.insert("administrators".to_string(), "admin".to_string());
+    let simple = SimpleAuthorityMapper {
+        convert_to_upper_case: true,
+        renames,
+        default_authority: Some("ROLE_USER".to_string()),
+        ..SimpleAuthorityMapper::default()
+    };
+    assert_eq!(names(&simple.map_authorities(authorities(vec!["administrators", "ROLE_USER"]))), vec!["ROLE_ADMIN", "ROLE_USER"]);
+
+    let mapper = DelegatingAuthoritiesMapper {
+        mappers: vec![Arc::new(simple), Arc::new(RoleHierarchy::parse("ROLE_ADMIN > ROLE_STAFF").unwrap())]
+    };
+    assert_eq!(names(&mapper.map_authorities(authorities(vec!["administrators"]))), vec!["ROLE_ADMIN", "ROLE_USER", "ROLE_STAFF"]);
+}
@@ -0,0 +1,138 @@
+use crate::cors::{CorsConfiguration, CorsProcessor, UrlBasedCorsConfigurationSource};
+use crate::http_method::HttpMethod;
+use crate::request::{StatusCode, WebRequest, WebResponse};
+
+fn cors_request(method: &str, path: &str, origin: &str) -> WebRequest {
+    let mut request = WebRequest::default();
+    request.method = method.parse().unwrap();
+    request.uri = path.parse().unwrap();
+    request.headers.insert("Origin".to_string(), origin.to_string());
+    request
+}
+
+fn header<'a>(response: &'a WebResponse, name: &str) -> Option<&'a str> {
+    response.headers.get(name).map(|value| value.to_str().unwrap())
+}
+
+#[test]
+fn test_wildcard_origin_with_credentials_is_rejected() {
+    let configuration = CorsConfiguration::parse("allow_credentials = true");
+    assert!(configuration.allows_any_origin_with_credentials());
+    assert!(configuration.check_origin("https://evil.com").is_none());
+
+    let request = cors_request("GET", "/api/one", "https://evil.com");
+    let mut response = WebResponse::default();
+    assert!(!CorsProcessor::process_request(&configuration, &request, &mut response));
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+    assert!(header(&response, "Access-Control-Allow-Credentials").is_none());
+
+    let mut preflight = cors_request("OPTIONS", "/api/one", "https://evil.com");
+    preflight.headers.insert("Access-Control-Request-Method".to_string(), "GET".to_string());
+    let mut response = WebResponse::default();
+    CorsProcessor::process_request(&configuration, &preflight, &mut response);
+    assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+}
+
+#[test]
+fn test_parse_cross_origin() {
+    let configuration = CorsConfiguration::parse("origins = \"https://one.com, https://two.com\", methods = \"GET, put\", exposed_headers = \"X-Total\", allow_credentials = true, max_age = 3600");
+    assert_eq!(configuration.allowed_origins, vec!["https://one.com", "https://two.com"]);
+    assert_eq!(configuration.allowed_methods, vec!["GET", "put"]);
+    assert_eq!(configuration.allowed_headers, vec!["*"]);
+    assert_eq!(configuration.exposed_headers, vec!["X-Total"]);
+    assert!(configuration.allow_credentials);
+    assert_eq!(configuration.max_age, Some(3600));
+
+    assert_eq!(CorsConfiguration::parse(""), CorsConfiguration::permit_default_values());
+    let patterns = CorsConfiguration::parse("origin_patterns = \"https://*.example.com\"");
+    assert!(patterns.allowed_origins.is_empty());
+    assert_eq!(patterns.allowed_origin_patterns, vec!["https://*.example.com"]);
+}
+
+#[test]
+fn test_check_origin() {
+    let configuration = CorsConfiguration {
+        allowed_origins: vec!["https://one.com".to_string()],
+        allowed_origin_patterns: vec!["https://*.example.com".to_string(), "http://localhost:*".to_string()],
+        ..CorsConfiguration::default()
+    };
+    assert_eq!(configuration.check_origin("https://ONE.com"), Some("https://ONE.com".to_string()));
+    assert_eq!(configuration.check_origin("https://api.example.com"), Some("https://api.example.com".to_string()));
+    assert_eq!(configuration.check_origin("http://localhost:8080"), Some("http://localhost:8080".to_string()));
+    assert_eq!(configuration.check_origin("https://example.com.evil.com"), None);
+    assert_eq!(configuration.check_origin("https://two.com"), None);
+
+    let any = CorsConfiguration::permit_default_values();
+    assert_eq!(any.check_origin("https://two.com"), Some("*".to_string()));
+    let credentials = CorsConfiguration { allow_credentials: true, ..any };
+    assert!(credentials.check_origin("https://two.com").is_none());
+
+    assert!(configuration.check_method("DELETE").is_none());
+    assert_eq!(configuration.check_method("post"), Some(vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()]));
+    let headers = CorsConfiguration {
+        allowed_headers: vec!["Content-Type".to_string()],
+        ..CorsConfiguration::default()
+    };
+    assert!(headers.check_headers(&vec!["content-type".to_string()]).is_some());
+    assert!(headers.check_headers(&vec!["X-Other".to_string()]).is_none());
+}
+
+#[test]
+fn test_cors_processor() {
+    let configuration = CorsConfiguration {
+        allowed_origins: vec!["https://one.com".to_string()],
+        allowed_methods: vec!["GET".to_string(), "PUT".to_string()],
+        allowed_headers: vec!["Content-Type".to_string(), "X-Request-Id".to_string()],
+        exposed_headers: vec!["X-Total".to_string()],
+        allow_credentials: true,
+        max_age: Some(600),
+        ..CorsConfiguration::default()
+    };
+
+    let mut preflight = cors_request("OPTIONS", "/api/one", "https://one.com");
+    preflight.headers.insert("Access-Control-Request-Method".to_string(), "PUT".to_string());
+    preflight.headers.insert("Access-Control-Request-Headers".to_string(), "content-type, x-request-id".to_string());
+    assert!(CorsProcessor::is_preflight(&preflight));
+    assert_eq!(CorsProcessor::preflight_method(&preflight), Some(HttpMethod::Put));
+    let mut response = WebResponse::default();
+    assert!(CorsProcessor::process_request(&configuration, &preflight, &mut response));
+    assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("https://one.com"));
+    assert_eq!(header(&response, "Access-Control-Allow-Methods"), Some("GET, PUT"));
+    assert_eq!(header(&response, "Access-Control-Allow-Headers"), Some("content-type, x-request-id"));
+    assert_eq!(header(&response, "Access-Control-Max-Age"), Some("600"));
+    assert_eq!(header(&response, "Access-Control-Allow-Credentials"), Some("true"));
+    assert!(!response.is_error());
+
+    preflight.headers.insert("Access-Control-Request-Method".to_string(), "DELETE".to_string());
+    let mut response = WebResponse::default();
+    assert!(!CorsProcessor::process_request(&configuration, &preflight, &mut response));
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+
+    let mut response = WebResponse::default();
+    assert!(CorsProcessor::process_request(&configuration, &cors_request("GET", "/api/one", "https://one.com"), &mut response));
+    assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("https://one.com"));
+    assert_eq!(header(&response, "Access-Control-Expose-Headers"), Some("X-Total"));
+    assert_eq!(header(&response, "Vary"), Some("Origin"));
+    assert!(header(&response, "Access-Control-Max-Age").is_none());
+
+    let mut response = WebResponse::default();
+    assert!(!CorsProcessor::process_request(&configuration, &cors_request("GET", "/api/one", "https://two.com"), &mut response));
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let mut response = WebResponse::default();
+    assert!(CorsProcessor::process_request(&configuration, &WebRequest::default(), &mut response));
+    assert!(response.headers.is_empty());
+}
+
+#[test]
+fn test_url_based_cors_configuration_source() {
+    let mut source = UrlBasedCorsConfigurationSource::default();
+    source.register("/**", CorsConfiguration::default());
+    source.register("/api/**", CorsConfiguration::permit_default_values());
+    let api = source.configuration(&cors_request("GET", "/api/one", "https://one.com")).unwrap();
+    assert_eq!(api, &CorsConfiguration::permit_default_values());
+    let other = source.configuration(&cors_request("GET", "/other", "https://one.com")).unwrap();
+    assert_eq!(other, &CorsConfiguration::default());
+}
@@ -0,0 +1,96 @@
+use http::StatusCode;
+use crate::media_type::{ContentNegotiator, MediaType, NegotiationError};
+use crate::request::{WebRequest, WebResponse};
+use crate::streaming::StreamingResponseBody;
+
+fn request_with_header(name: &str, value: &str) -> WebRequest {
+    let mut request = WebRequest::default();
+    request.headers.insert(name.to_string(), value.to_string());
+    request
+}
+
+#[test]
+fn test_parse_accept() {
+    let accepted = MediaType::parse_accept("text/*;q=0.5, application/json;q=0.9, text/html, */*;q=0.1");
+    let essences = accepted.iter()
+        .map(|m| m.essence())
+        .collect::<Vec<String>>();
+    assert_eq!(essences, vec!["text/html", "application/json", "text/*", "*/*"]);
+    assert_eq!(accepted[2].quality, 0.5);
+
+    let parsed = MediaType::parse("Application/JSON; charset=UTF-8").unwrap();
+    assert_eq!(parsed.essence(), "application/json");
+    assert_eq!(parsed.parameters, vec![("charset".to_string(), "UTF-8".to_string())]);
+    assert!(MediaType::parse("json").is_none());
+    assert!(MediaType::parse("application/*+json").unwrap().includes(&MediaType::parse("application/problem+json").unwrap()));
+}
+
+#[test]
+fn test_negotiate_produces() {
+    let producible = vec!["application/json".to_string(), "text/html".to_string()];
+
+    let request = request_with_header("Accept", "text/html;q=0.9, application/json;q=0.5");
+    assert_eq!(ContentNegotiator::negotiate_produces(&request, &producible), Ok("text/html".to_string()));
+
+    let request = request_with_header("accept", "*/*, application/json;q=0");
+    assert_eq!(ContentNegotiator::negotiate_produces(&request, &producible), Ok("text/html".to_string()));
+
+    assert_eq!(ContentNegotiator::negotiate_produces(&WebRequest::default(), &producible), Ok("application/json".to_string()));
+
+    let restricted = ContentNegotiator::restrict(&producible, &vec!["text/*".to_string()]);
+    assert_eq!(restricted, vec!["text/html".to_string()]);
+    assert_eq!(ContentNegotiator::restrict(&producible, &vec![]), producible);
+
+    let request = request_with_header("Accept", "application/xml");
+    let err = ContentNegotiator::negotiate_produces(&request, &producible).unwrap_err();
+    assert_eq!(err.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[test]
+fn test_negotiate_consumes() {
+    let consumable = vec!["application/json".to_string()];
+
+    let request = request_with_header("Content-Type", "application/json; charset=utf-8");
+    assert_eq!(ContentNegotiator::negotiate_consumes(&request, &consumable), Ok(Some("application/json".to_string())));
+
+    assert_eq!(ContentNegotiator::negotiate_consumes(&WebRequest::default(), &consumable), Ok(None));
+
+    let request = request_with_header("Content-Type", "text/plain");
+    let err = ContentNegotiator::negotiate_consumes(&request, &consumable).unwrap_err();
+    assert_eq!(err, NegotiationError::UnsupportedMediaType {
+        content_type: "text/plain".to_string(),
+        supported: consumable.clone()
+    });
+
+    let mut response = WebResponse::default();
+    ContentNegotiator::write_negotiation_error(&mut response, &err);
+    assert_eq!(response.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(response.headers.get("Accept").unwrap(), "application/json");
+    let body = String::from_utf8(response.response_bytes().unwrap()).unwrap();
+    assert_eq!(body, serde_json::to_string(&err).unwrap());
+}
+
+#[test]
+fn test_negotiate_stream() {
+    let request = request_with_header("Accept", "text/event-stream");
+    let mut response = WebResponse::default();
+    response.set_body_stream(StreamingResponseBody::from_bytes(b"data: one\n\n".to_vec(), Some("text/event-stream")));
+    assert_eq!(ContentNegotiator::negotiate_stream(&request, &mut response, None), Ok(()));
+    assert!(response.is_streaming());
+    assert_eq!(response.content_type(), Some("text/event-stream"));
+
+    let mut response = WebResponse::default();
+    response.set_body_stream(StreamingResponseBody::from_bytes(b"{}\n".to_vec(), None));
+    let declared = "application/x-ndjson".to_string();
+    let request = request_with_header("Accept", "application/x-ndjson");
+    assert_eq!(ContentNegotiator::negotiate_stream(&request, &mut response, Some(&declared)), Ok(()));
+    assert_eq!(response.content_type(), Some("application/x-ndjson"));
+
+    let request = request_with_header("Accept", "application/json");
+    let mut response = WebResponse::default();
+    response.set_body_stream(StreamingResponseBody::from_bytes(b"data: one\n\n".to_vec(), Some("text/event-stream")));
+    let err = ContentNegotiator::negotiate_stream(&request, &mut response, None).unwrap_err();
+    assert_eq!(err.status(), StatusCode::NOT_ACCEPTABLE);
+    assert!(!response.is_streaming());
+    assert!(response.content_type().is_none());
+}
@@ -0,0 +1,74 @@
+use crate::authority::GrantedAuthority;
+use crate::method_security::{AccessDeniedError, MethodSecurity, SecuredPrincipal, SecurityExpression, SecurityOperand};
+
+fn principal(name: &str, authorities: Vec<&str>) -> SecuredPrincipal {
+    SecuredPrincipal {
+        principal: name.to_string(),
+        authorities: authorities.into_iter()
+            .map(|authority| GrantedAuthority { authority: authority.to_string() })
+            .collect(),
+        authenticated: true,
+    }
+}
+
+#[test]
+fn test_check_secured() {
+    assert_eq!(MethodSecurity::check_secured(&["ROLE_ADMIN"]), Err(AccessDeniedError::new("ROLE_ADMIN")));
+    MethodSecurity::with_principal(principal("admin", vec!["ROLE_ADMIN"]), || {
+        assert!(MethodSecurity::check_secured(&["ROLE_USER", "ROLE_ADMIN"]).is_ok());
+        assert!(MethodSecurity::check_secured(&["ROLE_USER"]).is_err());
+    });
+    assert!(MethodSecurity::current().is_none());
+
+    let mut unauthenticated = principal("admin", vec!["ROLE_ADMIN"]);
+    unauthenticated.authenticated = false;
+    MethodSecurity::with_principal(unauthenticated, || assert!(MethodSecurity::check_secured(&["ROLE_ADMIN"]).is_err()));
+}
+
+#[test]
+fn test_principal_is_scoped_to_the_request() {
+    futures::executor::block_on(MethodSecurity::scope(async {
+        MethodSecurity::set_current(Some(principal("admin", vec!["ROLE_ADMIN"])));
+        futures::future::ready(()).await;
+        assert!(MethodSecurity::check_secured(&["ROLE_ADMIN"]).is_ok());
+    }));
+    assert!(MethodSecurity::current().is_none());
+    futures::executor::block_on(MethodSecurity::scope(async {
+        assert!(MethodSecurity::current().is_none());
+    }));
+    // outside of a request there is no principal to set.
+    MethodSecurity::set_current(Some(principal("admin", vec!["ROLE_ADMIN"])));
+    assert!(MethodSecurity::current().is_none());
+}
+
+#[test]
+fn test_parse_expression() {
+    let parsed = SecurityExpression::parse("hasAuthority('x') and principal == #id").unwrap();
+    assert_eq!(parsed, SecurityExpression::And(
+        Box::new(SecurityExpression::HasAnyAuthority(vec!["x".to_string()])),
+        Box::new(SecurityExpression::Equals(SecurityOperand::Principal, SecurityOperand::Argument("id".to_string())))
+    ));
+    assert_eq!(parsed.arguments(), vec!["id".to_string()]);
+    assert_eq!(SecurityExpression::parse("hasRole('ADMIN')").unwrap(), SecurityExpression::HasAnyAuthority(vec!["ROLE_ADMIN".to_string()]));
+    assert!(SecurityExpression::parse("hasAuthority('x') and").is_err());
+    assert!(SecurityExpression::parse("hasAuthority()").is_err());
+    assert!(SecurityExpression::parse("principal = #id").is_err());
+}
+
+#[test]
+fn test_check_pre_authorize() {
+    let expression = "hasAuthority('x') and principal == #id or hasRole('ADMIN')";
+    MethodSecurity::with_principal(principal("user", vec!["x"]), || {
+        assert!(MethodSecurity::check_pre_authorize(expression, &[("id", "user".to_string())]).is_ok());
+        assert!(MethodSecurity::check_pre_authorize(expression, &[("id", "other".to_string())]).is_err());
+        assert!(MethodSecurity::check_pre_authorize("not (isAnonymous() or denyAll)", &[]).is_ok());
+    });
+    MethodSecurity::with_principal(principal("admin", vec!["ROLE_ADMIN"]), || {
+        assert!(MethodSecurity::check_pre_authorize(expression, &[("id", "other".to_string())]).is_ok());
+    });
+    assert!(MethodSecurity::check_pre_authorize("isAnonymous()", &[]).is_ok());
+    assert_eq!(
+        MethodSecurity::check_pre_authorize("isAuthenticated()", &[]),
+        Err(AccessDeniedError::new("isAuthenticated()"))
+    );
+}
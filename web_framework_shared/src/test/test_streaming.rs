@@ -0,0 +1,58 @@
+use std::io::Write;
+use futures::executor::block_on;
+use futures::{stream, StreamExt};
+use http::header::CONTENT_TYPE;
+use serde::Serialize;
+use crate::request::WebResponse;
+use crate::streaming::{BodyChunkStream, SseEvent, StreamingResponseBody};
+
+#[derive(Serialize)]
+struct TestLine {
+    value: u8
+}
+
+fn collect_chunks(body_stream: BodyChunkStream) -> Vec<Vec<u8>> {
+    block_on(body_stream.map(|chunk| chunk.unwrap()).collect::<Vec<Vec<u8>>>())
+}
+
+#[test]
+fn test_ndjson_stream() {
+    let body = StreamingResponseBody::ndjson(stream::iter(vec![TestLine { value: 1 }, TestLine { value: 2 }]));
+    let mut response = WebResponse::default();
+    response.set_body_stream(body);
+    assert!(response.is_streaming());
+    assert_eq!(response.headers.get(CONTENT_TYPE).unwrap(), "application/x-ndjson");
+
+    let chunks = collect_chunks(response.take_body_stream().unwrap());
+    assert_eq!(chunks, vec!["{\"value\":1}\n".as_bytes().to_vec(), "{\"value\":2}\n".as_bytes().to_vec()]);
+    assert!(!response.is_streaming());
+}
+
+#[test]
+fn test_server_sent_events() {
+    let event = SseEvent::data("one\ntwo").id("1").event("update");
+    assert_eq!(String::from_utf8(event.to_bytes()).unwrap(), "id: 1\nevent: update\ndata: one\ndata: two\n\n");
+    assert_eq!(String::from_utf8(SseEvent::default().comment("ping").to_bytes()).unwrap(), ": ping\n\n");
+
+    let body = StreamingResponseBody::server_sent_events(stream::iter(vec![SseEvent::data("three")]));
+    assert_eq!(body.content_type, Some("text/event-stream".to_string()));
+    let shared = body.clone();
+    assert_eq!(collect_chunks(body.take_stream().unwrap()), vec!["data: three\n\n".as_bytes().to_vec()]);
+    assert!(shared.take_stream().is_none());
+}
+
+#[test]
+fn test_chunked_bytes_and_file() {
+    let bytes = vec![1; StreamingResponseBody::CHUNK_SIZE + 10];
+    let chunks = collect_chunks(StreamingResponseBody::from_bytes(bytes.clone(), None).take_stream().unwrap());
+    assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<usize>>(), vec![StreamingResponseBody::CHUNK_SIZE, 10]);
+
+    let path = std::env::temp_dir().join("web_framework_shared_test_streaming.bin");
+    std::fs::File::create(&path).unwrap().write_all(bytes.as_slice()).unwrap();
+    let file_chunks = collect_chunks(StreamingResponseBody::from_file(path.clone(), None).take_stream().unwrap());
+    assert_eq!(file_chunks.concat(), bytes);
+    std::fs::remove_file(path).unwrap();
+
+    let missing = StreamingResponseBody::from_file("/does/not/exist", None).take_stream().unwrap();
+    assert!(block_on(missing.collect::<Vec<_>>())[0].is_err());
+}
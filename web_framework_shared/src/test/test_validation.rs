@@ -0,0 +1,57 @@
+use spring_knockoff_boot_macro::Validate;
+use crate::validation::{Validate, ValidationErrors, Validator};
+
+#[derive(Default, Validate)]
+struct TestAddress {
+    #[length(min = 1)]
+    street: String
+}
+
+#[derive(Default, Validate)]
+struct TestUser {
+    #[length(min = 1, max = 5)]
+    name: String,
+    #[range(min = 18, max = 150, message = "too young.")]
+    age: u32,
+    #[pattern("^[a-z]+$")]
+    username: String,
+    #[email]
+    email: Option<String>,
+    #[valid]
+    address: TestAddress
+}
+
+#[test]
+fn test_validation_errors_collected() {
+    let user = TestUser {
+        name: "too long".to_string(),
+        age: 10,
+        username: "Not-Valid".to_string(),
+        email: Some("not an email".to_string()),
+        address: TestAddress::default(),
+    };
+    let errors = user.validate().unwrap_err();
+    let fields = errors.errors.iter()
+        .map(|e| e.field.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(fields, vec!["name", "age", "username", "email", "address.street"]);
+    assert_eq!(errors.errors[1].message, "too young.");
+    assert_eq!(errors.errors[1].rejected_value, Some("10".to_string()));
+    assert_eq!(errors.message, "Validation failed with 5 error(s).");
+}
+
+#[test]
+fn test_validation_passes() {
+    let user = TestUser {
+        name: "one".to_string(),
+        age: 20,
+        username: "one".to_string(),
+        email: None,
+        address: TestAddress { street: "two".to_string() },
+    };
+    assert!(user.validate().is_ok());
+
+    let mut errors = ValidationErrors::default();
+    Validator::validate_email(&"one@two.com", "email", None, &mut errors);
+    assert!(errors.is_empty());
+}
@@ -0,0 +1,47 @@
+use http::StatusCode;
+use http::header::{CONTENT_TYPE, SET_COOKIE};
+use serde::{Deserialize, Serialize};
+use crate::request::{ResponseEntity, WebResponse};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct TestBody {
+    value: String
+}
+
+#[test]
+fn test_web_response_builder() {
+    let mut response = WebResponse::builder()
+        .status(StatusCode::CREATED)
+        .content_type("application/json")
+        .cookie("one=two")
+        .cookie("three=four")
+        .body("{}".as_bytes())
+        .build();
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.content_type(), Some("application/json"));
+    assert_eq!(response.headers.get_all(SET_COOKIE).iter().count(), 2);
+    assert_eq!(response.response_bytes().unwrap(), "{}".as_bytes().to_vec());
+}
+
+#[test]
+fn test_web_response_defaults() {
+    let mut response = WebResponse::default();
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.is_error());
+    response.set_content_type_if_absent("text/html");
+    response.set_content_type_if_absent("application/json");
+    assert_eq!(response.headers.get(CONTENT_TYPE).unwrap(), "text/html");
+    response.set_status(StatusCode::NOT_FOUND);
+    assert!(response.is_error());
+}
+
+#[test]
+fn test_response_entity() {
+    let entity = ResponseEntity::with_status(TestBody { value: "one".to_string() }, StatusCode::ACCEPTED)
+        .header("X-Test", "two");
+    assert_eq!(serde_json::to_string(&entity).unwrap(), "{\"value\":\"one\"}");
+    let mut response = WebResponse::default();
+    response.apply_response_entity(&entity);
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    assert_eq!(response.headers.get("X-Test").unwrap(), "two");
+}
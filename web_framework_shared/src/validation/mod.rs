@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use knockoff_logging::*;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use codegen_utils::project_directory;
+use crate::logger_lazy;
+import_logger!("validation.rs");
+
+lazy_static! {
+    /// The compiled #[pattern] regexes, so each is only compiled the first time it is used.
+    static ref PATTERNS: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+/// Implemented with #[derive(Validate)], using the #[length], #[range], #[pattern] and #[email]
+/// field attributes. A #[request_body] #[validated] argument is validated before the controller
+/// is called, and the errors are written with 400.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+    pub rejected_value: Option<String>
+}
+
+/// The body of the 400 response, containing all of the violations rather than the first.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ValidationErrors {
+    pub message: String,
+    pub errors: Vec<FieldError>
+}
+
+impl ValidationErrors {
+    pub fn add(&mut self, field: &str, code: &str, message: String, rejected_value: Option<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message,
+            rejected_value,
+        });
+    }
+
+    /// Nested fields are prefixed with the name of the field, as in address.street.
+    pub fn add_nested(&mut self, field: &str, nested: ValidationErrors) {
+        nested.errors.into_iter()
+            .for_each(|mut e| {
+                e.field = format!("{}.{}", field, e.field);
+                self.errors.push(e);
+            });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.len() == 0
+    }
+
+    pub fn into_result(mut self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            self.message = format!("Validation failed with {} error(s).", self.errors.len());
+            Err(self)
+        }
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The length of strings and collections. None is not validated, so that Option<String> is only
+/// validated if it is provided.
+pub trait ValidateLength {
+    fn validation_length(&self) -> Option<usize>;
+}
+
+impl ValidateLength for String {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl ValidateLength for &str {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl<T> ValidateLength for Vec<T> {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<K, V> ValidateLength for HashMap<K, V> {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T: ValidateLength> ValidateLength for Option<T> {
+    fn validation_length(&self) -> Option<usize> {
+        self.as_ref().and_then(|t| t.validation_length())
+    }
+}
+
+pub trait ValidateRange {
+    fn validation_value(&self) -> Option<f64>;
+}
+
+macro_rules! validate_range_number {
+    ($($ty:ty),*) => {
+        $(
+            impl ValidateRange for $ty {
+                fn validation_value(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+validate_range_number!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl<T: ValidateRange> ValidateRange for Option<T> {
+    fn validation_value(&self) -> Option<f64> {
+        self.as_ref().and_then(|t| t.validation_value())
+    }
+}
+
+pub trait ValidateString {
+    fn validation_str(&self) -> Option<&str>;
+}
+
+impl ValidateString for String {
+    fn validation_str(&self) -> Option<&str> {
+        Some(self.as_str())
+    }
+}
+
+impl ValidateString for &str {
+    fn validation_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<T: ValidateString> ValidateString for Option<T> {
+    fn validation_str(&self) -> Option<&str> {
+        self.as_ref().and_then(|t| t.validation_str())
+    }
+}
+
+/// Called by the code generated by #[derive(Validate)].
+pub struct Validator;
+
+impl Validator {
+
+    pub fn validate_length<T: ValidateLength>(value: &T, field: &str, min: Option<usize>, max: Option<usize>,
+                                              message: Option<&str>, errors: &mut ValidationErrors) {
+        value.validation_length()
+            .filter(|length| min.map(|min| *length < min).unwrap_or(false) || max.map(|max| *length > max).unwrap_or(false))
+            .map(|length| errors.add(
+                field, "length",
+                message.map(|m| m.to_string())
+                    .unwrap_or(format!("length must be between {} and {}.", Self::bound_str(min), Self::bound_str(max))),
+                Some(length.to_string())
+            ));
+    }
+
+    pub fn validate_range<T: ValidateRange>(value: &T, field: &str, min: Option<f64>, max: Option<f64>,
+                                            message: Option<&str>, errors: &mut ValidationErrors) {
+        value.validation_value()
+            .filter(|value| min.map(|min| *value < min).unwrap_or(false) || max.map(|max| *value > max).unwrap_or(false))
+            .map(|value| errors.add(
+                field, "range",
+                message.map(|m| m.to_string())
+                    .unwrap_or(format!("must be between {} and {}.", Self::bound_str(min), Self::bound_str(max))),
+                Some(value.to_string())
+            ));
+    }
+
+    pub fn validate_pattern<T: ValidateString>(value: &T, field: &str, pattern: &str,
+                                               message: Option<&str>, errors: &mut ValidationErrors) {
+        let regex = Self::regex(pattern)
+            .map_err(|e| {
+                error!("Could not compile pattern {} for {}: {:?}", pattern, field, e);
+            });
+        value.validation_str()
+            .filter(|value| regex.as_ref().map(|r| !r.is_match(value)).unwrap_or(true))
+            .map(|value| errors.add(
+                field, "pattern",
+                message.map(|m| m.to_string())
+                    .unwrap_or(format!("must match {}.", pattern)),
+                Some(value.to_string())
+            ));
+    }
+
+    pub fn validate_email<T: ValidateString>(value: &T, field: &str, message: Option<&str>, errors: &mut ValidationErrors) {
+        value.validation_str()
+            .filter(|value| !Self::is_email(value))
+            .map(|value| errors.add(
+                field, "email",
+                message.map(|m| m.to_string())
+                    .unwrap_or("must be a well-formed email address.".to_string()),
+                Some(value.to_string())
+            ));
+    }
+
+    pub fn validate_nested<T: Validate>(value: &T, field: &str, errors: &mut ValidationErrors) {
+        if let Err(nested) = value.validate() {
+            errors.add_nested(field, nested);
+        }
+    }
+
+    fn regex(pattern: &str) -> Result<Regex, regex::Error> {
+        let mut patterns = PATTERNS.lock().unwrap();
+        if let Some(regex) = patterns.get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        patterns.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    }
+
+    fn is_email(value: &str) -> bool {
+        value.split_once("@")
+            .map(|(local, domain)| local.len() != 0
+                && !domain.starts_with(".")
+                && !domain.ends_with(".")
+                && domain.contains(".")
+                && !value.chars().any(|c| c.is_whitespace())
+                && !domain.contains("@"))
+            .unwrap_or(false)
+    }
+
+    fn bound_str<T: Display>(bound: Option<T>) -> String {
+        bound.map(|b| b.to_string())
+            .unwrap_or("unbounded".to_string())
+    }
+}